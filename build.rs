@@ -37,7 +37,7 @@ fn force_rebuild() {
 fn build_app_ui() -> Result<(), BuildError> {
     let app_entry = env::var("APP_ENTRY")?;
     let app_style = env::var("APP_STYLE")?;
-    let config = slint_build::CompilerConfiguration::new().with_style(app_style.into());
+    let config = slint_build::CompilerConfiguration::new().with_style(app_style);
     slint_build::compile_with_config(app_entry, config)?;
     Ok(())
 }
@@ -75,9 +75,246 @@ pub fn create_cache_table(conn: &Connection) -> Result<(), BuildError> {
     Ok(())
 }
 
+pub fn create_payout_tables(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS payout_batches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS payout_rows (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            batch_id INTEGER NOT NULL,
+            row_number INTEGER NOT NULL,
+            recipient TEXT NOT NULL,
+            amount TEXT NOT NULL,
+            token TEXT NOT NULL,
+            status TEXT NOT NULL,
+            signature TEXT NULL,
+            last_valid_block_height INTEGER NULL,
+            error TEXT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn create_schedule_tables(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scheduled_transfers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id INTEGER NOT NULL,
+            recipient TEXT NOT NULL,
+            amount TEXT NOT NULL,
+            token TEXT NOT NULL,
+            memo TEXT NOT NULL,
+            frequency TEXT NOT NULL,
+            start_at INTEGER NOT NULL,
+            occurrence INTEGER NOT NULL DEFAULT 0,
+            next_run_at INTEGER NULL,
+            active INTEGER NOT NULL DEFAULT 1
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scheduled_transfer_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            schedule_id INTEGER NOT NULL,
+            scheduled_for INTEGER NOT NULL,
+            ran_at INTEGER NULL,
+            status TEXT NOT NULL,
+            signature TEXT NULL,
+            error TEXT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn create_stake_reward_tables(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS stake_rewards (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id INTEGER NOT NULL,
+            stake_address TEXT NOT NULL,
+            vote_account TEXT NOT NULL,
+            epoch INTEGER NOT NULL,
+            amount INTEGER NOT NULL,
+            post_balance INTEGER NOT NULL,
+            commission INTEGER NULL,
+            effective_slot INTEGER NOT NULL,
+            block_time INTEGER NULL,
+            sol_price REAL NULL,
+            UNIQUE (account_id, stake_address, epoch)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS stake_reward_sync (
+            account_id INTEGER NOT NULL,
+            stake_address TEXT NOT NULL,
+            last_epoch INTEGER NOT NULL,
+            PRIMARY KEY (account_id, stake_address)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn create_validator_favourites_table(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS validator_favourites (
+            vote_account TEXT PRIMARY KEY
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn create_stake_pools_table(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS stake_pools (
+            address TEXT PRIMARY KEY,
+            name TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+// Amounts are stored as text since token supplies can exceed SQLite's signed integers
+pub fn create_token_balances_table(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS token_balances (
+            owner TEXT NOT NULL,
+            mint TEXT NOT NULL,
+            program_id TEXT NOT NULL,
+            amount TEXT NOT NULL,
+            decimals INTEGER NOT NULL,
+            PRIMARY KEY (owner, mint)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn create_token_metadata_table(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS token_metadata (
+            mint TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            symbol TEXT NOT NULL,
+            uri TEXT NOT NULL,
+            image TEXT NULL,
+            image_path TEXT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn create_token_registry_table(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS token_registry (
+            mint TEXT PRIMARY KEY,
+            symbol TEXT NOT NULL,
+            name TEXT NOT NULL,
+            decimals INTEGER NULL,
+            verified INTEGER NOT NULL DEFAULT 0,
+            trusted INTEGER NOT NULL DEFAULT 0,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn create_spam_overrides_table(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS spam_overrides (
+            address TEXT PRIMARY KEY,
+            hidden INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn create_memo_required_overrides_table(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS memo_required_overrides (
+            address TEXT PRIMARY KEY,
+            label TEXT NOT NULL,
+            required INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn create_prices_table(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS prices (
+            mint TEXT PRIMARY KEY,
+            price REAL NOT NULL,
+            price_change_24h REAL,
+            updated_at INTEGER NOT NULL,
+            fetched_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn create_price_history_table(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS price_history (
+            mint TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            price REAL NOT NULL,
+            PRIMARY KEY (mint, timestamp)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn create_price_backfills_table(conn: &Connection) -> Result<(), BuildError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS price_backfills (
+            mint TEXT NOT NULL,
+            days INTEGER NOT NULL,
+            attempted_at INTEGER NOT NULL,
+            PRIMARY KEY (mint, days)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
 pub fn create_db_tables() -> Result<(), BuildError> {
     let conn = database_connection()?;
     create_accounts_table(&conn)?;
     create_cache_table(&conn)?;
+    create_payout_tables(&conn)?;
+    create_schedule_tables(&conn)?;
+    create_stake_reward_tables(&conn)?;
+    create_validator_favourites_table(&conn)?;
+    create_stake_pools_table(&conn)?;
+    create_token_balances_table(&conn)?;
+    create_token_metadata_table(&conn)?;
+    create_token_registry_table(&conn)?;
+    create_spam_overrides_table(&conn)?;
+    create_memo_required_overrides_table(&conn)?;
+    create_prices_table(&conn)?;
+    create_price_history_table(&conn)?;
+    create_price_backfills_table(&conn)?;
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,105 @@
+use thiserror::Error;
+
+pub const SOL_DECIMALS: u8 = 9;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AmountError {
+    #[error("Invalid amount: {0}")]
+    Invalid(String),
+
+    #[error("Amount {0} has more than {1} decimal places")]
+    TooPrecise(String, u8),
+
+    #[error("Amount {0} is too large")]
+    Overflow(String),
+}
+
+/// Check that an amount is a plain non-negative decimal such as `1` or `0.25`
+pub fn validate_ui_amount(amount: &str) -> Result<(), AmountError> {
+    let (whole, fraction) = match amount.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (amount, None),
+    };
+
+    let is_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    if !is_digits(whole) || fraction.is_some_and(|fraction| !is_digits(fraction)) {
+        return Err(AmountError::Invalid(amount.to_string()));
+    }
+    Ok(())
+}
+
+/// Convert a decimal amount string into base units (lamports for SOL)
+pub fn ui_amount_to_base_units(amount: &str, decimals: u8) -> Result<u64, AmountError> {
+    validate_ui_amount(amount)?;
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.len() > decimals as usize {
+        return Err(AmountError::TooPrecise(amount.to_string(), decimals));
+    }
+
+    let digits = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    digits
+        .parse::<u64>()
+        .map_err(|_| AmountError::Overflow(amount.to_string()))
+}
+
+/// Format base units as a decimal string without trailing zeros
+pub fn base_units_to_ui_amount(units: u64, decimals: u8) -> String {
+    if decimals == 0 {
+        return units.to_string();
+    }
+
+    let divisor = 10u64.pow(decimals as u32);
+    let whole = units / divisor;
+    let fraction = units % divisor;
+    if fraction == 0 {
+        return whole.to_string();
+    }
+
+    let fraction = format!("{:0>width$}", fraction, width = decimals as usize);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_ui_amount() {
+        assert!(validate_ui_amount("1").is_ok());
+        assert!(validate_ui_amount("0.25").is_ok());
+        assert!(validate_ui_amount("").is_err());
+        assert!(validate_ui_amount(".5").is_err());
+        assert!(validate_ui_amount("1.").is_err());
+        assert!(validate_ui_amount("-1").is_err());
+        assert!(validate_ui_amount("1e9").is_err());
+    }
+
+    #[test]
+    fn test_ui_amount_to_base_units() {
+        assert_eq!(
+            ui_amount_to_base_units("1", SOL_DECIMALS),
+            Ok(1_000_000_000)
+        );
+        assert_eq!(ui_amount_to_base_units("0.000000001", SOL_DECIMALS), Ok(1));
+        assert_eq!(ui_amount_to_base_units("1.50", 6), Ok(1_500_000));
+        assert_eq!(ui_amount_to_base_units("7", 0), Ok(7));
+        assert_eq!(
+            ui_amount_to_base_units("0.0000001", 6),
+            Err(AmountError::TooPrecise("0.0000001".to_string(), 6))
+        );
+        assert_eq!(
+            ui_amount_to_base_units("99999999999999999999", SOL_DECIMALS),
+            Err(AmountError::Overflow("99999999999999999999".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_base_units_to_ui_amount() {
+        assert_eq!(base_units_to_ui_amount(1_000_000_000, SOL_DECIMALS), "1");
+        assert_eq!(base_units_to_ui_amount(1_500_000, 6), "1.5");
+        assert_eq!(base_units_to_ui_amount(1, SOL_DECIMALS), "0.000000001");
+        assert_eq!(base_units_to_ui_amount(42, 0), "42");
+    }
+}
@@ -1,6 +1,7 @@
 pub mod callback_manager;
 pub mod errors;
 pub mod global_manager;
+pub mod handlers;
 use crate::app::{
     callback_manager::CallbackManager, errors::AppError, global_manager::GlobalManager,
 };
@@ -56,11 +57,15 @@ pub fn app_view_selector(view: String) -> SlintViewEnum {
         "Explore" => SlintViewEnum::Explore,
         "Settings" => SlintViewEnum::Settings,
         "Accounts" => SlintViewEnum::Accounts,
+        "Payouts" => SlintViewEnum::Payouts,
+        "Staking" => SlintViewEnum::Staking,
+        "Validators" => SlintViewEnum::Validators,
         _ => SlintViewEnum::Wallet,
     }
 }
 
 #[cfg(test)]
+#[allow(dead_code)]
 mod tests {
     use super::*;
     use crate::database::database_connection;
@@ -180,6 +185,18 @@ mod tests {
             app_view_selector("Accounts".to_string()),
             SlintViewEnum::Accounts
         );
+        assert_eq!(
+            app_view_selector("Payouts".to_string()),
+            SlintViewEnum::Payouts
+        );
+        assert_eq!(
+            app_view_selector("Staking".to_string()),
+            SlintViewEnum::Staking
+        );
+        assert_eq!(
+            app_view_selector("Validators".to_string()),
+            SlintViewEnum::Validators
+        );
         assert_eq!(
             app_view_selector("Unknown".to_string()),
             SlintViewEnum::Wallet
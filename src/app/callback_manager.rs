@@ -1,4 +1,15 @@
-use crate::app::global_manager::GlobalManager;
+use crate::app::{
+    global_manager::GlobalManager,
+    handlers::{
+        approval_handler::ApprovalHandler, chart_handler::ChartHandler,
+        history_handler::HistoryHandler, nft_handler::NftHandler, payout_handler::PayoutHandler,
+        pool_handler::PoolHandler, reward_handler::RewardHandler,
+        schedule_handler::ScheduleHandler, send_handler::SendHandler,
+        shared_transaction_handler::SharedTransactionHandler, stake_handler::StakeHandler,
+        token_handler::TokenHandler, validator_handler::ValidatorHandler,
+        value_handler::ValueHandler, wrap_handler::WrapHandler,
+    },
+};
 use crate::database::{
     cache::{Cache, CacheValue},
     errors::DatabaseError,
@@ -32,6 +43,21 @@ impl CallbackManager {
         self.add_account_handler()?;
         self.change_account_handler()?;
         self.cache_active_view_handler()?;
+        SendHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        SharedTransactionHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        PayoutHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        ScheduleHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        HistoryHandler::new(self.app_instance.clone_strong()).run();
+        StakeHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        RewardHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        PoolHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        ValidatorHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        WrapHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        ApprovalHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        TokenHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        NftHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        ValueHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
+        ChartHandler::new(self.conn.clone(), self.app_instance.clone_strong()).run();
         Ok(())
     }
 
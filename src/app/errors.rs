@@ -1,4 +1,7 @@
+use crate::amount::AmountError;
 use crate::database::errors::DatabaseError;
+use crate::services::errors::ServiceError;
+use crate::solana_pay::errors::SolanaPayError;
 use anyhow::Error as AnyhowError;
 use serde::de::StdError;
 use slint::PlatformError;
@@ -20,6 +23,15 @@ pub enum AppError {
     #[error("Parse pubkey error: {0}")]
     ParsePubkeyError(#[from] ParsePubkeyError),
 
+    #[error("Amount error: {0}")]
+    AmountError(#[from] AmountError),
+
+    #[error("Service error: {0}")]
+    ServiceError(#[from] ServiceError),
+
+    #[error("Solana Pay error: {0}")]
+    SolanaPayError(#[from] SolanaPayError),
+
     #[error("Anyhow error: {0}")]
     AnyhowError(#[from] AnyhowError),
 
@@ -28,4 +40,7 @@ pub enum AppError {
 
     #[error("No account selected")]
     NoAccountSelected,
+
+    #[error("{0}")]
+    InvalidInput(String),
 }
@@ -1,9 +1,8 @@
 use crate::app::{app_view_selector, errors::AppError};
 use crate::database::{account::Account, cache::Cache};
 use crate::slint_generatedApp::{
-    Account as SlintAccount, AccountManager, App as SlintApp, SolValueManager, ViewManager,
+    Account as SlintAccount, AccountManager, App as SlintApp, ViewManager,
 };
-use crate::token_value::TokenValue;
 use rusqlite::Connection;
 use slint::{Global, ModelRc, SharedString, VecModel};
 use std::{
@@ -39,7 +38,6 @@ impl GlobalManager {
         self.set_selected_account()?;
         self.set_accounts();
         self.set_selected_view()?;
-        self.set_sol_usd_value().await?;
         Ok(())
     }
 
@@ -95,20 +93,6 @@ impl GlobalManager {
         }
         Ok(())
     }
-
-    async fn set_sol_usd_value(&self) -> Result<(), AppError> {
-        const SOL_KEY: &str = "So11111111111111111111111111111111111111112";
-        let token_keys = vec![SOL_KEY];
-
-        // Create a TokenValue instance and fetch prices
-        let token_value = TokenValue::new(&token_keys).await?;
-
-        if let Some(token_data) = token_value.get_price(SOL_KEY) {
-            SolValueManager::get(&self.app_instance).set_value(token_data.formatted_price().into());
-        };
-
-        Ok(())
-    }
 }
 
 fn slint_account_builder(account: &Account) -> SlintAccount {
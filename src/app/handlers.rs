@@ -0,0 +1,125 @@
+use crate::app::errors::AppError;
+use crate::connection::Connection as SolanaConnection;
+use crate::database::account::Account;
+use crate::services::{account_service::AccountService, errors::ServiceError};
+use crate::slint_generatedApp::{
+    AccountManager, App as SlintApp, NftManager, PoolManager, SendManager, StakeManager,
+    TokenManager,
+};
+use rusqlite::Connection;
+use slint::{ComponentHandle, SharedString, Weak};
+use solana_rpc_client::rpc_client::RpcClient;
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+pub mod approval_handler;
+pub mod chart_handler;
+pub mod history_handler;
+pub mod nft_handler;
+pub mod payout_handler;
+pub mod pool_handler;
+pub mod reward_handler;
+pub mod schedule_handler;
+pub mod send_handler;
+pub mod shared_transaction_handler;
+pub mod stake_handler;
+pub mod token_handler;
+pub mod validator_handler;
+pub mod value_handler;
+pub mod wrap_handler;
+
+pub fn rpc_client() -> Arc<RpcClient> {
+    Arc::new(SolanaConnection::new().connection())
+}
+
+/// Whether `pubkey` is still the selected account, so results loaded for one the user has
+/// since switched away from can be dropped
+pub fn is_selected(app: &SlintApp, pubkey: &str) -> bool {
+    app.global::<AccountManager>().get_selected_account().pubkey == pubkey
+}
+
+/// A view that runs one account operation at a time and shows how it went
+pub trait WorkManager {
+    fn is_busy(app: &SlintApp) -> bool;
+    fn show_busy(app: &SlintApp, busy: bool);
+    fn show_error(app: &SlintApp, error: SharedString);
+    fn show_status(app: &SlintApp, status: SharedString);
+}
+
+macro_rules! work_manager {
+    ($($manager:ident),*) => {$(
+        impl WorkManager for $manager<'_> {
+            fn is_busy(app: &SlintApp) -> bool {
+                app.global::<$manager>().get_busy()
+            }
+
+            fn show_busy(app: &SlintApp, busy: bool) {
+                app.global::<$manager>().set_busy(busy)
+            }
+
+            fn show_error(app: &SlintApp, error: SharedString) {
+                app.global::<$manager>().set_error(error)
+            }
+
+            fn show_status(app: &SlintApp, status: SharedString) {
+                app.global::<$manager>().set_status(status)
+            }
+        }
+    )*};
+}
+
+work_manager!(
+    NftManager,
+    PoolManager,
+    SendManager,
+    StakeManager,
+    TokenManager
+);
+
+/// Runs `work` for the selected account off the UI thread with the service `service`
+/// builds, and hands its result to `done`. Progress and errors show on the view `M`.
+pub fn spawn_account_work<M, S, T>(
+    weak_app: Weak<SlintApp>,
+    conn: Arc<Mutex<Connection>>,
+    service: impl FnOnce() -> S + Send + 'static,
+    work: impl FnOnce(&S, &Account) -> Result<T, ServiceError> + Send + 'static,
+    done: impl FnOnce(&SlintApp, T) + Send + 'static,
+) where
+    M: WorkManager + 'static,
+    T: Send + 'static,
+{
+    let app = weak_app.unwrap();
+    if M::is_busy(&app) {
+        return;
+    }
+    M::show_error(&app, SharedString::new());
+    M::show_status(&app, SharedString::new());
+
+    let account_id = app.global::<AccountManager>().get_selected_account().id;
+    let account = match AccountService::new(conn).get_account_by_id(account_id) {
+        Ok(Some(account)) => account,
+        Ok(None) => {
+            M::show_error(&app, AppError::NoAccountSelected.to_string().into());
+            return;
+        }
+        Err(e) => {
+            M::show_error(&app, e.to_string().into());
+            return;
+        }
+    };
+
+    M::show_busy(&app, true);
+    thread::spawn(move || {
+        let result = work(&service(), &account).map_err(|e| e.to_string());
+
+        let _ = weak_app.upgrade_in_event_loop(move |app| {
+            M::show_busy(&app, false);
+            match result {
+                Ok(value) => done(&app, value),
+                Err(e) => M::show_error(&app, e.into()),
+            }
+        });
+    });
+}
@@ -0,0 +1,224 @@
+use crate::amount::base_units_to_ui_amount;
+use crate::app::errors::AppError;
+use crate::app::handlers::{is_selected, rpc_client};
+use crate::database::account::Account;
+use crate::services::account_service::AccountService;
+use crate::services::errors::ServiceError;
+use crate::services::token_service::{OwnedTokenAccount, TokenService};
+use crate::slint_generatedApp::{AccountManager, App as SlintApp, ApprovalItem, ApprovalManager};
+use rusqlite::Connection;
+use slint::{ComponentHandle, ModelRc, SharedString, VecModel, Weak};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+type Approvals = Arc<Mutex<Vec<OwnedTokenAccount>>>;
+
+pub struct ApprovalHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+    // Token accounts from the last refresh, looked up again when revoking
+    approvals: Approvals,
+}
+
+impl ApprovalHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        ApprovalHandler {
+            app_instance,
+            conn,
+            approvals: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    pub fn run(&self) {
+        self.refresh_handler();
+        self.revoke_handler();
+        self.revoke_all_handler();
+    }
+
+    fn refresh_handler(&self) {
+        let approvals = self.approvals.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<ApprovalManager>()
+            .on_refresh(move || {
+                let app = weak_app.unwrap();
+                let approval_manager = app.global::<ApprovalManager>();
+                approval_manager.set_error(SharedString::new());
+
+                let pubkey = app.global::<AccountManager>().get_selected_account().pubkey;
+                let Ok(owner) = Pubkey::from_str(&pubkey) else {
+                    return;
+                };
+
+                approval_manager.set_loading(true);
+                let approvals = approvals.clone();
+                let weak_app = weak_app.clone();
+                std::thread::spawn(move || {
+                    let result = (|| {
+                        let service = TokenService::new(rpc_client());
+                        let accounts = service.approvals(&owner)?;
+                        let mut mints: Vec<Pubkey> = accounts
+                            .iter()
+                            .map(|account| account.account.mint)
+                            .collect();
+                        mints.sort();
+                        mints.dedup();
+                        let decimals = service.mint_decimals(&mints)?;
+                        Ok::<_, ServiceError>((accounts, decimals))
+                    })()
+                    .map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let approval_manager = app.global::<ApprovalManager>();
+                        approval_manager.set_loading(false);
+                        if !is_selected(&app, &pubkey) {
+                            return;
+                        }
+                        match result {
+                            Ok((accounts, decimals)) => {
+                                let items: Vec<ApprovalItem> = accounts
+                                    .iter()
+                                    .map(|account| approval_item_builder(account, &decimals))
+                                    .collect();
+                                approval_manager.set_has_delegations(
+                                    accounts
+                                        .iter()
+                                        .any(|account| account.account.delegate.is_some()),
+                                );
+                                approval_manager
+                                    .set_items(ModelRc::from(Rc::new(VecModel::from(items))));
+                                *approvals.lock().unwrap() = accounts;
+                            }
+                            Err(e) => approval_manager.set_error(e.into()),
+                        }
+                    });
+                });
+            });
+    }
+
+    fn revoke_handler(&self) {
+        let conn = self.conn.clone();
+        let approvals = self.approvals.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<ApprovalManager>()
+            .on_revoke(move |address| {
+                let selected: Vec<OwnedTokenAccount> = approvals
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|account| account.address.to_string() == address.as_str())
+                    .cloned()
+                    .collect();
+                run_action(weak_app.clone(), conn.clone(), move |service, account| {
+                    let signatures = service.revoke(account, &selected)?;
+                    Ok(format!("Revoked: {}", signatures_display(&signatures)))
+                });
+            });
+    }
+
+    fn revoke_all_handler(&self) {
+        let conn = self.conn.clone();
+        let approvals = self.approvals.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<ApprovalManager>()
+            .on_revoke_all(move || {
+                let all = approvals.lock().unwrap().clone();
+                run_action(weak_app.clone(), conn.clone(), move |service, account| {
+                    let signatures = service.revoke(account, &all)?;
+                    Ok(format!(
+                        "Revoked every delegation: {}",
+                        signatures_display(&signatures)
+                    ))
+                });
+            });
+    }
+}
+
+// Sends revokes for the selected account off the UI thread, then reloads the approvals
+fn run_action<F>(weak_app: Weak<SlintApp>, conn: Arc<Mutex<Connection>>, action: F)
+where
+    F: FnOnce(&TokenService, &Account) -> Result<String, ServiceError> + Send + 'static,
+{
+    let app = weak_app.unwrap();
+    let approval_manager = app.global::<ApprovalManager>();
+    if approval_manager.get_busy() {
+        return;
+    }
+    approval_manager.set_error(SharedString::new());
+    approval_manager.set_status(SharedString::new());
+
+    let account_id = app.global::<AccountManager>().get_selected_account().id;
+    let account = match AccountService::new(conn).get_account_by_id(account_id) {
+        Ok(Some(account)) => account,
+        Ok(None) => {
+            approval_manager.set_error(AppError::NoAccountSelected.to_string().into());
+            return;
+        }
+        Err(e) => {
+            approval_manager.set_error(e.to_string().into());
+            return;
+        }
+    };
+
+    approval_manager.set_busy(true);
+    std::thread::spawn(move || {
+        let result = action(&TokenService::new(rpc_client()), &account).map_err(|e| e.to_string());
+
+        let _ = weak_app.upgrade_in_event_loop(move |app| {
+            let approval_manager = app.global::<ApprovalManager>();
+            approval_manager.set_busy(false);
+            match result {
+                Ok(status) => {
+                    approval_manager.set_status(status.into());
+                    approval_manager.invoke_refresh();
+                }
+                Err(e) => approval_manager.set_error(e.into()),
+            }
+        });
+    });
+}
+
+fn signatures_display(signatures: &[Signature]) -> String {
+    signatures
+        .iter()
+        .map(|signature| signature.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn approval_item_builder(
+    account: &OwnedTokenAccount,
+    decimals: &HashMap<Pubkey, u8>,
+) -> ApprovalItem {
+    let token = &account.account;
+    // Without the mint's decimals, amounts are shown in base units
+    let amount = |units: u64| match decimals.get(&token.mint) {
+        Some(decimals) => base_units_to_ui_amount(units, *decimals),
+        None => format!("{} base units", units),
+    };
+    ApprovalItem {
+        address: account.address.to_string().into(),
+        mint: token.mint.to_string().into(),
+        balance: amount(token.amount).into(),
+        delegate: token
+            .delegate
+            .map(|delegate| delegate.to_string())
+            .unwrap_or_default()
+            .into(),
+        allowance: amount(token.delegated_amount).into(),
+        close_authority: token
+            .close_authority
+            .map(|authority| authority.to_string())
+            .unwrap_or_default()
+            .into(),
+        frozen: token.frozen,
+    }
+}
@@ -0,0 +1,125 @@
+use crate::programs::token::NATIVE_MINT;
+use crate::services::price_history_service::{ChartRange, PriceHistoryService};
+use crate::slint_generatedApp::{App as SlintApp, ChartManager};
+use chrono::Utc;
+use rusqlite::Connection;
+use slint::{ComponentHandle, SharedString, Weak};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+pub struct ChartHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl ChartHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        ChartHandler { app_instance, conn }
+    }
+
+    pub fn run(&self) {
+        self.show_handler();
+        self.select_range_handler();
+
+        // SOL is charted until a token is picked
+        let chart_manager = self.app_instance.global::<ChartManager>();
+        chart_manager.set_mint(NATIVE_MINT.to_string().into());
+        chart_manager.set_symbol("SOL".into());
+        draw(self.app_instance.as_weak(), self.conn.clone());
+    }
+
+    fn show_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<ChartManager>()
+            .on_show(move |mint, symbol| {
+                let app = weak_app.unwrap();
+                let chart_manager = app.global::<ChartManager>();
+                chart_manager.set_mint(mint);
+                chart_manager.set_symbol(symbol);
+                draw(weak_app.clone(), conn.clone());
+            });
+    }
+
+    fn select_range_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<ChartManager>()
+            .on_select_range(move |range| {
+                weak_app.unwrap().global::<ChartManager>().set_range(range);
+                draw(weak_app.clone(), conn.clone());
+            });
+    }
+}
+
+// Draws the chart from stored prices straight away, then backfills the range in the
+// background and redraws if anything new came in
+fn draw(weak_app: Weak<SlintApp>, conn: Arc<Mutex<Connection>>) {
+    let app = weak_app.unwrap();
+    let chart_manager = app.global::<ChartManager>();
+    let mint = chart_manager.get_mint();
+    let label = chart_manager.get_range();
+    let Some(range) = ChartRange::from_label(&label) else {
+        return;
+    };
+    show_chart(&chart_manager, &conn, &mint, range);
+
+    chart_manager.set_loading(true);
+    thread::spawn(move || {
+        let fetched = PriceHistoryService::new(conn.clone())
+            .backfill(&mint, range, Utc::now().timestamp())
+            .unwrap_or(false);
+        let _ = weak_app.upgrade_in_event_loop(move |app| {
+            let chart_manager = app.global::<ChartManager>();
+            // The user may have moved on to another token or range meanwhile
+            if chart_manager.get_mint() != mint || chart_manager.get_range() != label {
+                return;
+            }
+            chart_manager.set_loading(false);
+            if fetched {
+                show_chart(&chart_manager, &conn, &mint, range);
+            }
+        });
+    });
+}
+
+fn show_chart(
+    chart_manager: &ChartManager,
+    conn: &Arc<Mutex<Connection>>,
+    mint: &str,
+    range: ChartRange,
+) {
+    let chart = PriceHistoryService::new(conn.clone())
+        .chart(mint, range, Utc::now().timestamp())
+        .ok()
+        .flatten();
+    let price = |price: Option<f64>| -> SharedString {
+        price
+            .map(|price| format!("${:.2}", price))
+            .unwrap_or_default()
+            .into()
+    };
+    let change = chart.as_ref().and_then(|chart| chart.change());
+
+    chart_manager.set_commands(
+        chart
+            .as_ref()
+            .map(|chart| chart.commands.clone())
+            .unwrap_or_default()
+            .into(),
+    );
+    chart_manager.set_low(price(chart.as_ref().map(|chart| chart.low)));
+    chart_manager.set_high(price(chart.as_ref().map(|chart| chart.high)));
+    chart_manager.set_last(price(chart.as_ref().map(|chart| chart.last)));
+    chart_manager.set_change(
+        change
+            .map(|change| format!("{:+.2}%", change))
+            .unwrap_or_default()
+            .into(),
+    );
+    chart_manager.set_falling(change.is_some_and(|change| change < 0.0));
+}
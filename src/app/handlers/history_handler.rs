@@ -0,0 +1,82 @@
+use crate::app::handlers::{is_selected, rpc_client};
+use crate::services::history_service::{HistoryEntry, HistoryService};
+use crate::slint_generatedApp::{AccountManager, App as SlintApp, HistoryItem, HistoryManager};
+use crate::time::format_time;
+use slint::{ComponentHandle, ModelRc, SharedString, VecModel};
+use solana_sdk::pubkey::Pubkey;
+use std::{rc::Rc, str::FromStr};
+
+// Number of recent transactions shown in the wallet
+const HISTORY_LIMIT: usize = 20;
+
+pub struct HistoryHandler {
+    app_instance: SlintApp,
+}
+
+impl HistoryHandler {
+    pub fn new(app_instance: SlintApp) -> Self {
+        HistoryHandler { app_instance }
+    }
+
+    pub fn run(&self) {
+        self.refresh_handler();
+    }
+
+    fn refresh_handler(&self) {
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<HistoryManager>()
+            .on_refresh(move || {
+                let app = weak_app.unwrap();
+                let history_manager = app.global::<HistoryManager>();
+                history_manager.set_error(SharedString::new());
+
+                let pubkey = app.global::<AccountManager>().get_selected_account().pubkey;
+                let Ok(address) = Pubkey::from_str(&pubkey) else {
+                    return;
+                };
+
+                history_manager.set_loading(true);
+                let weak_app = weak_app.clone();
+                std::thread::spawn(move || {
+                    let client = rpc_client();
+                    let result = HistoryService::new(client)
+                        .recent(&address, HISTORY_LIMIT)
+                        .map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let history_manager = app.global::<HistoryManager>();
+                        history_manager.set_loading(false);
+                        if !is_selected(&app, &pubkey) {
+                            return;
+                        }
+                        match result {
+                            Ok(entries) => {
+                                let items: Vec<HistoryItem> =
+                                    entries.iter().map(history_item_builder).collect();
+                                history_manager
+                                    .set_items(ModelRc::from(Rc::new(VecModel::from(items))));
+                            }
+                            Err(e) => history_manager.set_error(e.into()),
+                        }
+                    });
+                });
+            });
+    }
+}
+
+fn history_item_builder(entry: &HistoryEntry) -> HistoryItem {
+    HistoryItem {
+        signature: entry.signature.clone().into(),
+        time: entry.block_time.map(format_time).unwrap_or_default().into(),
+        direction: entry.direction.as_str().into(),
+        memo: entry.memos.join(" | ").into(),
+        instructions: entry.instructions.join("\n").into(),
+        status: entry
+            .error
+            .as_ref()
+            .map(|error| format!("Failed: {}", error))
+            .unwrap_or_default()
+            .into(),
+    }
+}
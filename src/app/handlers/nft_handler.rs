@@ -0,0 +1,411 @@
+use crate::app::errors::AppError;
+use crate::app::handlers::{is_selected, spawn_account_work};
+use crate::connection::Connection as SolanaConnection;
+use crate::das::client::DasClient;
+use crate::services::{
+    nft_service::{sort_nfts, Nft, NftAction, NftService},
+    spam_service::{SpamService, SpamVerdict},
+};
+use crate::slint_generatedApp::{
+    AccountManager, App as SlintApp, NftGroup, NftItem, NftManager, NftRow,
+};
+use rusqlite::Connection;
+use slint::{ComponentHandle, Image, ModelRc, SharedString, VecModel};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::Path,
+    rc::Rc,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+const ALL_ATTRIBUTES: &str = "All attributes";
+const GRID_COLUMNS: usize = 4;
+
+// Decoded images by mint, so filtering does not read them from disk again
+type ImageCache = Rc<RefCell<HashMap<String, Image>>>;
+
+pub struct NftHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+    // Last fetched NFTs of the selected account, filtered locally
+    nfts: Arc<Mutex<Vec<Nft>>>,
+    images: ImageCache,
+}
+
+impl NftHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        NftHandler {
+            app_instance,
+            conn,
+            nfts: Arc::new(Mutex::new(vec![])),
+            images: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    pub fn run(&self) {
+        self.refresh_handler();
+        self.filter_handler();
+        self.select_handler();
+        self.set_hidden_handler();
+        self.review_action_handler();
+        self.confirm_action_handler();
+        self.cancel_action_handler();
+    }
+
+    fn refresh_handler(&self) {
+        let conn = self.conn.clone();
+        let nfts = self.nfts.clone();
+        let images = self.images.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<NftManager>()
+            .on_refresh(move || {
+                let app = weak_app.unwrap();
+                let nft_manager = app.global::<NftManager>();
+                nft_manager.set_error(SharedString::new());
+
+                let pubkey = app.global::<AccountManager>().get_selected_account().pubkey;
+                let Ok(owner) = Pubkey::from_str(&pubkey) else {
+                    return;
+                };
+                // Another account's NFTs must not linger while this one's load
+                nfts.lock().unwrap().clear();
+                images.borrow_mut().clear();
+                nft_manager.set_has_selection(false);
+                nft_manager.invoke_cancel_action();
+                show_nfts(&app, conn.clone(), &nfts, &images);
+                nft_manager.set_loading(true);
+
+                let nfts = nfts.clone();
+                let weak_app = weak_app.clone();
+                std::thread::spawn(move || {
+                    let service = nft_service();
+                    let result = service.list(&owner).map_err(|e| e.to_string());
+                    // Not every RPC endpoint serves the DAS API, which should not hide the rest
+                    let compressed = service.compressed(&owner).map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        if !is_selected(&app, &pubkey) {
+                            return;
+                        }
+                        let nft_manager = app.global::<NftManager>();
+                        nft_manager.set_loading(false);
+                        match (result, compressed) {
+                            (Ok(mut list), compressed) => {
+                                match compressed {
+                                    Ok(compressed) => list.extend(compressed),
+                                    Err(e) => nft_manager.set_error(
+                                        format!("Compressed NFTs could not be loaded: {}", e)
+                                            .into(),
+                                    ),
+                                }
+                                sort_nfts(&mut list);
+                                *nfts.lock().unwrap() = list;
+                                // Filtering renders with the handler's image cache
+                                nft_manager.invoke_filter();
+                            }
+                            (Err(e), _) => nft_manager.set_error(e.into()),
+                        }
+                    });
+                });
+            });
+    }
+
+    fn filter_handler(&self) {
+        let conn = self.conn.clone();
+        let nfts = self.nfts.clone();
+        let images = self.images.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance.global::<NftManager>().on_filter(move || {
+            let app = weak_app.unwrap();
+            show_nfts(&app, conn.clone(), &nfts, &images);
+        });
+    }
+
+    fn select_handler(&self) {
+        let conn = self.conn.clone();
+        let nfts = self.nfts.clone();
+        let images = self.images.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<NftManager>()
+            .on_select(move |mint| {
+                let app = weak_app.unwrap();
+                let nft_manager = app.global::<NftManager>();
+                let nfts = nfts.lock().unwrap();
+                if let Some(nft) = nfts
+                    .iter()
+                    .find(|nft| nft.mint.to_string() == mint.as_str())
+                {
+                    let spam = spam_verdicts(conn.clone(), &[nft]);
+                    nft_manager.set_selected(nft_item_builder(
+                        nft,
+                        &images,
+                        spam.get(&nft.mint.to_string()),
+                    ));
+                    nft_manager.set_has_selection(true);
+                    nft_manager.set_recipient(SharedString::new());
+                    nft_manager.set_status(SharedString::new());
+                    nft_manager.invoke_cancel_action();
+                }
+            });
+    }
+
+    fn set_hidden_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<NftManager>()
+            .on_set_hidden(move |mint, hidden| {
+                let app = weak_app.unwrap();
+                let nft_manager = app.global::<NftManager>();
+                match SpamService::new(conn.clone()).set_hidden(&mint, hidden) {
+                    Ok(()) => {
+                        nft_manager.invoke_filter();
+                        nft_manager.invoke_select(mint);
+                    }
+                    Err(e) => nft_manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+
+    fn review_action_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<NftManager>()
+            .on_review_action(move |name| {
+                let app = weak_app.unwrap();
+                let nft_manager = app.global::<NftManager>();
+                let selected = nft_manager.get_selected();
+                let action = match nft_action(&name, &nft_manager.get_recipient()) {
+                    Ok(action) => action,
+                    Err(e) => {
+                        nft_manager.set_error(e.to_string().into());
+                        return;
+                    }
+                };
+                nft_manager.set_action(name);
+                spawn_account_work::<NftManager, _, _>(
+                    weak_app.clone(),
+                    conn.clone(),
+                    nft_service,
+                    move |service, account| {
+                        let mint = Pubkey::from_str(&selected.mint)?;
+                        service.preview(account, &mint, selected.compressed, &action)
+                    },
+                    |app, consequences| {
+                        let consequences: Vec<SharedString> =
+                            consequences.into_iter().map(SharedString::from).collect();
+                        let nft_manager = app.global::<NftManager>();
+                        nft_manager
+                            .set_review(ModelRc::from(Rc::new(VecModel::from(consequences))));
+                        nft_manager.set_reviewing(true);
+                    },
+                );
+            });
+    }
+
+    fn confirm_action_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<NftManager>()
+            .on_confirm_action(move || {
+                let app = weak_app.unwrap();
+                let nft_manager = app.global::<NftManager>();
+                let selected = nft_manager.get_selected();
+                let action =
+                    match nft_action(&nft_manager.get_action(), &nft_manager.get_recipient()) {
+                        Ok(action) => action,
+                        Err(e) => {
+                            nft_manager.set_error(e.to_string().into());
+                            return;
+                        }
+                    };
+                spawn_account_work::<NftManager, _, _>(
+                    weak_app.clone(),
+                    conn.clone(),
+                    nft_service,
+                    move |service, account| {
+                        let mint = Pubkey::from_str(&selected.mint)?;
+                        let signature =
+                            service.apply(account, &mint, selected.compressed, &action)?;
+                        Ok(match action {
+                            NftAction::Transfer { .. } => format!("Transferred: {}", signature),
+                            NftAction::Burn => format!("Burned: {}", signature),
+                        })
+                    },
+                    |app, status| {
+                        let nft_manager = app.global::<NftManager>();
+                        nft_manager.set_status(status.into());
+                        nft_manager.set_has_selection(false);
+                        nft_manager.invoke_refresh();
+                    },
+                );
+            });
+    }
+
+    fn cancel_action_handler(&self) {
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<NftManager>()
+            .on_cancel_action(move || {
+                let app = weak_app.unwrap();
+                let nft_manager = app.global::<NftManager>();
+                nft_manager.set_reviewing(false);
+                nft_manager.set_review(ModelRc::default());
+                nft_manager.set_error(SharedString::new());
+            });
+    }
+}
+
+fn nft_action(name: &str, recipient: &str) -> Result<NftAction, AppError> {
+    match name {
+        "Transfer" => {
+            let recipient = recipient.trim();
+            Pubkey::from_str(recipient)
+                .map(|recipient| NftAction::Transfer { recipient })
+                .map_err(|_| {
+                    AppError::InvalidInput(format!("{} is not a valid address", recipient))
+                })
+        }
+        "Burn" => Ok(NftAction::Burn),
+        action => Err(AppError::InvalidInput(format!(
+            "Unknown NFT action: {}",
+            action
+        ))),
+    }
+}
+
+fn nft_service() -> NftService {
+    let connection = SolanaConnection::new();
+    NftService::new(
+        Arc::new(connection.connection()),
+        DasClient::new(connection.das_url()),
+    )
+}
+
+fn show_nfts(
+    app: &SlintApp,
+    conn: Arc<Mutex<Connection>>,
+    nfts: &Arc<Mutex<Vec<Nft>>>,
+    images: &ImageCache,
+) {
+    let nft_manager = app.global::<NftManager>();
+    let all = nfts.lock().unwrap();
+    let spam = spam_verdicts(conn, &all.iter().collect::<Vec<_>>());
+    let is_hidden = |nft: &Nft| {
+        spam.get(&nft.mint.to_string())
+            .is_some_and(|verdict| verdict.hidden)
+    };
+    let hidden_count = all.iter().filter(|nft| is_hidden(nft)).count();
+    let show_hidden = nft_manager.get_show_hidden();
+
+    let mut attributes: Vec<String> = all.iter().flat_map(Nft::attribute_labels).collect();
+    attributes.sort();
+    attributes.dedup();
+    let attribute = nft_manager.get_attribute().to_string();
+    if attribute != ALL_ATTRIBUTES && !attributes.contains(&attribute) {
+        nft_manager.set_attribute(ALL_ATTRIBUTES.into());
+    }
+    let attribute = nft_manager.get_attribute().to_string();
+    attributes.insert(0, ALL_ATTRIBUTES.to_string());
+
+    let query = nft_manager.get_query();
+    let shown: Vec<&Nft> = all
+        .iter()
+        .filter(|nft| show_hidden || !is_hidden(nft))
+        .filter(|nft| nft.matches(&query))
+        .filter(|nft| attribute == ALL_ATTRIBUTES || nft.attribute_labels().contains(&attribute))
+        .collect();
+
+    // NFTs arrive sorted by collection, so each group is a run of neighbours
+    let mut groups: Vec<(String, Vec<NftItem>)> = vec![];
+    for nft in &shown {
+        let item = nft_item_builder(nft, images, spam.get(&nft.mint.to_string()));
+        match groups.last_mut() {
+            Some((name, items)) if *name == nft.collection => items.push(item),
+            _ => groups.push((nft.collection.clone(), vec![item])),
+        }
+    }
+    let groups: Vec<NftGroup> = groups
+        .into_iter()
+        .map(|(name, items)| nft_group_builder(name, items))
+        .collect();
+
+    nft_manager.set_hidden_count(hidden_count as i32);
+    nft_manager.set_summary(if all.is_empty() {
+        SharedString::new()
+    } else {
+        format!("{} of {} NFTs", shown.len(), all.len()).into()
+    });
+    nft_manager.set_attributes(ModelRc::from(Rc::new(VecModel::from(
+        attributes
+            .into_iter()
+            .map(SharedString::from)
+            .collect::<Vec<_>>(),
+    ))));
+    nft_manager.set_groups(ModelRc::from(Rc::new(VecModel::from(groups))));
+}
+
+// Spam verdicts of `nfts` by mint, with the user's overrides applied
+fn spam_verdicts(conn: Arc<Mutex<Connection>>, nfts: &[&Nft]) -> HashMap<String, SpamVerdict> {
+    let service = SpamService::new(conn);
+    let reasons = nfts
+        .iter()
+        .map(|nft| (nft.mint.to_string(), service.nft_reason(nft)))
+        .collect();
+    service.verdicts(reasons).unwrap_or_default()
+}
+
+fn nft_group_builder(name: String, items: Vec<NftItem>) -> NftGroup {
+    let count = items.len() as i32;
+    let rows: Vec<NftRow> = items
+        .chunks(GRID_COLUMNS)
+        .map(|row| NftRow {
+            items: ModelRc::from(Rc::new(VecModel::from(row.to_vec()))),
+        })
+        .collect();
+    NftGroup {
+        name: name.into(),
+        count,
+        rows: ModelRc::from(Rc::new(VecModel::from(rows))),
+    }
+}
+
+fn nft_item_builder(nft: &Nft, images: &ImageCache, spam: Option<&SpamVerdict>) -> NftItem {
+    let mint = nft.mint.to_string();
+    let image = nft.image_path.as_deref().and_then(|path| {
+        let mut images = images.borrow_mut();
+        if let Some(image) = images.get(&mint) {
+            return Some(image.clone());
+        }
+        let image = Image::load_from_path(Path::new(path)).ok()?;
+        images.insert(mint.clone(), image.clone());
+        Some(image)
+    });
+    let attributes: Vec<SharedString> = nft
+        .attribute_labels()
+        .into_iter()
+        .map(SharedString::from)
+        .collect();
+    NftItem {
+        mint: mint.into(),
+        name: nft.name.clone().into(),
+        collection: nft.collection.clone().into(),
+        description: nft.description.clone().into(),
+        has_image: image.is_some(),
+        image: image.unwrap_or_default(),
+        attributes: ModelRc::from(Rc::new(VecModel::from(attributes))),
+        compressed: nft.compressed,
+        spam_reason: spam
+            .and_then(|spam| spam.reason.clone())
+            .unwrap_or_default()
+            .into(),
+        hidden: spam.is_some_and(|spam| spam.hidden),
+    }
+}
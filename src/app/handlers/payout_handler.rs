@@ -0,0 +1,251 @@
+use crate::amount::{base_units_to_ui_amount, SOL_DECIMALS};
+use crate::app::errors::AppError;
+use crate::app::handlers::rpc_client;
+use crate::database::payout::{PayoutBatch, PayoutRow};
+use crate::services::{
+    account_service::AccountService,
+    payout_service::{PayoutEstimate, PayoutService},
+};
+use crate::slint_generatedApp::{
+    AccountManager, App as SlintApp, PayoutManager, PayoutRow as SlintPayoutRow, PayoutSummary,
+};
+use rusqlite::Connection;
+use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel, Weak};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    cell::RefCell,
+    path::Path,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+pub struct PayoutHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+    // Batch shown in the Payouts view; it is always paid from the account that imported it
+    batch: Rc<RefCell<Option<PayoutBatch>>>,
+}
+
+impl PayoutHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        PayoutHandler {
+            app_instance,
+            conn,
+            batch: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    pub fn run(&self) {
+        self.load_latest_batch();
+        self.import_csv_handler();
+        self.execute_batch_handler();
+        self.export_results_handler();
+    }
+
+    fn load_latest_batch(&self) {
+        let account_id = self
+            .app_instance
+            .global::<AccountManager>()
+            .get_selected_account()
+            .id;
+        let service = PayoutService::new(self.conn.clone(), rpc_client());
+        match service.get_latest_batch(account_id) {
+            Ok(Some(batch)) => {
+                show_batch(&self.app_instance, self.conn.clone(), &self.batch, batch)
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Error loading payout batch: {}", e),
+        }
+    }
+
+    fn import_csv_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        let current_batch = self.batch.clone();
+        self.app_instance
+            .global::<PayoutManager>()
+            .on_import_csv(move |path| {
+                let app = weak_app.unwrap();
+                let payout_manager = app.global::<PayoutManager>();
+                reset_messages(&payout_manager);
+
+                let account_id = app.global::<AccountManager>().get_selected_account().id;
+                let service = PayoutService::new(conn.clone(), rpc_client());
+                match service.import_csv(account_id, Path::new(path.trim())) {
+                    Ok(batch) => show_batch(&app, conn.clone(), &current_batch, batch),
+                    Err(e) => payout_manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+
+    fn execute_batch_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        let current_batch = self.batch.clone();
+        self.app_instance
+            .global::<PayoutManager>()
+            .on_execute_batch(move || {
+                let app = weak_app.unwrap();
+                let payout_manager = app.global::<PayoutManager>();
+                reset_messages(&payout_manager);
+
+                let Some(batch) = current_batch.borrow().clone() else {
+                    return;
+                };
+                let account = match AccountService::new(conn.clone())
+                    .get_account_by_id(batch.account_id)
+                {
+                    Ok(Some(account)) => account,
+                    Ok(None) => {
+                        payout_manager.set_error(AppError::NoAccountSelected.to_string().into());
+                        return;
+                    }
+                    Err(e) => {
+                        payout_manager.set_error(e.to_string().into());
+                        return;
+                    }
+                };
+
+                payout_manager.set_busy(true);
+                let conn = conn.clone();
+                let weak_app = weak_app.clone();
+                std::thread::spawn(move || {
+                    let service = PayoutService::new(conn.clone(), rpc_client());
+                    let batch_id = batch.id.unwrap_or_default();
+                    let result = service
+                        .execute(&account, batch_id, |row| {
+                            let row = row.clone();
+                            let _ = weak_app.upgrade_in_event_loop(move |app| {
+                                update_row(&app, &row);
+                            });
+                        })
+                        .map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let payout_manager = app.global::<PayoutManager>();
+                        payout_manager.set_busy(false);
+                        match result {
+                            Ok(_) => payout_manager.set_status("Batch submitted".into()),
+                            Err(e) => payout_manager.set_error(e.into()),
+                        }
+                        refresh_estimate(app.as_weak(), conn, batch);
+                    });
+                });
+            });
+    }
+
+    fn export_results_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        let current_batch = self.batch.clone();
+        self.app_instance
+            .global::<PayoutManager>()
+            .on_export_results(move |path| {
+                let app = weak_app.unwrap();
+                let payout_manager = app.global::<PayoutManager>();
+                reset_messages(&payout_manager);
+
+                let Some(batch_id) = current_batch.borrow().as_ref().and_then(|batch| batch.id)
+                else {
+                    return;
+                };
+                let service = PayoutService::new(conn.clone(), rpc_client());
+                match service.export_results(batch_id, Path::new(path.trim())) {
+                    Ok(_) => payout_manager.set_status(format!("Saved {}", path.trim()).into()),
+                    Err(e) => payout_manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+}
+
+fn reset_messages(payout_manager: &PayoutManager) {
+    payout_manager.set_error(SharedString::new());
+    payout_manager.set_status(SharedString::new());
+}
+
+fn show_batch(
+    app: &SlintApp,
+    conn: Arc<Mutex<Connection>>,
+    current_batch: &Rc<RefCell<Option<PayoutBatch>>>,
+    batch: PayoutBatch,
+) {
+    let payout_manager = app.global::<PayoutManager>();
+    let service = PayoutService::new(conn.clone(), rpc_client());
+    let rows = match service.get_rows(batch.id.unwrap_or_default()) {
+        Ok(rows) => rows,
+        Err(e) => {
+            payout_manager.set_error(e.to_string().into());
+            return;
+        }
+    };
+
+    let rows: Vec<SlintPayoutRow> = rows.iter().map(payout_row_builder).collect();
+    payout_manager.set_rows(ModelRc::from(Rc::new(VecModel::from(rows))));
+    payout_manager.set_batch_name(batch.name.clone().into());
+    payout_manager.set_summary(PayoutSummary::default());
+    current_batch.replace(Some(batch.clone()));
+    refresh_estimate(app.as_weak(), conn, batch);
+}
+
+// Estimating needs several RPC calls, so it runs off the UI thread
+fn refresh_estimate(weak_app: Weak<SlintApp>, conn: Arc<Mutex<Connection>>, batch: PayoutBatch) {
+    std::thread::spawn(move || {
+        let result = (|| -> Result<PayoutEstimate, AppError> {
+            let account = AccountService::new(conn.clone())
+                .get_account_by_id(batch.account_id)?
+                .ok_or(AppError::NoAccountSelected)?;
+            let payer: Pubkey = account.pubkey()?;
+            let service = PayoutService::new(conn, rpc_client());
+            let rows = service.get_rows(batch.id.unwrap_or_default())?;
+            Ok(service.estimate(&payer, &rows)?)
+        })()
+        .map_err(|e| e.to_string());
+
+        let _ = weak_app.upgrade_in_event_loop(move |app| {
+            let payout_manager = app.global::<PayoutManager>();
+            match result {
+                Ok(estimate) => payout_manager.set_summary(payout_summary_builder(&estimate)),
+                Err(e) => payout_manager.set_error(e.into()),
+            }
+        });
+    });
+}
+
+fn update_row(app: &SlintApp, row: &PayoutRow) {
+    let rows = app.global::<PayoutManager>().get_rows();
+    let index = (row.row_number - 1) as usize;
+    if index < rows.row_count() {
+        rows.set_row_data(index, payout_row_builder(row));
+    }
+}
+
+fn payout_row_builder(row: &PayoutRow) -> SlintPayoutRow {
+    SlintPayoutRow {
+        row_number: row.row_number,
+        recipient: row.recipient.clone().into(),
+        amount: row.amount.clone().into(),
+        token: row.token_display().into(),
+        status: row.status.as_str().into(),
+        signature: row.signature.clone().unwrap_or_default().into(),
+        error: row.error.clone().unwrap_or_default().into(),
+    }
+}
+
+fn payout_summary_builder(estimate: &PayoutEstimate) -> PayoutSummary {
+    let token_totals = estimate
+        .token_totals
+        .iter()
+        .map(|(mint, amount)| format!("{} {}", amount, mint))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    PayoutSummary {
+        transactions: estimate.transactions as i32,
+        total_sol: base_units_to_ui_amount(estimate.total_lamports(), SOL_DECIMALS).into(),
+        token_totals: token_totals.into(),
+        network_fees: base_units_to_ui_amount(estimate.network_fees, SOL_DECIMALS).into(),
+        new_token_accounts: estimate.new_token_accounts as i32,
+        token_account_rent: base_units_to_ui_amount(estimate.token_account_rent, SOL_DECIMALS)
+            .into(),
+    }
+}
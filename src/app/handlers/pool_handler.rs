@@ -0,0 +1,318 @@
+use crate::amount::{base_units_to_ui_amount, SOL_DECIMALS};
+use crate::app::errors::AppError;
+use crate::app::handlers::{is_selected, rpc_client, spawn_account_work};
+use crate::database::account::Account;
+use crate::services::errors::ServiceError;
+use crate::services::stake_pool_service::{PoolAction, PoolSummary, StakePoolService};
+use crate::slint_generatedApp::{
+    AccountManager, App as SlintApp, PoolActionRequest, PoolItem, PoolManager,
+};
+use rusqlite::Connection;
+use slint::{ComponentHandle, ModelRc, SharedString, VecModel};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    rc::Rc,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+pub struct PoolHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl PoolHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        PoolHandler { app_instance, conn }
+    }
+
+    pub fn run(&self) {
+        self.refresh_handler();
+        self.add_pool_handler();
+        self.remove_pool_handler();
+        self.review_handler();
+        self.confirm_handler();
+        self.cancel_handler();
+    }
+
+    fn refresh_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<PoolManager>()
+            .on_refresh(move || {
+                let app = weak_app.unwrap();
+                let pool_manager = app.global::<PoolManager>();
+                pool_manager.set_error(SharedString::new());
+
+                let pubkey = app.global::<AccountManager>().get_selected_account().pubkey;
+                let Ok(owner) = Pubkey::from_str(&pubkey) else {
+                    return;
+                };
+
+                pool_manager.set_loading(true);
+                let conn = conn.clone();
+                let weak_app = weak_app.clone();
+                std::thread::spawn(move || {
+                    let result = StakePoolService::new(conn, rpc_client())
+                        .summaries(&owner)
+                        .map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let pool_manager = app.global::<PoolManager>();
+                        pool_manager.set_loading(false);
+                        if !is_selected(&app, &pubkey) {
+                            return;
+                        }
+                        match result {
+                            Ok(summaries) => {
+                                let total: u64 =
+                                    summaries.iter().map(PoolSummary::holding_value).sum();
+                                let items: Vec<PoolItem> =
+                                    summaries.iter().map(pool_item_builder).collect();
+                                pool_manager.set_total_value(
+                                    if total > 0 { sol(total) } else { String::new() }.into(),
+                                );
+                                pool_manager
+                                    .set_items(ModelRc::from(Rc::new(VecModel::from(items))));
+                            }
+                            Err(e) => pool_manager.set_error(e.into()),
+                        }
+                    });
+                });
+            });
+    }
+
+    fn add_pool_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<PoolManager>()
+            .on_add_pool(move |name, address| {
+                let app = weak_app.unwrap();
+                let pool_manager = app.global::<PoolManager>();
+                let name = name.trim().to_string();
+                let address = match parse_pool(&name, address.trim()) {
+                    Ok(address) => address,
+                    Err(e) => {
+                        pool_manager.set_error(e.to_string().into());
+                        return;
+                    }
+                };
+                pool_manager.set_error(SharedString::new());
+
+                let conn = conn.clone();
+                let weak_app = weak_app.clone();
+                std::thread::spawn(move || {
+                    let result = StakePoolService::new(conn, rpc_client())
+                        .add_pool(&name, &address)
+                        .map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let pool_manager = app.global::<PoolManager>();
+                        match result {
+                            Ok(_) => {
+                                pool_manager.set_status(format!("Added {}", name).into());
+                                pool_manager.invoke_refresh();
+                            }
+                            Err(e) => pool_manager.set_error(e.into()),
+                        }
+                    });
+                });
+            });
+    }
+
+    fn remove_pool_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<PoolManager>()
+            .on_remove_pool(move |address| {
+                let app = weak_app.unwrap();
+                let pool_manager = app.global::<PoolManager>();
+                let result = Pubkey::from_str(&address)
+                    .map_err(ServiceError::from)
+                    .and_then(|address| {
+                        Ok(StakePoolService::new(conn.clone(), rpc_client())
+                            .remove_pool(&address)?)
+                    });
+
+                match result {
+                    Ok(_) => {
+                        if pool_manager.get_selected_address() == address {
+                            pool_manager.set_selected_address(SharedString::new());
+                        }
+                        pool_manager.invoke_refresh();
+                    }
+                    Err(e) => pool_manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+
+    fn review_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<PoolManager>()
+            .on_review(move |request| {
+                let app = weak_app.unwrap();
+                let action = match pool_action_from_request(&request) {
+                    Ok(action) => action,
+                    Err(e) => {
+                        app.global::<PoolManager>().set_error(e.to_string().into());
+                        return;
+                    }
+                };
+                let address = request.address.to_string();
+                spawn_account_work::<PoolManager, _, _>(
+                    weak_app.clone(),
+                    conn.clone(),
+                    pool_service(conn.clone()),
+                    move |service, account| {
+                        let summary = current_summary(service, account, &address)?;
+                        service.preview(account, &summary, &action)
+                    },
+                    move |app, lines| {
+                        let lines: Vec<SharedString> =
+                            lines.into_iter().map(SharedString::from).collect();
+                        let pool_manager = app.global::<PoolManager>();
+                        pool_manager.set_pending(request);
+                        pool_manager
+                            .set_review_lines(ModelRc::from(Rc::new(VecModel::from(lines))));
+                        pool_manager.set_reviewing(true);
+                    },
+                );
+            });
+    }
+
+    fn confirm_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<PoolManager>()
+            .on_confirm(move || {
+                let app = weak_app.unwrap();
+                let request = app.global::<PoolManager>().get_pending();
+                let action = match pool_action_from_request(&request) {
+                    Ok(action) => action,
+                    Err(e) => {
+                        app.global::<PoolManager>().set_error(e.to_string().into());
+                        return;
+                    }
+                };
+                let address = request.address.to_string();
+                let label = request.action.to_string();
+                // The pool is read again so the transaction uses the latest rate and reserve
+                spawn_account_work::<PoolManager, _, _>(
+                    weak_app.clone(),
+                    conn.clone(),
+                    pool_service(conn.clone()),
+                    move |service, account| {
+                        let summary = current_summary(service, account, &address)?;
+                        let signature = service.apply(account, &summary, &action)?;
+                        Ok(format!("{}: {}", label, signature))
+                    },
+                    |app, status| {
+                        let pool_manager = app.global::<PoolManager>();
+                        pool_manager.set_reviewing(false);
+                        pool_manager.set_status(status.into());
+                        pool_manager.invoke_refresh();
+                    },
+                );
+            });
+    }
+
+    fn cancel_handler(&self) {
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<PoolManager>()
+            .on_cancel(move || {
+                let app = weak_app.unwrap();
+                let pool_manager = app.global::<PoolManager>();
+                pool_manager.set_reviewing(false);
+                pool_manager.set_review_lines(ModelRc::default());
+                pool_manager.set_error(SharedString::new());
+            });
+    }
+}
+
+fn pool_service(
+    conn: Arc<Mutex<Connection>>,
+) -> impl FnOnce() -> StakePoolService + Send + 'static {
+    move || StakePoolService::new(conn, rpc_client())
+}
+
+fn pool_action_from_request(request: &PoolActionRequest) -> Result<PoolAction, AppError> {
+    let amount = request.amount.trim().to_string();
+    match request.action.as_str() {
+        "Deposit" => Ok(PoolAction::Deposit { amount }),
+        "Withdraw" => Ok(PoolAction::Withdraw { amount }),
+        action => Err(AppError::InvalidInput(format!(
+            "Unknown pool action {}",
+            action
+        ))),
+    }
+}
+
+fn parse_pool(name: &str, address: &str) -> Result<Pubkey, AppError> {
+    if name.is_empty() {
+        return Err(AppError::InvalidInput(
+            "Enter a name for the pool".to_string(),
+        ));
+    }
+    Pubkey::from_str(address)
+        .map_err(|_| AppError::InvalidInput(format!("{} is not a valid address", address)))
+}
+
+// Actions use the on-chain state of the pool rather than the possibly stale list
+fn current_summary(
+    service: &StakePoolService,
+    account: &Account,
+    address: &str,
+) -> Result<PoolSummary, ServiceError> {
+    service
+        .summaries(&account.pubkey()?)?
+        .into_iter()
+        .find(|summary| summary.entry.address.to_string() == address)
+        .ok_or_else(|| ServiceError::AccountNotFound(address.to_string()))
+}
+
+fn sol(lamports: u64) -> String {
+    base_units_to_ui_amount(lamports, SOL_DECIMALS)
+}
+
+fn pool_item_builder(summary: &PoolSummary) -> PoolItem {
+    let pool = &summary.pool;
+    let withdrawal = if pool.sol_withdraw_authority.is_some() {
+        "Withdrawals arrive as a stake account that unlocks after the epoch ends".to_string()
+    } else {
+        format!(
+            "Instant withdrawals up to {} SOL from the reserve, larger ones arrive as stake",
+            sol(summary.reserve_available)
+        )
+    };
+
+    PoolItem {
+        address: summary.entry.address.to_string().into(),
+        name: summary.entry.name.clone().into(),
+        rate: format!(
+            "1 {} = {:.6} SOL",
+            summary.entry.name,
+            summary.sol_per_token()
+        )
+        .into(),
+        fees: format!(
+            "Deposit fee {:.2}% · Instant withdrawal fee {:.2}% · Stake withdrawal fee {:.2}% · Reward fee {:.2}%",
+            pool.sol_deposit_fee.percent(),
+            pool.sol_withdrawal_fee.percent(),
+            pool.stake_withdrawal_fee.percent(),
+            pool.epoch_fee.percent()
+        )
+        .into(),
+        withdrawal: withdrawal.into(),
+        holding: base_units_to_ui_amount(summary.holding, summary.decimals).into(),
+        holding_value: sol(summary.holding_value()).into(),
+        stale: !summary.up_to_date(),
+        custom: summary.entry.custom,
+    }
+}
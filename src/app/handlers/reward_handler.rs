@@ -0,0 +1,242 @@
+use crate::amount::{base_units_to_ui_amount, SOL_DECIMALS};
+use crate::app::errors::AppError;
+use crate::connection::Connection as SolanaConnection;
+use crate::database::stake_reward::StakeReward;
+use crate::price_provider::coingecko::CoinGecko;
+use crate::services::reward_service::{validator_apy, RewardService};
+use crate::services::stake_service::StakeService;
+use crate::slint_generatedApp::{
+    AccountManager, App as SlintApp, RewardBar, RewardManager, ValidatorApyItem,
+};
+use rusqlite::Connection;
+use slint::{ComponentHandle, ModelRc, SharedString, VecModel};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    rc::Rc,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+// Label of the chart filter entry covering every stake account
+const ALL_STAKE_ACCOUNTS: &str = "All stake accounts";
+
+// Number of most recent epochs charted
+const CHART_EPOCHS: usize = 30;
+
+pub struct RewardHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl RewardHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        RewardHandler { app_instance, conn }
+    }
+
+    pub fn run(&self) {
+        self.app_instance
+            .global::<RewardManager>()
+            .set_selected_stake(ALL_STAKE_ACCOUNTS.into());
+        self.refresh_handler();
+        self.select_stake_handler();
+        self.export_year_handler();
+    }
+
+    fn refresh_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<RewardManager>()
+            .on_refresh(move || {
+                let app = weak_app.unwrap();
+                let reward_manager = app.global::<RewardManager>();
+                reward_manager.set_error(SharedString::new());
+                reward_manager.set_status(SharedString::new());
+                load_rewards(&app, conn.clone());
+
+                let account = app.global::<AccountManager>().get_selected_account();
+                let Ok(owner) = Pubkey::from_str(&account.pubkey) else {
+                    return;
+                };
+                if reward_manager.get_syncing() {
+                    return;
+                }
+
+                reward_manager.set_syncing(true);
+                let conn = conn.clone();
+                let weak_app = weak_app.clone();
+                std::thread::spawn(move || {
+                    let client = Arc::new(SolanaConnection::new().connection());
+                    let service = RewardService::new(conn.clone(), client.clone());
+                    let stake_service = StakeService::new(client);
+                    let synced = stake_service
+                        .epoch_timing()
+                        .and_then(|timing| {
+                            let stakes = stake_service.discover(&owner, timing.epoch)?;
+                            service.sync(account.id, &stakes, timing.epoch)
+                        })
+                        .map_err(|e| e.to_string());
+                    // Prices are looked up separately so a rate-limited lookup keeps the synced rewards
+                    let coingecko = CoinGecko::from_env();
+                    let priced = service
+                        .fill_prices(account.id, |date| Ok(coingecko.sol_price_on(date)?))
+                        .map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let reward_manager = app.global::<RewardManager>();
+                        reward_manager.set_syncing(false);
+                        match (synced, priced) {
+                            (Err(e), _) => reward_manager.set_error(e.into()),
+                            (Ok(_), Err(e)) => reward_manager.set_error(
+                                format!("Some rewards could not be priced: {}", e).into(),
+                            ),
+                            (Ok(stored), Ok(_)) => {
+                                reward_manager.set_status(format!("{} new rewards", stored).into())
+                            }
+                        }
+                        load_rewards(&app, conn);
+                    });
+                });
+            });
+    }
+
+    fn select_stake_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<RewardManager>()
+            .on_select_stake(move |stake_address| {
+                let app = weak_app.unwrap();
+                app.global::<RewardManager>()
+                    .set_selected_stake(stake_address);
+                load_rewards(&app, conn.clone());
+            });
+    }
+
+    fn export_year_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<RewardManager>()
+            .on_export_year(move |year, path| {
+                let app = weak_app.unwrap();
+                let reward_manager = app.global::<RewardManager>();
+                reward_manager.set_error(SharedString::new());
+                reward_manager.set_status(SharedString::new());
+
+                let account_id = app.global::<AccountManager>().get_selected_account().id;
+                let result = year
+                    .trim()
+                    .parse::<i32>()
+                    .map_err(|_| AppError::InvalidInput("Enter a year such as 2024".to_string()))
+                    .and_then(|year| {
+                        let service = RewardService::new(
+                            conn.clone(),
+                            Arc::new(SolanaConnection::new().connection()),
+                        );
+                        Ok(service.export_year(account_id, year, Path::new(path.trim()))?)
+                    });
+
+                match result {
+                    Ok(count) => reward_manager
+                        .set_status(format!("Exported {} rewards to {}", count, path).into()),
+                    Err(e) => reward_manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+}
+
+fn load_rewards(app: &SlintApp, conn: Arc<Mutex<Connection>>) {
+    let reward_manager = app.global::<RewardManager>();
+    let account_id = app.global::<AccountManager>().get_selected_account().id;
+    let service = RewardService::new(conn, Arc::new(SolanaConnection::new().connection()));
+    let rewards = match service.get_rewards(account_id) {
+        Ok(rewards) => rewards,
+        Err(e) => {
+            reward_manager.set_error(e.to_string().into());
+            return;
+        }
+    };
+
+    let addresses: BTreeSet<&str> = rewards
+        .iter()
+        .map(|reward| reward.stake_address.as_str())
+        .collect();
+    let mut stake_accounts: Vec<SharedString> = vec![ALL_STAKE_ACCOUNTS.into()];
+    stake_accounts.extend(addresses.iter().map(|address| SharedString::from(*address)));
+
+    // Fall back to every account when the selection belongs to another wallet
+    let mut selected = reward_manager.get_selected_stake().to_string();
+    if !addresses.contains(selected.as_str()) {
+        selected = ALL_STAKE_ACCOUNTS.to_string();
+    }
+    let rewards: Vec<StakeReward> = rewards
+        .into_iter()
+        .filter(|reward| selected == ALL_STAKE_ACCOUNTS || reward.stake_address == selected)
+        .collect();
+
+    let validators: Vec<ValidatorApyItem> = validator_apy(&rewards)
+        .iter()
+        .map(|validator| ValidatorApyItem {
+            validator: validator.vote_account.clone().into(),
+            apy: format!("{:.2}%", validator.apy * 100.0).into(),
+            epochs: validator.epochs as i32,
+        })
+        .collect();
+
+    reward_manager.set_selected_stake(selected.into());
+    reward_manager.set_stake_accounts(ModelRc::from(Rc::new(VecModel::from(stake_accounts))));
+    reward_manager.set_bars(ModelRc::from(Rc::new(VecModel::from(reward_bars(
+        &rewards,
+    )))));
+    reward_manager.set_validators(ModelRc::from(Rc::new(VecModel::from(validators))));
+    reward_manager.set_total(total_display(&rewards).into());
+}
+
+fn reward_bars(rewards: &[StakeReward]) -> Vec<RewardBar> {
+    let mut epochs: BTreeMap<u64, u64> = BTreeMap::new();
+    for reward in rewards {
+        *epochs.entry(reward.epoch).or_default() += reward.amount;
+    }
+    let recent: Vec<(u64, u64)> = epochs.into_iter().rev().take(CHART_EPOCHS).rev().collect();
+    let highest = recent
+        .iter()
+        .map(|(_, amount)| *amount)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    recent
+        .into_iter()
+        .map(|(epoch, amount)| RewardBar {
+            epoch: epoch as i32,
+            amount: base_units_to_ui_amount(amount, SOL_DECIMALS).into(),
+            ratio: amount as f32 / highest as f32,
+        })
+        .collect()
+}
+
+fn total_display(rewards: &[StakeReward]) -> String {
+    if rewards.is_empty() {
+        return String::new();
+    }
+    let lamports: u64 = rewards.iter().map(|reward| reward.amount).sum();
+    let fiat: f64 = rewards.iter().filter_map(StakeReward::fiat_value).sum();
+    let unpriced = rewards
+        .iter()
+        .filter(|reward| reward.sol_price.is_none())
+        .count();
+
+    let mut total = format!(
+        "{} SOL earned over {} rewards, ${:.2} when paid",
+        base_units_to_ui_amount(lamports, SOL_DECIMALS),
+        rewards.len(),
+        fiat
+    );
+    if unpriced > 0 {
+        total.push_str(&format!(" ({} not yet priced)", unpriced));
+    }
+    total
+}
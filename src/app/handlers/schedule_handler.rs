@@ -0,0 +1,297 @@
+use crate::app::errors::AppError;
+use crate::app::handlers::rpc_client;
+use crate::database::schedule::{Frequency, ScheduledRun, ScheduledTransfer};
+use crate::services::schedule_service::{schedule_transfer, ScheduleService, MISSED_GRACE_SECS};
+use crate::slint_generatedApp::{
+    AccountManager, App as SlintApp, ScheduleItem, ScheduleManager,
+    ScheduleRequest as SlintScheduleRequest, ScheduleRunItem,
+};
+use crate::time::{format_time, DATE_TIME_FORMAT};
+use chrono::{Local, NaiveDateTime, TimeZone};
+use rusqlite::Connection;
+use slint::{ComponentHandle, ModelRc, SharedString, VecModel, Weak};
+use std::{
+    rc::Rc,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+// How often the scheduler looks for due transfers
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct ScheduleHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl ScheduleHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        ScheduleHandler { app_instance, conn }
+    }
+
+    pub fn run(&self) {
+        self.record_missed_runs();
+        self.refresh_handler();
+        self.create_schedule_handler();
+        self.cancel_schedule_handler();
+        self.send_missed_handler();
+        load_schedules(&self.app_instance, self.conn.clone());
+        start_scheduler(self.app_instance.as_weak(), self.conn.clone());
+    }
+
+    fn record_missed_runs(&self) {
+        let service = ScheduleService::new(self.conn.clone(), rpc_client());
+        match service.record_missed(now() - MISSED_GRACE_SECS) {
+            Ok(missed) => self
+                .app_instance
+                .global::<ScheduleManager>()
+                .set_missed_count(missed as i32),
+            Err(e) => eprintln!("Error recording missed scheduled transfers: {}", e),
+        }
+    }
+
+    fn refresh_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<ScheduleManager>()
+            .on_refresh(move || {
+                let app = weak_app.unwrap();
+                load_schedules(&app, conn.clone());
+            });
+    }
+
+    fn create_schedule_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<ScheduleManager>()
+            .on_create_schedule(move |request| {
+                let app = weak_app.unwrap();
+                let schedule_manager = app.global::<ScheduleManager>();
+                reset_messages(&schedule_manager);
+
+                let account_id = app.global::<AccountManager>().get_selected_account().id;
+                let result =
+                    scheduled_transfer_from_request(account_id, &request).and_then(|schedule| {
+                        Ok(ScheduleService::new(conn.clone(), rpc_client())
+                            .create(&schedule, now())?)
+                    });
+
+                match result {
+                    Ok(schedule) => {
+                        schedule_manager.set_status(
+                            format!("Scheduled for {}", format_time(schedule.start_at)).into(),
+                        );
+                        load_schedules(&app, conn.clone());
+                    }
+                    Err(e) => schedule_manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+
+    fn cancel_schedule_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<ScheduleManager>()
+            .on_cancel_schedule(move |schedule_id| {
+                let app = weak_app.unwrap();
+                let schedule_manager = app.global::<ScheduleManager>();
+                reset_messages(&schedule_manager);
+
+                match ScheduleService::new(conn.clone(), rpc_client()).cancel(schedule_id) {
+                    Ok(_) => load_schedules(&app, conn.clone()),
+                    Err(e) => schedule_manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+
+    fn send_missed_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<ScheduleManager>()
+            .on_send_missed(move |run_id| {
+                let app = weak_app.unwrap();
+                let schedule_manager = app.global::<ScheduleManager>();
+                if schedule_manager.get_busy() {
+                    return;
+                }
+                reset_messages(&schedule_manager);
+                schedule_manager.set_busy(true);
+
+                let weak_app = weak_app.clone();
+                let conn = conn.clone();
+                thread::spawn(move || {
+                    let result = ScheduleService::new(conn.clone(), rpc_client())
+                        .send_missed(run_id, now())
+                        .map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let schedule_manager = app.global::<ScheduleManager>();
+                        schedule_manager.set_busy(false);
+                        match result {
+                            Ok(run) => {
+                                schedule_manager.set_missed_count(
+                                    (schedule_manager.get_missed_count() - 1).max(0),
+                                );
+                                set_run_status(&schedule_manager, &run);
+                                load_schedules(&app, conn);
+                            }
+                            Err(e) => schedule_manager.set_error(e.into()),
+                        }
+                    });
+                });
+            });
+    }
+}
+
+// Sends due transfers while the app is open, until the window closes
+fn start_scheduler(weak_app: Weak<SlintApp>, conn: Arc<Mutex<Connection>>) {
+    thread::spawn(move || loop {
+        let service = ScheduleService::new(conn.clone(), rpc_client());
+        let missed = match service.take_due(now()) {
+            Ok((due, missed)) => {
+                for (schedule, scheduled_for) in due {
+                    if let Err(e) = service.run(&schedule, scheduled_for, now()) {
+                        eprintln!("Error recording scheduled transfer: {}", e);
+                    }
+                }
+                missed
+            }
+            Err(e) => {
+                eprintln!("Error loading scheduled transfers: {}", e);
+                0
+            }
+        };
+
+        let conn = conn.clone();
+        let refreshed = weak_app.upgrade_in_event_loop(move |app| {
+            let schedule_manager = app.global::<ScheduleManager>();
+            schedule_manager.set_missed_count(schedule_manager.get_missed_count() + missed as i32);
+            load_schedules(&app, conn);
+        });
+        if refreshed.is_err() {
+            break;
+        }
+        thread::sleep(SCHEDULER_INTERVAL);
+    });
+}
+
+fn now() -> i64 {
+    Local::now().timestamp()
+}
+
+fn reset_messages(schedule_manager: &ScheduleManager) {
+    schedule_manager.set_error(SharedString::new());
+    schedule_manager.set_status(SharedString::new());
+}
+
+fn set_run_status(schedule_manager: &ScheduleManager, run: &ScheduledRun) {
+    let status = match &run.signature {
+        Some(signature) => format!("Sent: {}", signature),
+        None => format!("Failed: {}", run.error.clone().unwrap_or_default()),
+    };
+    schedule_manager.set_status(status.into());
+}
+
+fn load_schedules(app: &SlintApp, conn: Arc<Mutex<Connection>>) {
+    let schedule_manager = app.global::<ScheduleManager>();
+    let account_id = app.global::<AccountManager>().get_selected_account().id;
+    let service = ScheduleService::new(conn, rpc_client());
+
+    let result = (|| -> Result<(Vec<ScheduleItem>, Vec<ScheduleRunItem>), AppError> {
+        let schedules = service
+            .get_schedules(account_id)?
+            .iter()
+            .map(schedule_item_builder)
+            .collect();
+        let runs = service
+            .get_runs(account_id)?
+            .iter()
+            .map(|(run, schedule)| schedule_run_item_builder(run, schedule))
+            .collect();
+        Ok((schedules, runs))
+    })();
+
+    match result {
+        Ok((schedules, runs)) => {
+            schedule_manager.set_schedules(ModelRc::from(Rc::new(VecModel::from(schedules))));
+            schedule_manager.set_runs(ModelRc::from(Rc::new(VecModel::from(runs))));
+        }
+        Err(e) => schedule_manager.set_error(e.to_string().into()),
+    }
+}
+
+fn token_display(token: &str) -> SharedString {
+    if token.is_empty() {
+        "SOL".into()
+    } else {
+        token.into()
+    }
+}
+
+fn schedule_item_builder(schedule: &ScheduledTransfer) -> ScheduleItem {
+    ScheduleItem {
+        id: schedule.id.unwrap_or_default(),
+        recipient: schedule.recipient.clone().into(),
+        amount: schedule.amount.clone().into(),
+        token: token_display(&schedule.token),
+        memo: schedule.memo.clone().into(),
+        frequency: schedule.frequency.as_str().into(),
+        next_run: schedule
+            .next_run_at
+            .map(format_time)
+            .unwrap_or_default()
+            .into(),
+    }
+}
+
+fn schedule_run_item_builder(run: &ScheduledRun, schedule: &ScheduledTransfer) -> ScheduleRunItem {
+    ScheduleRunItem {
+        id: run.id.unwrap_or_default(),
+        recipient: schedule.recipient.clone().into(),
+        amount: schedule.amount.clone().into(),
+        token: token_display(&schedule.token),
+        scheduled_for: format_time(run.scheduled_for).into(),
+        status: run.status.as_str().into(),
+        signature: run.signature.clone().unwrap_or_default().into(),
+        error: run.error.clone().unwrap_or_default().into(),
+    }
+}
+
+fn scheduled_transfer_from_request(
+    account_id: i32,
+    request: &SlintScheduleRequest,
+) -> Result<ScheduledTransfer, AppError> {
+    let frequency = Frequency::from_db(&request.frequency.to_lowercase()).ok_or_else(|| {
+        AppError::InvalidInput(format!("Unknown frequency {}", request.frequency))
+    })?;
+    let start =
+        NaiveDateTime::parse_from_str(request.start.trim(), DATE_TIME_FORMAT).map_err(|_| {
+            AppError::InvalidInput("Enter the first run as YYYY-MM-DD HH:MM".to_string())
+        })?;
+    let start_at = Local
+        .from_local_datetime(&start)
+        .earliest()
+        .ok_or_else(|| AppError::InvalidInput(format!("{} does not exist locally", start)))?
+        .timestamp();
+
+    let schedule = ScheduledTransfer {
+        id: None,
+        account_id,
+        recipient: request.recipient.trim().to_string(),
+        amount: request.amount.trim().to_string(),
+        token: request.token.trim().to_string(),
+        memo: request.memo.to_string(),
+        frequency,
+        start_at,
+        occurrence: 0,
+        next_run_at: Some(start_at),
+        active: true,
+    };
+    schedule_transfer(&schedule)?;
+    Ok(schedule)
+}
@@ -0,0 +1,466 @@
+use crate::amount::validate_ui_amount;
+use crate::app::errors::AppError;
+use crate::app::handlers::{rpc_client, spawn_account_work};
+use crate::database::account::Account;
+use crate::programs::decoder::{describe_instructions, describe_transaction};
+use crate::services::{
+    account_service::AccountService,
+    memo_service::MemoService,
+    metadata_service::MetadataService,
+    registry_service::RegistryService,
+    risk_service::RiskService,
+    transaction_service::{
+        check_merchant_transaction, sign_partial, SimulationSummary, TransactionService,
+    },
+    transfer_service::{Transfer, TransferService},
+};
+use crate::slint_generatedApp::{
+    AccountManager, App as SlintApp, PaymentReview, SendManager, SendRequest as SlintSendRequest,
+};
+use crate::solana_pay::{
+    transaction_request::TransactionRequestClient,
+    uri::{SolanaPayUri, TransactionRequest, TransferRequest},
+};
+use rusqlite::Connection;
+use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel, Weak};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::VersionedTransaction;
+use std::{
+    rc::Rc,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+type PendingTransaction = Arc<Mutex<Option<VersionedTransaction>>>;
+
+// What confirming a review sends
+enum Payment {
+    /// Received from a Solana Pay transaction request, still to be signed by the wallet
+    Transaction(VersionedTransaction),
+    Transfer(Transfer),
+}
+
+pub struct SendHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+    // Transaction received from a Solana Pay transaction request, awaiting confirmation
+    pending_transaction: PendingTransaction,
+}
+
+impl SendHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        SendHandler {
+            app_instance,
+            conn,
+            pending_transaction: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn run(&self) {
+        self.open_payment_uri_handler();
+        self.review_send_handler();
+        self.confirm_send_handler();
+        self.cancel_send_handler();
+        self.mark_memo_required_handler();
+    }
+
+    fn open_payment_uri_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        let pending_transaction = self.pending_transaction.clone();
+        self.app_instance
+            .global::<SendManager>()
+            .on_open_payment_uri(move |uri| {
+                let app = weak_app.unwrap();
+                let send_manager = app.global::<SendManager>();
+                reset_messages(&send_manager);
+
+                match SolanaPayUri::parse(&uri) {
+                    Ok(SolanaPayUri::Transfer(request)) => {
+                        let request = send_request_builder(&request);
+                        // The amount may still be missing, in which case there is nothing to decode yet
+                        if transfer_from_send_request(&request).is_ok() {
+                            review_transfer(weak_app.clone(), conn.clone(), request);
+                            return;
+                        }
+                        show_memo_warning(&send_manager, conn.clone(), &request);
+                        send_manager.set_request(request);
+                        send_manager.set_review(PaymentReview::default());
+                        send_manager.set_acknowledged(false);
+                        send_manager.set_reviewing(true);
+                    }
+                    Ok(SolanaPayUri::Transaction(request)) => {
+                        send_manager.set_busy(true);
+                        load_transaction_request(
+                            conn.clone(),
+                            weak_app.clone(),
+                            pending_transaction.clone(),
+                            request,
+                        );
+                    }
+                    Err(e) => send_manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+
+    fn review_send_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<SendManager>()
+            .on_review_send(move |request| {
+                review_transfer(weak_app.clone(), conn.clone(), request);
+            });
+    }
+
+    fn confirm_send_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        let pending_transaction = self.pending_transaction.clone();
+        self.app_instance
+            .global::<SendManager>()
+            .on_confirm_send(move || {
+                let app = weak_app.unwrap();
+                let send_manager = app.global::<SendManager>();
+                if send_manager.get_busy() {
+                    return;
+                }
+                reset_messages(&send_manager);
+
+                if send_manager.get_review().warnings.row_count() > 0
+                    && !send_manager.get_acknowledged()
+                {
+                    send_manager.set_error("Confirm the warnings before signing".into());
+                    return;
+                }
+                let pending = pending_transaction.lock().unwrap().clone();
+                let payment = match pending {
+                    Some(transaction) => Payment::Transaction(transaction),
+                    None => match transfer_from_send_request(&send_manager.get_request()) {
+                        Ok(transfer) => Payment::Transfer(transfer),
+                        Err(e) => {
+                            send_manager.set_error(e.to_string().into());
+                            return;
+                        }
+                    },
+                };
+
+                let pending_transaction = pending_transaction.clone();
+                spawn_account_work::<SendManager, _, _>(
+                    weak_app.clone(),
+                    conn.clone(),
+                    rpc_client,
+                    move |client, account| match payment {
+                        Payment::Transaction(mut transaction) => {
+                            sign_partial(&mut transaction, &account.account_keypair()?)?;
+                            TransactionService::new(client.clone()).send(&transaction)
+                        }
+                        Payment::Transfer(transfer) => {
+                            TransferService::new(client.clone()).send(account, &transfer)
+                        }
+                    },
+                    move |app, signature| {
+                        let send_manager = app.global::<SendManager>();
+                        *pending_transaction.lock().unwrap() = None;
+                        send_manager.set_request(SlintSendRequest::default());
+                        send_manager.set_memo_warning(SharedString::new());
+                        send_manager.set_reviewing(false);
+                        send_manager.set_status(format!("Sent: {}", signature).into());
+                    },
+                );
+            });
+    }
+
+    fn mark_memo_required_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<SendManager>()
+            .on_mark_memo_required(move |required| {
+                let app = weak_app.unwrap();
+                let send_manager = app.global::<SendManager>();
+                let request = send_manager.get_request();
+                let Ok(recipient) = Pubkey::from_str(request.recipient.trim()) else {
+                    return;
+                };
+                let label = match request.label.trim() {
+                    "" => "This recipient",
+                    label => label,
+                };
+                if let Err(e) =
+                    MemoService::new(conn.clone()).set_required(&recipient, label, required)
+                {
+                    send_manager.set_error(e.to_string().into());
+                }
+                show_memo_warning(&send_manager, conn.clone(), &request);
+            });
+    }
+
+    fn cancel_send_handler(&self) {
+        let weak_app = self.app_instance.as_weak();
+        let pending_transaction = self.pending_transaction.clone();
+        self.app_instance
+            .global::<SendManager>()
+            .on_cancel_send(move || {
+                let app = weak_app.unwrap();
+                let send_manager = app.global::<SendManager>();
+                *pending_transaction.lock().unwrap() = None;
+                reset_messages(&send_manager);
+                send_manager.set_memo_warning(SharedString::new());
+                send_manager.set_review(PaymentReview::default());
+                send_manager.set_reviewing(false);
+            });
+    }
+}
+
+// Fetches the merchant's transaction and simulates it off the UI thread, then shows it
+// for review once it checks out
+fn load_transaction_request(
+    conn: Arc<Mutex<Connection>>,
+    weak_app: Weak<SlintApp>,
+    pending_transaction: PendingTransaction,
+    request: TransactionRequest,
+) {
+    let app = weak_app.unwrap();
+    let owned = owned_accounts(conn.clone());
+    let account = selected_account(&app, conn)
+        .ok()
+        .and_then(|account| account.pubkey().ok());
+
+    thread::spawn(move || {
+        let result = (|| -> Result<_, AppError> {
+            let account_pubkey = account.ok_or(AppError::NoAccountSelected)?;
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| AppError::Other(Box::new(e)))?;
+            let client = TransactionRequestClient::new();
+            let (metadata, response) = runtime.block_on(async {
+                let metadata = client.fetch_metadata(&request.link).await?;
+                let response = client
+                    .fetch_transaction(&request.link, &account_pubkey)
+                    .await?;
+                Ok::<_, AppError>((metadata, response))
+            })?;
+            let transaction = response.decode_transaction()?;
+            check_merchant_transaction(&transaction, &account_pubkey)?;
+            let simulation = TransactionService::new(rpc_client()).simulate(&transaction)?;
+            let warnings =
+                RiskService::new(rpc_client()).check_transaction(&transaction, &owned)?;
+            Ok((
+                metadata,
+                response.message,
+                transaction,
+                simulation,
+                warnings,
+            ))
+        })()
+        .map_err(|e| e.to_string());
+
+        let _ = weak_app.upgrade_in_event_loop(move |app| {
+            let send_manager = app.global::<SendManager>();
+            send_manager.set_busy(false);
+
+            match result {
+                Ok((metadata, message, transaction, simulation, warnings)) => {
+                    let instructions = describe_transaction(&transaction, &[]).join("\n");
+                    *pending_transaction.lock().unwrap() = Some(transaction);
+                    send_manager.set_memo_warning(SharedString::new());
+                    send_manager.set_request(SlintSendRequest {
+                        message: message.unwrap_or_default().into(),
+                        ..SlintSendRequest::default()
+                    });
+                    send_manager.set_review(PaymentReview {
+                        is_transaction_request: true,
+                        merchant_label: metadata.label.into(),
+                        merchant_icon: metadata.icon.into(),
+                        simulation: simulation_display(&simulation),
+                        instructions: instructions.into(),
+                        warnings: shared_strings(warnings),
+                    });
+                    send_manager.set_acknowledged(false);
+                    send_manager.set_reviewing(true);
+                }
+                Err(e) => send_manager.set_error(e.into()),
+            }
+        });
+    });
+}
+
+fn selected_account(app: &SlintApp, conn: Arc<Mutex<Connection>>) -> Result<Account, AppError> {
+    let account_id = app.global::<AccountManager>().get_selected_account().id;
+    AccountService::new(conn)
+        .get_account_by_id(account_id)?
+        .ok_or(AppError::NoAccountSelected)
+}
+
+// Reviews a plain transfer off the UI thread, listing the instructions that will be signed
+// along with anything risky about them
+fn review_transfer(
+    weak_app: Weak<SlintApp>,
+    conn: Arc<Mutex<Connection>>,
+    request: SlintSendRequest,
+) {
+    let app = weak_app.unwrap();
+    let send_manager = app.global::<SendManager>();
+    if send_manager.get_busy() {
+        return;
+    }
+    reset_messages(&send_manager);
+    let transfer = match transfer_from_send_request(&request) {
+        Ok(transfer) => transfer,
+        Err(e) => {
+            send_manager.set_error(e.to_string().into());
+            return;
+        }
+    };
+    send_manager.set_request(request);
+
+    let owned = owned_accounts(conn.clone());
+    let impersonation = transfer
+        .mint
+        .and_then(|mint| impersonation_warning(conn.clone(), &mint));
+    let memo_conn = conn.clone();
+    spawn_account_work::<SendManager, _, _>(
+        weak_app,
+        conn,
+        rpc_client,
+        move |client, account| {
+            let instructions =
+                TransferService::new(client.clone()).instructions(&account.pubkey()?, &transfer)?;
+            let mut warnings =
+                RiskService::new(client.clone()).check_instructions(&instructions, &owned)?;
+            warnings.extend(impersonation);
+            Ok((describe_instructions(&instructions).join("\n"), warnings))
+        },
+        move |app, (instructions, warnings)| {
+            let send_manager = app.global::<SendManager>();
+            show_memo_warning(&send_manager, memo_conn, &send_manager.get_request());
+            send_manager.set_review(PaymentReview {
+                instructions: instructions.into(),
+                warnings: shared_strings(warnings),
+                ..PaymentReview::default()
+            });
+            send_manager.set_acknowledged(false);
+            send_manager.set_reviewing(true);
+        },
+    );
+}
+
+// Held tokens have their metadata cached by the token list, which is enough to catch a copied symbol
+fn impersonation_warning(conn: Arc<Mutex<Connection>>, mint: &Pubkey) -> Option<String> {
+    let mint = mint.to_string();
+    let symbol = MetadataService::new(conn.clone(), rpc_client())
+        .cached(std::slice::from_ref(&mint))
+        .ok()?
+        .remove(&mint)?
+        .symbol;
+    let verdict = RegistryService::new(conn)
+        .verdicts(&[(mint.clone(), symbol)])
+        .ok()?
+        .remove(&mint)?;
+    verdict.impersonates.map(|symbol| {
+        format!(
+            "{} uses the symbol {} of a verified token but is a different mint. It may be an impersonator",
+            mint, symbol
+        )
+    })
+}
+
+// Every account in the wallet, so moving funds between them is not treated as risky
+fn owned_accounts(conn: Arc<Mutex<Connection>>) -> Vec<Pubkey> {
+    AccountService::new(conn)
+        .get_all_accounts()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|account| account.pubkey().ok())
+        .collect()
+}
+
+fn shared_strings(lines: Vec<String>) -> ModelRc<SharedString> {
+    let lines: Vec<SharedString> = lines.into_iter().map(SharedString::from).collect();
+    ModelRc::from(Rc::new(VecModel::from(lines)))
+}
+
+fn reset_messages(send_manager: &SendManager) {
+    send_manager.set_error(SharedString::new());
+    send_manager.set_status(SharedString::new());
+}
+
+fn simulation_display(simulation: &SimulationSummary) -> SharedString {
+    if simulation.succeeded() {
+        return format!(
+            "Succeeded using {} compute units",
+            simulation.units_consumed.unwrap_or_default()
+        )
+        .into();
+    }
+
+    let error = simulation.error.clone().unwrap_or_default();
+    match simulation.logs.last() {
+        Some(log) => format!("Failed: {} ({})", error, log).into(),
+        None => format!("Failed: {}", error).into(),
+    }
+}
+
+// Warn before sending without a memo to a deposit address that needs one
+fn show_memo_warning(
+    send_manager: &SendManager,
+    conn: Arc<Mutex<Connection>>,
+    request: &SlintSendRequest,
+) {
+    let service = MemoService::new(conn);
+    let recipient = Pubkey::from_str(request.recipient.trim()).ok();
+    let required = recipient
+        .and_then(|recipient| service.required_label(&recipient).ok().flatten())
+        .is_some();
+    let warning = recipient
+        .and_then(|recipient| service.warning(&recipient, &request.memo).ok().flatten())
+        .unwrap_or_default();
+    send_manager.set_memo_required(required);
+    send_manager.set_memo_warning(warning.into());
+}
+
+fn send_request_builder(request: &TransferRequest) -> SlintSendRequest {
+    let references: Vec<SharedString> = request
+        .references
+        .iter()
+        .map(|reference| SharedString::from(reference.to_string()))
+        .collect();
+
+    SlintSendRequest {
+        recipient: request.recipient.to_string().into(),
+        amount: request.amount.clone().unwrap_or_default().into(),
+        token: request
+            .spl_token
+            .map(|mint| mint.to_string())
+            .unwrap_or_default()
+            .into(),
+        memo: request.memo.clone().unwrap_or_default().into(),
+        label: request.label.clone().unwrap_or_default().into(),
+        message: request.message.clone().unwrap_or_default().into(),
+        references: ModelRc::from(Rc::new(VecModel::from(references))),
+    }
+}
+
+fn transfer_from_send_request(request: &SlintSendRequest) -> Result<Transfer, AppError> {
+    validate_ui_amount(&request.amount)?;
+    let mint = match request.token.trim() {
+        "" => None,
+        token => Some(Pubkey::from_str(token)?),
+    };
+    let references = request
+        .references
+        .iter()
+        .map(|reference| Pubkey::from_str(&reference))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Transfer {
+        recipient: Pubkey::from_str(request.recipient.trim())?,
+        amount: request.amount.to_string(),
+        mint,
+        memo: Some(request.memo.to_string()).filter(|memo| !memo.is_empty()),
+        references,
+    })
+}
@@ -0,0 +1,321 @@
+use crate::app::errors::AppError;
+use crate::app::handlers::rpc_client;
+use crate::database::account::Account;
+use crate::programs::decoder::describe_transaction;
+use crate::services::{
+    account_service::AccountService,
+    risk_service::RiskService,
+    transaction_service::{
+        read_transaction_file, required_signers, sign_partial, uses_durable_nonce,
+        write_transaction_file, RequiredSigner, SignatureState, TransactionService,
+    },
+};
+use crate::slint_generatedApp::{App as SlintApp, SharedTransactionManager, SignerItem};
+use rusqlite::Connection;
+use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::VersionedTransaction;
+use std::{
+    cell::RefCell,
+    path::Path,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+type SharedTransaction = Rc<RefCell<Option<VersionedTransaction>>>;
+
+pub struct SharedTransactionHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+    // Imported transaction with the signatures collected so far
+    transaction: SharedTransaction,
+}
+
+impl SharedTransactionHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        SharedTransactionHandler {
+            app_instance,
+            conn,
+            transaction: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    pub fn run(&self) {
+        self.import_handler();
+        self.export_handler();
+        self.sign_handler();
+        self.broadcast_handler();
+        self.clear_handler();
+    }
+
+    fn import_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        let shared_transaction = self.transaction.clone();
+        self.app_instance
+            .global::<SharedTransactionManager>()
+            .on_import(move |path| {
+                let app = weak_app.unwrap();
+                let manager = app.global::<SharedTransactionManager>();
+                reset_messages(&app);
+
+                let result = (|| -> Result<VersionedTransaction, AppError> {
+                    let path = path.trim();
+                    if path.is_empty() {
+                        return Err(AppError::InvalidInput(
+                            "Enter the path of the transaction file".to_string(),
+                        ));
+                    }
+                    Ok(read_transaction_file(Path::new(path))?)
+                })();
+
+                match result {
+                    Ok(transaction) => {
+                        shared_transaction.replace(Some(transaction.clone()));
+                        manager.set_acknowledged(false);
+                        show_transaction(&app, conn.clone(), &shared_transaction);
+                        check_risks(&app, conn.clone(), transaction);
+                        manager.set_status("Imported".into());
+                    }
+                    Err(e) => manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+
+    fn export_handler(&self) {
+        let weak_app = self.app_instance.as_weak();
+        let shared_transaction = self.transaction.clone();
+        self.app_instance
+            .global::<SharedTransactionManager>()
+            .on_export(move |path| {
+                let app = weak_app.unwrap();
+                let manager = app.global::<SharedTransactionManager>();
+                reset_messages(&app);
+
+                let Some(transaction) = shared_transaction.borrow().clone() else {
+                    return;
+                };
+                let path = path.trim().to_string();
+                match write_transaction_file(Path::new(&path), &transaction) {
+                    Ok(_) => manager.set_status(format!("Exported to {}", path).into()),
+                    Err(e) => manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+
+    fn sign_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        let shared_transaction = self.transaction.clone();
+        self.app_instance
+            .global::<SharedTransactionManager>()
+            .on_sign(move || {
+                let app = weak_app.unwrap();
+                let manager = app.global::<SharedTransactionManager>();
+                reset_messages(&app);
+
+                let Some(mut transaction) = shared_transaction.borrow().clone() else {
+                    return;
+                };
+                if manager.get_checking() {
+                    manager.set_error("Still checking this transaction for risks".into());
+                    return;
+                }
+                if manager.get_warnings().row_count() > 0 && !manager.get_acknowledged() {
+                    manager.set_error("Confirm the warnings before signing".into());
+                    return;
+                }
+                let result = (|| -> Result<Vec<String>, AppError> {
+                    let accounts = AccountService::new(conn.clone()).get_all_accounts()?;
+                    let mut signed_by = vec![];
+                    for signer in required_signers(&transaction) {
+                        if signer.state == SignatureState::Signed {
+                            continue;
+                        }
+                        if let Some(account) = local_account(&accounts, &signer) {
+                            sign_partial(&mut transaction, &account.account_keypair()?)?;
+                            signed_by.push(account.name.clone());
+                        }
+                    }
+                    Ok(signed_by)
+                })();
+
+                match result {
+                    Ok(signed_by) if signed_by.is_empty() => manager.set_error(
+                        "None of the missing signers are accounts in this wallet".into(),
+                    ),
+                    Ok(signed_by) => {
+                        shared_transaction.replace(Some(transaction));
+                        show_transaction(&app, conn.clone(), &shared_transaction);
+                        manager.set_status(format!("Signed with {}", signed_by.join(", ")).into());
+                    }
+                    Err(e) => manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+
+    fn broadcast_handler(&self) {
+        let weak_app = self.app_instance.as_weak();
+        let shared_transaction = self.transaction.clone();
+        self.app_instance
+            .global::<SharedTransactionManager>()
+            .on_broadcast(move || {
+                let app = weak_app.unwrap();
+                let manager = app.global::<SharedTransactionManager>();
+                if manager.get_busy() || manager.get_checking() {
+                    return;
+                }
+                reset_messages(&app);
+
+                let Some(transaction) = shared_transaction.borrow().clone() else {
+                    return;
+                };
+                let missing: Vec<String> = required_signers(&transaction)
+                    .iter()
+                    .filter(|signer| signer.state != SignatureState::Signed)
+                    .map(|signer| signer.pubkey.to_string())
+                    .collect();
+                if !missing.is_empty() {
+                    manager.set_error(format!("Still waiting for {}", missing.join(", ")).into());
+                    return;
+                }
+
+                manager.set_busy(true);
+                let weak_app = weak_app.clone();
+                thread::spawn(move || {
+                    let result = TransactionService::new(rpc_client())
+                        .send(&transaction)
+                        .map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let manager = app.global::<SharedTransactionManager>();
+                        manager.set_busy(false);
+                        match result {
+                            Ok(signature) => {
+                                manager.set_status(format!("Sent: {}", signature).into())
+                            }
+                            Err(e) => manager.set_error(e.into()),
+                        }
+                    });
+                });
+            });
+    }
+
+    fn clear_handler(&self) {
+        let weak_app = self.app_instance.as_weak();
+        let shared_transaction = self.transaction.clone();
+        self.app_instance
+            .global::<SharedTransactionManager>()
+            .on_clear(move || {
+                let app = weak_app.unwrap();
+                let manager = app.global::<SharedTransactionManager>();
+                shared_transaction.replace(None);
+                reset_messages(&app);
+                manager.set_signers(ModelRc::default());
+                manager.set_summary(SharedString::new());
+                manager.set_instructions(SharedString::new());
+                manager.set_warnings(ModelRc::default());
+                manager.set_acknowledged(false);
+                manager.set_checking(false);
+                manager.set_complete(false);
+                manager.set_loaded(false);
+            });
+    }
+}
+
+fn reset_messages(app: &SlintApp) {
+    let manager = app.global::<SharedTransactionManager>();
+    manager.set_error(SharedString::new());
+    manager.set_status(SharedString::new());
+}
+
+fn local_account<'a>(accounts: &'a [Account], signer: &RequiredSigner) -> Option<&'a Account> {
+    let pubkey = signer.pubkey.to_string();
+    accounts.iter().find(|account| account.pubkey == pubkey)
+}
+
+fn show_transaction(
+    app: &SlintApp,
+    conn: Arc<Mutex<Connection>>,
+    shared_transaction: &SharedTransaction,
+) {
+    let manager = app.global::<SharedTransactionManager>();
+    let Some(transaction) = shared_transaction.borrow().clone() else {
+        return;
+    };
+    // Signers still show without names if the accounts cannot be read
+    let accounts = AccountService::new(conn)
+        .get_all_accounts()
+        .unwrap_or_default();
+
+    let signers = required_signers(&transaction);
+    let signed = signers
+        .iter()
+        .filter(|signer| signer.state == SignatureState::Signed)
+        .count();
+    let items: Vec<SignerItem> = signers
+        .iter()
+        .enumerate()
+        .map(|(index, signer)| SignerItem {
+            pubkey: signer.pubkey.to_string().into(),
+            state: match signer.state {
+                SignatureState::Signed => "Signed",
+                SignatureState::Missing => "Missing",
+                SignatureState::Invalid => "Invalid signature",
+            }
+            .into(),
+            account_name: local_account(&accounts, signer)
+                .map(|account| account.name.clone())
+                .unwrap_or_default()
+                .into(),
+            fee_payer: index == 0,
+        })
+        .collect();
+
+    let lifetime = if uses_durable_nonce(&transaction) {
+        "Uses a durable nonce, so signing can take as long as needed"
+    } else {
+        "Uses a recent blockhash, so it must be fully signed and sent within about a minute of being created"
+    };
+    manager.set_summary(format!("{} of {} signatures. {}", signed, signers.len(), lifetime).into());
+    // Lookup tables are not resolved offline, so their addresses show as unknown
+    manager.set_instructions(describe_transaction(&transaction, &[]).join("\n").into());
+    manager.set_complete(signed == signers.len());
+    manager.set_signers(ModelRc::from(Rc::new(VecModel::from(items))));
+    manager.set_loaded(true);
+}
+
+// Checks the transaction for risks off the UI thread, since that simulates it and looks up the
+// accounts it touches. Signatures do not change the risks, so this runs once per import.
+fn check_risks(app: &SlintApp, conn: Arc<Mutex<Connection>>, transaction: VersionedTransaction) {
+    let manager = app.global::<SharedTransactionManager>();
+    let owned: Vec<Pubkey> = AccountService::new(conn)
+        .get_all_accounts()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|account| account.pubkey().ok())
+        .collect();
+    let instructions = manager.get_instructions();
+    manager.set_warnings(ModelRc::default());
+    manager.set_checking(true);
+
+    let weak_app = app.as_weak();
+    thread::spawn(move || {
+        let warnings = RiskService::new(rpc_client())
+            .check_transaction(&transaction, &owned)
+            .unwrap_or_else(|e| vec![format!("Could not check this transaction for risks: {}", e)]);
+
+        let _ = weak_app.upgrade_in_event_loop(move |app| {
+            let manager = app.global::<SharedTransactionManager>();
+            // The user may have cleared it or imported another meanwhile
+            if !manager.get_loaded() || manager.get_instructions() != instructions {
+                return;
+            }
+            let warnings: Vec<SharedString> =
+                warnings.into_iter().map(SharedString::from).collect();
+            manager.set_warnings(ModelRc::from(Rc::new(VecModel::from(warnings))));
+            manager.set_checking(false);
+        });
+    });
+}
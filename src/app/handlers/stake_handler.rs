@@ -0,0 +1,382 @@
+use crate::amount::{base_units_to_ui_amount, SOL_DECIMALS};
+use crate::app::errors::AppError;
+use crate::app::handlers::{is_selected, rpc_client, spawn_account_work};
+use crate::database::account::Account;
+use crate::services::errors::ServiceError;
+use crate::services::stake_service::{
+    ActivationState, EpochTiming, StakeAccount, StakeChange, StakeService,
+};
+use crate::slint_generatedApp::{
+    AccountManager, App as SlintApp, StakeChangeRequest, StakeItem, StakeManager,
+};
+use chrono::{Local, NaiveDate, TimeZone};
+use rusqlite::Connection;
+use slint::{ComponentHandle, ModelRc, SharedString, VecModel, Weak};
+use solana_sdk::pubkey::Pubkey;
+use solana_stake_interface::state::StakeAuthorize;
+use std::{
+    rc::Rc,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+pub struct StakeHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl StakeHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        StakeHandler { app_instance, conn }
+    }
+
+    pub fn run(&self) {
+        self.refresh_handler();
+        self.create_stake_handler();
+        self.delegate_handler();
+        self.deactivate_handler();
+        self.withdraw_handler();
+        self.review_change_handler();
+        self.confirm_change_handler();
+        self.cancel_change_handler();
+    }
+
+    fn refresh_handler(&self) {
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<StakeManager>()
+            .on_refresh(move || {
+                let app = weak_app.unwrap();
+                let stake_manager = app.global::<StakeManager>();
+                stake_manager.set_error(SharedString::new());
+
+                let pubkey = app.global::<AccountManager>().get_selected_account().pubkey;
+                let Ok(owner) = Pubkey::from_str(&pubkey) else {
+                    return;
+                };
+
+                stake_manager.set_loading(true);
+                let weak_app = weak_app.clone();
+                std::thread::spawn(move || {
+                    let service = StakeService::new(rpc_client());
+                    let result = service
+                        .epoch_timing()
+                        .and_then(|timing| Ok((timing, service.discover(&owner, timing.epoch)?)))
+                        .map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let stake_manager = app.global::<StakeManager>();
+                        stake_manager.set_loading(false);
+                        if !is_selected(&app, &pubkey) {
+                            return;
+                        }
+                        match result {
+                            Ok((timing, accounts)) => {
+                                let items: Vec<StakeItem> = accounts
+                                    .iter()
+                                    .map(|stake| stake_item_builder(stake, &owner, &timing))
+                                    .collect();
+                                stake_manager.set_epoch(epoch_display(&timing).into());
+                                stake_manager
+                                    .set_items(ModelRc::from(Rc::new(VecModel::from(items))));
+                            }
+                            Err(e) => stake_manager.set_error(e.into()),
+                        }
+                    });
+                });
+            });
+    }
+
+    fn create_stake_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<StakeManager>()
+            .on_create_stake(move |amount, vote_account| {
+                let amount = amount.trim().to_string();
+                let vote_account = vote_account.trim().to_string();
+                run_action(weak_app.clone(), conn.clone(), move |service, account| {
+                    let vote_account = match vote_account.as_str() {
+                        "" => None,
+                        vote_account => Some(parse_pubkey(vote_account)?),
+                    };
+                    let (address, signature) =
+                        service.create(account, &amount, vote_account.as_ref())?;
+                    Ok(format!("Created {}: {}", address, signature))
+                });
+            });
+    }
+
+    fn delegate_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<StakeManager>()
+            .on_delegate(move |address, vote_account| {
+                let vote_account = vote_account.trim().to_string();
+                run_action(weak_app.clone(), conn.clone(), move |service, account| {
+                    let vote_account = parse_pubkey(&vote_account)?;
+                    let stake = current_stake(service, &address)?;
+                    let signature = service.delegate(account, &stake, &vote_account)?;
+                    Ok(format!("Delegated: {}", signature))
+                });
+            });
+    }
+
+    fn deactivate_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<StakeManager>()
+            .on_deactivate(move |address| {
+                run_action(weak_app.clone(), conn.clone(), move |service, account| {
+                    let stake = current_stake(service, &address)?;
+                    let signature = service.deactivate(account, &stake)?;
+                    Ok(format!("Deactivated: {}", signature))
+                });
+            });
+    }
+
+    fn withdraw_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<StakeManager>()
+            .on_withdraw(move |address, amount| {
+                let amount = amount.trim().to_string();
+                run_action(weak_app.clone(), conn.clone(), move |service, account| {
+                    let stake = current_stake(service, &address)?;
+                    let amount = Some(amount.as_str()).filter(|amount| !amount.is_empty());
+                    let signature = service.withdraw(account, &stake, amount)?;
+                    Ok(format!("Withdrawn: {}", signature))
+                });
+            });
+    }
+
+    fn review_change_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<StakeManager>()
+            .on_review_change(move |request| {
+                let app = weak_app.unwrap();
+                let change = match stake_change_from_request(&request) {
+                    Ok(change) => change,
+                    Err(e) => {
+                        app.global::<StakeManager>().set_error(e.to_string().into());
+                        return;
+                    }
+                };
+                let address = request.address.trim().to_string();
+                spawn_account_work::<StakeManager, _, _>(
+                    weak_app.clone(),
+                    conn.clone(),
+                    || StakeService::new(rpc_client()),
+                    move |service, account| {
+                        let stake = current_stake(service, &address)?;
+                        service.preview_change(account, &stake, &change)
+                    },
+                    move |app, consequences| {
+                        let consequences: Vec<SharedString> =
+                            consequences.into_iter().map(SharedString::from).collect();
+                        let stake_manager = app.global::<StakeManager>();
+                        stake_manager.set_change(request);
+                        stake_manager.set_change_review(ModelRc::from(Rc::new(VecModel::from(
+                            consequences,
+                        ))));
+                        stake_manager.set_reviewing_change(true);
+                    },
+                );
+            });
+    }
+
+    fn confirm_change_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<StakeManager>()
+            .on_confirm_change(move || {
+                let app = weak_app.unwrap();
+                let request = app.global::<StakeManager>().get_change();
+                let change = match stake_change_from_request(&request) {
+                    Ok(change) => change,
+                    Err(e) => {
+                        app.global::<StakeManager>().set_error(e.to_string().into());
+                        return;
+                    }
+                };
+                let address = request.address.trim().to_string();
+                let action = request.action.to_string();
+                // The change is planned again against the current on-chain state before sending
+                run_action(weak_app.clone(), conn.clone(), move |service, account| {
+                    let stake = current_stake(service, &address)?;
+                    let signature = service.apply_change(account, &stake, &change)?;
+                    Ok(format!("{}: {}", action, signature))
+                });
+            });
+    }
+
+    fn cancel_change_handler(&self) {
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<StakeManager>()
+            .on_cancel_change(move || {
+                let app = weak_app.unwrap();
+                let stake_manager = app.global::<StakeManager>();
+                stake_manager.set_reviewing_change(false);
+                stake_manager.set_change_review(ModelRc::default());
+                stake_manager.set_error(SharedString::new());
+            });
+    }
+}
+
+// Sends a stake transaction for the selected account, then reloads the list
+fn run_action<F>(weak_app: Weak<SlintApp>, conn: Arc<Mutex<Connection>>, action: F)
+where
+    F: FnOnce(&StakeService, &Account) -> Result<String, ServiceError> + Send + 'static,
+{
+    let service = || StakeService::new(rpc_client());
+    spawn_account_work::<StakeManager, _, _>(weak_app, conn, service, action, |app, status| {
+        let stake_manager = app.global::<StakeManager>();
+        stake_manager.set_reviewing_change(false);
+        stake_manager.set_status(status.into());
+        stake_manager.invoke_refresh();
+    });
+}
+
+fn stake_change_from_request(request: &StakeChangeRequest) -> Result<StakeChange, AppError> {
+    let counterpart = request.counterpart.trim();
+    let change = match request.action.as_str() {
+        "Split" => StakeChange::Split {
+            amount: request.amount.trim().to_string(),
+        },
+        "Merge" => StakeChange::Merge {
+            source: parse_address(counterpart)?,
+        },
+        "Change staker" => StakeChange::Authorize {
+            role: StakeAuthorize::Staker,
+            new_authority: parse_address(counterpart)?,
+        },
+        "Change withdrawer" => StakeChange::Authorize {
+            role: StakeAuthorize::Withdrawer,
+            new_authority: parse_address(counterpart)?,
+        },
+        "Set lockup" => StakeChange::SetLockup {
+            unix_timestamp: parse_lockup_date(request.lockup_date.trim())?,
+            epoch: match request.lockup_epoch.trim() {
+                "" => None,
+                epoch => Some(epoch.parse().map_err(|_| {
+                    AppError::InvalidInput(format!("{} is not an epoch number", epoch))
+                })?),
+            },
+            custodian: match counterpart {
+                "" => None,
+                custodian => Some(parse_address(custodian)?),
+            },
+        },
+        action => {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown stake action {}",
+                action
+            )))
+        }
+    };
+    Ok(change)
+}
+
+fn parse_address(address: &str) -> Result<Pubkey, AppError> {
+    Pubkey::from_str(address)
+        .map_err(|_| AppError::InvalidInput(format!("{} is not a valid address", address)))
+}
+
+// Lockups end at local midnight of the given day
+fn parse_lockup_date(date: &str) -> Result<Option<i64>, AppError> {
+    if date.is_empty() {
+        return Ok(None);
+    }
+    let midnight = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .ok_or_else(|| AppError::InvalidInput("Enter the lockup date as YYYY-MM-DD".to_string()))?;
+    let timestamp = Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .ok_or_else(|| AppError::InvalidInput(format!("{} does not exist locally", date)))?
+        .timestamp();
+    Ok(Some(timestamp))
+}
+
+fn parse_pubkey(address: &str) -> Result<Pubkey, ServiceError> {
+    Ok(Pubkey::from_str(address)?)
+}
+
+// Actions check the on-chain state rather than the possibly stale list
+fn current_stake(service: &StakeService, address: &str) -> Result<StakeAccount, ServiceError> {
+    let epoch = service.epoch_timing()?.epoch;
+    service.get_stake_account(&parse_pubkey(address)?, epoch)
+}
+
+fn sol(lamports: u64) -> String {
+    base_units_to_ui_amount(lamports, SOL_DECIMALS)
+}
+
+fn duration_display(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    let minutes = seconds % 3600 / 60;
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+fn epoch_display(timing: &EpochTiming) -> String {
+    format!(
+        "Epoch {} · next epoch in about {}",
+        timing.epoch,
+        duration_display(timing.seconds_remaining())
+    )
+}
+
+// Warm-up and cool-down progress at each epoch boundary, so only the next step can be timed
+fn activation_display(stake: &StakeAccount, timing: &EpochTiming) -> String {
+    let next = format!(
+        "epoch {} in about {}",
+        timing.epoch + 1,
+        duration_display(timing.seconds_remaining())
+    );
+    match stake.state {
+        ActivationState::Activating => format!(
+            "{} SOL warming up, next step at {}",
+            sol(stake.activation.activating),
+            next
+        ),
+        ActivationState::Deactivating => format!(
+            "{} SOL cooling down, next step at {}",
+            sol(stake.activation.deactivating),
+            next
+        ),
+        ActivationState::Active => format!("{} SOL earning", sol(stake.activation.effective)),
+        ActivationState::Inactive => String::new(),
+    }
+}
+
+fn stake_item_builder(stake: &StakeAccount, owner: &Pubkey, timing: &EpochTiming) -> StakeItem {
+    StakeItem {
+        address: stake.address.to_string().into(),
+        balance: sol(stake.lamports).into(),
+        state: stake.state.as_str().into(),
+        validator: stake
+            .delegation
+            .filter(|_| stake.state != ActivationState::Inactive)
+            .map(|delegation| delegation.voter_pubkey.to_string())
+            .unwrap_or_default()
+            .into(),
+        activation: activation_display(stake, timing).into(),
+        staker: stake.authorized.staker.to_string().into(),
+        withdrawer: stake.authorized.withdrawer.to_string().into(),
+        withdrawable: sol(stake.withdrawable()).into(),
+        is_staker: stake.authorized.staker == *owner,
+        is_withdrawer: stake.authorized.withdrawer == *owner,
+    }
+}
@@ -0,0 +1,475 @@
+use crate::app::handlers::{is_selected, rpc_client, spawn_account_work};
+use crate::database::{
+    token_balance::TokenBalance,
+    token_metadata::TokenMetadata,
+    token_registry::{TokenStatus, TokenVerdict},
+};
+use crate::programs::token::known_symbol;
+use crate::services::{
+    errors::ServiceError,
+    metadata_service::MetadataService,
+    portfolio_service::PortfolioService,
+    price_history_service::{ChartRange, PriceHistoryService},
+    price_service::{PriceService, Prices},
+    registry_service::RegistryService,
+    spam_service::{SpamService, SpamVerdict},
+    token_service::{OwnedTokenAccount, TokenService},
+};
+use crate::slint_generatedApp::{AccountManager, App as SlintApp, TokenItem, TokenManager};
+use crate::time::format_time;
+use chrono::Utc;
+use rusqlite::Connection;
+use slint::{ComponentHandle, Image, ModelRc, SharedString, VecModel};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::HashMap,
+    path::Path,
+    rc::Rc,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+// What the token list was last built from, so filtering does not fetch it again
+#[derive(Default)]
+struct Listing {
+    balances: Vec<TokenBalance>,
+    prices: HashMap<String, f64>,
+    /// When the prices were last fetched, if they could not be refreshed
+    prices_stale_since: Option<i64>,
+    metadata: HashMap<String, TokenMetadata>,
+}
+
+pub struct TokenHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+    listing: Arc<Mutex<Listing>>,
+}
+
+impl TokenHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        TokenHandler {
+            app_instance,
+            conn,
+            listing: Arc::new(Mutex::new(Listing::default())),
+        }
+    }
+
+    pub fn run(&self) {
+        self.refresh_handler();
+        self.filter_handler();
+        self.set_trusted_handler();
+        self.set_hidden_handler();
+        self.review_cleanup_handler();
+        self.confirm_cleanup_handler();
+        self.cancel_cleanup_handler();
+    }
+
+    fn refresh_handler(&self) {
+        let conn = self.conn.clone();
+        let listing = self.listing.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<TokenManager>()
+            .on_refresh(move || {
+                let app = weak_app.unwrap();
+                let token_manager = app.global::<TokenManager>();
+                token_manager.set_error(SharedString::new());
+
+                let pubkey = app.global::<AccountManager>().get_selected_account().pubkey;
+                let Ok(owner) = Pubkey::from_str(&pubkey) else {
+                    return;
+                };
+
+                // Cached balances show straight away, without prices until the refresh lands
+                let service = PortfolioService::new(conn.clone(), rpc_client());
+                let metadata_service = MetadataService::new(conn.clone(), rpc_client());
+                match service.cached(&owner) {
+                    Ok(balances) => {
+                        let mints: Vec<String> = balances
+                            .iter()
+                            .map(|balance| balance.mint.clone())
+                            .collect();
+                        let metadata = metadata_service.cached(&mints).unwrap_or_default();
+                        let prices = PriceService::new(conn.clone())
+                            .cached(&mints)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|(mint, (data, _))| (mint, data.price))
+                            .collect();
+                        *listing.lock().unwrap() = Listing {
+                            balances,
+                            prices,
+                            prices_stale_since: None,
+                            metadata,
+                        };
+                        token_manager.invoke_filter();
+                    }
+                    Err(e) => token_manager.set_error(e.to_string().into()),
+                }
+
+                token_manager.set_loading(true);
+                let weak_app = weak_app.clone();
+                let conn = conn.clone();
+                let listing = listing.clone();
+                std::thread::spawn(move || {
+                    // Without a fresh list the bundled or last downloaded one still applies
+                    let _ = RegistryService::new(conn.clone()).refresh_if_stale();
+                    let result = service.refresh(&owner).map_err(|e| e.to_string());
+                    // Balances are still worth showing when prices or metadata cannot be fetched
+                    let (prices, metadata) = match &result {
+                        Ok(balances) => {
+                            let mints: Vec<String> = balances
+                                .iter()
+                                .map(|balance| balance.mint.clone())
+                                .collect();
+                            let pubkeys: Vec<Pubkey> = mints
+                                .iter()
+                                .filter_map(|mint| Pubkey::from_str(mint).ok())
+                                .collect();
+                            let metadata = metadata_service
+                                .resolve(&pubkeys)
+                                .or_else(|_| metadata_service.cached(&mints))
+                                .unwrap_or_default();
+                            let prices = PriceService::new(conn.clone())
+                                .prices(&mints)
+                                .unwrap_or_default();
+                            (prices, metadata)
+                        }
+                        Err(_) => (Prices::default(), HashMap::new()),
+                    };
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let token_manager = app.global::<TokenManager>();
+                        token_manager.set_loading(false);
+                        if !is_selected(&app, &pubkey) {
+                            return;
+                        }
+                        match result {
+                            Ok(balances) => {
+                                *listing.lock().unwrap() = Listing {
+                                    balances,
+                                    prices: prices.usd(),
+                                    prices_stale_since: prices.stale_since,
+                                    metadata,
+                                };
+                                token_manager.invoke_filter();
+                            }
+                            Err(e) => token_manager.set_error(e.into()),
+                        }
+                    });
+                });
+            });
+    }
+
+    fn filter_handler(&self) {
+        let conn = self.conn.clone();
+        let listing = self.listing.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<TokenManager>()
+            .on_filter(move || {
+                let app = weak_app.unwrap();
+                show_balances(
+                    &app.global::<TokenManager>(),
+                    conn.clone(),
+                    &listing.lock().unwrap(),
+                );
+            });
+    }
+
+    fn set_trusted_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<TokenManager>()
+            .on_set_trusted(move |mint, trusted| {
+                let app = weak_app.unwrap();
+                let token_manager = app.global::<TokenManager>();
+                match RegistryService::new(conn.clone()).set_trusted(&mint, trusted) {
+                    Ok(()) => token_manager.invoke_refresh(),
+                    Err(e) => token_manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+
+    fn set_hidden_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<TokenManager>()
+            .on_set_hidden(move |mint, hidden| {
+                let app = weak_app.unwrap();
+                let token_manager = app.global::<TokenManager>();
+                match SpamService::new(conn.clone()).set_hidden(&mint, hidden) {
+                    Ok(()) => token_manager.invoke_filter(),
+                    Err(e) => token_manager.set_error(e.to_string().into()),
+                }
+            });
+    }
+
+    fn review_cleanup_handler(&self) {
+        let conn = self.conn.clone();
+        let listing = self.listing.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<TokenManager>()
+            .on_review_cleanup(move || {
+                let hidden = hidden_mints(conn.clone(), &listing.lock().unwrap());
+                spawn_account_work::<TokenManager, _, _>(
+                    weak_app.clone(),
+                    conn.clone(),
+                    || TokenService::new(rpc_client()),
+                    move |service, account| {
+                        let owner = account.pubkey()?;
+                        let spam = spam_accounts(service, &owner, &hidden)?;
+                        if spam.is_empty() {
+                            return Err(ServiceError::InvalidTransaction(
+                                "No token accounts are hidden as spam".to_string(),
+                            ));
+                        }
+                        service.preview_burn_and_close(&owner, &spam)
+                    },
+                    |app, review| {
+                        let review: Vec<SharedString> =
+                            review.into_iter().map(SharedString::from).collect();
+                        let token_manager = app.global::<TokenManager>();
+                        token_manager
+                            .set_cleanup_review(ModelRc::from(Rc::new(VecModel::from(review))));
+                        token_manager.set_reviewing_cleanup(true);
+                    },
+                );
+            });
+    }
+
+    fn confirm_cleanup_handler(&self) {
+        let conn = self.conn.clone();
+        let listing = self.listing.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<TokenManager>()
+            .on_confirm_cleanup(move || {
+                let hidden = hidden_mints(conn.clone(), &listing.lock().unwrap());
+                spawn_account_work::<TokenManager, _, _>(
+                    weak_app.clone(),
+                    conn.clone(),
+                    || TokenService::new(rpc_client()),
+                    move |service, account| {
+                        let spam = spam_accounts(service, &account.pubkey()?, &hidden)?;
+                        let signatures = service.burn_and_close(account, &spam)?;
+                        Ok(format!(
+                            "Closed {} spam token accounts in {} transactions",
+                            spam.len(),
+                            signatures.len()
+                        ))
+                    },
+                    |app, status| {
+                        let token_manager = app.global::<TokenManager>();
+                        token_manager.set_reviewing_cleanup(false);
+                        token_manager.set_status(status.into());
+                        token_manager.invoke_refresh();
+                    },
+                );
+            });
+    }
+
+    fn cancel_cleanup_handler(&self) {
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<TokenManager>()
+            .on_cancel_cleanup(move || {
+                let app = weak_app.unwrap();
+                let token_manager = app.global::<TokenManager>();
+                token_manager.set_reviewing_cleanup(false);
+                token_manager.set_cleanup_review(ModelRc::default());
+                token_manager.set_error(SharedString::new());
+            });
+    }
+}
+
+// Token accounts of `owner` holding one of the `hidden` mints
+fn spam_accounts(
+    service: &TokenService,
+    owner: &Pubkey,
+    hidden: &[String],
+) -> Result<Vec<OwnedTokenAccount>, ServiceError> {
+    Ok(service
+        .token_accounts(owner)?
+        .into_iter()
+        .filter(|token_account| hidden.contains(&token_account.account.mint.to_string()))
+        .collect())
+}
+
+fn hidden_mints(conn: Arc<Mutex<Connection>>, listing: &Listing) -> Vec<String> {
+    let (_, spam) = judge(conn, listing);
+    spam.into_iter()
+        .filter(|(_, verdict)| verdict.hidden)
+        .map(|(mint, _)| mint)
+        .collect()
+}
+
+// Registry and spam verdicts of every listed mint
+fn judge(
+    conn: Arc<Mutex<Connection>>,
+    listing: &Listing,
+) -> (HashMap<String, TokenVerdict>, HashMap<String, SpamVerdict>) {
+    // Judged on the on-chain symbol, since that is what an impersonator copies
+    let symbols: Vec<(String, String)> = listing
+        .balances
+        .iter()
+        .map(|balance| {
+            let symbol = listing
+                .metadata
+                .get(&balance.mint)
+                .map(|metadata| metadata.symbol.clone())
+                .unwrap_or_default();
+            (balance.mint.clone(), symbol)
+        })
+        .collect();
+    let verdicts = RegistryService::new(conn.clone())
+        .verdicts(&symbols)
+        .unwrap_or_default();
+
+    let spam_service = SpamService::new(conn);
+    // Without any prices, a missing one says nothing about the token
+    let priced = !listing.prices.is_empty();
+    let reasons: Vec<(String, Option<String>)> = listing
+        .balances
+        .iter()
+        .map(|balance| {
+            let status = verdicts
+                .get(&balance.mint)
+                .map(|verdict| verdict.status)
+                .unwrap_or(TokenStatus::Unverified);
+            let reason = spam_service.token_reason(
+                status,
+                listing.metadata.get(&balance.mint),
+                listing.prices.get(&balance.mint).copied(),
+                priced,
+            );
+            (balance.mint.clone(), reason)
+        })
+        .collect();
+    let spam = spam_service.verdicts(reasons).unwrap_or_default();
+    (verdicts, spam)
+}
+
+fn show_balances(token_manager: &TokenManager, conn: Arc<Mutex<Connection>>, listing: &Listing) {
+    let history = PriceHistoryService::new(conn.clone());
+    let (verdicts, spam) = judge(conn, listing);
+    let is_hidden = |mint: &String| spam.get(mint).is_some_and(|verdict| verdict.hidden);
+    let show_hidden = token_manager.get_show_hidden();
+    let now = Utc::now().timestamp();
+    let items: Vec<TokenItem> = listing
+        .balances
+        .iter()
+        .filter(|balance| show_hidden || !is_hidden(&balance.mint))
+        .map(|balance| {
+            token_item_builder(
+                balance,
+                listing.prices.get(&balance.mint).copied(),
+                listing.metadata.get(&balance.mint),
+                verdicts.get(&balance.mint),
+                spam.get(&balance.mint),
+                // Drawn from stored prices, so sparklines show offline too
+                history
+                    .chart(&balance.mint, ChartRange::Week, now)
+                    .ok()
+                    .flatten()
+                    .map(|chart| chart.commands)
+                    .unwrap_or_default(),
+            )
+        })
+        .collect();
+    // Spam does not count towards the total, even while it is shown
+    let priced: Vec<f64> = listing
+        .balances
+        .iter()
+        .filter(|balance| !is_hidden(&balance.mint))
+        .filter_map(|balance| {
+            listing
+                .prices
+                .get(&balance.mint)
+                .map(|price| balance.fiat_value(*price))
+        })
+        .collect();
+    let hidden_count = listing
+        .balances
+        .iter()
+        .filter(|balance| is_hidden(&balance.mint))
+        .count();
+
+    token_manager.set_total_value(if priced.is_empty() {
+        SharedString::new()
+    } else {
+        format!("${:.2}", priced.iter().sum::<f64>()).into()
+    });
+    token_manager.set_prices_stale_since(
+        listing
+            .prices_stale_since
+            .map(format_time)
+            .unwrap_or_default()
+            .into(),
+    );
+    token_manager.set_hidden_count(hidden_count as i32);
+    token_manager.set_items(ModelRc::from(Rc::new(VecModel::from(items))));
+}
+
+fn token_item_builder(
+    balance: &TokenBalance,
+    price: Option<f64>,
+    metadata: Option<&TokenMetadata>,
+    verdict: Option<&TokenVerdict>,
+    spam: Option<&SpamVerdict>,
+    sparkline: String,
+) -> TokenItem {
+    let symbol = metadata
+        .map(|metadata| metadata.symbol.clone())
+        .filter(|symbol| !symbol.is_empty())
+        .or_else(|| {
+            Pubkey::from_str(&balance.mint)
+                .ok()
+                .and_then(|mint| known_symbol(&mint))
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| short_address(&balance.mint));
+    // Logos are loaded from the local cache, never straight from the network
+    let logo = metadata
+        .and_then(|metadata| metadata.image_path.as_deref())
+        .and_then(|path| Image::load_from_path(Path::new(path)).ok());
+    TokenItem {
+        mint: balance.mint.clone().into(),
+        symbol: symbol.into(),
+        name: metadata
+            .map(|metadata| metadata.name.clone())
+            .unwrap_or_default()
+            .into(),
+        has_logo: logo.is_some(),
+        logo: logo.unwrap_or_default(),
+        status: verdict
+            .map(|verdict| verdict.status.as_str())
+            .unwrap_or_default()
+            .into(),
+        impersonates: verdict
+            .and_then(|verdict| verdict.impersonates.clone())
+            .unwrap_or_default()
+            .into(),
+        spam_reason: spam
+            .and_then(|spam| spam.reason.clone())
+            .unwrap_or_default()
+            .into(),
+        hidden: spam.is_some_and(|spam| spam.hidden),
+        amount: balance.ui_amount().into(),
+        value: price
+            .map(|price| format!("${:.2}", balance.fiat_value(price)))
+            .unwrap_or_default()
+            .into(),
+        sparkline: sparkline.into(),
+    }
+}
+
+fn short_address(address: &str) -> String {
+    if address.len() <= 9 {
+        return address.to_string();
+    }
+    format!("{}...{}", &address[..5], &address[address.len() - 4..])
+}
@@ -0,0 +1,197 @@
+use crate::app::handlers::rpc_client;
+use crate::services::validator_service::{
+    sort_validators, Validator, ValidatorService, ValidatorSort,
+};
+use crate::slint_generatedApp::{App as SlintApp, ValidatorItem, ValidatorManager};
+use rusqlite::Connection;
+use slint::{ComponentHandle, ModelRc, SharedString, VecModel};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use std::{
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+pub struct ValidatorHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+    // Last fetched vote accounts, filtered and sorted locally
+    validators: Arc<Mutex<Vec<Validator>>>,
+}
+
+impl ValidatorHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        ValidatorHandler {
+            app_instance,
+            conn,
+            validators: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    pub fn run(&self) {
+        self.refresh_handler();
+        self.filter_handler();
+        self.toggle_favourite_handler();
+    }
+
+    fn refresh_handler(&self) {
+        let conn = self.conn.clone();
+        let validators = self.validators.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<ValidatorManager>()
+            .on_refresh(move || {
+                let app = weak_app.unwrap();
+                let validator_manager = app.global::<ValidatorManager>();
+                if validator_manager.get_loading() {
+                    return;
+                }
+                validator_manager.set_error(SharedString::new());
+                validator_manager.set_loading(true);
+
+                let conn = conn.clone();
+                let validators = validators.clone();
+                let weak_app = weak_app.clone();
+                std::thread::spawn(move || {
+                    let result = ValidatorService::new(conn.clone(), rpc_client())
+                        .list()
+                        .map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let validator_manager = app.global::<ValidatorManager>();
+                        validator_manager.set_loading(false);
+                        match result {
+                            Ok(list) => {
+                                *validators.lock().unwrap() = list;
+                                show_validators(&app, conn, &validators);
+                            }
+                            Err(e) => validator_manager.set_error(e.into()),
+                        }
+                    });
+                });
+            });
+    }
+
+    fn filter_handler(&self) {
+        let conn = self.conn.clone();
+        let validators = self.validators.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<ValidatorManager>()
+            .on_filter(move || {
+                let app = weak_app.unwrap();
+                show_validators(&app, conn.clone(), &validators);
+            });
+    }
+
+    fn toggle_favourite_handler(&self) {
+        let conn = self.conn.clone();
+        let validators = self.validators.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<ValidatorManager>()
+            .on_toggle_favourite(move |vote_account| {
+                let app = weak_app.unwrap();
+                let service = ValidatorService::new(conn.clone(), rpc_client());
+                let result = service.get_favourites().and_then(|favourites| {
+                    let favourite = !favourites.contains(vote_account.as_str());
+                    service.set_favourite(&vote_account, favourite)
+                });
+
+                match result {
+                    Ok(_) => show_validators(&app, conn.clone(), &validators),
+                    Err(e) => app
+                        .global::<ValidatorManager>()
+                        .set_error(e.to_string().into()),
+                }
+            });
+    }
+}
+
+fn show_validators(
+    app: &SlintApp,
+    conn: Arc<Mutex<Connection>>,
+    validators: &Arc<Mutex<Vec<Validator>>>,
+) {
+    let validator_manager = app.global::<ValidatorManager>();
+    let favourites = match ValidatorService::new(conn, rpc_client()).get_favourites() {
+        Ok(favourites) => favourites,
+        Err(e) => {
+            validator_manager.set_error(e.to_string().into());
+            return;
+        }
+    };
+
+    let all = validators.lock().unwrap();
+    let delinquent = all.iter().filter(|validator| validator.delinquent).count();
+    let query = validator_manager.get_query();
+    let favourites_only = validator_manager.get_favourites_only();
+    let mut shown: Vec<Validator> = all
+        .iter()
+        .filter(|validator| validator.matches(&query))
+        .filter(|validator| !favourites_only || favourites.contains(&validator.vote_account))
+        .cloned()
+        .collect();
+    sort_validators(
+        &mut shown,
+        ValidatorSort::from_label(&validator_manager.get_sort()),
+    );
+
+    let items: Vec<ValidatorItem> = shown
+        .iter()
+        .map(|validator| {
+            validator_item_builder(validator, favourites.contains(&validator.vote_account))
+        })
+        .collect();
+    validator_manager.set_summary(
+        format!(
+            "{} of {} validators, {} delinquent",
+            items.len(),
+            all.len(),
+            delinquent
+        )
+        .into(),
+    );
+    validator_manager.set_items(ModelRc::from(Rc::new(VecModel::from(items))));
+}
+
+// Whole SOL with thousands separators, as stake figures are large
+fn stake_display(lamports: u64) -> String {
+    let digits = (lamports / LAMPORTS_PER_SOL).to_string();
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+fn credits_display(credits: Option<u64>) -> String {
+    credits
+        .map(|credits| credits.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn validator_item_builder(validator: &Validator, favourite: bool) -> ValidatorItem {
+    ValidatorItem {
+        vote_account: validator.vote_account.clone().into(),
+        identity: validator.identity.clone().into(),
+        commission: format!("{}%", validator.commission).into(),
+        stake: stake_display(validator.activated_stake).into(),
+        last_vote: validator.last_vote.to_string().into(),
+        skip_rate: validator
+            .skip_rate
+            .map(|rate| format!("{:.1}%", rate * 100.0))
+            .unwrap_or_else(|| "-".to_string())
+            .into(),
+        credits: format!(
+            "{} / {}",
+            credits_display(validator.epoch_credits),
+            credits_display(validator.previous_epoch_credits)
+        )
+        .into(),
+        delinquent: validator.delinquent,
+        favourite,
+    }
+}
@@ -0,0 +1,206 @@
+use crate::app::handlers::rpc_client;
+use crate::programs::token::NATIVE_MINT;
+use crate::services::{
+    price_service::{PriceService, Prices, PRICE_TTL_SECS},
+    valuation_service::{Valuation, ValuationService},
+};
+use crate::slint_generatedApp::{AccountManager, App as SlintApp, SolValueManager};
+use crate::time::format_time;
+use rusqlite::Connection;
+use slint::{ComponentHandle, Model, SharedString, Weak};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+// How often the portfolio is valued at new prices while the app is open
+const REFRESH_INTERVAL: Duration = Duration::from_secs(PRICE_TTL_SECS as u64);
+
+pub struct ValueHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+    // Last valuation, kept so switching accounts shows that account's value straight away
+    valuation: Arc<Mutex<Option<Valuation>>>,
+}
+
+impl ValueHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        ValueHandler {
+            app_instance,
+            conn,
+            valuation: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn run(&self) {
+        self.refresh_handler();
+        self.show_account_handler();
+        self.show_cached_price();
+        start_refresher(
+            self.app_instance.as_weak(),
+            self.conn.clone(),
+            self.valuation.clone(),
+        );
+    }
+
+    // The last known SOL price shows until the first refresh lands
+    fn show_cached_price(&self) {
+        let sol = NATIVE_MINT.to_string();
+        let cached = PriceService::new(self.conn.clone()).cached(std::slice::from_ref(&sol));
+        if let Some((data, _)) = cached.unwrap_or_default().remove(&sol) {
+            self.app_instance
+                .global::<SolValueManager>()
+                .set_value(data.formatted_price().into());
+        }
+    }
+
+    fn refresh_handler(&self) {
+        let conn = self.conn.clone();
+        let valuation = self.valuation.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<SolValueManager>()
+            .on_refresh(move || {
+                let app = weak_app.unwrap();
+                let value_manager = app.global::<SolValueManager>();
+                if value_manager.get_loading() {
+                    return;
+                }
+                value_manager.set_error(SharedString::new());
+
+                let owners: Vec<Pubkey> = app
+                    .global::<AccountManager>()
+                    .get_accounts()
+                    .iter()
+                    .filter_map(|account| Pubkey::from_str(&account.pubkey).ok())
+                    .collect();
+
+                value_manager.set_loading(true);
+                let weak_app = weak_app.clone();
+                let conn = conn.clone();
+                let valuation = valuation.clone();
+                thread::spawn(move || {
+                    update_sol_price(&weak_app, &conn);
+                    let result = ValuationService::new(conn, rpc_client())
+                        .value(&owners)
+                        .map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let value_manager = app.global::<SolValueManager>();
+                        value_manager.set_loading(false);
+                        match result {
+                            Ok(result) => show_valuation(&value_manager, &valuation, result),
+                            Err(e) => value_manager.set_error(e.into()),
+                        }
+                    });
+                });
+            });
+    }
+
+    fn show_account_handler(&self) {
+        let valuation = self.valuation.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<SolValueManager>()
+            .on_show_account(move || {
+                let app = weak_app.unwrap();
+                let pubkey = app.global::<AccountManager>().get_selected_account().pubkey;
+                let value = Pubkey::from_str(&pubkey).ok().and_then(|owner| {
+                    valuation.lock().unwrap().as_ref().map(|valuation| {
+                        valuation.accounts.get(&owner).copied().unwrap_or_default()
+                    })
+                });
+                app.global::<SolValueManager>().set_account_value(
+                    value.map(|value| usd(value.usd)).unwrap_or_default().into(),
+                );
+            });
+    }
+}
+
+// Looks up holdings once, then values them at new prices until the window closes. Holdings
+// are only looked up again when the user refreshes.
+fn start_refresher(
+    weak_app: Weak<SlintApp>,
+    conn: Arc<Mutex<Connection>>,
+    valuation: Arc<Mutex<Option<Valuation>>>,
+) {
+    thread::spawn(move || loop {
+        let last = valuation.lock().unwrap().clone();
+        let shown = match last {
+            None => weak_app
+                .upgrade_in_event_loop(|app| app.global::<SolValueManager>().invoke_refresh()),
+            Some(last) => {
+                update_sol_price(&weak_app, &conn);
+                let result = ValuationService::new(conn.clone(), rpc_client())
+                    .reprice(&last)
+                    .map_err(|e| e.to_string());
+                let valuation = valuation.clone();
+                weak_app.upgrade_in_event_loop(move |app| {
+                    let value_manager = app.global::<SolValueManager>();
+                    // A refresh under way brings newer holdings
+                    if value_manager.get_loading() {
+                        return;
+                    }
+                    match result {
+                        Ok(result) => show_valuation(&value_manager, &valuation, result),
+                        Err(e) => value_manager.set_error(e.into()),
+                    }
+                })
+            }
+        };
+        if shown.is_err() {
+            break;
+        }
+        thread::sleep(REFRESH_INTERVAL);
+    });
+}
+
+// The SOL price needs no RPC calls, so it shows before the portfolio is valued
+fn update_sol_price(weak_app: &Weak<SlintApp>, conn: &Arc<Mutex<Connection>>) {
+    let sol = PriceService::new(conn.clone())
+        .prices(&[NATIVE_MINT.to_string()])
+        .ok();
+    let _ = weak_app.upgrade_in_event_loop(move |app| {
+        if let Some(sol) = sol {
+            show_sol_price(&app.global::<SolValueManager>(), &sol);
+        }
+    });
+}
+
+fn show_valuation(
+    value_manager: &SolValueManager,
+    valuation: &Arc<Mutex<Option<Valuation>>>,
+    result: Valuation,
+) {
+    value_manager.set_error(SharedString::new());
+    value_manager.set_total(usd(result.total.usd).into());
+    let change = result.total.change_24h();
+    value_manager.set_change(
+        change
+            .map(|change| format!("{:+.2}%", change))
+            .unwrap_or_default()
+            .into(),
+    );
+    value_manager.set_falling(change.is_some_and(|change| change < 0.0));
+    value_manager.set_stale_since(stale_since(result.stale_since));
+    *valuation.lock().unwrap() = Some(result);
+    value_manager.invoke_show_account();
+}
+
+fn show_sol_price(value_manager: &SolValueManager, prices: &Prices) {
+    if let Some(sol) = prices.prices.get(&NATIVE_MINT.to_string()) {
+        value_manager.set_value(sol.formatted_price().into());
+    }
+    value_manager.set_stale_since(stale_since(prices.stale_since));
+}
+
+fn stale_since(fetched_at: Option<i64>) -> SharedString {
+    fetched_at.map(format_time).unwrap_or_default().into()
+}
+
+fn usd(amount: f64) -> String {
+    format!("${:.2}", amount)
+}
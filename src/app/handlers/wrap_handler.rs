@@ -0,0 +1,145 @@
+use crate::amount::{base_units_to_ui_amount, SOL_DECIMALS};
+use crate::app::errors::AppError;
+use crate::app::handlers::{is_selected, rpc_client};
+use crate::database::account::Account;
+use crate::services::account_service::AccountService;
+use crate::services::errors::ServiceError;
+use crate::services::wrap_service::WrapService;
+use crate::slint_generatedApp::{AccountManager, App as SlintApp, WrapManager};
+use rusqlite::Connection;
+use slint::{ComponentHandle, SharedString, Weak};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+pub struct WrapHandler {
+    app_instance: SlintApp,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl WrapHandler {
+    pub fn new(conn: Arc<Mutex<Connection>>, app_instance: SlintApp) -> Self {
+        WrapHandler { app_instance, conn }
+    }
+
+    pub fn run(&self) {
+        self.refresh_handler();
+        self.wrap_handler();
+        self.unwrap_handler();
+    }
+
+    fn refresh_handler(&self) {
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<WrapManager>()
+            .on_refresh(move || {
+                let app = weak_app.unwrap();
+                let wrap_manager = app.global::<WrapManager>();
+                wrap_manager.set_error(SharedString::new());
+
+                let pubkey = app.global::<AccountManager>().get_selected_account().pubkey;
+                let Ok(owner) = Pubkey::from_str(&pubkey) else {
+                    return;
+                };
+
+                wrap_manager.set_loading(true);
+                let weak_app = weak_app.clone();
+                std::thread::spawn(move || {
+                    let result = WrapService::new(rpc_client())
+                        .wrapped_balance(&owner)
+                        .map_err(|e| e.to_string());
+
+                    let _ = weak_app.upgrade_in_event_loop(move |app| {
+                        let wrap_manager = app.global::<WrapManager>();
+                        wrap_manager.set_loading(false);
+                        if !is_selected(&app, &pubkey) {
+                            return;
+                        }
+                        match result {
+                            Ok(balance) => {
+                                let balance = balance.unwrap_or(0);
+                                wrap_manager.set_wrapped(
+                                    base_units_to_ui_amount(balance, SOL_DECIMALS).into(),
+                                );
+                                wrap_manager.set_has_wrapped(balance > 0);
+                            }
+                            Err(e) => wrap_manager.set_error(e.into()),
+                        }
+                    });
+                });
+            });
+    }
+
+    fn wrap_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<WrapManager>()
+            .on_wrap(move |amount| {
+                let amount = amount.trim().to_string();
+                run_action(weak_app.clone(), conn.clone(), move |service, account| {
+                    let signature = service.wrap(account, &amount)?;
+                    Ok(format!("Wrapped {} SOL: {}", amount, signature))
+                });
+            });
+    }
+
+    fn unwrap_handler(&self) {
+        let conn = self.conn.clone();
+        let weak_app = self.app_instance.as_weak();
+        self.app_instance
+            .global::<WrapManager>()
+            .on_unwrap(move || {
+                run_action(weak_app.clone(), conn.clone(), |service, account| {
+                    let signature = service.unwrap(account)?;
+                    Ok(format!("Unwrapped: {}", signature))
+                });
+            });
+    }
+}
+
+// Sends a wrap or unwrap for the selected account off the UI thread, then reloads the balance
+fn run_action<F>(weak_app: Weak<SlintApp>, conn: Arc<Mutex<Connection>>, action: F)
+where
+    F: FnOnce(&WrapService, &Account) -> Result<String, ServiceError> + Send + 'static,
+{
+    let app = weak_app.unwrap();
+    let wrap_manager = app.global::<WrapManager>();
+    if wrap_manager.get_busy() {
+        return;
+    }
+    wrap_manager.set_error(SharedString::new());
+    wrap_manager.set_status(SharedString::new());
+
+    let account_id = app.global::<AccountManager>().get_selected_account().id;
+    let account = match AccountService::new(conn).get_account_by_id(account_id) {
+        Ok(Some(account)) => account,
+        Ok(None) => {
+            wrap_manager.set_error(AppError::NoAccountSelected.to_string().into());
+            return;
+        }
+        Err(e) => {
+            wrap_manager.set_error(e.to_string().into());
+            return;
+        }
+    };
+
+    wrap_manager.set_busy(true);
+    std::thread::spawn(move || {
+        let result = action(&WrapService::new(rpc_client()), &account).map_err(|e| e.to_string());
+
+        let _ = weak_app.upgrade_in_event_loop(move |app| {
+            let wrap_manager = app.global::<WrapManager>();
+            wrap_manager.set_busy(false);
+            match result {
+                Ok(status) => {
+                    wrap_manager.set_status(status.into());
+                    wrap_manager.invoke_refresh();
+                }
+                Err(e) => wrap_manager.set_error(e.into()),
+            }
+        });
+    });
+}
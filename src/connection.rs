@@ -1,6 +1,7 @@
 use solana_rpc_client::rpc_client::RpcClient;
 use std::env;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq)]
 pub enum ConnectionNetwork {
     MAINNET,
@@ -43,6 +44,14 @@ impl Connection {
         RpcClient::new(url)
     }
 
+    /// Endpoint of a Digital Asset Standard indexer, which many RPC providers serve alongside RPC
+    pub fn das_url(&self) -> String {
+        env::var("DAS_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| self.solana_url())
+    }
+
     fn solana_url(&self) -> String {
         // Define the URLs
         let solana_mainnet =
@@ -150,6 +159,16 @@ mod tests {
         assert_eq!(connection.solana_url(), "https://api.testnet.solana.com");
     }
 
+    #[test]
+    fn test_das_url() {
+        let connection = Connection {
+            network: ConnectionNetwork::DEVNET,
+        };
+        env::set_var("DAS_URL", "http://das.url");
+        assert_eq!(connection.das_url(), "http://das.url");
+        env::remove_var("DAS_URL");
+    }
+
     #[test]
     fn test_connection_network_default_url() {
         assert_eq!(
@@ -0,0 +1,321 @@
+use crate::das::errors::DasError;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+// Most assets the indexer returns per page
+const PAGE_LIMIT: usize = 1000;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DasAsset {
+    pub id: String,
+    #[serde(default)]
+    pub content: AssetContent,
+    pub compression: Option<AssetCompression>,
+    pub ownership: AssetOwnership,
+    #[serde(default)]
+    pub grouping: Vec<AssetGroup>,
+    #[serde(default)]
+    pub burnt: bool,
+}
+
+impl DasAsset {
+    pub fn is_compressed(&self) -> bool {
+        self.compression
+            .as_ref()
+            .is_some_and(|compression| compression.compressed)
+    }
+
+    /// Mint of the collection the asset is grouped under
+    pub fn collection(&self) -> Option<&str> {
+        self.grouping
+            .iter()
+            .find(|group| group.group_key == "collection")
+            .map(|group| group.group_value.as_str())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AssetContent {
+    #[serde(default)]
+    pub json_uri: String,
+    #[serde(default)]
+    pub metadata: AssetMetadata,
+    #[serde(default)]
+    pub links: AssetLinks,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AssetMetadata {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub attributes: Vec<AssetAttribute>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AssetAttribute {
+    pub trait_type: Option<String>,
+    pub value: Value,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AssetLinks {
+    pub image: Option<String>,
+}
+
+// Hashes are base58 encoded, like public keys
+#[derive(Deserialize, Debug, Clone)]
+pub struct AssetCompression {
+    pub compressed: bool,
+    pub data_hash: String,
+    pub creator_hash: String,
+    pub leaf_id: u64,
+    pub tree: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AssetOwnership {
+    pub owner: String,
+    pub delegate: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AssetGroup {
+    pub group_key: String,
+    pub group_value: String,
+}
+
+/// Merkle proof of a compressed asset's leaf, from the leaf up to the root
+#[derive(Deserialize, Debug, Clone)]
+pub struct AssetProof {
+    pub root: String,
+    pub proof: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct AssetPage {
+    items: Vec<DasAsset>,
+}
+
+#[derive(Deserialize)]
+struct RpcErrorBody {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcErrorBody>,
+}
+
+/// Client for the Digital Asset Standard API served by compatible indexers
+pub struct DasClient {
+    url: String,
+    client: reqwest::blocking::Client,
+}
+
+impl DasClient {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: reqwest::blocking::Client::builder()
+                .timeout(HTTP_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Every asset owned by `owner`, walking through all pages
+    pub fn get_assets_by_owner(&self, owner: &str) -> Result<Vec<DasAsset>, DasError> {
+        let mut assets = vec![];
+        for page in 1.. {
+            let response: AssetPage = self.call(
+                "getAssetsByOwner",
+                json!({ "ownerAddress": owner, "page": page, "limit": PAGE_LIMIT }),
+            )?;
+            let count = response.items.len();
+            assets.extend(response.items);
+            if count < PAGE_LIMIT {
+                break;
+            }
+        }
+        Ok(assets)
+    }
+
+    pub fn get_asset(&self, id: &str) -> Result<DasAsset, DasError> {
+        self.call("getAsset", json!({ "id": id }))
+    }
+
+    pub fn get_asset_proof(&self, id: &str) -> Result<AssetProof, DasError> {
+        self.call("getAssetProof", json!({ "id": id }))
+    }
+
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, DasError> {
+        let response: RpcResponse<T> = self
+            .client
+            .post(&self.url)
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            }))
+            .send()?
+            .error_for_status()?
+            .json()?;
+        if let Some(error) = response.error {
+            return Err(DasError::RpcError {
+                code: error.code,
+                message: error.message,
+            });
+        }
+        response
+            .result
+            .ok_or_else(|| DasError::InvalidResponse(format!("{} returned no result", method)))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Minimal JSON-RPC server answering each DAS method with a canned result
+    pub fn spawn_stub_indexer(results: Vec<(&'static str, Value)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    break;
+                };
+                let body = read_body(&mut stream);
+                let request: Value = serde_json::from_str(&body).unwrap_or_default();
+                let method = request["method"].as_str().unwrap_or_default();
+                let response = match results.iter().find(|(name, _)| *name == method) {
+                    Some((_, result)) => json!({"jsonrpc": "2.0", "id": 1, "result": result}),
+                    None => json!({
+                        "jsonrpc": "2.0",
+                        "id": 1,
+                        "error": {"code": -32601, "message": "Method not found"},
+                    }),
+                }
+                .to_string();
+                let _ = stream.write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        response.len(),
+                        response
+                    )
+                    .as_bytes(),
+                );
+            }
+        });
+
+        format!("http://{}", address)
+    }
+
+    // Read the headers, then as much body as they announce
+    fn read_body(stream: &mut std::net::TcpStream) -> String {
+        let mut data = vec![];
+        let mut buffer = [0u8; 4096];
+        loop {
+            let read = stream.read(&mut buffer).unwrap_or(0);
+            if read == 0 {
+                break;
+            }
+            data.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&data).to_string();
+            if let Some((headers, body)) = text.split_once("\r\n\r\n") {
+                let length = headers
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    return body.to_string();
+                }
+            }
+        }
+        String::new()
+    }
+
+    pub fn compressed_asset(id: &str, owner: &str, tree: &str) -> Value {
+        json!({
+            "id": id,
+            "content": {
+                "json_uri": "",
+                "metadata": {
+                    "name": "Compressed #7",
+                    "description": "Lives in a tree",
+                    "attributes": [{"trait_type": "Rarity", "value": "Rare"}],
+                },
+                "links": {"image": null},
+            },
+            "compression": {
+                "compressed": true,
+                "data_hash": "11111111111111111111111111111111",
+                "creator_hash": "11111111111111111111111111111111",
+                "leaf_id": 7,
+                "tree": tree,
+            },
+            "ownership": {"owner": owner, "delegate": null},
+            "grouping": [{"group_key": "collection", "group_value": "Collection111111111111111111111111111111111"}],
+            "burnt": false,
+        })
+    }
+
+    #[test]
+    fn test_das_client_against_stub() {
+        let url = spawn_stub_indexer(vec![
+            (
+                "getAssetsByOwner",
+                json!({
+                    "total": 1,
+                    "limit": 1000,
+                    "page": 1,
+                    "items": [compressed_asset("Asset1", "Owner1", "Tree1")],
+                }),
+            ),
+            (
+                "getAssetProof",
+                json!({
+                    "root": "11111111111111111111111111111111",
+                    "proof": ["11111111111111111111111111111111"],
+                    "node_index": 16391,
+                    "leaf": "11111111111111111111111111111111",
+                    "tree_id": "Tree1",
+                }),
+            ),
+        ]);
+        let client = DasClient::new(url);
+
+        let assets = client.get_assets_by_owner("Owner1").unwrap();
+        assert_eq!(assets.len(), 1);
+        assert!(assets[0].is_compressed());
+        assert_eq!(assets[0].content.metadata.name, "Compressed #7");
+        assert_eq!(
+            assets[0].collection(),
+            Some("Collection111111111111111111111111111111111")
+        );
+
+        let proof = client.get_asset_proof("Asset1").unwrap();
+        assert_eq!(proof.proof.len(), 1);
+
+        // The stub does not know getAsset, which surfaces as an indexer error
+        assert!(matches!(
+            client.get_asset("Asset1"),
+            Err(DasError::RpcError { code: -32601, .. })
+        ));
+    }
+}
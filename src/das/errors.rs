@@ -0,0 +1,14 @@
+use reqwest::Error as ReqwestError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DasError {
+    #[error("Request error: {0}")]
+    RequestError(#[from] ReqwestError),
+
+    #[error("Indexer error {code}: {message}")]
+    RpcError { code: i64, message: String },
+
+    #[error("Invalid indexer response: {0}")]
+    InvalidResponse(String),
+}
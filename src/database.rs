@@ -2,6 +2,12 @@ use rusqlite::{Connection, Result};
 pub mod account;
 pub mod cache;
 pub mod errors;
+pub mod payout;
+pub mod schedule;
+pub mod stake_reward;
+pub mod token_balance;
+pub mod token_metadata;
+pub mod token_registry;
 
 use crate::database::errors::DatabaseError;
 
@@ -1,5 +1,5 @@
 use slint::SharedString;
-use solana_sdk::native_token::lamports_to_sol;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
 use solana_sdk::pubkey::{ParsePubkeyError, Pubkey};
 use solana_sdk::signature::{keypair, Keypair};
 use std::{error::Error, str::FromStr};
@@ -27,12 +27,12 @@ impl Account {
     }
 
     pub fn balance_in_sol(&self) -> f64 {
-        lamports_to_sol(self.balance.unwrap_or_else(|| 0u64))
+        self.balance.unwrap_or(0) as f64 / LAMPORTS_PER_SOL as f64
     }
 
     pub fn account_keypair(&self) -> Result<Keypair, Box<dyn Error>> {
         let keypair =
-            keypair::keypair_from_seed_phrase_and_passphrase(&*self.seed, &*self.passphrase)?;
+            keypair::keypair_from_seed_phrase_and_passphrase(&self.seed, &self.passphrase)?;
         Ok(keypair)
     }
 }
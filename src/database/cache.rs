@@ -55,6 +55,7 @@ impl Cache {
         }
     }
 
+    #[allow(dead_code)]
     fn remove(&self, key: CacheKey) -> Result<(), DatabaseError> {
         let conn = self.conn.lock().unwrap();
         conn.execute("DELETE FROM cache WHERE key = ?1", params![key.key()])?;
@@ -69,6 +70,7 @@ impl Cache {
         self.get(CacheKey::SelectedAccount)
     }
 
+    #[allow(dead_code)]
     pub fn remove_selected_account(&self) -> Result<(), DatabaseError> {
         self.remove(CacheKey::SelectedAccount)
     }
@@ -81,6 +83,7 @@ impl Cache {
         self.get(CacheKey::SelectedView)
     }
 
+    #[allow(dead_code)]
     pub fn remove_selected_view(&self) -> Result<(), DatabaseError> {
         self.remove(CacheKey::SelectedView)
     }
@@ -0,0 +1,94 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PayoutStatus {
+    Invalid,
+    Pending,
+    Sent,
+    Confirmed,
+    Failed,
+}
+
+impl PayoutStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayoutStatus::Invalid => "invalid",
+            PayoutStatus::Pending => "pending",
+            PayoutStatus::Sent => "sent",
+            PayoutStatus::Confirmed => "confirmed",
+            PayoutStatus::Failed => "failed",
+        }
+    }
+
+    pub fn from_db(status: &str) -> Self {
+        match status {
+            "invalid" => PayoutStatus::Invalid,
+            "sent" => PayoutStatus::Sent,
+            "confirmed" => PayoutStatus::Confirmed,
+            "failed" => PayoutStatus::Failed,
+            _ => PayoutStatus::Pending,
+        }
+    }
+
+    /// Rows in these states have not paid anyone and can be (re)sent
+    pub fn is_payable(&self) -> bool {
+        matches!(self, PayoutStatus::Pending | PayoutStatus::Failed)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PayoutBatch {
+    pub id: Option<i32>,
+    pub account_id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayoutRow {
+    pub id: Option<i32>,
+    pub batch_id: i32,
+    pub row_number: i32,
+    pub recipient: String,
+    pub amount: String,
+    /// Mint address, empty for native SOL
+    pub token: String,
+    pub status: PayoutStatus,
+    pub signature: Option<String>,
+    pub last_valid_block_height: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl PayoutRow {
+    pub fn token_display(&self) -> &str {
+        if self.token.is_empty() {
+            "SOL"
+        } else {
+            &self.token
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payout_status_round_trip() {
+        for status in [
+            PayoutStatus::Invalid,
+            PayoutStatus::Pending,
+            PayoutStatus::Sent,
+            PayoutStatus::Confirmed,
+            PayoutStatus::Failed,
+        ] {
+            assert_eq!(PayoutStatus::from_db(status.as_str()), status);
+        }
+    }
+
+    #[test]
+    fn test_payout_status_is_payable() {
+        assert!(PayoutStatus::Pending.is_payable());
+        assert!(PayoutStatus::Failed.is_payable());
+        assert!(!PayoutStatus::Sent.is_payable());
+        assert!(!PayoutStatus::Confirmed.is_payable());
+        assert!(!PayoutStatus::Invalid.is_payable());
+    }
+}
@@ -0,0 +1,167 @@
+use chrono::{DateTime, Duration, Local, Months, TimeZone};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frequency {
+    Once,
+    Weekly,
+    Monthly,
+}
+
+impl Frequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Frequency::Once => "once",
+            Frequency::Weekly => "weekly",
+            Frequency::Monthly => "monthly",
+        }
+    }
+
+    pub fn from_db(frequency: &str) -> Option<Self> {
+        match frequency {
+            "once" => Some(Frequency::Once),
+            "weekly" => Some(Frequency::Weekly),
+            "monthly" => Some(Frequency::Monthly),
+            _ => None,
+        }
+    }
+
+    /// Unix time of the `index`th run, counted from the first run at `start_at`.
+    /// Monthly runs keep the start day, falling back to the last day of shorter months.
+    pub fn occurrence(&self, start_at: i64, index: u32) -> Option<i64> {
+        let start: DateTime<Local> = Local.timestamp_opt(start_at, 0).single()?;
+        let run = match self {
+            Frequency::Once if index == 0 => Some(start),
+            Frequency::Once => None,
+            Frequency::Weekly => start.checked_add_signed(Duration::weeks(index as i64)),
+            Frequency::Monthly => start.checked_add_months(Months::new(index)),
+        };
+        run.map(|run| run.timestamp())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledTransfer {
+    pub id: Option<i32>,
+    pub account_id: i32,
+    pub recipient: String,
+    pub amount: String,
+    /// Mint address, empty for native SOL
+    pub token: String,
+    pub memo: String,
+    pub frequency: Frequency,
+    pub start_at: i64,
+    /// Number of runs already taken, executed or missed
+    pub occurrence: u32,
+    pub next_run_at: Option<i64>,
+    pub active: bool,
+}
+
+impl ScheduledTransfer {
+    /// Move on to the following run, deactivating the schedule when there is none
+    pub fn advance(&mut self) {
+        self.occurrence += 1;
+        self.next_run_at = self.frequency.occurrence(self.start_at, self.occurrence);
+        self.active = self.next_run_at.is_some();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunStatus {
+    Sent,
+    Failed,
+    Missed,
+    /// A missed run claimed for sending, until the send is confirmed or fails
+    Sending,
+}
+
+impl RunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunStatus::Sent => "sent",
+            RunStatus::Failed => "failed",
+            RunStatus::Missed => "missed",
+            RunStatus::Sending => "sending",
+        }
+    }
+
+    pub fn from_db(status: &str) -> Self {
+        match status {
+            "sent" => RunStatus::Sent,
+            "failed" => RunStatus::Failed,
+            "sending" => RunStatus::Sending,
+            _ => RunStatus::Missed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledRun {
+    pub id: Option<i32>,
+    pub schedule_id: i32,
+    pub scheduled_for: i64,
+    pub ran_at: Option<i64>,
+    pub status: RunStatus,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, NaiveDate};
+
+    fn local_timestamp(year: i32, month: u32, day: u32) -> i64 {
+        let date = NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        Local.from_local_datetime(&date).unwrap().timestamp()
+    }
+
+    #[test]
+    fn test_frequency_round_trip() {
+        for frequency in [Frequency::Once, Frequency::Weekly, Frequency::Monthly] {
+            assert_eq!(Frequency::from_db(frequency.as_str()), Some(frequency));
+        }
+        assert_eq!(Frequency::from_db("daily"), None);
+    }
+
+    #[test]
+    fn test_frequency_occurrence() {
+        let start = local_timestamp(2025, 1, 31);
+        assert_eq!(Frequency::Once.occurrence(start, 0), Some(start));
+        assert_eq!(Frequency::Once.occurrence(start, 1), None);
+        assert_eq!(
+            Frequency::Weekly.occurrence(start, 2),
+            Some(local_timestamp(2025, 2, 14))
+        );
+
+        let february = Frequency::Monthly.occurrence(start, 1).unwrap();
+        assert_eq!(february, local_timestamp(2025, 2, 28));
+        let march = Frequency::Monthly.occurrence(start, 2).unwrap();
+        assert_eq!(Local.timestamp_opt(march, 0).unwrap().day(), 31);
+    }
+
+    #[test]
+    fn test_scheduled_transfer_advance() {
+        let start = local_timestamp(2025, 1, 1);
+        let mut schedule = ScheduledTransfer {
+            id: Some(1),
+            account_id: 1,
+            recipient: String::new(),
+            amount: "1".to_string(),
+            token: String::new(),
+            memo: String::new(),
+            frequency: Frequency::Once,
+            start_at: start,
+            occurrence: 0,
+            next_run_at: Some(start),
+            active: true,
+        };
+
+        schedule.advance();
+        assert_eq!(schedule.occurrence, 1);
+        assert_eq!(schedule.next_run_at, None);
+        assert!(!schedule.active);
+    }
+}
@@ -0,0 +1,54 @@
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StakeReward {
+    pub id: Option<i32>,
+    pub account_id: i32,
+    pub stake_address: String,
+    /// Validator the stake was delegated to when the reward was synced
+    pub vote_account: String,
+    pub epoch: u64,
+    pub amount: u64,
+    pub post_balance: u64,
+    pub commission: Option<u8>,
+    pub effective_slot: u64,
+    pub block_time: Option<i64>,
+    /// USD price of SOL on the day the reward was paid
+    pub sol_price: Option<f64>,
+}
+
+impl StakeReward {
+    /// Stake the reward was earned on
+    pub fn principal(&self) -> u64 {
+        self.post_balance.saturating_sub(self.amount)
+    }
+
+    pub fn fiat_value(&self) -> Option<f64> {
+        self.sol_price
+            .map(|price| self.amount as f64 / LAMPORTS_PER_SOL as f64 * price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_principal_and_fiat_value() {
+        let reward = StakeReward {
+            id: None,
+            account_id: 1,
+            stake_address: String::new(),
+            vote_account: String::new(),
+            epoch: 600,
+            amount: 500_000_000,
+            post_balance: 100_500_000_000,
+            commission: Some(5),
+            effective_slot: 0,
+            block_time: None,
+            sol_price: Some(150.0),
+        };
+        assert_eq!(reward.principal(), 100_000_000_000);
+        assert_eq!(reward.fiat_value(), Some(75.0));
+    }
+}
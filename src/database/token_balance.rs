@@ -0,0 +1,39 @@
+use crate::amount::base_units_to_ui_amount;
+
+/// Holding of one mint across all token accounts of an owner
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenBalance {
+    pub owner: String,
+    pub mint: String,
+    pub program_id: String,
+    pub amount: u64,
+    pub decimals: u8,
+}
+
+impl TokenBalance {
+    pub fn ui_amount(&self) -> String {
+        base_units_to_ui_amount(self.amount, self.decimals)
+    }
+
+    pub fn fiat_value(&self, price: f64) -> f64 {
+        self.amount as f64 / 10f64.powi(self.decimals as i32) * price
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ui_amount_and_fiat_value() {
+        let balance = TokenBalance {
+            owner: String::new(),
+            mint: String::new(),
+            program_id: String::new(),
+            amount: 2_500_000,
+            decimals: 6,
+        };
+        assert_eq!(balance.ui_amount(), "2.5");
+        assert_eq!(balance.fiat_value(2.0), 5.0);
+    }
+}
@@ -0,0 +1,13 @@
+/// Resolved metadata of a mint, with its logo in the local image cache once downloaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenMetadata {
+    pub mint: String,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    /// Logo URL from the off-chain JSON the URI points to
+    pub image: Option<String>,
+    pub image_path: Option<String>,
+    /// Unix time the metadata was last fetched
+    pub updated_at: i64,
+}
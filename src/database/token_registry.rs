@@ -0,0 +1,38 @@
+/// How far the wallet trusts a mint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenStatus {
+    /// On the downloaded verified token list
+    Verified,
+    /// Marked as trusted by the user
+    Trusted,
+    Unverified,
+}
+
+impl TokenStatus {
+    /// Status of a registry entry, where being on the verified list outranks the user's trust
+    pub fn from_flags(verified: bool, trusted: bool) -> Self {
+        if verified {
+            TokenStatus::Verified
+        } else if trusted {
+            TokenStatus::Trusted
+        } else {
+            TokenStatus::Unverified
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenStatus::Verified => "verified",
+            TokenStatus::Trusted => "trusted",
+            TokenStatus::Unverified => "unverified",
+        }
+    }
+}
+
+/// What the registry says about a held or entered mint
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenVerdict {
+    pub status: TokenStatus,
+    /// Symbol of the verified token this mint copies, if it copies one
+    pub impersonates: Option<String>,
+}
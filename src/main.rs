@@ -3,13 +3,18 @@
 
 use slint::include_modules as include_slint_modules;
 use std::sync::{Arc, Mutex};
-use tokio;
 
+mod amount;
 mod app;
 mod connection;
+mod das;
 mod database;
 mod initializer;
+mod price_provider;
+mod programs;
 mod services;
+mod solana_pay;
+mod time;
 mod token_value;
 
 use crate::app::errors::AppError;
@@ -0,0 +1,224 @@
+pub mod coingecko;
+pub mod errors;
+pub mod jupiter;
+pub mod pyth;
+
+use crate::connection::Connection;
+use crate::price_provider::{
+    coingecko::CoinGecko, errors::PriceError, jupiter::Jupiter, pyth::Pyth,
+};
+use crate::token_value::TokenData;
+use chrono::Utc;
+use std::{collections::HashMap, env, sync::Arc};
+
+// Providers in the order they are asked when PRICE_PROVIDERS is not set
+const DEFAULT_PROVIDERS: &str = "jupiter,coingecko,pyth";
+/// Prices older than this are asked of the next provider
+pub const MAX_PRICE_AGE_SECS: i64 = 10 * 60;
+
+/// A source of USD prices keyed by mint address
+pub trait PriceProvider {
+    fn name(&self) -> &'static str;
+
+    /// Prices of whichever of `ids` the provider knows. Blocking.
+    fn prices(&self, ids: &[String]) -> Result<HashMap<String, TokenData>, PriceError>;
+}
+
+/// Providers in priority order. Ids a provider fails on, does not know or only has a stale
+/// price for are asked of the next one.
+pub struct PriceChain {
+    providers: Vec<Box<dyn PriceProvider>>,
+    max_age: i64,
+}
+
+impl PriceChain {
+    pub fn new(providers: Vec<Box<dyn PriceProvider>>) -> Self {
+        Self {
+            providers,
+            max_age: MAX_PRICE_AGE_SECS,
+        }
+    }
+
+    /// Providers named in PRICE_PROVIDERS, comma separated, e.g. `pyth,jupiter`
+    pub fn from_env() -> Self {
+        let order = env::var("PRICE_PROVIDERS")
+            .ok()
+            .filter(|order| !order.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_PROVIDERS.to_string());
+        Self::new(order.split(',').filter_map(provider).collect())
+    }
+
+    pub fn prices(&self, ids: &[String]) -> Result<HashMap<String, TokenData>, PriceError> {
+        self.prices_at(ids, Utc::now().timestamp())
+    }
+
+    fn prices_at(
+        &self,
+        ids: &[String],
+        now: i64,
+    ) -> Result<HashMap<String, TokenData>, PriceError> {
+        if self.providers.is_empty() {
+            return Err(PriceError::NoProviders);
+        }
+
+        let mut prices: HashMap<String, TokenData> = HashMap::new();
+        // Stale prices only stand in when no provider has a fresh one
+        let mut stale: HashMap<String, TokenData> = HashMap::new();
+        let mut missing = ids.to_vec();
+        let mut failures = vec![];
+        let mut answered = false;
+        for provider in &self.providers {
+            if missing.is_empty() {
+                break;
+            }
+            match provider.prices(&missing) {
+                Ok(found) => {
+                    answered = true;
+                    for (id, data) in found {
+                        if !missing.contains(&id) {
+                            continue;
+                        }
+                        if now - data.updated_at <= self.max_age {
+                            prices.insert(id, data);
+                        } else if stale
+                            .get(&id)
+                            .is_none_or(|known| known.updated_at < data.updated_at)
+                        {
+                            stale.insert(id, data);
+                        }
+                    }
+                }
+                Err(e) => failures.push(format!("{}: {}", provider.name(), e)),
+            }
+            missing.retain(|id| !prices.contains_key(id));
+        }
+
+        if !answered {
+            return Err(PriceError::Unavailable(failures.join("; ")));
+        }
+        for id in missing {
+            if let Some(data) = stale.remove(&id) {
+                prices.insert(id, data);
+            }
+        }
+        Ok(prices)
+    }
+}
+
+fn provider(name: &str) -> Option<Box<dyn PriceProvider>> {
+    match name.trim().to_lowercase().as_str() {
+        "jupiter" => Some(Box::new(Jupiter::from_env())),
+        "coingecko" => Some(Box::new(CoinGecko::from_env())),
+        "pyth" => Some(Box::new(Pyth::new(Arc::new(
+            Connection::new().connection(),
+        )))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    // Ids asked of a provider, one entry per call
+    type Asked = Rc<RefCell<Vec<Vec<String>>>>;
+
+    // Answers from a fixed table, or fails when it has none, and records what it was asked
+    struct MockProvider {
+        prices: Option<Vec<(&'static str, f64, i64)>>,
+        asked: Asked,
+    }
+
+    impl PriceProvider for MockProvider {
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn prices(&self, ids: &[String]) -> Result<HashMap<String, TokenData>, PriceError> {
+            self.asked.borrow_mut().push(ids.to_vec());
+            let prices = self.prices.as_ref().ok_or(PriceError::NoProviders)?;
+            Ok(prices
+                .iter()
+                .filter(|(id, _, _)| ids.iter().any(|wanted| wanted == id))
+                .map(|&(id, price, updated_at)| {
+                    (
+                        id.to_string(),
+                        TokenData {
+                            id: id.to_string(),
+                            price,
+                            price_change_24h: None,
+                            updated_at,
+                        },
+                    )
+                })
+                .collect())
+        }
+    }
+
+    fn mock(prices: Option<Vec<(&'static str, f64, i64)>>) -> (Box<dyn PriceProvider>, Asked) {
+        let asked = Rc::new(RefCell::new(vec![]));
+        let provider = MockProvider {
+            prices,
+            asked: asked.clone(),
+        };
+        (Box::new(provider), asked)
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_fallback_order() {
+        let now = 1_000_000;
+        let (failing, failing_asked) = mock(None);
+        let (first, _) = mock(Some(vec![("Sol", 150.0, now), ("Old", 2.0, now - 3600)]));
+        let (second, second_asked) = mock(Some(vec![("Bonk", 0.00002, now), ("Sol", 1.0, now)]));
+        let chain = PriceChain::new(vec![failing, first, second]);
+
+        let prices = chain
+            .prices_at(&ids(&["Sol", "Bonk", "Old", "Unknown"]), now)
+            .unwrap();
+        assert_eq!(prices["Sol"].price, 150.0);
+        assert_eq!(prices["Bonk"].price, 0.00002);
+        // No one had a fresh price, so the stale one stands in
+        assert_eq!(prices["Old"].price, 2.0);
+        assert!(!prices.contains_key("Unknown"));
+
+        assert_eq!(failing_asked.borrow().len(), 1);
+        // Only what the earlier providers could not price freshly moves down the chain
+        assert_eq!(
+            *second_asked.borrow(),
+            vec![ids(&["Bonk", "Old", "Unknown"])]
+        );
+    }
+
+    #[test]
+    fn test_fresh_price_replaces_stale() {
+        let now = 1_000_000;
+        let (stale, _) = mock(Some(vec![("Sol", 100.0, now - 3600)]));
+        let (fresh, _) = mock(Some(vec![("Sol", 150.0, now - 60)]));
+        let chain = PriceChain::new(vec![stale, fresh]);
+
+        let prices = chain.prices_at(&ids(&["Sol"]), now).unwrap();
+        assert_eq!(prices["Sol"].price, 150.0);
+    }
+
+    #[test]
+    fn test_every_provider_failing() {
+        let (first, _) = mock(None);
+        let (second, _) = mock(None);
+        let chain = PriceChain::new(vec![first, second]);
+        assert!(matches!(
+            chain.prices_at(&ids(&["Sol"]), 0),
+            Err(PriceError::Unavailable(reason)) if reason.starts_with("mock: ")
+        ));
+
+        let chain = PriceChain::new(vec![]);
+        assert!(matches!(
+            chain.prices_at(&ids(&["Sol"]), 0),
+            Err(PriceError::NoProviders)
+        ));
+    }
+}
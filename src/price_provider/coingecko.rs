@@ -0,0 +1,227 @@
+use crate::price_provider::{errors::PriceError, PriceProvider};
+use crate::token_value::TokenData;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::{collections::HashMap, env};
+
+pub const COINGECKO_URL: &str = "https://api.coingecko.com/api/v3";
+// Addresses per request, kept small for the public API's limits
+const IDS_PER_REQUEST: usize = 30;
+
+#[derive(Deserialize, Debug)]
+struct CoinGeckoPrice {
+    usd: Option<f64>,
+    usd_24h_change: Option<f64>,
+    #[serde(default)]
+    last_updated_at: i64,
+}
+
+#[derive(Deserialize, Debug)]
+struct MarketChart {
+    /// Millisecond timestamps with the USD price at each
+    prices: Vec<(f64, f64)>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CoinHistory {
+    /// Missing for days before the coin was listed
+    market_data: Option<MarketData>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MarketData {
+    current_price: HashMap<String, f64>,
+}
+
+/// Any API that serves CoinGecko's `simple/token_price`, `market_chart` and `history`
+/// endpoints for Solana mints
+pub struct CoinGecko {
+    url: String,
+}
+
+impl CoinGecko {
+    pub fn new(url: String) -> Self {
+        Self { url }
+    }
+
+    /// The API at COINGECKO_URL, or CoinGecko's own when it is not set
+    pub fn from_env() -> Self {
+        Self::new(env::var("COINGECKO_URL").unwrap_or_else(|_| COINGECKO_URL.to_string()))
+    }
+
+    /// USD prices of `mint` over the last `days` days, oldest first, as unix timestamps with
+    /// the price at each. Blocking.
+    pub fn history(&self, mint: &str, days: u32) -> Result<Vec<(i64, f64)>, PriceError> {
+        let url = format!(
+            "{}/coins/solana/contract/{}/market_chart?vs_currency=usd&days={}",
+            self.url, mint, days
+        );
+        let chart: MarketChart = reqwest::blocking::get(&url)?.error_for_status()?.json()?;
+        Ok(chart_points(chart))
+    }
+
+    /// USD price of SOL at 00:00 UTC on `date`, `None` when no market data exists for that
+    /// day. Blocking.
+    pub fn sol_price_on(&self, date: NaiveDate) -> Result<Option<f64>, PriceError> {
+        let url = format!(
+            "{}/coins/solana/history?date={}&localization=false",
+            self.url,
+            date.format("%d-%m-%Y")
+        );
+        let history: CoinHistory = reqwest::blocking::get(&url)?.error_for_status()?.json()?;
+        Ok(usd_price(history))
+    }
+}
+
+impl PriceProvider for CoinGecko {
+    fn name(&self) -> &'static str {
+        "coingecko"
+    }
+
+    fn prices(&self, ids: &[String]) -> Result<HashMap<String, TokenData>, PriceError> {
+        let mut prices = HashMap::new();
+        for batch in ids.chunks(IDS_PER_REQUEST) {
+            let url = format!(
+                "{}/simple/token_price/solana?contract_addresses={}&vs_currencies=usd\
+                &include_24hr_change=true&include_last_updated_at=true",
+                self.url,
+                batch.join(",")
+            );
+            let response: HashMap<String, CoinGeckoPrice> =
+                reqwest::blocking::get(&url)?.error_for_status()?.json()?;
+            prices.extend(batch_prices(batch, response));
+        }
+        Ok(prices)
+    }
+}
+
+// Prices in `response` keyed by the mint in `batch` each was asked for, leaving out those
+// without a USD price
+fn batch_prices(
+    batch: &[String],
+    response: HashMap<String, CoinGeckoPrice>,
+) -> HashMap<String, TokenData> {
+    let mut prices = HashMap::new();
+    for (address, price) in response {
+        // Addresses may come back lowercased, so match them to the requested mint
+        let (Some(id), Some(usd)) = (
+            batch.iter().find(|id| id.eq_ignore_ascii_case(&address)),
+            price.usd,
+        ) else {
+            continue;
+        };
+        let data = TokenData {
+            id: id.clone(),
+            price: usd,
+            price_change_24h: price.usd_24h_change,
+            updated_at: price.last_updated_at,
+        };
+        prices.insert(id.clone(), data);
+    }
+    prices
+}
+
+fn chart_points(chart: MarketChart) -> Vec<(i64, f64)> {
+    chart
+        .prices
+        .into_iter()
+        .map(|(millis, price)| ((millis / 1000.0) as i64, price))
+        .collect()
+}
+
+fn usd_price(history: CoinHistory) -> Option<f64> {
+    history
+        .market_data
+        .and_then(|data| data.current_price.get("usd").copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BONK: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qJxa2NJ1YazzXzVN9m5zHPXbiy";
+
+    #[test]
+    fn test_batch_prices() {
+        let response: HashMap<String, CoinGeckoPrice> = serde_json::from_str(&format!(
+            r#"{{
+                "{}": {{
+                    "usd": 0.00002,
+                    "usd_24h_change": -3.5,
+                    "last_updated_at": 1700000000
+                }},
+                "{}": {{ "usd": 1.0 }},
+                "Unasked": {{ "usd": 5.0, "last_updated_at": 1700000000 }},
+                "{}": {{}}
+            }}"#,
+            BONK.to_lowercase(),
+            USDC,
+            "So11111111111111111111111111111111111111112"
+        ))
+        .unwrap();
+        let batch = vec![
+            BONK.to_string(),
+            USDC.to_string(),
+            "So11111111111111111111111111111111111111112".to_string(),
+        ];
+
+        let prices = batch_prices(&batch, response);
+        // Lowercased addresses are keyed by the mint as asked for
+        let bonk = &prices[BONK];
+        assert_eq!(bonk.id, BONK);
+        assert_eq!(bonk.price, 0.00002);
+        assert_eq!(bonk.price_change_24h, Some(-3.5));
+        assert_eq!(bonk.updated_at, 1_700_000_000);
+        // Partial entries keep what they have
+        assert_eq!(prices[USDC].price_change_24h, None);
+        assert_eq!(prices[USDC].updated_at, 0);
+        // Entries without a price or that were not asked for are left out
+        assert_eq!(prices.len(), 2);
+    }
+
+    #[test]
+    fn test_chart_points() {
+        let chart: MarketChart = serde_json::from_str(
+            r#"{
+                "prices": [[1700000000000, 1.5], [1700003600500.0, 1.6]],
+                "market_caps": [[1700000000000, 1000000]],
+                "total_volumes": [[1700000000000, 5000]]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            chart_points(chart),
+            vec![(1_700_000_000, 1.5), (1_700_003_600, 1.6)]
+        );
+
+        let empty: MarketChart = serde_json::from_str(r#"{ "prices": [] }"#).unwrap();
+        assert!(chart_points(empty).is_empty());
+        assert!(serde_json::from_str::<MarketChart>(r#"{ "error": "coin not found" }"#).is_err());
+    }
+
+    #[test]
+    fn test_usd_price() {
+        let history: CoinHistory = serde_json::from_str(
+            r#"{
+                "id": "solana",
+                "symbol": "sol",
+                "market_data": {
+                    "current_price": { "eur": 120.5, "usd": 131.25 },
+                    "market_cap": { "usd": 60000000000 }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(usd_price(history), Some(131.25));
+
+        // Days before the coin was listed come back without market data
+        let unlisted: CoinHistory =
+            serde_json::from_str(r#"{ "id": "solana", "symbol": "sol" }"#).unwrap();
+        assert_eq!(usd_price(unlisted), None);
+        let no_usd: CoinHistory =
+            serde_json::from_str(r#"{ "market_data": { "current_price": { "eur": 1.0 } } }"#)
+                .unwrap();
+        assert_eq!(usd_price(no_usd), None);
+    }
+}
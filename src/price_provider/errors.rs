@@ -0,0 +1,24 @@
+use reqwest::Error as ReqwestError;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PriceError {
+    #[error("Request error: {0}")]
+    RequestError(#[from] ReqwestError),
+
+    #[error("Client error: {0}")]
+    ClientError(#[from] Box<ClientError>),
+
+    #[error("No price providers are configured")]
+    NoProviders,
+
+    #[error("No price provider answered: {0}")]
+    Unavailable(String),
+}
+
+impl From<ClientError> for PriceError {
+    fn from(error: ClientError) -> Self {
+        PriceError::ClientError(Box::new(error))
+    }
+}
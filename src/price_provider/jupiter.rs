@@ -0,0 +1,141 @@
+use crate::connection::{Connection, ConnectionNetwork};
+use crate::price_provider::{errors::PriceError, PriceProvider};
+use crate::token_value::TokenData;
+use chrono::Utc;
+use serde::Deserialize;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::clock::DEFAULT_MS_PER_SLOT;
+use std::{collections::HashMap, env, sync::Arc};
+
+pub const JUPITER_PRICE_URL: &str = "https://lite-api.jup.ag/price/v3";
+// Most ids the price API accepts per request
+const IDS_PER_REQUEST: usize = 50;
+
+#[derive(Deserialize, Debug)]
+struct JupiterPrice {
+    #[serde(rename = "usdPrice")]
+    usd_price: f64,
+    #[serde(rename = "priceChange24h", default)]
+    price_change_24h: Option<f64>,
+    /// Slot the price was last updated in
+    #[serde(rename = "blockId", default)]
+    block_id: Option<u64>,
+}
+
+/// Jupiter's price API, which prices any mint with enough liquidity
+pub struct Jupiter {
+    url: String,
+    /// Mainnet, whose slots the prices are dated by
+    client: Arc<RpcClient>,
+}
+
+impl Jupiter {
+    pub fn new(url: String, client: Arc<RpcClient>) -> Self {
+        Self { url, client }
+    }
+
+    /// The API at JUPITER_URL, or Jupiter's own when it is not set, with prices dated by
+    /// mainnet whichever network the wallet is on
+    pub fn from_env() -> Self {
+        let mainnet = Connection {
+            network: ConnectionNetwork::MAINNET,
+        };
+        Self::new(
+            env::var("JUPITER_URL").unwrap_or_else(|_| JUPITER_PRICE_URL.to_string()),
+            Arc::new(mainnet.connection()),
+        )
+    }
+
+    // Current mainnet slot, if it can be had. Prices are still worth serving without it.
+    fn reference_slot(&self) -> Option<u64> {
+        self.client.get_slot().ok()
+    }
+}
+
+impl PriceProvider for Jupiter {
+    fn name(&self) -> &'static str {
+        "jupiter"
+    }
+
+    fn prices(&self, ids: &[String]) -> Result<HashMap<String, TokenData>, PriceError> {
+        let now = Utc::now().timestamp();
+        // Prices carry the slot they were updated in rather than a time
+        let slot = self.reference_slot();
+        let mut prices = HashMap::new();
+        for batch in ids.chunks(IDS_PER_REQUEST) {
+            let url = format!("{}?ids={}", self.url, batch.join(","));
+            // Mints without a price are either missing or null
+            let response: HashMap<String, Option<JupiterPrice>> =
+                reqwest::blocking::get(&url)?.error_for_status()?.json()?;
+            for (id, price) in response {
+                if let Some(price) = price {
+                    let data = TokenData {
+                        id: id.clone(),
+                        price: price.usd_price,
+                        price_change_24h: price.price_change_24h,
+                        updated_at: updated_at(now, slot, price.block_id),
+                    };
+                    prices.insert(id, data);
+                }
+            }
+        }
+        Ok(prices)
+    }
+}
+
+// When a price updated in `block_id` was set, going by how many slots before the current
+// mainnet `slot` that was. Prices are taken to be live when either slot is unknown.
+fn updated_at(now: i64, slot: Option<u64>, block_id: Option<u64>) -> i64 {
+    let slots_ago = match (slot, block_id) {
+        (Some(slot), Some(block_id)) => slot.saturating_sub(block_id),
+        _ => 0,
+    };
+    now - (slots_ago * DEFAULT_MS_PER_SLOT / 1000) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_updated_at() {
+        let response: HashMap<String, Option<JupiterPrice>> = serde_json::from_str(
+            r#"{
+                "So11111111111111111111111111111111111111112": {
+                    "usdPrice": 147.47,
+                    "blockId": 348004023,
+                    "decimals": 9,
+                    "priceChange24h": 1.29
+                },
+                "Unpriced": null
+            }"#,
+        )
+        .unwrap();
+        let sol = response["So11111111111111111111111111111111111111112"]
+            .as_ref()
+            .unwrap();
+        assert_eq!(sol.block_id, Some(348_004_023));
+
+        let now = 1_000_000;
+        // 1,500 slots of 400ms each
+        assert_eq!(updated_at(now, Some(348_005_523), sol.block_id), now - 600);
+        assert_eq!(updated_at(now, Some(348_004_023), sol.block_id), now);
+        assert_eq!(updated_at(now, Some(348_005_523), None), now);
+        assert_eq!(updated_at(now, None, sol.block_id), now);
+    }
+
+    #[test]
+    fn test_reference_slot_when_rpc_fails() {
+        let failing = Jupiter::new(
+            JUPITER_PRICE_URL.to_string(),
+            Arc::new(RpcClient::new_mock("fails".to_string())),
+        );
+        assert_eq!(failing.reference_slot(), None);
+
+        let working = Jupiter::new(
+            JUPITER_PRICE_URL.to_string(),
+            Arc::new(RpcClient::new_mock("succeeds".to_string())),
+        );
+        assert!(working.reference_slot().is_some());
+    }
+}
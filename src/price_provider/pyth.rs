@@ -0,0 +1,162 @@
+use crate::price_provider::{errors::PriceError, PriceProvider};
+use crate::token_value::TokenData;
+use serde::Deserialize;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+// USD price feed of each mint Pyth covers, and the account its updates are posted to
+const FEEDS: &str = include_str!("pyth_feeds.json");
+
+#[derive(Deserialize, Debug)]
+struct FeedEntry {
+    feed_id: String,
+    account: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PriceFeed {
+    id: [u8; 32],
+    account: Pubkey,
+}
+
+/// Pyth price feed accounts read straight from the cluster
+pub struct Pyth {
+    client: Arc<RpcClient>,
+    feeds: HashMap<String, PriceFeed>,
+}
+
+impl Pyth {
+    pub fn new(client: Arc<RpcClient>) -> Self {
+        let entries: HashMap<String, FeedEntry> = serde_json::from_str(FEEDS).unwrap_or_default();
+        Self {
+            client,
+            feeds: entries
+                .into_iter()
+                .filter_map(|(mint, entry)| {
+                    let feed = PriceFeed {
+                        id: feed_id(&entry.feed_id)?,
+                        account: Pubkey::from_str(&entry.account).ok()?,
+                    };
+                    Some((mint, feed))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl PriceProvider for Pyth {
+    fn name(&self) -> &'static str {
+        "pyth"
+    }
+
+    fn prices(&self, ids: &[String]) -> Result<HashMap<String, TokenData>, PriceError> {
+        let feeds: Vec<(&String, &PriceFeed)> = ids
+            .iter()
+            .filter_map(|id| Some((id, self.feeds.get(id)?)))
+            .collect();
+        if feeds.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let addresses: Vec<Pubkey> = feeds.iter().map(|(_, feed)| feed.account).collect();
+        let accounts = self.client.get_multiple_accounts(&addresses)?;
+
+        Ok(feeds
+            .into_iter()
+            .zip(accounts)
+            .filter_map(|((id, feed), account)| {
+                let update = parse_price_update(&account?.data)?;
+                // An account holding some other feed's price is not this mint's price
+                (update.feed_id == feed.id).then(|| {
+                    let data = TokenData {
+                        id: id.clone(),
+                        price: update.price,
+                        price_change_24h: None,
+                        updated_at: update.publish_time,
+                    };
+                    (id.clone(), data)
+                })
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct PriceUpdate {
+    feed_id: [u8; 32],
+    price: f64,
+    publish_time: i64,
+}
+
+// A PriceUpdateV2 account: discriminator, write authority, verification level, then the
+// price message
+fn parse_price_update(data: &[u8]) -> Option<PriceUpdate> {
+    let level = 8 + 32;
+    // Partial verification carries the number of signatures checked
+    let message = match data.get(level)? {
+        0 => level + 2,
+        1 => level + 1,
+        _ => return None,
+    };
+    let bytes = |offset: usize, len: usize| data.get(message + offset..message + offset + len);
+    let feed_id: [u8; 32] = bytes(0, 32)?.try_into().ok()?;
+    let price = i64::from_le_bytes(bytes(32, 8)?.try_into().ok()?);
+    let exponent = i32::from_le_bytes(bytes(48, 4)?.try_into().ok()?);
+    let publish_time = i64::from_le_bytes(bytes(52, 8)?.try_into().ok()?);
+    Some(PriceUpdate {
+        feed_id,
+        price: price as f64 * 10f64.powi(exponent),
+        publish_time,
+    })
+}
+
+fn feed_id(hex: &str) -> Option<[u8; 32]> {
+    let hex = hex.trim_start_matches("0x");
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    bytes.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_price_update() {
+        let feed =
+            feed_id("ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d").unwrap();
+        assert_eq!(feed[0], 0xef);
+        assert_eq!(feed_id("0xef0d"), None);
+
+        let mut data = vec![0; 8];
+        data.extend(Pubkey::new_unique().to_bytes());
+        // Fully verified
+        data.push(1);
+        data.extend(feed);
+        data.extend(15_012_345_678i64.to_le_bytes());
+        data.extend(1_000_000u64.to_le_bytes());
+        data.extend((-8i32).to_le_bytes());
+        data.extend(1_700_000_000i64.to_le_bytes());
+
+        let update = parse_price_update(&data).unwrap();
+        assert_eq!(update.feed_id, feed);
+        assert!((update.price - 150.12345678).abs() < 1e-9);
+        assert_eq!(update.publish_time, 1_700_000_000);
+
+        // Partially verified updates carry one more byte before the message
+        data.insert(41, 3);
+        data[40] = 0;
+        assert_eq!(parse_price_update(&data), Some(update));
+        assert_eq!(parse_price_update(&data[..60]), None);
+    }
+
+    #[test]
+    fn test_bundled_feeds() {
+        let pyth = Pyth::new(Arc::new(RpcClient::new_mock("succeeds".to_string())));
+        let sol = pyth.feeds["So11111111111111111111111111111111111111112"];
+        assert_eq!(sol.id[..2], [0xef, 0x0d]);
+        assert_eq!(pyth.feeds.len(), 3);
+    }
+}
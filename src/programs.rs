@@ -0,0 +1,7 @@
+pub mod associated_token;
+pub mod bubblegum;
+pub mod decoder;
+pub mod memo;
+pub mod metadata;
+pub mod stake_pool;
+pub mod token;
@@ -0,0 +1,81 @@
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use solana_system_interface::program::ID as SYSTEM_PROGRAM_ID;
+
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+const CREATE_IDEMPOTENT: u8 = 1;
+
+pub fn get_associated_token_address(
+    wallet: &Pubkey,
+    mint: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[wallet.as_ref(), token_program_id.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Create the associated token account if it does not exist yet, otherwise do nothing
+pub fn create_associated_token_account_idempotent(
+    payer: &Pubkey,
+    wallet: &Pubkey,
+    mint: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    let associated_account = get_associated_token_address(wallet, mint, token_program_id);
+
+    Instruction {
+        program_id: ASSOCIATED_TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(associated_account, false),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new_readonly(*token_program_id, false),
+        ],
+        data: vec![CREATE_IDEMPOTENT],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::token::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_get_associated_token_address() {
+        let wallet = Pubkey::from_str("11111111111111111111111111111111").unwrap();
+        let mint = Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap();
+        let address = get_associated_token_address(&wallet, &mint, &TOKEN_PROGRAM_ID);
+
+        assert_ne!(
+            address,
+            get_associated_token_address(&wallet, &mint, &TOKEN_2022_PROGRAM_ID)
+        );
+        assert!(!address.is_on_curve());
+    }
+
+    #[test]
+    fn test_create_associated_token_account_idempotent() {
+        let payer = Pubkey::new_unique();
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let instruction =
+            create_associated_token_account_idempotent(&payer, &wallet, &mint, &TOKEN_PROGRAM_ID);
+
+        assert_eq!(instruction.program_id, ASSOCIATED_TOKEN_PROGRAM_ID);
+        assert_eq!(instruction.data, vec![CREATE_IDEMPOTENT]);
+        assert_eq!(
+            instruction.accounts[1].pubkey,
+            get_associated_token_address(&wallet, &mint, &TOKEN_PROGRAM_ID)
+        );
+        assert!(instruction.accounts[0].is_signer);
+    }
+}
@@ -0,0 +1,184 @@
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use solana_system_interface::program::ID as SYSTEM_PROGRAM_ID;
+
+pub const BUBBLEGUM_PROGRAM_ID: Pubkey = pubkey!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+pub const NOOP_PROGRAM_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+pub const ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey =
+    pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+
+// Anchor discriminators of Bubblegum's instructions
+const TRANSFER: [u8; 8] = [163, 52, 200, 231, 140, 3, 69, 186];
+const BURN: [u8; 8] = [116, 110, 29, 56, 107, 219, 42, 93];
+
+// Account type, version, buffer size, depth, authority, creation slot and padding
+const TREE_HEADER_LEN: usize = 56;
+// Sequence number, active index and buffer size of a concurrent merkle tree
+const TREE_COUNTERS_LEN: usize = 24;
+
+/// A compressed leaf as the indexer describes it, with the proof that it is in the tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leaf {
+    pub merkle_tree: Pubkey,
+    pub root: [u8; 32],
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+    /// Leaf index, which Bubblegum also uses as the nonce
+    pub index: u32,
+    /// Proof nodes from the leaf upwards
+    pub proof: Vec<Pubkey>,
+}
+
+pub fn tree_authority(merkle_tree: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[merkle_tree.as_ref()], &BUBBLEGUM_PROGRAM_ID).0
+}
+
+/// Move a compressed NFT from `owner` to `new_owner`.
+/// Proof nodes the tree keeps in its canopy are left out, so large trees still fit.
+pub fn transfer(
+    leaf: &Leaf,
+    owner: &Pubkey,
+    delegate: &Pubkey,
+    new_owner: &Pubkey,
+    canopy_depth: usize,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(tree_authority(&leaf.merkle_tree), false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(*delegate, false),
+        AccountMeta::new_readonly(*new_owner, false),
+        AccountMeta::new(leaf.merkle_tree, false),
+        AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+        AccountMeta::new_readonly(ACCOUNT_COMPRESSION_PROGRAM_ID, false),
+        AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+    ];
+    leaf_instruction(TRANSFER, leaf, accounts, canopy_depth)
+}
+
+/// Burn a compressed NFT of `owner` by replacing its leaf with an empty one
+pub fn burn(leaf: &Leaf, owner: &Pubkey, delegate: &Pubkey, canopy_depth: usize) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(tree_authority(&leaf.merkle_tree), false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(*delegate, false),
+        AccountMeta::new(leaf.merkle_tree, false),
+        AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+        AccountMeta::new_readonly(ACCOUNT_COMPRESSION_PROGRAM_ID, false),
+        AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+    ];
+    leaf_instruction(BURN, leaf, accounts, canopy_depth)
+}
+
+// Both instructions identify the leaf the same way, followed by the proof nodes
+fn leaf_instruction(
+    discriminator: [u8; 8],
+    leaf: &Leaf,
+    mut accounts: Vec<AccountMeta>,
+    canopy_depth: usize,
+) -> Instruction {
+    let needed = leaf.proof.len().saturating_sub(canopy_depth);
+    accounts.extend(
+        leaf.proof[..needed]
+            .iter()
+            .map(|node| AccountMeta::new_readonly(*node, false)),
+    );
+
+    let mut data = discriminator.to_vec();
+    data.extend_from_slice(&leaf.root);
+    data.extend_from_slice(&leaf.data_hash);
+    data.extend_from_slice(&leaf.creator_hash);
+    data.extend_from_slice(&u64::from(leaf.index).to_le_bytes());
+    data.extend_from_slice(&leaf.index.to_le_bytes());
+    Instruction {
+        program_id: BUBBLEGUM_PROGRAM_ID,
+        accounts,
+        data,
+    }
+}
+
+/// How many levels of the tree sit in its on-chain canopy, from the raw tree account
+pub fn canopy_depth(data: &[u8]) -> Option<usize> {
+    let max_buffer_size = u32::from_le_bytes(data.get(2..6)?.try_into().ok()?) as usize;
+    let max_depth = u32::from_le_bytes(data.get(6..10)?.try_into().ok()?) as usize;
+    // Each changelog entry and the rightmost path hold a path of nodes plus a leaf or root and an index
+    let path_len = 40 + 32 * max_depth;
+    let tree_len = TREE_COUNTERS_LEN + max_buffer_size * path_len + path_len;
+    let canopy_nodes = data.len().checked_sub(TREE_HEADER_LEN + tree_len)? / 32;
+    // A canopy of depth d stores 2 + 4 + ... + 2^d nodes
+    Some(((canopy_nodes + 2).ilog2() as usize).saturating_sub(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree_data(max_depth: u32, max_buffer_size: u32, canopy_depth: u32) -> Vec<u8> {
+        let mut data = vec![1, 1];
+        data.extend_from_slice(&max_buffer_size.to_le_bytes());
+        data.extend_from_slice(&max_depth.to_le_bytes());
+        let path_len = 40 + 32 * max_depth as usize;
+        let canopy_nodes = (1usize << (canopy_depth + 1)) - 2;
+        data.resize(
+            TREE_HEADER_LEN
+                + TREE_COUNTERS_LEN
+                + (max_buffer_size as usize + 1) * path_len
+                + canopy_nodes * 32,
+            0,
+        );
+        data
+    }
+
+    #[test]
+    fn test_canopy_depth() {
+        assert_eq!(canopy_depth(&tree_data(14, 64, 0)), Some(0));
+        assert_eq!(canopy_depth(&tree_data(14, 64, 10)), Some(10));
+        assert_eq!(canopy_depth(&tree_data(20, 256, 14)), Some(14));
+        assert_eq!(canopy_depth(&[1, 1]), None);
+    }
+
+    fn leaf() -> Leaf {
+        Leaf {
+            merkle_tree: Pubkey::new_unique(),
+            root: [1; 32],
+            data_hash: [2; 32],
+            creator_hash: [3; 32],
+            index: 7,
+            proof: (0..14).map(|_| Pubkey::new_unique()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_transfer() {
+        let leaf = leaf();
+        let owner = Pubkey::new_unique();
+        let new_owner = Pubkey::new_unique();
+
+        let instruction = transfer(&leaf, &owner, &owner, &new_owner, 10);
+        assert_eq!(instruction.program_id, BUBBLEGUM_PROGRAM_ID);
+        assert_eq!(instruction.accounts.len(), 8 + 4);
+        assert_eq!(
+            instruction.accounts[0].pubkey,
+            tree_authority(&leaf.merkle_tree)
+        );
+        assert!(instruction.accounts[1].is_signer);
+        assert!(instruction.accounts[4].is_writable);
+        assert_eq!(instruction.accounts[8].pubkey, leaf.proof[0]);
+        assert_eq!(&instruction.data[..8], &TRANSFER);
+        assert_eq!(instruction.data.len(), 8 + 96 + 8 + 4);
+        assert_eq!(&instruction.data[104..112], &7u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_burn() {
+        let leaf = leaf();
+        let owner = Pubkey::new_unique();
+
+        let instruction = burn(&leaf, &owner, &owner, 14);
+        assert_eq!(instruction.accounts.len(), 7);
+        assert!(instruction.accounts[1].is_signer);
+        assert!(instruction.accounts[3].is_writable);
+        assert_eq!(&instruction.data[..8], &BURN);
+        assert_eq!(instruction.data.len(), 8 + 96 + 8 + 4);
+    }
+}
@@ -0,0 +1,789 @@
+use crate::amount::{base_units_to_ui_amount, SOL_DECIMALS};
+use crate::programs::{
+    associated_token::ASSOCIATED_TOKEN_PROGRAM_ID,
+    bubblegum::BUBBLEGUM_PROGRAM_ID,
+    memo::is_memo_program,
+    metadata::METADATA_PROGRAM_ID,
+    token::{is_token_program, known_symbol, TOKEN_2022_PROGRAM_ID},
+};
+use solana_address_lookup_table_interface::{
+    instruction::ProgramInstruction as LookupTableInstruction,
+    program::ID as LOOKUP_TABLE_PROGRAM_ID,
+};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::VersionedTransaction;
+use solana_stake_interface::{
+    instruction::StakeInstruction, program::ID as STAKE_PROGRAM_ID, state::StakeAuthorize,
+};
+use solana_system_interface::{instruction::SystemInstruction, program::ID as SYSTEM_PROGRAM_ID};
+use solana_vote_interface::{
+    instruction::VoteInstruction, program::ID as VOTE_PROGRAM_ID, state::VoteAuthorize,
+};
+
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey =
+    pubkey!("ComputeBudget111111111111111111111111111111");
+
+// Authority kinds of SetAuthority, in Token-2022 order which extends the original four
+const AUTHORITY_TYPES: [&str; 15] = [
+    "mint",
+    "freeze",
+    "owner",
+    "close",
+    "transfer fee config",
+    "withheld withdraw",
+    "close mint",
+    "interest rate",
+    "permanent delegate",
+    "confidential transfer mint",
+    "transfer hook program",
+    "confidential transfer fee config",
+    "metadata pointer",
+    "group pointer",
+    "group member pointer",
+];
+
+// Token-2022 extension instructions, starting at tag 25
+const TOKEN_2022_EXTENSIONS: [&str; 17] = [
+    "Initialize mint close authority",
+    "Transfer fee extension",
+    "Confidential transfer extension",
+    "Default account state extension",
+    "Reallocate",
+    "Memo transfer extension",
+    "Create native mint",
+    "Initialize non-transferable mint",
+    "Interest-bearing mint extension",
+    "CPI guard extension",
+    "Initialize permanent delegate",
+    "Transfer hook extension",
+    "Confidential transfer fee extension",
+    "Withdraw excess lamports",
+    "Metadata pointer extension",
+    "Group pointer extension",
+    "Group member pointer extension",
+];
+
+/// Accounts of one instruction; those loaded from lookup tables may be unknown
+struct Accounts<'a>(&'a [Option<Pubkey>]);
+
+impl Accounts<'_> {
+    fn key(&self, index: usize) -> Option<Pubkey> {
+        self.0.get(index).copied().flatten()
+    }
+
+    fn get(&self, index: usize) -> String {
+        match self.key(index) {
+            Some(key) => key.to_string(),
+            None => "an unknown account".to_string(),
+        }
+    }
+}
+
+/// One readable line per instruction
+pub fn describe_instructions(instructions: &[Instruction]) -> Vec<String> {
+    instructions
+        .iter()
+        .map(|instruction| {
+            let accounts: Vec<Pubkey> = instruction
+                .accounts
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect();
+            describe_instruction(&instruction.program_id, &accounts, &instruction.data)
+        })
+        .collect()
+}
+
+/// Describe the top-level instructions of a transaction. `loaded` holds the addresses it
+/// loads from lookup tables, writable before read-only, when they are known
+pub fn describe_transaction(transaction: &VersionedTransaction, loaded: &[Pubkey]) -> Vec<String> {
+    let keys: Vec<Pubkey> = transaction
+        .message
+        .static_account_keys()
+        .iter()
+        .chain(loaded)
+        .copied()
+        .collect();
+    transaction
+        .message
+        .instructions()
+        .iter()
+        .map(|instruction| {
+            let accounts: Vec<Option<Pubkey>> = instruction
+                .accounts
+                .iter()
+                .map(|index| keys.get(*index as usize).copied())
+                .collect();
+            match keys.get(instruction.program_id_index as usize) {
+                Some(program_id) => describe(program_id, &Accounts(&accounts), &instruction.data),
+                None => format!(
+                    "Instruction for a program from a lookup table: {}",
+                    hex(&instruction.data)
+                ),
+            }
+        })
+        .collect()
+}
+
+pub fn describe_instruction(program_id: &Pubkey, accounts: &[Pubkey], data: &[u8]) -> String {
+    let accounts: Vec<Option<Pubkey>> = accounts.iter().copied().map(Some).collect();
+    describe(program_id, &Accounts(&accounts), data)
+}
+
+fn describe(program_id: &Pubkey, accounts: &Accounts, data: &[u8]) -> String {
+    let description = if *program_id == SYSTEM_PROGRAM_ID {
+        describe_system(accounts, data)
+    } else if is_token_program(program_id) {
+        describe_token(program_id, accounts, data)
+    } else if *program_id == ASSOCIATED_TOKEN_PROGRAM_ID {
+        describe_associated_token(accounts, data)
+    } else if *program_id == STAKE_PROGRAM_ID {
+        describe_stake(accounts, data)
+    } else if is_memo_program(program_id) {
+        Some(format!("Memo: \"{}\"", String::from_utf8_lossy(data)))
+    } else if *program_id == COMPUTE_BUDGET_PROGRAM_ID {
+        describe_compute_budget(data)
+    } else if *program_id == LOOKUP_TABLE_PROGRAM_ID {
+        describe_lookup_table(accounts, data)
+    } else if *program_id == VOTE_PROGRAM_ID {
+        describe_vote(accounts, data)
+    } else {
+        None
+    };
+
+    description.unwrap_or_else(|| match program_name(program_id) {
+        Some(name) => format!("Unrecognised {} instruction: {}", name, hex(data)),
+        None => format!("Program {}: {}", program_id, hex(data)),
+    })
+}
+
+fn program_name(program_id: &Pubkey) -> Option<&'static str> {
+    let name = if *program_id == SYSTEM_PROGRAM_ID {
+        "System Program"
+    } else if *program_id == TOKEN_2022_PROGRAM_ID {
+        "Token-2022"
+    } else if is_token_program(program_id) {
+        "SPL Token"
+    } else if *program_id == ASSOCIATED_TOKEN_PROGRAM_ID {
+        "Associated Token Account"
+    } else if *program_id == STAKE_PROGRAM_ID {
+        "Stake Program"
+    } else if *program_id == COMPUTE_BUDGET_PROGRAM_ID {
+        "Compute Budget"
+    } else if *program_id == LOOKUP_TABLE_PROGRAM_ID {
+        "Address Lookup Table"
+    } else if *program_id == VOTE_PROGRAM_ID {
+        "Vote Program"
+    } else if *program_id == METADATA_PROGRAM_ID {
+        "Token Metadata"
+    } else if *program_id == BUBBLEGUM_PROGRAM_ID {
+        "Bubblegum"
+    } else {
+        return None;
+    };
+    Some(name)
+}
+
+fn hex(data: &[u8]) -> String {
+    if data.is_empty() {
+        return "no data".to_string();
+    }
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn sol(lamports: u64) -> String {
+    format!("{} SOL", base_units_to_ui_amount(lamports, SOL_DECIMALS))
+}
+
+fn token_amount(amount: u64, decimals: u8, mint: Option<Pubkey>) -> String {
+    let amount = base_units_to_ui_amount(amount, decimals);
+    let symbol = mint.and_then(|mint| known_symbol(&mint));
+    match (symbol, mint) {
+        (Some(symbol), _) => format!("{} {}", amount, symbol),
+        (None, Some(mint)) => format!("{} of token {}", amount, mint),
+        (None, None) => format!("{} tokens", amount),
+    }
+}
+
+fn describe_system(accounts: &Accounts, data: &[u8]) -> Option<String> {
+    let description = match bincode::deserialize::<SystemInstruction>(data).ok()? {
+        SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner,
+        }
+        | SystemInstruction::CreateAccountWithSeed {
+            lamports,
+            space,
+            owner,
+            ..
+        } => format!(
+            "Create account {} with {} and {} bytes, owned by {}",
+            accounts.get(1),
+            sol(lamports),
+            space,
+            owner
+        ),
+        SystemInstruction::Assign { owner } | SystemInstruction::AssignWithSeed { owner, .. } => {
+            format!("Assign {} to program {}", accounts.get(0), owner)
+        }
+        SystemInstruction::Transfer { lamports } => format!(
+            "Transfer {} from {} to {}",
+            sol(lamports),
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        SystemInstruction::TransferWithSeed { lamports, .. } => format!(
+            "Transfer {} from {} to {}",
+            sol(lamports),
+            accounts.get(0),
+            accounts.get(2)
+        ),
+        SystemInstruction::AdvanceNonceAccount => {
+            format!("Advance durable nonce {}", accounts.get(0))
+        }
+        SystemInstruction::WithdrawNonceAccount(lamports) => format!(
+            "Withdraw {} from nonce account {} to {}",
+            sol(lamports),
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        SystemInstruction::InitializeNonceAccount(authority) => format!(
+            "Initialize nonce account {} with authority {}",
+            accounts.get(0),
+            authority
+        ),
+        SystemInstruction::AuthorizeNonceAccount(authority) => format!(
+            "Set the authority of nonce account {} to {}",
+            accounts.get(0),
+            authority
+        ),
+        SystemInstruction::Allocate { space }
+        | SystemInstruction::AllocateWithSeed { space, .. } => {
+            format!("Allocate {} bytes for {}", space, accounts.get(0))
+        }
+        SystemInstruction::UpgradeNonceAccount => {
+            format!("Upgrade nonce account {}", accounts.get(0))
+        }
+    };
+    Some(description)
+}
+
+// Little-endian reads from packed SPL instruction data
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Option<Pubkey> {
+    Some(Pubkey::new_from_array(
+        data.get(offset..offset + 32)?.try_into().ok()?,
+    ))
+}
+
+fn describe_token(program_id: &Pubkey, accounts: &Accounts, data: &[u8]) -> Option<String> {
+    let (tag, rest) = data.split_first()?;
+    let amount = || read_u64(rest, 0);
+    // Checked variants carry the decimals after the amount
+    let checked = |mint_index: usize| -> Option<String> {
+        Some(token_amount(
+            amount()?,
+            *rest.get(8)?,
+            accounts.key(mint_index),
+        ))
+    };
+
+    let description = match tag {
+        0 | 20 => format!(
+            "Initialize mint {} with {} decimals and mint authority {}",
+            accounts.get(0),
+            rest.first()?,
+            read_pubkey(rest, 1)?
+        ),
+        1 | 16 | 18 => format!(
+            "Initialize token account {} for mint {}",
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        2 | 19 => format!("Initialize multisig {}", accounts.get(0)),
+        3 => format!(
+            "Transfer {} base units from {} to {}",
+            amount()?,
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        4 => format!(
+            "Approve delegate {} for {} base units from {}",
+            accounts.get(1),
+            amount()?,
+            accounts.get(0)
+        ),
+        5 => format!("Revoke the delegate of {}", accounts.get(0)),
+        6 => {
+            let authority_type = AUTHORITY_TYPES
+                .get(*rest.first()? as usize)
+                .copied()
+                .unwrap_or("unknown");
+            match rest.get(1)? {
+                0 => format!(
+                    "Remove the {} authority of {}",
+                    authority_type,
+                    accounts.get(0)
+                ),
+                _ => format!(
+                    "Set the {} authority of {} to {}",
+                    authority_type,
+                    accounts.get(0),
+                    read_pubkey(rest, 2)?
+                ),
+            }
+        }
+        7 => format!(
+            "Mint {} base units of {} to {}",
+            amount()?,
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        8 => format!(
+            "Burn {} base units of {} from {}",
+            amount()?,
+            accounts.get(1),
+            accounts.get(0)
+        ),
+        9 => format!(
+            "Close token account {}, sending its SOL to {}",
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        10 => format!("Freeze token account {}", accounts.get(0)),
+        11 => format!("Thaw token account {}", accounts.get(0)),
+        12 => format!(
+            "Transfer {} from {} to {}",
+            checked(1)?,
+            accounts.get(0),
+            accounts.get(2)
+        ),
+        13 => format!(
+            "Approve delegate {} for {} from {}",
+            accounts.get(2),
+            checked(1)?,
+            accounts.get(0)
+        ),
+        14 => format!("Mint {} to {}", checked(0)?, accounts.get(1)),
+        15 => format!("Burn {} from {}", checked(1)?, accounts.get(0)),
+        17 => format!("Sync the wrapped SOL balance of {}", accounts.get(0)),
+        21 => format!("Get the account size for mint {}", accounts.get(0)),
+        22 => format!("Make token account {} immutable", accounts.get(0)),
+        23 => format!(
+            "Convert {} base units of {} to a UI amount",
+            amount()?,
+            accounts.get(0)
+        ),
+        24 => format!("Convert a UI amount of {} to base units", accounts.get(0)),
+        tag if *program_id == TOKEN_2022_PROGRAM_ID => format!(
+            "{} on {}",
+            TOKEN_2022_EXTENSIONS.get((*tag as usize).checked_sub(25)?)?,
+            accounts.get(0)
+        ),
+        _ => return None,
+    };
+    Some(description)
+}
+
+fn describe_associated_token(accounts: &Accounts, data: &[u8]) -> Option<String> {
+    let mint = accounts.key(3);
+    let token = match mint.and_then(|mint| known_symbol(&mint)) {
+        Some(symbol) => symbol.to_string(),
+        None => format!("mint {}", accounts.get(3)),
+    };
+    let description = match data.first() {
+        None | Some(0) => format!(
+            "Create the {} account {} for {}",
+            token,
+            accounts.get(1),
+            accounts.get(2)
+        ),
+        Some(1) => format!(
+            "Create the {} account {} for {} if it does not exist",
+            token,
+            accounts.get(1),
+            accounts.get(2)
+        ),
+        Some(2) => format!("Recover nested token account {}", accounts.get(0)),
+        _ => return None,
+    };
+    Some(description)
+}
+
+fn stake_role(role: &StakeAuthorize) -> &'static str {
+    match role {
+        StakeAuthorize::Staker => "staker",
+        StakeAuthorize::Withdrawer => "withdrawer",
+    }
+}
+
+fn describe_stake(accounts: &Accounts, data: &[u8]) -> Option<String> {
+    let description = match bincode::deserialize::<StakeInstruction>(data).ok()? {
+        StakeInstruction::Initialize(authorized, _) => format!(
+            "Initialize stake account {} with staker {} and withdrawer {}",
+            accounts.get(0),
+            authorized.staker,
+            authorized.withdrawer
+        ),
+        StakeInstruction::InitializeChecked => format!(
+            "Initialize stake account {} with staker {} and withdrawer {}",
+            accounts.get(0),
+            accounts.get(2),
+            accounts.get(3)
+        ),
+        StakeInstruction::Authorize(new_authority, role) => format!(
+            "Set the {} of stake account {} to {}",
+            stake_role(&role),
+            accounts.get(0),
+            new_authority
+        ),
+        StakeInstruction::AuthorizeWithSeed(args) => format!(
+            "Set the {} of stake account {} to {}",
+            stake_role(&args.stake_authorize),
+            accounts.get(0),
+            args.new_authorized_pubkey
+        ),
+        StakeInstruction::AuthorizeChecked(role) => format!(
+            "Set the {} of stake account {} to {}",
+            stake_role(&role),
+            accounts.get(0),
+            accounts.get(3)
+        ),
+        StakeInstruction::AuthorizeCheckedWithSeed(args) => format!(
+            "Set the {} of stake account {} to {}",
+            stake_role(&args.stake_authorize),
+            accounts.get(0),
+            accounts.get(3)
+        ),
+        StakeInstruction::DelegateStake => format!(
+            "Delegate stake account {} to validator {}",
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        StakeInstruction::Split(lamports) => format!(
+            "Split {} from stake account {} into {}",
+            sol(lamports),
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        StakeInstruction::Withdraw(lamports) => format!(
+            "Withdraw {} from stake account {} to {}",
+            sol(lamports),
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        StakeInstruction::Deactivate => format!("Deactivate stake account {}", accounts.get(0)),
+        StakeInstruction::DeactivateDelinquent => format!(
+            "Deactivate stake account {} delegated to delinquent validator {}",
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        StakeInstruction::SetLockup(_) | StakeInstruction::SetLockupChecked(_) => {
+            format!("Change the lockup of stake account {}", accounts.get(0))
+        }
+        StakeInstruction::Merge => format!(
+            "Merge stake account {} into {}",
+            accounts.get(1),
+            accounts.get(0)
+        ),
+        StakeInstruction::GetMinimumDelegation => "Get the minimum stake delegation".to_string(),
+        StakeInstruction::MoveStake(lamports) => format!(
+            "Move {} of active stake from {} to {}",
+            sol(lamports),
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        StakeInstruction::MoveLamports(lamports) => format!(
+            "Move {} of inactive balance from {} to {}",
+            sol(lamports),
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        _ => return None,
+    };
+    Some(description)
+}
+
+fn describe_compute_budget(data: &[u8]) -> Option<String> {
+    let (tag, rest) = data.split_first()?;
+    let description = match tag {
+        1 => format!("Request a {} byte heap frame", read_u32(rest, 0)?),
+        2 => format!("Set compute unit limit to {}", read_u32(rest, 0)?),
+        3 => format!(
+            "Set compute unit price to {} micro-lamports",
+            read_u64(rest, 0)?
+        ),
+        4 => format!(
+            "Set loaded accounts data size limit to {} bytes",
+            read_u32(rest, 0)?
+        ),
+        _ => return None,
+    };
+    Some(description)
+}
+
+fn describe_lookup_table(accounts: &Accounts, data: &[u8]) -> Option<String> {
+    let description = match bincode::deserialize::<LookupTableInstruction>(data).ok()? {
+        LookupTableInstruction::CreateLookupTable { .. } => {
+            format!("Create address lookup table {}", accounts.get(0))
+        }
+        LookupTableInstruction::FreezeLookupTable => {
+            format!("Freeze address lookup table {}", accounts.get(0))
+        }
+        LookupTableInstruction::ExtendLookupTable { new_addresses } => format!(
+            "Add {} addresses to lookup table {}",
+            new_addresses.len(),
+            accounts.get(0)
+        ),
+        LookupTableInstruction::DeactivateLookupTable => {
+            format!("Deactivate address lookup table {}", accounts.get(0))
+        }
+        LookupTableInstruction::CloseLookupTable => format!(
+            "Close address lookup table {}, sending its SOL to {}",
+            accounts.get(0),
+            accounts.get(2)
+        ),
+    };
+    Some(description)
+}
+
+fn vote_role(role: &VoteAuthorize) -> &'static str {
+    match role {
+        VoteAuthorize::Voter => "voter",
+        VoteAuthorize::Withdrawer => "withdrawer",
+    }
+}
+
+fn describe_vote(accounts: &Accounts, data: &[u8]) -> Option<String> {
+    let description = match bincode::deserialize::<VoteInstruction>(data).ok()? {
+        VoteInstruction::InitializeAccount(init) => format!(
+            "Initialize vote account {} for validator {} with {}% commission",
+            accounts.get(0),
+            init.node_pubkey,
+            init.commission
+        ),
+        VoteInstruction::Authorize(new_authority, role) => format!(
+            "Set the {} of vote account {} to {}",
+            vote_role(&role),
+            accounts.get(0),
+            new_authority
+        ),
+        VoteInstruction::AuthorizeWithSeed(args) => format!(
+            "Set the {} of vote account {} to {}",
+            vote_role(&args.authorization_type),
+            accounts.get(0),
+            args.new_authority
+        ),
+        VoteInstruction::AuthorizeChecked(role) => format!(
+            "Set the {} of vote account {} to {}",
+            vote_role(&role),
+            accounts.get(0),
+            accounts.get(3)
+        ),
+        VoteInstruction::AuthorizeCheckedWithSeed(args) => format!(
+            "Set the {} of vote account {} to {}",
+            vote_role(&args.authorization_type),
+            accounts.get(0),
+            accounts.get(3)
+        ),
+        VoteInstruction::Withdraw(lamports) => format!(
+            "Withdraw {} from vote account {} to {}",
+            sol(lamports),
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        VoteInstruction::UpdateValidatorIdentity => format!(
+            "Change the validator identity of vote account {} to {}",
+            accounts.get(0),
+            accounts.get(1)
+        ),
+        VoteInstruction::UpdateCommission(commission) => format!(
+            "Set the commission of vote account {} to {}%",
+            accounts.get(0),
+            commission
+        ),
+        _ => format!("Vote from vote account {}", accounts.get(0)),
+    };
+    Some(description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::{memo::build_memo, token::TOKEN_PROGRAM_ID};
+    use solana_sdk::hash::Hash;
+    use solana_sdk::instruction::AccountMeta;
+    use solana_sdk::message::v0::MessageAddressTableLookup;
+    use solana_sdk::message::{v0, VersionedMessage};
+    use solana_system_interface::instruction::transfer;
+
+    #[test]
+    fn test_describe_system_transfer() {
+        let from = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let lines = describe_instructions(&[transfer(&from, &to, 1_500_000_000)]);
+        assert_eq!(
+            lines,
+            vec![format!("Transfer 1.5 SOL from {} to {}", from, to)]
+        );
+    }
+
+    #[test]
+    fn test_describe_token() {
+        let source = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let usdc = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+        let instruction = crate::programs::token::transfer_checked(
+            &TOKEN_PROGRAM_ID,
+            &source,
+            &usdc,
+            &destination,
+            &owner,
+            2_500_000,
+            6,
+        );
+        assert_eq!(
+            describe_instructions(&[instruction])[0],
+            format!("Transfer 2.5 USDC from {} to {}", source, destination)
+        );
+
+        // ApproveChecked: source, mint, delegate, owner
+        let delegate = Pubkey::new_unique();
+        let mut data = vec![13];
+        data.extend_from_slice(&100_000_000u64.to_le_bytes());
+        data.push(6);
+        let approve = Instruction {
+            program_id: TOKEN_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(source, false),
+                AccountMeta::new_readonly(usdc, false),
+                AccountMeta::new_readonly(delegate, false),
+                AccountMeta::new_readonly(owner, true),
+            ],
+            data,
+        };
+        assert_eq!(
+            describe_instructions(&[approve])[0],
+            format!("Approve delegate {} for 100 USDC from {}", delegate, source)
+        );
+
+        let mut data = vec![6, 3, 0];
+        data.resize(35, 0);
+        assert_eq!(
+            describe_instruction(&TOKEN_2022_PROGRAM_ID, &[source], &data),
+            format!("Remove the close authority of {}", source)
+        );
+    }
+
+    #[test]
+    fn test_describe_compute_budget() {
+        let mut data = vec![3];
+        data.extend_from_slice(&5_000u64.to_le_bytes());
+        assert_eq!(
+            describe_instruction(&COMPUTE_BUDGET_PROGRAM_ID, &[], &data),
+            "Set compute unit price to 5000 micro-lamports"
+        );
+        let mut data = vec![2];
+        data.extend_from_slice(&200_000u32.to_le_bytes());
+        assert_eq!(
+            describe_instruction(&COMPUTE_BUDGET_PROGRAM_ID, &[], &data),
+            "Set compute unit limit to 200000"
+        );
+    }
+
+    #[test]
+    fn test_describe_other_programs() {
+        let stake = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let deactivate = solana_stake_interface::instruction::deactivate_stake(&stake, &authority);
+        let vote = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let withdraw =
+            solana_vote_interface::instruction::withdraw(&vote, &authority, 1_000_000_000, &to);
+        let table = Pubkey::new_unique();
+        let extend = solana_address_lookup_table_interface::instruction::extend_lookup_table(
+            table,
+            authority,
+            None,
+            vec![Pubkey::new_unique(), Pubkey::new_unique()],
+        );
+        let memo = build_memo("thanks", &[&authority]);
+
+        assert_eq!(
+            describe_instructions(&[deactivate, withdraw, extend, memo]),
+            vec![
+                format!("Deactivate stake account {}", stake),
+                format!("Withdraw 1 SOL from vote account {} to {}", vote, to),
+                format!("Add 2 addresses to lookup table {}", table),
+                "Memo: \"thanks\"".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe_unknown() {
+        let program = Pubkey::new_unique();
+        assert_eq!(
+            describe_instruction(&program, &[], &[0xde, 0xad]),
+            format!("Program {}: dead", program)
+        );
+        assert_eq!(
+            describe_instruction(&COMPUTE_BUDGET_PROGRAM_ID, &[], &[9]),
+            "Unrecognised Compute Budget instruction: 09"
+        );
+    }
+
+    #[test]
+    fn test_describe_transaction_with_lookup_table() {
+        let payer = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let mut message = v0::Message::try_compile(
+            &payer,
+            &[transfer(&payer, &to, 1_000_000)],
+            &[],
+            Hash::default(),
+        )
+        .unwrap();
+        // Move the recipient behind a lookup table the wallet has not resolved
+        message.account_keys.retain(|key| *key != to);
+        message.header.num_readonly_unsigned_accounts = 1;
+        message.address_table_lookups = vec![MessageAddressTableLookup {
+            account_key: Pubkey::new_unique(),
+            writable_indexes: vec![0],
+            readonly_indexes: vec![],
+        }];
+        message.instructions[0].accounts = vec![0, 2];
+        message.instructions[0].program_id_index = 1;
+        let transaction = VersionedTransaction {
+            signatures: vec![Default::default()],
+            message: VersionedMessage::V0(message),
+        };
+
+        assert_eq!(
+            describe_transaction(&transaction, &[]),
+            vec![format!(
+                "Transfer 0.001 SOL from {} to an unknown account",
+                payer
+            )]
+        );
+        assert_eq!(
+            describe_transaction(&transaction, &[to]),
+            vec![format!("Transfer 0.001 SOL from {} to {}", payer, to)]
+        );
+    }
+}
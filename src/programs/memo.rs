@@ -0,0 +1,84 @@
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::VersionedTransaction;
+
+pub const MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+pub const MEMO_V1_PROGRAM_ID: Pubkey = pubkey!("Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo");
+
+pub fn is_memo_program(program_id: &Pubkey) -> bool {
+    *program_id == MEMO_PROGRAM_ID || *program_id == MEMO_V1_PROGRAM_ID
+}
+
+/// Build an SPL Memo instruction, optionally requiring the given signers
+pub fn build_memo(memo: &str, signers: &[&Pubkey]) -> Instruction {
+    Instruction {
+        program_id: MEMO_PROGRAM_ID,
+        accounts: signers
+            .iter()
+            .map(|signer| AccountMeta::new_readonly(**signer, true))
+            .collect(),
+        data: memo.as_bytes().to_vec(),
+    }
+}
+
+/// Memos attached by top-level Memo instructions, in instruction order
+pub fn parse_memos(transaction: &VersionedTransaction) -> Vec<String> {
+    let account_keys = transaction.message.static_account_keys();
+    transaction
+        .message
+        .instructions()
+        .iter()
+        .filter(|instruction| {
+            account_keys
+                .get(instruction.program_id_index as usize)
+                .is_some_and(is_memo_program)
+        })
+        .map(|instruction| String::from_utf8_lossy(&instruction.data).to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{Message, VersionedMessage};
+    use solana_system_interface::instruction::transfer;
+
+    #[test]
+    fn test_build_memo() {
+        let signer = Pubkey::new_unique();
+        let instruction = build_memo("invoice 42", &[&signer]);
+
+        assert_eq!(instruction.program_id, MEMO_PROGRAM_ID);
+        assert_eq!(instruction.data, b"invoice 42".to_vec());
+        assert_eq!(instruction.accounts.len(), 1);
+        assert!(instruction.accounts[0].is_signer);
+        assert!(!instruction.accounts[0].is_writable);
+    }
+
+    #[test]
+    fn test_parse_memos() {
+        let payer = Pubkey::new_unique();
+        let v1_memo = Instruction {
+            program_id: MEMO_V1_PROGRAM_ID,
+            accounts: vec![],
+            data: b"legacy".to_vec(),
+        };
+        let message = Message::new_with_blockhash(
+            &[
+                build_memo("invoice 42", &[&payer]),
+                transfer(&payer, &Pubkey::new_unique(), 10),
+                v1_memo,
+            ],
+            Some(&payer),
+            &Hash::new_unique(),
+        );
+        let transaction = VersionedTransaction {
+            signatures: vec![Default::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+
+        assert_eq!(parse_memos(&transaction), vec!["invoice 42", "legacy"]);
+    }
+}
@@ -0,0 +1,494 @@
+use crate::programs::associated_token::{
+    get_associated_token_address, ASSOCIATED_TOKEN_PROGRAM_ID,
+};
+use crate::programs::token::ACCOUNT_LEN;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sysvar;
+use solana_system_interface::program::ID as SYSTEM_PROGRAM_ID;
+
+pub const METADATA_PROGRAM_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// Token standard of programmable NFTs, which only move through Token Metadata
+pub const PROGRAMMABLE_NON_FUNGIBLE: u8 = 4;
+/// Account key of a master edition, as opposed to a printed edition
+pub const MASTER_EDITION_V2: u8 = 6;
+pub const TOKEN_RECORD_LEN: usize = 80;
+
+// Token Metadata instructions, each taking its V1 arguments
+const BURN: u8 = 41;
+const TRANSFER: u8 = 49;
+
+// Metaplex account key of a MetadataV1 account
+const METADATA_V1: u8 = 4;
+// Token-2022 mints carry their extensions after the base layout padded to an account's size
+const MINT_ACCOUNT_TYPE: u8 = 1;
+const TOKEN_METADATA_EXTENSION: u16 = 19;
+
+/// Name, symbol and URI shared by Metaplex metadata and the Token-2022 metadata extension
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OnChainMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    /// Mint of the collection the token belongs to, once the collection has verified it
+    pub collection: Option<Pubkey>,
+    /// Fungible, non-fungible, programmable and so on, when the account records it
+    pub token_standard: Option<u8>,
+    /// Authorization rules a programmable NFT's transfers are checked against
+    pub rule_set: Option<Pubkey>,
+}
+
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn master_edition_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"metadata",
+            METADATA_PROGRAM_ID.as_ref(),
+            mint.as_ref(),
+            b"edition",
+        ],
+        &METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Record Token Metadata keeps of a programmable NFT's delegate and lock, per token account
+pub fn token_record_address(mint: &Pubkey, token_account: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"metadata",
+            METADATA_PROGRAM_ID.as_ref(),
+            mint.as_ref(),
+            b"token_record",
+            token_account.as_ref(),
+        ],
+        &METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Move a programmable NFT to the associated account of `new_owner`, creating that account
+/// and its token record. Token Metadata closes the emptied source account and its record.
+/// `authorization_rules` is the program owning the NFT's rule set and the rule set itself.
+pub fn transfer_programmable(
+    token_program_id: &Pubkey,
+    mint: &Pubkey,
+    source: &Pubkey,
+    owner: &Pubkey,
+    new_owner: &Pubkey,
+    authorization_rules: Option<(Pubkey, Pubkey)>,
+) -> Instruction {
+    let destination = get_associated_token_address(new_owner, mint, token_program_id);
+    let mut data = vec![TRANSFER, 0];
+    data.extend_from_slice(&1u64.to_le_bytes());
+    // No authorization data
+    data.push(0);
+
+    Instruction {
+        program_id: METADATA_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new(destination, false),
+            AccountMeta::new_readonly(*new_owner, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(metadata_address(mint), false),
+            AccountMeta::new_readonly(master_edition_address(mint), false),
+            AccountMeta::new(token_record_address(mint, source), false),
+            AccountMeta::new(token_record_address(mint, &destination), false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new_readonly(sysvar::instructions::ID, false),
+            AccountMeta::new_readonly(*token_program_id, false),
+            AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+            optional_account(authorization_rules.map(|(program, _)| program), false),
+            optional_account(authorization_rules.map(|(_, rule_set)| rule_set), false),
+        ],
+        data,
+    }
+}
+
+/// Burn an NFT with a master edition, closing its token account, metadata, edition and
+/// (for programmable NFTs) token record. A verified collection has its size updated.
+pub fn burn(
+    token_program_id: &Pubkey,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    owner: &Pubkey,
+    collection: Option<Pubkey>,
+    programmable: bool,
+) -> Instruction {
+    let mut data = vec![BURN, 0];
+    data.extend_from_slice(&1u64.to_le_bytes());
+
+    Instruction {
+        program_id: METADATA_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            optional_account(collection.as_ref().map(metadata_address), true),
+            AccountMeta::new(metadata_address(mint), false),
+            AccountMeta::new(master_edition_address(mint), false),
+            AccountMeta::new(*mint, false),
+            AccountMeta::new(*token_account, false),
+            // Parent edition, its mint and token account and the edition marker, which
+            // only printed editions need
+            optional_account(None, false),
+            optional_account(None, false),
+            optional_account(None, false),
+            optional_account(None, true),
+            optional_account(
+                programmable.then(|| token_record_address(mint, token_account)),
+                true,
+            ),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new_readonly(sysvar::instructions::ID, false),
+            AccountMeta::new_readonly(*token_program_id, false),
+        ],
+        data,
+    }
+}
+
+// Token Metadata takes its own program id in place of an omitted optional account
+fn optional_account(account: Option<Pubkey>, writable: bool) -> AccountMeta {
+    match account {
+        Some(account) if writable => AccountMeta::new(account, false),
+        Some(account) => AccountMeta::new_readonly(account, false),
+        None => AccountMeta::new_readonly(METADATA_PROGRAM_ID, false),
+    }
+}
+
+/// Parse a Metaplex metadata account
+pub fn parse_metadata(data: &[u8]) -> Option<OnChainMetadata> {
+    if *data.first()? != METADATA_V1 {
+        return None;
+    }
+    // Key, update authority and mint come before the strings
+    let mut reader = Reader { data, offset: 65 };
+    let name = reader.string()?;
+    let symbol = reader.string()?;
+    let uri = reader.string()?;
+    let mut metadata = OnChainMetadata {
+        name,
+        symbol,
+        uri,
+        ..Default::default()
+    };
+    // Older accounts end early, which only means the later fields are unset
+    reader.optional_fields(&mut metadata);
+    Some(metadata)
+}
+
+/// Parse the metadata extension of a Token-2022 mint, if it has one
+pub fn parse_token_2022_metadata(data: &[u8]) -> Option<OnChainMetadata> {
+    if *data.get(ACCOUNT_LEN)? != MINT_ACCOUNT_TYPE {
+        return None;
+    }
+    let mut offset = ACCOUNT_LEN + 1;
+    while let (Some(kind), Some(length)) = (read_u16(data, offset), read_u16(data, offset + 2)) {
+        let start = offset + 4;
+        let end = start + length as usize;
+        if kind == TOKEN_METADATA_EXTENSION {
+            // Update authority and mint come before the strings
+            let mut reader = Reader {
+                data: data.get(start..end)?,
+                offset: 64,
+            };
+            return Some(OnChainMetadata {
+                name: reader.string()?,
+                symbol: reader.string()?,
+                uri: reader.string()?,
+                ..Default::default()
+            });
+        }
+        offset = end;
+    }
+    None
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+// Borsh values, where Metaplex pads strings with trailing zero bytes
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn string(&mut self) -> Option<String> {
+        let length = u32::from_le_bytes(
+            self.data
+                .get(self.offset..self.offset + 4)?
+                .try_into()
+                .ok()?,
+        ) as usize;
+        let start = self.offset + 4;
+        let bytes = self.data.get(start..start + length)?;
+        self.offset = start + length;
+        Some(
+            String::from_utf8_lossy(bytes)
+                .trim_end_matches('\0')
+                .trim()
+                .to_string(),
+        )
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        let byte = *self.data.get(self.offset)?;
+        self.offset += 1;
+        Some(byte)
+    }
+
+    fn skip(&mut self, length: usize) -> Option<()> {
+        self.data.get(self.offset..self.offset + length)?;
+        self.offset += length;
+        Some(())
+    }
+
+    fn pubkey(&mut self) -> Option<Pubkey> {
+        let key = Pubkey::new_from_array(
+            self.data
+                .get(self.offset..self.offset + 32)?
+                .try_into()
+                .ok()?,
+        );
+        self.offset += 32;
+        Some(key)
+    }
+
+    // Seller fee, creators, sale and mutability flags and edition nonce come before the
+    // token standard, then the collection, uses, collection details and programmable config
+    fn optional_fields(&mut self, metadata: &mut OnChainMetadata) -> Option<()> {
+        self.skip(2)?;
+        if self.byte()? == 1 {
+            let creators = u32::from_le_bytes(
+                self.data
+                    .get(self.offset..self.offset + 4)?
+                    .try_into()
+                    .ok()?,
+            ) as usize;
+            // Address, verified flag and share of each creator
+            self.skip(4 + creators * 34)?;
+        }
+        self.skip(2)?;
+        if self.byte()? == 1 {
+            self.skip(1)?;
+        }
+        if self.byte()? == 1 {
+            metadata.token_standard = Some(self.byte()?);
+        }
+        if self.byte()? == 1 {
+            let verified = self.byte()? == 1;
+            let key = self.pubkey()?;
+            metadata.collection = verified.then_some(key);
+        }
+        // Use method, remaining and total uses
+        if self.byte()? == 1 {
+            self.skip(17)?;
+        }
+        // Both versions of the collection details hold eight bytes
+        if self.byte()? == 1 {
+            self.skip(9)?;
+        }
+        // The only programmable config version holds an optional rule set
+        if self.byte()? == 1 {
+            self.skip(1)?;
+            if self.byte()? == 1 {
+                metadata.rule_set = Some(self.pubkey()?);
+            }
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::token::TOKEN_PROGRAM_ID;
+
+    fn borsh_string(value: &str, padded: usize) -> Vec<u8> {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.resize(padded.max(bytes.len()), 0);
+        let mut data = (bytes.len() as u32).to_le_bytes().to_vec();
+        data.extend(bytes);
+        data
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let mut data = vec![METADATA_V1];
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend(borsh_string("USD Coin", 32));
+        data.extend(borsh_string("USDC", 10));
+        data.extend(borsh_string("https://example.com/usdc.json", 200));
+        data.extend_from_slice(&[0; 20]);
+
+        assert_eq!(
+            parse_metadata(&data),
+            Some(OnChainMetadata {
+                name: "USD Coin".to_string(),
+                symbol: "USDC".to_string(),
+                uri: "https://example.com/usdc.json".to_string(),
+                ..Default::default()
+            })
+        );
+        data[0] = 0;
+        assert_eq!(parse_metadata(&data), None);
+    }
+
+    #[test]
+    fn test_parse_metadata_collection() {
+        let collection = Pubkey::new_unique();
+        let mut data = vec![METADATA_V1];
+        data.extend_from_slice(&[0; 64]);
+        data.extend(borsh_string("Mad Lad #1", 32));
+        data.extend(borsh_string("MAD", 10));
+        data.extend(borsh_string("https://example.com/1.json", 200));
+        // Seller fee, then two creators
+        data.extend_from_slice(&500u16.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[7; 68]);
+        // Primary sale, mutable, edition nonce and token standard
+        data.extend_from_slice(&[1, 1, 1, 255, 1, 4]);
+        data.push(1);
+        data.push(1);
+        data.extend_from_slice(collection.as_ref());
+
+        let metadata = parse_metadata(&data).unwrap();
+        assert_eq!(metadata.collection, Some(collection));
+        assert_eq!(metadata.token_standard, Some(PROGRAMMABLE_NON_FUNGIBLE));
+        assert_eq!(metadata.rule_set, None);
+        // An unverified collection could be claimed by anyone
+        let verified_flag = data.len() - 33;
+        data[verified_flag] = 0;
+        assert_eq!(parse_metadata(&data).unwrap().collection, None);
+
+        // No uses or collection details, then a programmable config with a rule set
+        let rule_set = Pubkey::new_unique();
+        data.extend_from_slice(&[0, 0, 1, 0, 1]);
+        data.extend_from_slice(rule_set.as_ref());
+        assert_eq!(parse_metadata(&data).unwrap().rule_set, Some(rule_set));
+    }
+
+    #[test]
+    fn test_parse_token_2022_metadata() {
+        let mut data = vec![0u8; ACCOUNT_LEN];
+        data.push(MINT_ACCOUNT_TYPE);
+        // An unrelated extension before the metadata
+        data.extend_from_slice(&3u16.to_le_bytes());
+        data.extend_from_slice(&4u16.to_le_bytes());
+        data.extend_from_slice(&[9; 4]);
+
+        let mut value = vec![0u8; 64];
+        value.extend(borsh_string("Paypal USD", 0));
+        value.extend(borsh_string("PYUSD", 0));
+        value.extend(borsh_string("https://example.com/pyusd.json", 0));
+        value.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&TOKEN_METADATA_EXTENSION.to_le_bytes());
+        data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        data.extend(value);
+
+        let metadata = parse_token_2022_metadata(&data).unwrap();
+        assert_eq!(metadata.name, "Paypal USD");
+        assert_eq!(metadata.symbol, "PYUSD");
+        assert_eq!(metadata.uri, "https://example.com/pyusd.json");
+        assert_eq!(parse_token_2022_metadata(&data[..ACCOUNT_LEN]), None);
+    }
+
+    #[test]
+    fn test_metadata_address() {
+        let mint = Pubkey::new_unique();
+        let address = metadata_address(&mint);
+        assert!(!address.is_on_curve());
+        assert_ne!(address, metadata_address(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn test_transfer_programmable() {
+        let mint = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let new_owner = Pubkey::new_unique();
+
+        let instruction =
+            transfer_programmable(&TOKEN_PROGRAM_ID, &mint, &source, &owner, &new_owner, None);
+        let destination = get_associated_token_address(&new_owner, &mint, &TOKEN_PROGRAM_ID);
+        assert_eq!(instruction.accounts.len(), 17);
+        assert_eq!(instruction.accounts[2].pubkey, destination);
+        assert_eq!(
+            instruction.accounts[7].pubkey,
+            token_record_address(&mint, &source)
+        );
+        assert_eq!(
+            instruction.accounts[8].pubkey,
+            token_record_address(&mint, &destination)
+        );
+        assert!(instruction.accounts[9].is_signer && instruction.accounts[10].is_writable);
+        assert_eq!(instruction.accounts[16].pubkey, METADATA_PROGRAM_ID);
+        assert_eq!(instruction.data, [49, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        let (rules_program, rule_set) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let instruction = transfer_programmable(
+            &TOKEN_PROGRAM_ID,
+            &mint,
+            &source,
+            &owner,
+            &new_owner,
+            Some((rules_program, rule_set)),
+        );
+        assert_eq!(instruction.accounts[15].pubkey, rules_program);
+        assert_eq!(instruction.accounts[16].pubkey, rule_set);
+    }
+
+    #[test]
+    fn test_burn() {
+        let mint = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+
+        let instruction = burn(
+            &TOKEN_PROGRAM_ID,
+            &mint,
+            &token_account,
+            &owner,
+            Some(collection),
+            false,
+        );
+        assert_eq!(instruction.accounts.len(), 14);
+        assert!(instruction.accounts[0].is_signer);
+        assert_eq!(
+            instruction.accounts[1].pubkey,
+            metadata_address(&collection)
+        );
+        assert_eq!(
+            instruction.accounts[3].pubkey,
+            master_edition_address(&mint)
+        );
+        assert_eq!(instruction.accounts[5].pubkey, token_account);
+        assert_eq!(instruction.accounts[10].pubkey, METADATA_PROGRAM_ID);
+        assert_eq!(instruction.data, [41, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+
+        let instruction = burn(&TOKEN_PROGRAM_ID, &mint, &token_account, &owner, None, true);
+        assert_eq!(instruction.accounts[1].pubkey, METADATA_PROGRAM_ID);
+        assert_eq!(
+            instruction.accounts[10].pubkey,
+            token_record_address(&mint, &token_account)
+        );
+    }
+}
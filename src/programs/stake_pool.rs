@@ -0,0 +1,545 @@
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sysvar;
+use solana_stake_interface::program::ID as STAKE_PROGRAM_ID;
+use solana_system_interface::program::ID as SYSTEM_PROGRAM_ID;
+
+pub const STAKE_POOL_PROGRAM_ID: Pubkey = pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+
+const STAKE_POOL_ACCOUNT_TYPE: u8 = 1;
+const VALIDATOR_LIST_ACCOUNT_TYPE: u8 = 2;
+// Account type and max validators precede the length-prefixed entries
+const VALIDATOR_LIST_HEADER_LEN: usize = 5;
+const VALIDATOR_STAKE_INFO_LEN: usize = 73;
+const VALIDATOR_STATUS_ACTIVE: u8 = 0;
+
+const WITHDRAW_STAKE: u8 = 10;
+const DEPOSIT_SOL: u8 = 14;
+const WITHDRAW_SOL: u8 = 16;
+
+/// A fee charged in pool tokens, as a fraction
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Fee {
+    pub denominator: u64,
+    pub numerator: u64,
+}
+
+impl Fee {
+    /// The fee on `amount`, rounded up as the stake pool program does
+    pub fn apply(&self, amount: u64) -> u64 {
+        if self.denominator == 0 {
+            return 0;
+        }
+        let denominator = self.denominator as u128;
+        (amount as u128 * self.numerator as u128).div_ceil(denominator) as u64
+    }
+
+    pub fn percent(&self) -> f64 {
+        if self.denominator == 0 {
+            return 0.0;
+        }
+        self.numerator as f64 * 100.0 / self.denominator as f64
+    }
+}
+
+/// The fields of a stake pool account the wallet needs to deposit and withdraw
+#[derive(Debug, Clone, PartialEq)]
+pub struct StakePool {
+    pub validator_list: Pubkey,
+    pub reserve_stake: Pubkey,
+    pub pool_mint: Pubkey,
+    pub manager_fee_account: Pubkey,
+    pub token_program_id: Pubkey,
+    pub total_lamports: u64,
+    pub pool_token_supply: u64,
+    pub last_update_epoch: u64,
+    pub epoch_fee: Fee,
+    pub preferred_withdraw_validator: Option<Pubkey>,
+    pub stake_withdrawal_fee: Fee,
+    pub sol_deposit_authority: Option<Pubkey>,
+    pub sol_deposit_fee: Fee,
+    /// Percentage of the SOL deposit fee paid to the referrer
+    pub sol_referral_fee: u8,
+    pub sol_withdraw_authority: Option<Pubkey>,
+    pub sol_withdrawal_fee: Fee,
+}
+
+impl StakePool {
+    /// Pool tokens minted for a deposit of `lamports`, before fees
+    pub fn pool_tokens_for(&self, lamports: u64) -> u64 {
+        if self.total_lamports == 0 || self.pool_token_supply == 0 {
+            return lamports;
+        }
+        (lamports as u128 * self.pool_token_supply as u128 / self.total_lamports as u128) as u64
+    }
+
+    /// Lamports that `pool_tokens` are worth at the current exchange rate
+    pub fn lamports_for(&self, pool_tokens: u64) -> u64 {
+        if self.pool_token_supply == 0 {
+            return 0;
+        }
+        (pool_tokens as u128 * self.total_lamports as u128 / self.pool_token_supply as u128) as u64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatorStakeInfo {
+    pub active_stake_lamports: u64,
+    pub validator_seed_suffix: u32,
+    pub active: bool,
+    pub vote_account: Pubkey,
+}
+
+// Sequential reader for the Borsh layout of stake pool accounts
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn pubkey(&mut self) -> Option<Pubkey> {
+        Some(Pubkey::new_from_array(self.take(32)?.try_into().ok()?))
+    }
+
+    fn fee(&mut self) -> Option<Fee> {
+        Some(Fee {
+            denominator: self.u64()?,
+            numerator: self.u64()?,
+        })
+    }
+
+    fn optional_pubkey(&mut self) -> Option<Option<Pubkey>> {
+        match self.u8()? {
+            0 => Some(None),
+            _ => Some(Some(self.pubkey()?)),
+        }
+    }
+
+    // Scheduled fee changes only matter to the pool, so they are skipped
+    fn skip_future_fee(&mut self) -> Option<()> {
+        if self.u8()? != 0 {
+            self.fee()?;
+        }
+        Some(())
+    }
+}
+
+pub fn parse_stake_pool(data: &[u8]) -> Option<StakePool> {
+    let mut reader = Reader { data };
+    if reader.u8()? != STAKE_POOL_ACCOUNT_TYPE {
+        return None;
+    }
+    // Manager, staker and stake deposit authority
+    reader.take(96)?;
+    // Withdraw authority bump seed
+    reader.u8()?;
+    let validator_list = reader.pubkey()?;
+    let reserve_stake = reader.pubkey()?;
+    let pool_mint = reader.pubkey()?;
+    let manager_fee_account = reader.pubkey()?;
+    let token_program_id = reader.pubkey()?;
+    let total_lamports = reader.u64()?;
+    let pool_token_supply = reader.u64()?;
+    let last_update_epoch = reader.u64()?;
+    // Lockup
+    reader.take(48)?;
+    let epoch_fee = reader.fee()?;
+    reader.skip_future_fee()?;
+    // Preferred deposit validator
+    reader.optional_pubkey()?;
+    let preferred_withdraw_validator = reader.optional_pubkey()?;
+    // Stake deposit fee
+    reader.fee()?;
+    let stake_withdrawal_fee = reader.fee()?;
+    reader.skip_future_fee()?;
+    // Stake referral fee
+    reader.u8()?;
+    let sol_deposit_authority = reader.optional_pubkey()?;
+    let sol_deposit_fee = reader.fee()?;
+    let sol_referral_fee = reader.u8()?;
+    let sol_withdraw_authority = reader.optional_pubkey()?;
+    let sol_withdrawal_fee = reader.fee()?;
+
+    Some(StakePool {
+        validator_list,
+        reserve_stake,
+        pool_mint,
+        manager_fee_account,
+        token_program_id,
+        total_lamports,
+        pool_token_supply,
+        last_update_epoch,
+        epoch_fee,
+        preferred_withdraw_validator,
+        stake_withdrawal_fee,
+        sol_deposit_authority,
+        sol_deposit_fee,
+        sol_referral_fee,
+        sol_withdraw_authority,
+        sol_withdrawal_fee,
+    })
+}
+
+pub fn parse_validator_list(data: &[u8]) -> Option<Vec<ValidatorStakeInfo>> {
+    let mut reader = Reader { data };
+    if reader.u8()? != VALIDATOR_LIST_ACCOUNT_TYPE {
+        return None;
+    }
+    reader.take(VALIDATOR_LIST_HEADER_LEN - 1)?;
+    let len = reader.u32()? as usize;
+
+    (0..len)
+        .map(|_| {
+            let mut entry = Reader {
+                data: reader.take(VALIDATOR_STAKE_INFO_LEN)?,
+            };
+            let active_stake_lamports = entry.u64()?;
+            // Transient stake, last update epoch, transient seed suffix and padding
+            entry.take(28)?;
+            Some(ValidatorStakeInfo {
+                active_stake_lamports,
+                validator_seed_suffix: entry.u32()?,
+                active: entry.u8()? == VALIDATOR_STATUS_ACTIVE,
+                vote_account: entry.pubkey()?,
+            })
+        })
+        .collect()
+}
+
+pub fn withdraw_authority(stake_pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[stake_pool.as_ref(), b"withdraw"], &STAKE_POOL_PROGRAM_ID).0
+}
+
+/// The pool's stake account delegated to `vote_account`
+pub fn validator_stake_address(stake_pool: &Pubkey, vote_account: &Pubkey, seed: u32) -> Pubkey {
+    let seed_bytes = seed.to_le_bytes();
+    let mut seeds = vec![vote_account.as_ref(), stake_pool.as_ref()];
+    if seed != 0 {
+        seeds.push(&seed_bytes);
+    }
+    Pubkey::find_program_address(&seeds, &STAKE_POOL_PROGRAM_ID).0
+}
+
+fn amount_data(instruction: u8, amount: u64) -> Vec<u8> {
+    let mut data = vec![instruction];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data
+}
+
+/// Deposit SOL from `depositor`, with the referral share of the fee paid back to `pool_tokens_to`
+pub fn deposit_sol(
+    stake_pool_address: &Pubkey,
+    stake_pool: &StakePool,
+    depositor: &Pubkey,
+    pool_tokens_to: &Pubkey,
+    lamports: u64,
+) -> Instruction {
+    Instruction {
+        program_id: STAKE_POOL_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*stake_pool_address, false),
+            AccountMeta::new_readonly(withdraw_authority(stake_pool_address), false),
+            AccountMeta::new(stake_pool.reserve_stake, false),
+            AccountMeta::new(*depositor, true),
+            AccountMeta::new(*pool_tokens_to, false),
+            AccountMeta::new(stake_pool.manager_fee_account, false),
+            AccountMeta::new(*pool_tokens_to, false),
+            AccountMeta::new(stake_pool.pool_mint, false),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new_readonly(stake_pool.token_program_id, false),
+        ],
+        data: amount_data(DEPOSIT_SOL, lamports),
+    }
+}
+
+/// Burn `pool_tokens` for SOL taken straight from the pool reserve
+pub fn withdraw_sol(
+    stake_pool_address: &Pubkey,
+    stake_pool: &StakePool,
+    owner: &Pubkey,
+    pool_tokens_from: &Pubkey,
+    pool_tokens: u64,
+) -> Instruction {
+    Instruction {
+        program_id: STAKE_POOL_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*stake_pool_address, false),
+            AccountMeta::new_readonly(withdraw_authority(stake_pool_address), false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*pool_tokens_from, false),
+            AccountMeta::new(stake_pool.reserve_stake, false),
+            AccountMeta::new(*owner, false),
+            AccountMeta::new(stake_pool.manager_fee_account, false),
+            AccountMeta::new(stake_pool.pool_mint, false),
+            AccountMeta::new_readonly(sysvar::clock::ID, false),
+            AccountMeta::new_readonly(sysvar::stake_history::ID, false),
+            AccountMeta::new_readonly(STAKE_PROGRAM_ID, false),
+            AccountMeta::new_readonly(stake_pool.token_program_id, false),
+        ],
+        data: amount_data(WITHDRAW_SOL, pool_tokens),
+    }
+}
+
+/// Burn `pool_tokens` for stake split off `stake_to_split` into the uninitialized `stake_to_receive`
+pub fn withdraw_stake(
+    stake_pool_address: &Pubkey,
+    stake_pool: &StakePool,
+    stake_to_split: &Pubkey,
+    stake_to_receive: &Pubkey,
+    owner: &Pubkey,
+    pool_tokens_from: &Pubkey,
+    pool_tokens: u64,
+) -> Instruction {
+    Instruction {
+        program_id: STAKE_POOL_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*stake_pool_address, false),
+            AccountMeta::new(stake_pool.validator_list, false),
+            AccountMeta::new_readonly(withdraw_authority(stake_pool_address), false),
+            AccountMeta::new(*stake_to_split, false),
+            AccountMeta::new(*stake_to_receive, false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*pool_tokens_from, false),
+            AccountMeta::new(stake_pool.manager_fee_account, false),
+            AccountMeta::new(stake_pool.pool_mint, false),
+            AccountMeta::new_readonly(sysvar::clock::ID, false),
+            AccountMeta::new_readonly(stake_pool.token_program_id, false),
+            AccountMeta::new_readonly(STAKE_PROGRAM_ID, false),
+        ],
+        data: amount_data(WITHDRAW_STAKE, pool_tokens),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::programs::token::TOKEN_PROGRAM_ID;
+
+    pub fn stake_pool() -> StakePool {
+        StakePool {
+            validator_list: Pubkey::new_unique(),
+            reserve_stake: Pubkey::new_unique(),
+            pool_mint: Pubkey::new_unique(),
+            manager_fee_account: Pubkey::new_unique(),
+            token_program_id: TOKEN_PROGRAM_ID,
+            total_lamports: 1_100_000_000_000,
+            pool_token_supply: 1_000_000_000_000,
+            last_update_epoch: 600,
+            epoch_fee: Fee {
+                denominator: 100,
+                numerator: 4,
+            },
+            preferred_withdraw_validator: None,
+            stake_withdrawal_fee: Fee {
+                denominator: 1000,
+                numerator: 1,
+            },
+            sol_deposit_authority: None,
+            sol_deposit_fee: Fee {
+                denominator: 1000,
+                numerator: 1,
+            },
+            sol_referral_fee: 50,
+            sol_withdraw_authority: None,
+            sol_withdrawal_fee: Fee {
+                denominator: 1000,
+                numerator: 3,
+            },
+        }
+    }
+
+    fn push_fee(data: &mut Vec<u8>, fee: &Fee) {
+        data.extend_from_slice(&fee.denominator.to_le_bytes());
+        data.extend_from_slice(&fee.numerator.to_le_bytes());
+    }
+
+    /// Serialize `pool` the way the stake pool program lays out its account
+    pub fn stake_pool_data(pool: &StakePool) -> Vec<u8> {
+        let mut data = vec![STAKE_POOL_ACCOUNT_TYPE];
+        data.extend_from_slice(&[7u8; 96]);
+        data.push(255);
+        for key in [
+            pool.validator_list,
+            pool.reserve_stake,
+            pool.pool_mint,
+            pool.manager_fee_account,
+            pool.token_program_id,
+        ] {
+            data.extend_from_slice(key.as_ref());
+        }
+        for value in [
+            pool.total_lamports,
+            pool.pool_token_supply,
+            pool.last_update_epoch,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&[0u8; 48]);
+        push_fee(&mut data, &pool.epoch_fee);
+        // A scheduled epoch fee change, which the parser skips
+        data.push(1);
+        push_fee(&mut data, &pool.epoch_fee);
+        data.push(0);
+        match pool.preferred_withdraw_validator {
+            Some(vote) => {
+                data.push(1);
+                data.extend_from_slice(vote.as_ref());
+            }
+            None => data.push(0),
+        }
+        push_fee(&mut data, &Fee::default());
+        push_fee(&mut data, &pool.stake_withdrawal_fee);
+        data.push(0);
+        data.push(0);
+        data.push(0);
+        push_fee(&mut data, &pool.sol_deposit_fee);
+        data.push(pool.sol_referral_fee);
+        data.push(0);
+        push_fee(&mut data, &pool.sol_withdrawal_fee);
+        data.push(0);
+        data.extend_from_slice(&[0u8; 16]);
+        data
+    }
+
+    pub fn validator_list_data(entries: &[ValidatorStakeInfo]) -> Vec<u8> {
+        let mut data = vec![VALIDATOR_LIST_ACCOUNT_TYPE];
+        data.extend_from_slice(&100u32.to_le_bytes());
+        data.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for entry in entries {
+            data.extend_from_slice(&entry.active_stake_lamports.to_le_bytes());
+            data.extend_from_slice(&[0u8; 28]);
+            data.extend_from_slice(&entry.validator_seed_suffix.to_le_bytes());
+            data.push(if entry.active { 0 } else { 1 });
+            data.extend_from_slice(entry.vote_account.as_ref());
+        }
+        data
+    }
+
+    #[test]
+    fn test_fee() {
+        let fee = Fee {
+            denominator: 1000,
+            numerator: 3,
+        };
+        assert_eq!(fee.apply(1_000_000), 3_000);
+        // Rounded up in the pool's favour
+        assert_eq!(fee.apply(1), 1);
+        assert_eq!(Fee::default().apply(1_000), 0);
+        assert!((fee.percent() - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_stake_pool() {
+        let mut pool = stake_pool();
+        pool.preferred_withdraw_validator = Some(Pubkey::new_unique());
+        assert_eq!(
+            parse_stake_pool(&stake_pool_data(&pool)),
+            Some(pool.clone())
+        );
+
+        let mut data = stake_pool_data(&pool);
+        data[0] = VALIDATOR_LIST_ACCOUNT_TYPE;
+        assert_eq!(parse_stake_pool(&data), None);
+        assert_eq!(parse_stake_pool(&stake_pool_data(&pool)[..200]), None);
+    }
+
+    #[test]
+    fn test_exchange_rate() {
+        let pool = stake_pool();
+        assert_eq!(pool.pool_tokens_for(1_100_000_000), 1_000_000_000);
+        assert_eq!(pool.lamports_for(1_000_000_000), 1_100_000_000);
+
+        let empty = StakePool {
+            total_lamports: 0,
+            pool_token_supply: 0,
+            ..pool
+        };
+        assert_eq!(empty.pool_tokens_for(5), 5);
+        assert_eq!(empty.lamports_for(5), 0);
+    }
+
+    #[test]
+    fn test_parse_validator_list() {
+        let entries = vec![
+            ValidatorStakeInfo {
+                active_stake_lamports: 5_000_000_000,
+                validator_seed_suffix: 0,
+                active: true,
+                vote_account: Pubkey::new_unique(),
+            },
+            ValidatorStakeInfo {
+                active_stake_lamports: 0,
+                validator_seed_suffix: 3,
+                active: false,
+                vote_account: Pubkey::new_unique(),
+            },
+        ];
+        assert_eq!(
+            parse_validator_list(&validator_list_data(&entries)),
+            Some(entries)
+        );
+    }
+
+    #[test]
+    fn test_instructions() {
+        let pool_address = Pubkey::new_unique();
+        let pool = stake_pool();
+        let owner = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+
+        let deposit = deposit_sol(&pool_address, &pool, &owner, &token_account, 42);
+        assert_eq!(deposit.program_id, STAKE_POOL_PROGRAM_ID);
+        assert_eq!(deposit.data, amount_data(DEPOSIT_SOL, 42));
+        assert!(deposit.accounts[3].is_signer);
+        assert_eq!(deposit.accounts[6].pubkey, token_account);
+
+        let withdraw = withdraw_sol(&pool_address, &pool, &owner, &token_account, 7);
+        assert_eq!(withdraw.data[0], WITHDRAW_SOL);
+        assert_eq!(
+            withdraw.accounts[1].pubkey,
+            withdraw_authority(&pool_address)
+        );
+        assert!(withdraw.accounts[2].is_signer);
+        assert_eq!(withdraw.accounts[5].pubkey, owner);
+
+        let vote = Pubkey::new_unique();
+        let split_from = validator_stake_address(&pool_address, &vote, 0);
+        assert_ne!(split_from, validator_stake_address(&pool_address, &vote, 1));
+        let receive = Pubkey::new_unique();
+        let withdraw = withdraw_stake(
+            &pool_address,
+            &pool,
+            &split_from,
+            &receive,
+            &owner,
+            &token_account,
+            7,
+        );
+        assert_eq!(withdraw.data[0], WITHDRAW_STAKE);
+        assert_eq!(withdraw.accounts[3].pubkey, split_from);
+        assert_eq!(withdraw.accounts[4].pubkey, receive);
+        assert!(withdraw.accounts[6].is_signer);
+    }
+}
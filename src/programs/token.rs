@@ -0,0 +1,334 @@
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+/// Mint of wrapped SOL, whose token accounts hold their balance as lamports
+pub const NATIVE_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+
+// Mints common enough to name without looking up their metadata
+const KNOWN_SYMBOLS: [(&str, Pubkey); 3] = [
+    (
+        "USDC",
+        pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+    ),
+    (
+        "USDT",
+        pubkey!("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"),
+    ),
+    ("wSOL", NATIVE_MINT),
+];
+
+pub const MINT_LEN: usize = 82;
+pub const ACCOUNT_LEN: usize = 165;
+// Token-2022 associated accounts always carry the immutable owner extension
+const TOKEN_2022_ASSOCIATED_ACCOUNT_LEN: usize = 170;
+const MINT_SUPPLY_OFFSET: usize = 36;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const ACCOUNT_AMOUNT_OFFSET: usize = 64;
+const ACCOUNT_DELEGATE_OFFSET: usize = 72;
+const ACCOUNT_STATE_OFFSET: usize = 108;
+const ACCOUNT_DELEGATED_AMOUNT_OFFSET: usize = 121;
+const ACCOUNT_CLOSE_AUTHORITY_OFFSET: usize = 129;
+const ACCOUNT_STATE_FROZEN: u8 = 2;
+
+const REVOKE: u8 = 5;
+const BURN: u8 = 8;
+const CLOSE_ACCOUNT: u8 = 9;
+const TRANSFER_CHECKED: u8 = 12;
+const SYNC_NATIVE: u8 = 17;
+
+/// The base fields of a token account, shared by Token and Token-2022
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenAccount {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub delegate: Option<Pubkey>,
+    pub delegated_amount: u64,
+    pub close_authority: Option<Pubkey>,
+    pub frozen: bool,
+}
+
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == TOKEN_PROGRAM_ID || *program_id == TOKEN_2022_PROGRAM_ID
+}
+
+pub fn known_symbol(mint: &Pubkey) -> Option<&'static str> {
+    KNOWN_SYMBOLS
+        .iter()
+        .find(|(_, known)| known == mint)
+        .map(|(symbol, _)| *symbol)
+}
+
+/// Size of a freshly created associated token account, used to work out its rent
+pub fn associated_account_len(program_id: &Pubkey) -> usize {
+    if *program_id == TOKEN_2022_PROGRAM_ID {
+        TOKEN_2022_ASSOCIATED_ACCOUNT_LEN
+    } else {
+        ACCOUNT_LEN
+    }
+}
+
+/// Read the decimals from raw mint account data (Token and Token-2022 share the base layout)
+pub fn mint_decimals(data: &[u8]) -> Option<u8> {
+    if data.len() < MINT_LEN {
+        return None;
+    }
+    Some(data[MINT_DECIMALS_OFFSET])
+}
+
+/// Read the total supply from raw mint account data, in base units
+pub fn mint_supply(data: &[u8]) -> Option<u64> {
+    if data.len() < MINT_LEN {
+        return None;
+    }
+    let bytes = data[MINT_SUPPLY_OFFSET..MINT_SUPPLY_OFFSET + 8]
+        .try_into()
+        .ok()?;
+    Some(u64::from_le_bytes(bytes))
+}
+
+/// Read the balance from raw token account data, in base units
+pub fn account_amount(data: &[u8]) -> Option<u64> {
+    if data.len() < ACCOUNT_LEN {
+        return None;
+    }
+    let bytes = data[ACCOUNT_AMOUNT_OFFSET..ACCOUNT_AMOUNT_OFFSET + 8]
+        .try_into()
+        .ok()?;
+    Some(u64::from_le_bytes(bytes))
+}
+
+/// Parse raw token account data, ignoring any Token-2022 extensions after the base layout
+pub fn parse_account(data: &[u8]) -> Option<TokenAccount> {
+    if data.len() < ACCOUNT_LEN {
+        return None;
+    }
+    Some(TokenAccount {
+        mint: read_pubkey(data, 0)?,
+        owner: read_pubkey(data, 32)?,
+        amount: account_amount(data)?,
+        delegate: read_optional_pubkey(data, ACCOUNT_DELEGATE_OFFSET),
+        delegated_amount: u64::from_le_bytes(
+            data[ACCOUNT_DELEGATED_AMOUNT_OFFSET..ACCOUNT_DELEGATED_AMOUNT_OFFSET + 8]
+                .try_into()
+                .ok()?,
+        ),
+        close_authority: read_optional_pubkey(data, ACCOUNT_CLOSE_AUTHORITY_OFFSET),
+        frozen: data[ACCOUNT_STATE_OFFSET] == ACCOUNT_STATE_FROZEN,
+    })
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Option<Pubkey> {
+    Some(Pubkey::new_from_array(
+        data.get(offset..offset + 32)?.try_into().ok()?,
+    ))
+}
+
+// A COption<Pubkey>: a four byte tag followed by the key
+fn read_optional_pubkey(data: &[u8], offset: usize) -> Option<Pubkey> {
+    match data.get(offset..offset + 4)? {
+        [1, 0, 0, 0] => read_pubkey(data, offset + 4),
+        _ => None,
+    }
+}
+
+pub fn transfer_checked(
+    token_program_id: &Pubkey,
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let mut data = vec![TRANSFER_CHECKED];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+
+    Instruction {
+        program_id: *token_program_id,
+        accounts: vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data,
+    }
+}
+
+/// Destroy `amount` tokens held in `account`, reducing the mint's supply
+pub fn burn(
+    token_program_id: &Pubkey,
+    account: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![BURN];
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    Instruction {
+        program_id: *token_program_id,
+        accounts: vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new(*mint, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data,
+    }
+}
+
+/// Close `account`, sending its lamports (and any wrapped SOL) to `destination`
+pub fn close_account(
+    token_program_id: &Pubkey,
+    account: &Pubkey,
+    destination: &Pubkey,
+    owner: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *token_program_id,
+        accounts: vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data: vec![CLOSE_ACCOUNT],
+    }
+}
+
+/// Remove the delegate of `source`, cancelling whatever allowance it had left
+pub fn revoke(token_program_id: &Pubkey, source: &Pubkey, owner: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *token_program_id,
+        accounts: vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data: vec![REVOKE],
+    }
+}
+
+/// Update the token balance of a wrapped SOL account to match the lamports sent to it
+pub fn sync_native(token_program_id: &Pubkey, account: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*account, false)],
+        data: vec![SYNC_NATIVE],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mint_decimals_and_supply() {
+        let mut data = vec![0u8; MINT_LEN];
+        data[MINT_DECIMALS_OFFSET] = 6;
+        data[MINT_SUPPLY_OFFSET..MINT_SUPPLY_OFFSET + 8].copy_from_slice(&1u64.to_le_bytes());
+        assert_eq!(mint_decimals(&data), Some(6));
+        assert_eq!(mint_supply(&data), Some(1));
+        assert_eq!(mint_decimals(&data[..10]), None);
+        assert_eq!(mint_supply(&data[..10]), None);
+    }
+
+    #[test]
+    fn test_account_amount() {
+        let mut data = vec![0u8; ACCOUNT_LEN];
+        data[ACCOUNT_AMOUNT_OFFSET..ACCOUNT_AMOUNT_OFFSET + 8]
+            .copy_from_slice(&2_500_000u64.to_le_bytes());
+        assert_eq!(account_amount(&data), Some(2_500_000));
+        assert_eq!(account_amount(&data[..MINT_LEN]), None);
+    }
+
+    #[test]
+    fn test_parse_account() {
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let mut data = vec![0u8; ACCOUNT_LEN];
+        data[..32].copy_from_slice(mint.as_ref());
+        data[32..64].copy_from_slice(owner.as_ref());
+        data[ACCOUNT_AMOUNT_OFFSET..ACCOUNT_AMOUNT_OFFSET + 8]
+            .copy_from_slice(&500u64.to_le_bytes());
+        data[ACCOUNT_DELEGATE_OFFSET] = 1;
+        data[ACCOUNT_DELEGATE_OFFSET + 4..ACCOUNT_DELEGATE_OFFSET + 36]
+            .copy_from_slice(delegate.as_ref());
+        data[ACCOUNT_STATE_OFFSET] = ACCOUNT_STATE_FROZEN;
+        data[ACCOUNT_DELEGATED_AMOUNT_OFFSET..ACCOUNT_DELEGATED_AMOUNT_OFFSET + 8]
+            .copy_from_slice(&200u64.to_le_bytes());
+
+        let account = parse_account(&data).unwrap();
+        assert_eq!(account.mint, mint);
+        assert_eq!(account.owner, owner);
+        assert_eq!(account.amount, 500);
+        assert_eq!(account.delegate, Some(delegate));
+        assert_eq!(account.delegated_amount, 200);
+        assert_eq!(account.close_authority, None);
+        assert!(account.frozen);
+        assert_eq!(parse_account(&data[..MINT_LEN]), None);
+    }
+
+    #[test]
+    fn test_transfer_checked() {
+        let source = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let instruction = transfer_checked(
+            &TOKEN_PROGRAM_ID,
+            &source,
+            &mint,
+            &destination,
+            &owner,
+            1_500_000,
+            6,
+        );
+
+        assert_eq!(instruction.program_id, TOKEN_PROGRAM_ID);
+        assert_eq!(instruction.data[0], TRANSFER_CHECKED);
+        assert_eq!(&instruction.data[1..9], &1_500_000u64.to_le_bytes());
+        assert_eq!(instruction.data[9], 6);
+        assert_eq!(instruction.accounts[3].pubkey, owner);
+        assert!(instruction.accounts[3].is_signer);
+    }
+
+    #[test]
+    fn test_close_account_revoke_and_sync_native() {
+        let account = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let close = close_account(&TOKEN_PROGRAM_ID, &account, &owner, &owner);
+        assert_eq!(close.data, vec![CLOSE_ACCOUNT]);
+        assert!(close.accounts[1].is_writable);
+        assert!(close.accounts[2].is_signer);
+
+        let revoke = revoke(&TOKEN_PROGRAM_ID, &account, &owner);
+        assert_eq!(revoke.data, vec![REVOKE]);
+        assert!(revoke.accounts[1].is_signer);
+
+        let sync = sync_native(&TOKEN_PROGRAM_ID, &account);
+        assert_eq!(sync.data, vec![SYNC_NATIVE]);
+        assert_eq!(sync.accounts.len(), 1);
+        assert!(sync.accounts[0].is_writable);
+    }
+
+    #[test]
+    fn test_associated_account_len() {
+        assert_eq!(associated_account_len(&TOKEN_PROGRAM_ID), ACCOUNT_LEN);
+        assert_eq!(
+            associated_account_len(&TOKEN_2022_PROGRAM_ID),
+            TOKEN_2022_ASSOCIATED_ACCOUNT_LEN
+        );
+    }
+
+    #[test]
+    fn test_is_token_program() {
+        assert!(is_token_program(&TOKEN_PROGRAM_ID));
+        assert!(is_token_program(&TOKEN_2022_PROGRAM_ID));
+        assert!(!is_token_program(&Pubkey::new_unique()));
+    }
+}
@@ -1 +1,23 @@
 pub mod account_service;
+pub mod errors;
+pub mod history_service;
+pub mod memo_service;
+pub mod metadata_service;
+pub mod nft_service;
+pub mod payout_service;
+pub mod portfolio_service;
+pub mod price_history_service;
+pub mod price_service;
+pub mod registry_service;
+pub mod reward_service;
+pub mod risk_service;
+pub mod schedule_service;
+pub mod spam_service;
+pub mod stake_pool_service;
+pub mod stake_service;
+pub mod token_service;
+pub mod transaction_service;
+pub mod transfer_service;
+pub mod validator_service;
+pub mod valuation_service;
+pub mod wrap_service;
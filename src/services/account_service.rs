@@ -72,6 +72,11 @@ impl AccountService {
         Ok(accounts)
     }
 
+    pub fn get_account_by_id(&self, id: i32) -> Result<Option<Account>, DatabaseError> {
+        let accounts = self.get_all_accounts()?;
+        Ok(accounts.into_iter().find(|account| account.id == Some(id)))
+    }
+
     fn account_name_generator(&self) -> Result<String, DatabaseError> {
         let accounts_count = self.get_all_accounts()?.len();
         Ok(if accounts_count > 0 {
@@ -88,8 +93,8 @@ impl AccountService {
 
     fn pubkey_from_keypair_generator(
         &self,
-        seed_phrase: &String,
-        passphrase: &String,
+        seed_phrase: &str,
+        passphrase: &str,
     ) -> Result<String, Box<dyn StdError>> {
         let keypair = keypair::keypair_from_seed_phrase_and_passphrase(seed_phrase, passphrase)?;
         Ok(keypair.pubkey().to_string())
@@ -129,7 +134,7 @@ mod tests {
         let account = account_service.create_account().unwrap();
 
         // Validate that the account properties are correctly generated
-        assert!(!account.id.is_some());
+        assert!(account.id.is_none());
         assert!(account.name.starts_with("Main Account") || account.name.starts_with("Account"));
         assert!(!account.seed.is_empty());
         assert!(!account.pubkey.is_empty());
@@ -167,6 +172,18 @@ mod tests {
         assert_eq!(retrieved_account.balance, account.balance);
     }
 
+    #[test]
+    fn test_get_account_by_id() {
+        let conn = setup_test_db();
+        let account_service = AccountService::new(conn);
+        account_service.create_account().unwrap();
+
+        let account = account_service.get_account_by_id(1).unwrap();
+        assert!(account.is_some());
+        assert_eq!(account.unwrap().name, "Main Account");
+        assert!(account_service.get_account_by_id(2).unwrap().is_none());
+    }
+
     #[test]
     fn test_account_name_generator() {
         let conn = setup_test_db();
@@ -181,10 +198,7 @@ mod tests {
         let conn = setup_test_db(); // Set up the in-memory database
         let account_service = AccountService::new(conn);
 
-        let result = account_service.pubkey_from_keypair_generator(
-            &"mock_seed".to_string(),
-            &"mock_passphrase".to_string(),
-        );
+        let result = account_service.pubkey_from_keypair_generator("mock_seed", "mock_passphrase");
         assert!(result.is_ok());
         assert!(!result.unwrap().is_empty());
     }
@@ -0,0 +1,63 @@
+use crate::amount::AmountError;
+use crate::das::errors::DasError;
+use crate::database::errors::DatabaseError;
+use crate::price_provider::errors::PriceError;
+use serde::de::StdError;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_sdk::pubkey::ParsePubkeyError;
+use solana_sdk::signer::SignerError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ServiceError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] DatabaseError),
+
+    #[error("Client error: {0}")]
+    ClientError(#[from] Box<ClientError>),
+
+    #[error("Signer error: {0}")]
+    SignerError(#[from] SignerError),
+
+    #[error("Parse pubkey error: {0}")]
+    ParsePubkeyError(#[from] ParsePubkeyError),
+
+    #[error("Amount error: {0}")]
+    AmountError(#[from] AmountError),
+
+    #[error("CSV error: {0}")]
+    CsvError(#[from] csv::Error),
+
+    #[error("Account {0} not found")]
+    AccountNotFound(String),
+
+    #[error("{0} is not a required signer of this transaction")]
+    NotASigner(String),
+
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
+
+    #[error("Invalid stake operation: {0}")]
+    InvalidStake(String),
+
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+
+    #[error("DAS error: {0}")]
+    DasError(#[from] DasError),
+
+    #[error("Price error: {0}")]
+    PriceError(#[from] PriceError),
+
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error("Other error: {0}")]
+    Other(#[from] Box<dyn StdError>),
+}
+
+impl From<ClientError> for ServiceError {
+    fn from(error: ClientError) -> Self {
+        ServiceError::ClientError(Box::new(error))
+    }
+}
@@ -0,0 +1,203 @@
+use crate::programs::{decoder::describe_transaction, memo::parse_memos};
+use crate::services::errors::ServiceError;
+use solana_rpc_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_rpc_client_api::config::RpcTransactionConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status_client_types::{
+    option_serializer::OptionSerializer, UiTransactionEncoding, UiTransactionStatusMeta,
+};
+use std::{str::FromStr, sync::Arc};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Sent => "Sent",
+            Direction::Received => "Received",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub signature: String,
+    pub block_time: Option<i64>,
+    pub direction: Direction,
+    pub memos: Vec<String>,
+    pub instructions: Vec<String>,
+    pub error: Option<String>,
+}
+
+pub struct HistoryService {
+    client: Arc<RpcClient>,
+}
+
+impl HistoryService {
+    pub fn new(client: Arc<RpcClient>) -> Self {
+        Self { client }
+    }
+
+    /// Latest transactions touching `address`, newest first
+    pub fn recent(
+        &self,
+        address: &Pubkey,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>, ServiceError> {
+        let statuses = self.client.get_signatures_for_address_with_config(
+            address,
+            GetConfirmedSignaturesForAddress2Config {
+                limit: Some(limit),
+                ..GetConfirmedSignaturesForAddress2Config::default()
+            },
+        )?;
+
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: None,
+            max_supported_transaction_version: Some(0),
+        };
+
+        let mut entries = vec![];
+        for status in statuses {
+            let signature = Signature::from_str(&status.signature)
+                .map_err(|e| ServiceError::Other(Box::new(e)))?;
+            let confirmed = self
+                .client
+                .get_transaction_with_config(&signature, config)?;
+
+            // Without a decodable transaction we cannot tell who paid or read its memos
+            let Some(transaction) = confirmed.transaction.transaction.decode() else {
+                continue;
+            };
+            let fee_payer = transaction.message.static_account_keys().first().copied();
+            let loaded = confirmed
+                .transaction
+                .meta
+                .as_ref()
+                .map(loaded_addresses)
+                .unwrap_or_default();
+
+            entries.push(HistoryEntry {
+                signature: status.signature,
+                block_time: status.block_time,
+                direction: if fee_payer == Some(*address) {
+                    Direction::Sent
+                } else {
+                    Direction::Received
+                },
+                memos: parse_memos(&transaction),
+                instructions: describe_transaction(&transaction, &loaded),
+                error: status.err.map(|err| err.to_string()),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// Addresses the transaction loaded from lookup tables, writable before read-only
+fn loaded_addresses(meta: &UiTransactionStatusMeta) -> Vec<Pubkey> {
+    let OptionSerializer::Some(loaded) = &meta.loaded_addresses else {
+        return vec![];
+    };
+    loaded
+        .writable
+        .iter()
+        .chain(&loaded.readonly)
+        .map(|address| Pubkey::from_str(address))
+        .collect::<Result<_, _>>()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::memo::build_memo;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde_json::json;
+    use solana_rpc_client::rpc_client::Mocks;
+    use solana_rpc_client_api::request::RpcRequest;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{Message, VersionedMessage};
+    use solana_sdk::transaction::VersionedTransaction;
+    use solana_system_interface::instruction::transfer;
+
+    fn encoded_transaction(payer: &Pubkey, recipient: &Pubkey, memo: &str) -> String {
+        let message = Message::new_with_blockhash(
+            &[build_memo(memo, &[payer]), transfer(payer, recipient, 10)],
+            Some(payer),
+            &Hash::new_unique(),
+        );
+        let transaction = VersionedTransaction {
+            signatures: vec![Default::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        STANDARD.encode(bincode::serialize(&transaction).unwrap())
+    }
+
+    fn history_client(payer: &Pubkey, recipient: &Pubkey, memo: &str) -> Arc<RpcClient> {
+        let signature = Signature::new_unique().to_string();
+        let mut mocks = Mocks::default();
+        mocks.insert(
+            RpcRequest::GetSignaturesForAddress,
+            json!([{
+                "signature": signature,
+                "slot": 5,
+                "err": null,
+                "memo": null,
+                "blockTime": 1_700_000_000,
+                "confirmationStatus": "finalized",
+            }]),
+        );
+        mocks.insert(
+            RpcRequest::GetTransaction,
+            json!({
+                "slot": 5,
+                "blockTime": 1_700_000_000,
+                "meta": null,
+                "transaction": [encoded_transaction(payer, recipient, memo), "base64"],
+            }),
+        );
+        Arc::new(RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            mocks,
+        ))
+    }
+
+    #[test]
+    fn test_recent_sent_with_memo() {
+        let payer = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let service = HistoryService::new(history_client(&payer, &recipient, "invoice 42"));
+
+        let entries = service.recent(&payer, 10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].direction, Direction::Sent);
+        assert_eq!(entries[0].memos, vec!["invoice 42"]);
+        assert_eq!(
+            entries[0].instructions,
+            vec![
+                "Memo: \"invoice 42\"".to_string(),
+                format!("Transfer 0.00000001 SOL from {} to {}", payer, recipient),
+            ]
+        );
+        assert_eq!(entries[0].block_time, Some(1_700_000_000));
+        assert_eq!(entries[0].error, None);
+    }
+
+    #[test]
+    fn test_recent_received_with_memo() {
+        let payer = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let service = HistoryService::new(history_client(&payer, &recipient, "deposit 7"));
+
+        let entries = service.recent(&recipient, 10).unwrap();
+        assert_eq!(entries[0].direction, Direction::Received);
+        assert_eq!(entries[0].memos, vec!["deposit 7"]);
+    }
+}
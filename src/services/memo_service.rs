@@ -0,0 +1,155 @@
+use crate::database::errors::DatabaseError;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+// Exchange and service deposit addresses that credit incoming funds by memo
+const MEMO_REQUIRED_ADDRESSES: &str = include_str!("memo_required.json");
+
+#[derive(Deserialize)]
+struct MemoRequiredAddress {
+    address: String,
+    label: String,
+}
+
+pub struct MemoService {
+    conn: Arc<Mutex<Connection>>,
+    bundled: HashMap<Pubkey, String>,
+}
+
+impl MemoService {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self {
+            conn,
+            bundled: parse_memo_required(MEMO_REQUIRED_ADDRESSES),
+        }
+    }
+
+    /// Name of the service behind `recipient` when it needs a memo to credit a deposit.
+    /// What the user marked wins over the bundled list.
+    pub fn required_label(&self, recipient: &Pubkey) -> Result<Option<String>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let marked: Option<(String, bool)> = conn
+            .query_row(
+                "SELECT label, required FROM memo_required_overrides WHERE address = ?1",
+                [recipient.to_string()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(match marked {
+            Some((label, required)) => required.then_some(label),
+            None => self.bundled.get(recipient).cloned(),
+        })
+    }
+
+    /// Mark `address` as needing a memo, shown as `label` in warnings, or as not needing one
+    pub fn set_required(
+        &self,
+        address: &Pubkey,
+        label: &str,
+        required: bool,
+    ) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO memo_required_overrides (address, label, required, updated_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (address) DO UPDATE SET label = excluded.label,
+            required = excluded.required, updated_at = excluded.updated_at",
+            params![address.to_string(), label, required, Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Warning for sending to `recipient` without a memo when it needs one
+    pub fn warning(&self, recipient: &Pubkey, memo: &str) -> Result<Option<String>, DatabaseError> {
+        if !memo.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(self.required_label(recipient)?.map(|label| {
+            format!(
+                "{} requires a memo to credit this deposit. Funds sent without one may be lost.",
+                label
+            )
+        }))
+    }
+}
+
+fn parse_memo_required(json: &str) -> HashMap<Pubkey, String> {
+    serde_json::from_str::<Vec<MemoRequiredAddress>>(json)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|entry| Some((Pubkey::from_str(&entry.address).ok()?, entry.label)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database_connection;
+
+    fn setup_test_db() -> Arc<Mutex<Connection>> {
+        let conn = Arc::new(Mutex::new(database_connection().unwrap()));
+        conn.lock()
+            .unwrap()
+            .execute(
+                "CREATE TABLE memo_required_overrides (
+                address TEXT PRIMARY KEY,
+                label TEXT NOT NULL,
+                required INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            )",
+                [],
+            )
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_parse_memo_required() {
+        let exchange = Pubkey::new_unique();
+        let json = format!(
+            r#"[{{"address":"{}","label":"Exchange"}},{{"address":"not-a-key","label":"Broken"}}]"#,
+            exchange
+        );
+
+        let addresses = parse_memo_required(&json);
+        assert_eq!(addresses.len(), 1);
+        assert_eq!(
+            addresses.get(&exchange).map(String::as_str),
+            Some("Exchange")
+        );
+        assert!(parse_memo_required("not json").is_empty());
+    }
+
+    #[test]
+    fn test_memo_warning() {
+        let mut service = MemoService::new(setup_test_db());
+        let listed = Pubkey::new_unique();
+        let marked = Pubkey::new_unique();
+        service.bundled.insert(listed, "Exchange".to_string());
+
+        assert_eq!(
+            service.warning(&listed, " ").unwrap().as_deref(),
+            Some(
+                "Exchange requires a memo to credit this deposit. Funds sent without one may be lost."
+            )
+        );
+        assert_eq!(service.warning(&listed, "12345").unwrap(), None);
+        assert_eq!(service.warning(&marked, "").unwrap(), None);
+
+        // The user's own marks apply on top of the bundled list
+        service.set_required(&marked, "My broker", true).unwrap();
+        service.set_required(&listed, "Exchange", false).unwrap();
+        assert_eq!(
+            service.required_label(&marked).unwrap().as_deref(),
+            Some("My broker")
+        );
+        assert_eq!(service.warning(&listed, "").unwrap(), None);
+    }
+}
@@ -0,0 +1,385 @@
+use crate::database::{errors::DatabaseError, token_metadata::TokenMetadata};
+use crate::programs::{
+    metadata::{metadata_address, parse_metadata, parse_token_2022_metadata, OnChainMetadata},
+    token::TOKEN_2022_PROGRAM_ID,
+};
+use crate::services::errors::ServiceError;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{de::DeserializeOwned, Deserialize};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+// Metadata rarely changes, so a week-old copy is still good enough to show
+const METADATA_MAX_AGE_SECS: i64 = 7 * 86_400;
+const LOGO_CACHE_DIR: &str = "resources/cache/token_logos";
+const ACCOUNTS_PER_REQUEST: usize = 100;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "webp", "svg"];
+
+// The part of the off-chain JSON the wallet shows
+#[derive(Deserialize)]
+struct OffChainMetadata {
+    image: Option<String>,
+}
+
+pub struct MetadataService {
+    conn: Arc<Mutex<Connection>>,
+    client: Arc<RpcClient>,
+}
+
+impl MetadataService {
+    pub fn new(conn: Arc<Mutex<Connection>>, client: Arc<RpcClient>) -> Self {
+        Self { conn, client }
+    }
+
+    /// Stored metadata of each of `mints` that has been resolved before
+    pub fn cached(
+        &self,
+        mints: &[String],
+    ) -> Result<HashMap<String, TokenMetadata>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT mint, name, symbol, uri, image, image_path, updated_at FROM token_metadata
+            WHERE mint = ?1",
+        )?;
+        let mut metadata = HashMap::new();
+        for mint in mints {
+            if let Some(row) = stmt.query_row([mint], metadata_from_row).optional()? {
+                metadata.insert(mint.clone(), row);
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// Metadata of each of `mints`, fetching whatever is missing or older than a week.
+    /// Blocking, since it downloads the off-chain JSON and logos.
+    pub fn resolve(
+        &self,
+        mints: &[Pubkey],
+    ) -> Result<HashMap<String, TokenMetadata>, ServiceError> {
+        let keys: Vec<String> = mints.iter().map(Pubkey::to_string).collect();
+        let mut metadata = self.cached(&keys)?;
+        let now = Utc::now().timestamp();
+        let stale: Vec<Pubkey> = mints
+            .iter()
+            .filter(|mint| {
+                metadata
+                    .get(&mint.to_string())
+                    .is_none_or(|entry| now - entry.updated_at > METADATA_MAX_AGE_SECS)
+            })
+            .copied()
+            .collect();
+
+        for (mint, on_chain) in self.fetch_on_chain(&stale)? {
+            let previous = metadata.remove(&mint.to_string());
+            let entry = self.resolve_off_chain(&mint, on_chain, previous, now);
+            self.store(&entry)?;
+            metadata.insert(entry.mint.clone(), entry);
+        }
+        Ok(metadata)
+    }
+
+    // Metaplex metadata first, then the Token-2022 extension for mints without it
+    fn fetch_on_chain(
+        &self,
+        mints: &[Pubkey],
+    ) -> Result<Vec<(Pubkey, Option<OnChainMetadata>)>, ServiceError> {
+        let mut resolved = vec![];
+        for batch in mints.chunks(ACCOUNTS_PER_REQUEST) {
+            let addresses: Vec<Pubkey> = batch.iter().map(metadata_address).collect();
+            let accounts = self.client.get_multiple_accounts(&addresses)?;
+            let mut found: Vec<Option<OnChainMetadata>> = accounts
+                .iter()
+                .map(|account| {
+                    account
+                        .as_ref()
+                        .and_then(|account| parse_metadata(&account.data))
+                })
+                .collect();
+            found.resize(batch.len(), None);
+
+            let missing: Vec<Pubkey> = batch
+                .iter()
+                .zip(&found)
+                .filter(|(_, metadata)| metadata.is_none())
+                .map(|(mint, _)| *mint)
+                .collect();
+            let mut extensions = HashMap::new();
+            if !missing.is_empty() {
+                for (mint, account) in missing
+                    .iter()
+                    .zip(self.client.get_multiple_accounts(&missing)?)
+                {
+                    if let Some(metadata) = account
+                        .filter(|account| account.owner == TOKEN_2022_PROGRAM_ID)
+                        .and_then(|account| parse_token_2022_metadata(&account.data))
+                    {
+                        extensions.insert(*mint, metadata);
+                    }
+                }
+            }
+
+            for (mint, metadata) in batch.iter().zip(found) {
+                resolved.push((*mint, metadata.or_else(|| extensions.remove(mint))));
+            }
+        }
+        Ok(resolved)
+    }
+
+    // Mints without metadata are stored too, so they are not looked up again on every launch
+    fn resolve_off_chain(
+        &self,
+        mint: &Pubkey,
+        on_chain: Option<OnChainMetadata>,
+        previous: Option<TokenMetadata>,
+        now: i64,
+    ) -> TokenMetadata {
+        let on_chain = on_chain.unwrap_or_default();
+        // Keep the previous logo when the JSON is unreachable this time
+        let image = fetch_image_url(&on_chain.uri)
+            .or_else(|| previous.as_ref().and_then(|entry| entry.image.clone()));
+        let image_path = image.as_deref().and_then(|image| {
+            let cached = previous
+                .as_ref()
+                .filter(|entry| entry.image.as_deref() == Some(image))
+                .and_then(|entry| entry.image_path.clone())
+                .filter(|path| Path::new(path).exists());
+            cached.or_else(|| download_image(LOGO_CACHE_DIR, mint, image))
+        });
+
+        TokenMetadata {
+            mint: mint.to_string(),
+            name: on_chain.name,
+            symbol: on_chain.symbol,
+            uri: on_chain.uri,
+            image,
+            image_path,
+            updated_at: now,
+        }
+    }
+
+    fn store(&self, metadata: &TokenMetadata) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO token_metadata (mint, name, symbol, uri, image, image_path, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ON CONFLICT (mint) DO UPDATE SET name = excluded.name, symbol = excluded.symbol,
+            uri = excluded.uri, image = excluded.image, image_path = excluded.image_path,
+            updated_at = excluded.updated_at",
+            params![
+                metadata.mint,
+                metadata.name,
+                metadata.symbol,
+                metadata.uri,
+                metadata.image,
+                metadata.image_path,
+                metadata.updated_at,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn metadata_from_row(row: &Row) -> rusqlite::Result<TokenMetadata> {
+    Ok(TokenMetadata {
+        mint: row.get(0)?,
+        name: row.get(1)?,
+        symbol: row.get(2)?,
+        uri: row.get(3)?,
+        image: row.get(4)?,
+        image_path: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+fn http_client() -> Option<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .ok()
+}
+
+// IPFS and Arweave links go through public gateways; anything else that is not HTTP is skipped
+fn gateway_url(uri: &str) -> Option<String> {
+    if let Some(path) = uri.strip_prefix("ipfs://") {
+        return Some(format!(
+            "https://ipfs.io/ipfs/{}",
+            path.trim_start_matches("ipfs/")
+        ));
+    }
+    if let Some(path) = uri.strip_prefix("ar://") {
+        return Some(format!("https://arweave.net/{}", path));
+    }
+    (uri.starts_with("https://") || uri.starts_with("http://")).then(|| uri.to_string())
+}
+
+/// Off-chain JSON that a metadata URI points to
+pub fn fetch_json<T: DeserializeOwned>(uri: &str) -> Option<T> {
+    http_client()?
+        .get(gateway_url(uri)?)
+        .send()
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .ok()
+}
+
+fn fetch_image_url(uri: &str) -> Option<String> {
+    fetch_json::<OffChainMetadata>(uri)?
+        .image
+        .filter(|image| !image.is_empty())
+}
+
+/// Path of the image of `mint` in the cache directory `dir`, downloading it from `url` the first time
+pub fn cached_image(dir: &str, mint: &Pubkey, url: &str) -> Option<String> {
+    let path = image_path(dir, mint, url);
+    if path.exists() {
+        return Some(path.to_string_lossy().to_string());
+    }
+    download_image(dir, mint, url)
+}
+
+// Save the image of `mint` at `url` in the cache directory `dir`, returning where it was written
+fn download_image(dir: &str, mint: &Pubkey, url: &str) -> Option<String> {
+    let bytes = http_client()?
+        .get(gateway_url(url)?)
+        .send()
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .bytes()
+        .ok()?;
+    fs::create_dir_all(dir).ok()?;
+    let path = image_path(dir, mint, url);
+    fs::write(&path, bytes).ok()?;
+    Some(path.to_string_lossy().to_string())
+}
+
+// The image loader picks the format from the extension, so keep the one in the URL
+fn image_path(dir: &str, mint: &Pubkey, url: &str) -> PathBuf {
+    let extension = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .filter(|extension| IMAGE_EXTENSIONS.contains(&extension.as_str()))
+        .unwrap_or_else(|| "png".to_string());
+    Path::new(dir).join(format!("{}.{}", mint, extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database_connection;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde_json::json;
+    use solana_rpc_client::rpc_client::Mocks;
+    use solana_rpc_client_api::request::RpcRequest;
+
+    fn setup_test_db() -> Arc<Mutex<Connection>> {
+        let conn = Arc::new(Mutex::new(database_connection().unwrap()));
+        conn.lock()
+            .unwrap()
+            .execute(
+                "CREATE TABLE token_metadata (
+                mint TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                symbol TEXT NOT NULL,
+                uri TEXT NOT NULL,
+                image TEXT NULL,
+                image_path TEXT NULL,
+                updated_at INTEGER NOT NULL
+            )",
+                [],
+            )
+            .unwrap();
+        conn
+    }
+
+    fn metadata_account(name: &str, symbol: &str) -> serde_json::Value {
+        let mut data = vec![4u8];
+        data.extend_from_slice(&[0; 64]);
+        for value in [name, symbol, ""] {
+            data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            data.extend_from_slice(value.as_bytes());
+        }
+        json!({
+            "data": [STANDARD.encode(&data), "base64"],
+            "executable": false,
+            "lamports": 5_616_720,
+            "owner": crate::programs::metadata::METADATA_PROGRAM_ID.to_string(),
+            "rentEpoch": 0,
+            "space": data.len(),
+        })
+    }
+
+    #[test]
+    fn test_resolve_and_cache() {
+        let mint = Pubkey::new_unique();
+        let mut mocks = Mocks::default();
+        mocks.insert(
+            RpcRequest::GetMultipleAccounts,
+            json!({
+                "context": {"slot": 1},
+                "value": [metadata_account("Bonk", "BONK")],
+            }),
+        );
+        let conn = setup_test_db();
+        let service = MetadataService::new(
+            conn.clone(),
+            Arc::new(RpcClient::new_mock_with_mocks(
+                "succeeds".to_string(),
+                mocks,
+            )),
+        );
+
+        let metadata = service.resolve(&[mint]).unwrap();
+        let entry = &metadata[&mint.to_string()];
+        assert_eq!(entry.name, "Bonk");
+        assert_eq!(entry.symbol, "BONK");
+        assert_eq!(entry.image, None);
+
+        // Fresh entries come from the table without touching the network again
+        let metadata = service.resolve(&[mint]).unwrap();
+        assert_eq!(metadata[&mint.to_string()].symbol, "BONK");
+    }
+
+    #[test]
+    fn test_image_path() {
+        let mint = Pubkey::new_unique();
+        assert_eq!(
+            image_path(LOGO_CACHE_DIR, &mint, "https://example.com/logo.SVG?v=2"),
+            Path::new(LOGO_CACHE_DIR).join(format!("{}.svg", mint))
+        );
+        assert_eq!(
+            image_path(LOGO_CACHE_DIR, &mint, "https://arweave.net/abc123"),
+            Path::new(LOGO_CACHE_DIR).join(format!("{}.png", mint))
+        );
+    }
+
+    #[test]
+    fn test_gateway_url() {
+        assert_eq!(
+            gateway_url("ipfs://ipfs/bafy/1.json").as_deref(),
+            Some("https://ipfs.io/ipfs/bafy/1.json")
+        );
+        assert_eq!(
+            gateway_url("ar://abc").as_deref(),
+            Some("https://arweave.net/abc")
+        );
+        assert_eq!(
+            gateway_url("https://example.com/a.json").as_deref(),
+            Some("https://example.com/a.json")
+        );
+        assert_eq!(gateway_url("file:///etc/passwd"), None);
+    }
+}
@@ -0,0 +1,956 @@
+use crate::amount::{base_units_to_ui_amount, SOL_DECIMALS};
+use crate::das::client::{AssetProof, DasAsset, DasClient};
+use crate::database::account::Account;
+use crate::programs::{
+    associated_token::{create_associated_token_account_idempotent, get_associated_token_address},
+    bubblegum::{self, canopy_depth, Leaf},
+    decoder::describe_instructions,
+    metadata::{
+        self, master_edition_address, metadata_address, parse_metadata, token_record_address,
+        transfer_programmable, OnChainMetadata, MASTER_EDITION_V2, PROGRAMMABLE_NON_FUNGIBLE,
+        TOKEN_RECORD_LEN,
+    },
+    token::{
+        associated_account_len, close_account, is_token_program, mint_decimals, mint_supply,
+        transfer_checked,
+    },
+};
+use crate::services::{
+    errors::ServiceError,
+    metadata_service::{cached_image, fetch_json},
+    token_service::{OwnedTokenAccount, TokenService},
+    transaction_service::TransactionService,
+};
+use serde::Deserialize;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::{collections::HashMap, fs, path::Path, str::FromStr, sync::Arc};
+
+const NFT_JSON_CACHE_DIR: &str = "resources/cache/nft_json";
+const NFT_IMAGE_CACHE_DIR: &str = "resources/cache/nft_images";
+const ACCOUNTS_PER_REQUEST: usize = 100;
+const UNCATEGORISED: &str = "Uncategorised";
+
+// The parts of the off-chain JSON the gallery shows
+#[derive(Debug, Default, Deserialize)]
+struct OffChainNft {
+    name: Option<String>,
+    description: Option<String>,
+    image: Option<String>,
+    #[serde(default)]
+    attributes: Vec<OffChainAttribute>,
+    collection: Option<OffChainCollection>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OffChainAttribute {
+    trait_type: Option<String>,
+    value: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct OffChainCollection {
+    name: Option<String>,
+}
+
+/// An NFT held by one of the wallet's accounts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Nft {
+    pub mint: Pubkey,
+    pub name: String,
+    /// Name of the verified collection, or the one the off-chain JSON claims
+    pub collection: String,
+    pub description: String,
+    /// Where the off-chain JSON lives
+    pub uri: String,
+    /// Mint of the verified collection, or the collection an indexer groups it under
+    pub collection_mint: Option<Pubkey>,
+    pub image_path: Option<String>,
+    /// Trait type and value pairs
+    pub attributes: Vec<(String, String)>,
+    /// Held as a leaf of a Bubblegum tree rather than in a token account
+    pub compressed: bool,
+}
+
+impl Nft {
+    /// Attributes as shown and filtered on, such as "Background: Blue"
+    pub fn attribute_labels(&self) -> Vec<String> {
+        self.attributes
+            .iter()
+            .map(|(trait_type, value)| format!("{}: {}", trait_type, value))
+            .collect()
+    }
+
+    /// Whether the name or collection contains `query`, ignoring case
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        query.is_empty()
+            || self.name.to_lowercase().contains(&query)
+            || self.collection.to_lowercase().contains(&query)
+    }
+}
+
+/// What the detail pane can do with an NFT
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NftAction {
+    Transfer { recipient: Pubkey },
+    Burn,
+}
+
+// Instructions for an action and what they will do, reviewed before signing
+struct ActionPlan {
+    instructions: Vec<Instruction>,
+    consequences: Vec<String>,
+}
+
+// An NFT in one of the owner's token accounts, with the lamports each of its accounts holds
+struct HeldNft {
+    mint: Pubkey,
+    token_account: OwnedTokenAccount,
+    token_lamports: u64,
+    metadata: OnChainMetadata,
+    metadata_lamports: u64,
+    /// Account key and lamports of the mint's edition account
+    edition: Option<(u8, u64)>,
+    token_record_lamports: Option<u64>,
+    /// Program owning the rule set of a programmable NFT, and the rule set
+    authorization_rules: Option<(Pubkey, Pubkey)>,
+}
+
+impl HeldNft {
+    fn programmable(&self) -> bool {
+        self.metadata.token_standard == Some(PROGRAMMABLE_NON_FUNGIBLE)
+    }
+}
+
+pub struct NftService {
+    client: Arc<RpcClient>,
+    das: DasClient,
+}
+
+impl NftService {
+    pub fn new(client: Arc<RpcClient>, das: DasClient) -> Self {
+        Self { client, das }
+    }
+
+    /// NFTs owned by `owner`, sorted by collection then name.
+    /// Blocking, since it downloads off-chain JSON and images missing from the disk cache.
+    pub fn list(&self, owner: &Pubkey) -> Result<Vec<Nft>, ServiceError> {
+        let mut candidates: Vec<Pubkey> = TokenService::new(self.client.clone())
+            .token_accounts(owner)?
+            .iter()
+            .filter(|token_account| token_account.account.amount == 1)
+            .map(|token_account| token_account.account.mint)
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+
+        let mints = self.nft_mints(&candidates)?;
+        let metadata = self.metadata(&mints)?;
+        let mut collections: Vec<Pubkey> = metadata
+            .iter()
+            .filter_map(|(_, metadata)| metadata.collection)
+            .collect();
+        collections.sort();
+        collections.dedup();
+        let collection_names: HashMap<Pubkey, String> = self
+            .metadata(&collections)?
+            .into_iter()
+            .map(|(mint, metadata)| (mint, metadata.name))
+            .collect();
+
+        let mut nfts: Vec<Nft> = metadata
+            .into_iter()
+            .map(|(mint, on_chain)| {
+                let off_chain = load_off_chain(&mint, &on_chain.uri);
+                let image_path = off_chain
+                    .as_ref()
+                    .and_then(|off_chain| off_chain.image.as_deref())
+                    .and_then(|image| cached_image(NFT_IMAGE_CACHE_DIR, &mint, image));
+                build_nft(mint, on_chain, off_chain, &collection_names, image_path)
+            })
+            .collect();
+        sort_nfts(&mut nfts);
+        Ok(nfts)
+    }
+
+    /// Compressed NFTs owned by `owner`, which only an indexer can list
+    pub fn compressed(&self, owner: &Pubkey) -> Result<Vec<Nft>, ServiceError> {
+        let assets: Vec<DasAsset> = self
+            .das
+            .get_assets_by_owner(&owner.to_string())?
+            .into_iter()
+            .filter(|asset| asset.is_compressed() && !asset.burnt)
+            .collect();
+        let mut collections: Vec<Pubkey> = assets
+            .iter()
+            .filter_map(|asset| Pubkey::from_str(asset.collection()?).ok())
+            .collect();
+        collections.sort();
+        collections.dedup();
+        let collection_names: HashMap<Pubkey, String> = self
+            .metadata(&collections)?
+            .into_iter()
+            .map(|(mint, metadata)| (mint, metadata.name))
+            .collect();
+
+        let mut nfts: Vec<Nft> = assets
+            .into_iter()
+            .filter_map(|asset| {
+                let id = Pubkey::from_str(&asset.id).ok()?;
+                let image_path = asset
+                    .content
+                    .links
+                    .image
+                    .as_deref()
+                    .and_then(|image| cached_image(NFT_IMAGE_CACHE_DIR, &id, image));
+                Some(build_compressed_nft(
+                    id,
+                    asset,
+                    &collection_names,
+                    image_path,
+                ))
+            })
+            .collect();
+        sort_nfts(&mut nfts);
+        Ok(nfts)
+    }
+
+    /// What `action` will do to the NFT `mint`, including the accounts it closes and the
+    /// SOL they refund, followed by the instructions that will be signed
+    pub fn preview(
+        &self,
+        account: &Account,
+        mint: &Pubkey,
+        compressed: bool,
+        action: &NftAction,
+    ) -> Result<Vec<String>, ServiceError> {
+        let plan = self.plan(&account.pubkey()?, mint, compressed, action)?;
+        Ok(plan
+            .consequences
+            .into_iter()
+            .chain(describe_instructions(&plan.instructions))
+            .collect())
+    }
+
+    pub fn apply(
+        &self,
+        account: &Account,
+        mint: &Pubkey,
+        compressed: bool,
+        action: &NftAction,
+    ) -> Result<Signature, ServiceError> {
+        let keypair = account.account_keypair()?;
+        let plan = self.plan(&account.pubkey()?, mint, compressed, action)?;
+        TransactionService::new(self.client.clone()).send_instructions(&plan.instructions, &keypair)
+    }
+
+    fn plan(
+        &self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        compressed: bool,
+        action: &NftAction,
+    ) -> Result<ActionPlan, ServiceError> {
+        if let NftAction::Transfer { recipient } = action {
+            if recipient == owner {
+                return Err(ServiceError::InvalidTransaction(format!(
+                    "{} already holds this NFT",
+                    owner
+                )));
+            }
+        }
+        if compressed {
+            return self.plan_compressed(owner, mint, action);
+        }
+
+        let held = self.held_nft(owner, mint)?;
+        match action {
+            NftAction::Transfer { recipient } => {
+                let program_id = held.token_account.program_id;
+                let destination = get_associated_token_address(recipient, mint, &program_id);
+                let mut new_accounts = vec![(destination, associated_account_len(&program_id))];
+                if held.programmable() {
+                    new_accounts.push((token_record_address(mint, &destination), TOKEN_RECORD_LEN));
+                }
+                let addresses: Vec<Pubkey> =
+                    new_accounts.iter().map(|(address, _)| *address).collect();
+                let existing = self.client.get_multiple_accounts(&addresses)?;
+                // The sender pays rent for whichever of the recipient's accounts are missing
+                let mut created = vec![];
+                for ((address, len), account) in new_accounts.into_iter().zip(existing) {
+                    if account.is_none() {
+                        let rent = self.client.get_minimum_balance_for_rent_exemption(len)?;
+                        created.push((address, rent));
+                    }
+                }
+                plan_transfer(owner, &held, recipient, &created)
+            }
+            NftAction::Burn => plan_burn(owner, &held),
+        }
+    }
+
+    fn plan_compressed(
+        &self,
+        owner: &Pubkey,
+        asset_id: &Pubkey,
+        action: &NftAction,
+    ) -> Result<ActionPlan, ServiceError> {
+        let asset = self.das.get_asset(&asset_id.to_string())?;
+        let proof = self.das.get_asset_proof(&asset_id.to_string())?;
+        let tree = asset
+            .compression
+            .as_ref()
+            .map(|compression| Pubkey::from_str(&compression.tree))
+            .transpose()?;
+        // Without the tree account every proof node is sent, which only costs space
+        let canopy = match tree {
+            Some(tree) => canopy_depth(&self.client.get_account(&tree)?.data).unwrap_or(0),
+            None => 0,
+        };
+        let (leaf, delegate) = compressed_leaf(&asset, &proof, owner)?;
+        let name = &asset.content.metadata.name;
+        let (instruction, outcome) = match action {
+            NftAction::Transfer { recipient } => (
+                bubblegum::transfer(&leaf, owner, &delegate, recipient, canopy),
+                format!("{} moves to {}", name, recipient),
+            ),
+            NftAction::Burn => (
+                bubblegum::burn(&leaf, owner, &delegate, canopy),
+                format!("{} is burned for good", name),
+            ),
+        };
+        Ok(ActionPlan {
+            instructions: vec![instruction],
+            consequences: vec![
+                outcome,
+                "Compressed NFTs are leaves of a shared tree, so no accounts are closed and no SOL is refunded".to_string(),
+            ],
+        })
+    }
+
+    // The owner's token account holding `mint`, with its metadata, edition and token record
+    fn held_nft(&self, owner: &Pubkey, mint: &Pubkey) -> Result<HeldNft, ServiceError> {
+        let token_account = TokenService::new(self.client.clone())
+            .token_accounts(owner)?
+            .into_iter()
+            .find(|token_account| {
+                token_account.account.mint == *mint && token_account.account.amount == 1
+            })
+            .ok_or_else(|| {
+                ServiceError::InvalidTransaction(format!("{} does not hold {}", owner, mint))
+            })?;
+        let addresses = [
+            token_account.address,
+            metadata_address(mint),
+            master_edition_address(mint),
+            token_record_address(mint, &token_account.address),
+        ];
+        let mut accounts = self.client.get_multiple_accounts(&addresses)?.into_iter();
+        let mut next = || accounts.next().flatten();
+
+        let token_lamports = next().map_or(0, |account| account.lamports);
+        let (metadata, metadata_lamports) = next()
+            .and_then(|account| Some((parse_metadata(&account.data)?, account.lamports)))
+            .ok_or_else(|| {
+                ServiceError::InvalidTransaction(format!("{} has no Metaplex metadata", mint))
+            })?;
+        let edition = next().and_then(|account| Some((*account.data.first()?, account.lamports)));
+        let token_record_lamports = next().map(|account| account.lamports);
+        let authorization_rules = match metadata.rule_set {
+            Some(rule_set) => Some((self.client.get_account(&rule_set)?.owner, rule_set)),
+            None => None,
+        };
+        Ok(HeldNft {
+            mint: *mint,
+            token_account,
+            token_lamports,
+            metadata,
+            metadata_lamports,
+            edition,
+            token_record_lamports,
+            authorization_rules,
+        })
+    }
+
+    // Mints with a supply of one and no decimals
+    fn nft_mints(&self, mints: &[Pubkey]) -> Result<Vec<Pubkey>, ServiceError> {
+        let mut nft_mints = vec![];
+        for batch in mints.chunks(ACCOUNTS_PER_REQUEST) {
+            let accounts = self.client.get_multiple_accounts(batch)?;
+            for (mint, account) in batch.iter().zip(accounts) {
+                let Some(account) = account.filter(|account| is_token_program(&account.owner))
+                else {
+                    continue;
+                };
+                if mint_decimals(&account.data) == Some(0) && mint_supply(&account.data) == Some(1)
+                {
+                    nft_mints.push(*mint);
+                }
+            }
+        }
+        Ok(nft_mints)
+    }
+
+    // Metaplex metadata of each of `mints` that has it
+    fn metadata(&self, mints: &[Pubkey]) -> Result<Vec<(Pubkey, OnChainMetadata)>, ServiceError> {
+        let mut metadata = vec![];
+        for batch in mints.chunks(ACCOUNTS_PER_REQUEST) {
+            let addresses: Vec<Pubkey> = batch.iter().map(metadata_address).collect();
+            let accounts = self.client.get_multiple_accounts(&addresses)?;
+            for (mint, account) in batch.iter().zip(accounts) {
+                if let Some(parsed) = account.and_then(|account| parse_metadata(&account.data)) {
+                    metadata.push((*mint, parsed));
+                }
+            }
+        }
+        Ok(metadata)
+    }
+}
+
+// Off-chain JSON rarely changes once minted, so a copy on disk is used instead of the network
+fn load_off_chain(mint: &Pubkey, uri: &str) -> Option<OffChainNft> {
+    let path = Path::new(NFT_JSON_CACHE_DIR).join(format!("{}.json", mint));
+    if let Some(cached) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+    {
+        return Some(cached);
+    }
+    let json: serde_json::Value = fetch_json(uri)?;
+    if fs::create_dir_all(NFT_JSON_CACHE_DIR).is_ok() {
+        let _ = fs::write(&path, json.to_string());
+    }
+    serde_json::from_value(json).ok()
+}
+
+/// Group NFTs by collection, with the uncategorised ones last
+pub fn sort_nfts(nfts: &mut [Nft]) {
+    nfts.sort_by(|a, b| {
+        (a.collection == UNCATEGORISED, &a.collection, &a.name).cmp(&(
+            b.collection == UNCATEGORISED,
+            &b.collection,
+            &b.name,
+        ))
+    });
+}
+
+fn attribute_value(value: serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value,
+        value => value.to_string(),
+    }
+}
+
+fn build_nft(
+    mint: Pubkey,
+    on_chain: OnChainMetadata,
+    off_chain: Option<OffChainNft>,
+    collection_names: &HashMap<Pubkey, String>,
+    image_path: Option<String>,
+) -> Nft {
+    let off_chain = off_chain.unwrap_or_default();
+    let collection_mint = on_chain.collection;
+    // Only a verified collection is trusted over what the JSON claims
+    let collection = on_chain
+        .collection
+        .and_then(|collection| collection_names.get(&collection).cloned())
+        .or_else(|| off_chain.collection.and_then(|collection| collection.name))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| UNCATEGORISED.to_string());
+    let name = Some(on_chain.name)
+        .filter(|name| !name.is_empty())
+        .or(off_chain.name)
+        .unwrap_or_else(|| mint.to_string());
+    let attributes = off_chain
+        .attributes
+        .into_iter()
+        .map(|attribute| {
+            (
+                attribute.trait_type.unwrap_or_default(),
+                attribute_value(attribute.value),
+            )
+        })
+        .collect();
+
+    Nft {
+        mint,
+        name,
+        collection,
+        description: off_chain.description.unwrap_or_default(),
+        uri: on_chain.uri,
+        collection_mint,
+        image_path,
+        attributes,
+        compressed: false,
+    }
+}
+
+fn build_compressed_nft(
+    id: Pubkey,
+    asset: DasAsset,
+    collection_names: &HashMap<Pubkey, String>,
+    image_path: Option<String>,
+) -> Nft {
+    // Indexers only group an asset under a collection that verified it
+    let collection_mint = asset
+        .collection()
+        .and_then(|collection| Pubkey::from_str(collection).ok());
+    let collection = collection_mint
+        .and_then(|collection| collection_names.get(&collection).cloned())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| UNCATEGORISED.to_string());
+    let metadata = asset.content.metadata;
+    Nft {
+        uri: asset.content.json_uri,
+        collection_mint,
+        mint: id,
+        name: Some(metadata.name)
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| id.to_string()),
+        collection,
+        description: metadata.description,
+        image_path,
+        attributes: metadata
+            .attributes
+            .into_iter()
+            .map(|attribute| {
+                (
+                    attribute.trait_type.unwrap_or_default(),
+                    attribute_value(attribute.value),
+                )
+            })
+            .collect(),
+        compressed: true,
+    }
+}
+
+// Transfer `held` to `recipient`, paying rent for the `created` accounts it needs
+fn plan_transfer(
+    owner: &Pubkey,
+    held: &HeldNft,
+    recipient: &Pubkey,
+    created: &[(Pubkey, u64)],
+) -> Result<ActionPlan, ServiceError> {
+    let source = &held.token_account;
+    let mut consequences = vec![format!("{} moves to {}", held.metadata.name, recipient)];
+    consequences.extend(created.iter().map(|(address, rent)| {
+        format!(
+            "This wallet pays {} SOL rent to create {} for the recipient",
+            sol(*rent),
+            address
+        )
+    }));
+
+    let mut closed = vec![(source.address, "token account", held.token_lamports)];
+    let instructions = if held.programmable() {
+        if let Some(lamports) = held.token_record_lamports {
+            closed.push((
+                token_record_address(&held.mint, &source.address),
+                "token record",
+                lamports,
+            ));
+        }
+        vec![transfer_programmable(
+            &source.program_id,
+            &held.mint,
+            &source.address,
+            owner,
+            recipient,
+            held.authorization_rules,
+        )]
+    } else {
+        check_not_frozen(held)?;
+        let destination = get_associated_token_address(recipient, &held.mint, &source.program_id);
+        let mut instructions = vec![
+            create_associated_token_account_idempotent(
+                owner,
+                recipient,
+                &held.mint,
+                &source.program_id,
+            ),
+            transfer_checked(
+                &source.program_id,
+                &source.address,
+                &held.mint,
+                &destination,
+                owner,
+                1,
+                0,
+            ),
+        ];
+        // Closing fails while someone else holds the close authority, so the account stays
+        match source.account.close_authority {
+            Some(authority) if authority != *owner => {
+                closed.clear();
+                consequences.push(format!(
+                    "{} stays open, as {} holds its close authority",
+                    source.address, authority
+                ));
+            }
+            _ => instructions.push(close_account(
+                &source.program_id,
+                &source.address,
+                owner,
+                owner,
+            )),
+        }
+        instructions
+    };
+    consequences.extend(closed_consequences(&closed));
+    Ok(ActionPlan {
+        instructions,
+        consequences,
+    })
+}
+
+fn plan_burn(owner: &Pubkey, held: &HeldNft) -> Result<ActionPlan, ServiceError> {
+    let edition_lamports = match held.edition {
+        Some((MASTER_EDITION_V2, lamports)) => lamports,
+        Some(_) => {
+            return Err(ServiceError::InvalidTransaction(format!(
+                "{} is a printed edition, which cannot be burned here yet",
+                held.mint
+            )))
+        }
+        None => {
+            return Err(ServiceError::InvalidTransaction(format!(
+                "{} has no master edition to burn",
+                held.mint
+            )))
+        }
+    };
+    if !held.programmable() {
+        check_not_frozen(held)?;
+    }
+    let source = &held.token_account;
+    let mut closed = vec![
+        (source.address, "token account", held.token_lamports),
+        (
+            metadata_address(&held.mint),
+            "metadata account",
+            held.metadata_lamports,
+        ),
+        (
+            master_edition_address(&held.mint),
+            "master edition",
+            edition_lamports,
+        ),
+    ];
+    if let (true, Some(lamports)) = (held.programmable(), held.token_record_lamports) {
+        closed.push((
+            token_record_address(&held.mint, &source.address),
+            "token record",
+            lamports,
+        ));
+    }
+
+    let mut consequences = vec![format!("{} is burned for good", held.metadata.name)];
+    consequences.extend(closed_consequences(&closed));
+    consequences.push(format!(
+        "The mint {} stays on chain with a supply of zero",
+        held.mint
+    ));
+    Ok(ActionPlan {
+        instructions: vec![metadata::burn(
+            &source.program_id,
+            &held.mint,
+            &source.address,
+            owner,
+            held.metadata.collection,
+            held.programmable(),
+        )],
+        consequences,
+    })
+}
+
+// Programmable NFTs are always frozen and thawed by Token Metadata, other NFTs never should be
+fn check_not_frozen(held: &HeldNft) -> Result<(), ServiceError> {
+    if held.token_account.account.frozen {
+        return Err(ServiceError::InvalidTransaction(format!(
+            "{} is frozen",
+            held.token_account.address
+        )));
+    }
+    Ok(())
+}
+
+// One line per closed account, then the SOL they refund to this wallet
+fn closed_consequences(closed: &[(Pubkey, &str, u64)]) -> Vec<String> {
+    let mut lines: Vec<String> = closed
+        .iter()
+        .map(|(address, kind, lamports)| {
+            format!(
+                "Closes the {} {}, refunding {} SOL",
+                kind,
+                address,
+                sol(*lamports)
+            )
+        })
+        .collect();
+    let total: u64 = closed.iter().map(|(_, _, lamports)| lamports).sum();
+    lines.push(format!("This wallet gets {} SOL back in total", sol(total)));
+    lines
+}
+
+fn sol(lamports: u64) -> String {
+    base_units_to_ui_amount(lamports, SOL_DECIMALS)
+}
+
+/// The leaf of a compressed asset owned by `owner` and its delegate, from what the indexer
+/// knows about it
+fn compressed_leaf(
+    asset: &DasAsset,
+    proof: &AssetProof,
+    owner: &Pubkey,
+) -> Result<(Leaf, Pubkey), ServiceError> {
+    let compression = asset
+        .compression
+        .as_ref()
+        .filter(|compression| compression.compressed)
+        .ok_or_else(|| {
+            ServiceError::InvalidTransaction(format!("{} is not a compressed NFT", asset.id))
+        })?;
+    if asset.ownership.owner != owner.to_string() {
+        return Err(ServiceError::InvalidTransaction(format!(
+            "{} is not owned by {}",
+            asset.id, owner
+        )));
+    }
+    let delegate = match &asset.ownership.delegate {
+        Some(delegate) => Pubkey::from_str(delegate)?,
+        None => *owner,
+    };
+    // Hashes share the base58 encoding of public keys
+    let hash = |value: &str| Pubkey::from_str(value).map(|hash| hash.to_bytes());
+    let leaf = Leaf {
+        merkle_tree: Pubkey::from_str(&compression.tree)?,
+        root: hash(&proof.root)?,
+        data_hash: hash(&compression.data_hash)?,
+        creator_hash: hash(&compression.creator_hash)?,
+        index: u32::try_from(compression.leaf_id).map_err(|_| {
+            ServiceError::InvalidTransaction(format!(
+                "Leaf index {} is too large",
+                compression.leaf_id
+            ))
+        })?,
+        proof: proof
+            .proof
+            .iter()
+            .map(|node| Pubkey::from_str(node))
+            .collect::<Result<_, _>>()?,
+    };
+    Ok((leaf, delegate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::das::client::tests::{compressed_asset, spawn_stub_indexer};
+    use crate::programs::{
+        metadata::METADATA_PROGRAM_ID,
+        token::{TokenAccount, TOKEN_PROGRAM_ID},
+    };
+    use serde_json::json;
+
+    #[test]
+    fn test_build_nft() {
+        let mint = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+        let off_chain: OffChainNft = serde_json::from_str(
+            r#"{
+                "name": "Off-chain name",
+                "description": "A lad",
+                "image": "https://example.com/1.png",
+                "attributes": [
+                    {"trait_type": "Background", "value": "Blue"},
+                    {"trait_type": "Level", "value": 3}
+                ],
+                "collection": {"name": "Claimed collection"}
+            }"#,
+        )
+        .unwrap();
+        let on_chain = OnChainMetadata {
+            name: "Mad Lad #1".to_string(),
+            symbol: "MAD".to_string(),
+            uri: String::new(),
+            collection: Some(collection),
+            ..Default::default()
+        };
+        let names = HashMap::from([(collection, "Mad Lads".to_string())]);
+
+        let nft = build_nft(mint, on_chain.clone(), Some(off_chain), &names, None);
+        assert_eq!(nft.name, "Mad Lad #1");
+        assert_eq!(nft.collection, "Mad Lads");
+        assert_eq!(nft.collection_mint, Some(collection));
+        assert_eq!(nft.description, "A lad");
+        assert_eq!(nft.attribute_labels(), vec!["Background: Blue", "Level: 3"]);
+        assert!(nft.matches(" mad LADS"));
+        assert!(!nft.matches("okay bears"));
+
+        // Without a verified collection or any JSON there is nothing to group by
+        let nft = build_nft(
+            mint,
+            OnChainMetadata {
+                collection: None,
+                ..on_chain
+            },
+            None,
+            &names,
+            None,
+        );
+        assert_eq!(nft.collection, UNCATEGORISED);
+        assert!(nft.attributes.is_empty());
+    }
+
+    #[test]
+    fn test_compressed_against_stub_indexer() {
+        let owner = Pubkey::new_unique();
+        let asset_id = Pubkey::new_unique();
+        let tree = Pubkey::new_unique();
+        let burnt_id = Pubkey::new_unique();
+        let mut burnt =
+            compressed_asset(&burnt_id.to_string(), &owner.to_string(), &tree.to_string());
+        burnt["burnt"] = json!(true);
+        let url = spawn_stub_indexer(vec![(
+            "getAssetsByOwner",
+            json!({
+                "items": [
+                    compressed_asset(&asset_id.to_string(), &owner.to_string(), &tree.to_string()),
+                    burnt,
+                ],
+            }),
+        )]);
+        let service = NftService::new(
+            Arc::new(RpcClient::new_mock("succeeds".to_string())),
+            DasClient::new(url),
+        );
+
+        let nfts = service.compressed(&owner).unwrap();
+        assert_eq!(nfts.len(), 1);
+        assert_eq!(nfts[0].mint, asset_id);
+        assert_eq!(nfts[0].name, "Compressed #7");
+        assert_eq!(nfts[0].collection, UNCATEGORISED);
+        assert_eq!(nfts[0].attribute_labels(), vec!["Rarity: Rare"]);
+        assert!(nfts[0].compressed);
+    }
+
+    #[test]
+    fn test_compressed_leaf() {
+        let owner = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let tree = Pubkey::new_unique();
+        let asset: DasAsset = serde_json::from_value(compressed_asset(
+            &Pubkey::new_unique().to_string(),
+            &owner.to_string(),
+            &tree.to_string(),
+        ))
+        .unwrap();
+        let proof = AssetProof {
+            root: Pubkey::new_from_array([9; 32]).to_string(),
+            proof: (0..3).map(|_| Pubkey::new_unique().to_string()).collect(),
+        };
+
+        let (leaf, delegate) = compressed_leaf(&asset, &proof, &owner).unwrap();
+        assert_eq!(leaf.merkle_tree, tree);
+        assert_eq!(leaf.root, [9; 32]);
+        assert_eq!(leaf.index, 7);
+        assert_eq!(leaf.proof.len(), 3);
+        // Without a delegate the owner stands in for one
+        assert_eq!(delegate, owner);
+
+        // Only the owner can move it
+        assert!(compressed_leaf(&asset, &proof, &recipient).is_err());
+    }
+
+    fn held_nft(owner: &Pubkey, token_standard: u8) -> HeldNft {
+        let mint = Pubkey::new_unique();
+        HeldNft {
+            mint,
+            token_account: OwnedTokenAccount {
+                address: Pubkey::new_unique(),
+                program_id: TOKEN_PROGRAM_ID,
+                account: TokenAccount {
+                    mint,
+                    owner: *owner,
+                    amount: 1,
+                    delegate: None,
+                    delegated_amount: 0,
+                    close_authority: None,
+                    frozen: token_standard == PROGRAMMABLE_NON_FUNGIBLE,
+                },
+            },
+            token_lamports: 2_039_280,
+            metadata: OnChainMetadata {
+                name: "Mad Lad #1".to_string(),
+                token_standard: Some(token_standard),
+                ..Default::default()
+            },
+            metadata_lamports: 5_616_720,
+            edition: Some((MASTER_EDITION_V2, 2_853_600)),
+            token_record_lamports: (token_standard == PROGRAMMABLE_NON_FUNGIBLE)
+                .then_some(1_447_680),
+            authorization_rules: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_transfer() {
+        let owner = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let held = held_nft(&owner, 0);
+        let destination = Pubkey::new_unique();
+
+        let plan = plan_transfer(&owner, &held, &recipient, &[(destination, 2_039_280)]).unwrap();
+        assert_eq!(plan.instructions.len(), 3);
+        assert_eq!(
+            plan.instructions[2].accounts[0].pubkey,
+            held.token_account.address
+        );
+        assert!(plan.consequences[1].contains(&destination.to_string()));
+        assert!(plan.consequences[2].contains("token account"));
+        assert_eq!(
+            plan.consequences[3],
+            "This wallet gets 0.00203928 SOL back in total"
+        );
+
+        // Someone else's close authority keeps the emptied account open
+        let mut held = held_nft(&owner, 0);
+        held.token_account.account.close_authority = Some(Pubkey::new_unique());
+        let plan = plan_transfer(&owner, &held, &recipient, &[]).unwrap();
+        assert_eq!(plan.instructions.len(), 2);
+        assert!(plan.consequences.last().unwrap().contains("0 SOL"));
+
+        // Programmable NFTs go through Token Metadata, which also closes the token record
+        let held = held_nft(&owner, PROGRAMMABLE_NON_FUNGIBLE);
+        let plan = plan_transfer(&owner, &held, &recipient, &[]).unwrap();
+        assert_eq!(plan.instructions.len(), 1);
+        assert_eq!(plan.instructions[0].program_id, METADATA_PROGRAM_ID);
+        assert!(plan.consequences[2].contains("token record"));
+        assert!(plan.consequences[3].contains("0.00348696"));
+    }
+
+    #[test]
+    fn test_plan_burn() {
+        let owner = Pubkey::new_unique();
+        let held = held_nft(&owner, PROGRAMMABLE_NON_FUNGIBLE);
+
+        let plan = plan_burn(&owner, &held).unwrap();
+        assert_eq!(plan.instructions[0].program_id, METADATA_PROGRAM_ID);
+        // Token account, metadata, master edition and token record
+        assert_eq!(plan.consequences.len(), 1 + 4 + 2);
+        assert_eq!(
+            plan.consequences[5],
+            "This wallet gets 0.01195728 SOL back in total"
+        );
+
+        let mut held = held_nft(&owner, 0);
+        held.edition = Some((1, 2_853_600));
+        assert!(plan_burn(&owner, &held).is_err());
+        held.edition = Some((MASTER_EDITION_V2, 2_853_600));
+        held.token_account.account.frozen = true;
+        assert!(plan_burn(&owner, &held).is_err());
+    }
+}
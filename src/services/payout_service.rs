@@ -0,0 +1,784 @@
+use crate::amount::{base_units_to_ui_amount, ui_amount_to_base_units, SOL_DECIMALS};
+use crate::database::{
+    account::Account,
+    errors::DatabaseError,
+    payout::{PayoutBatch, PayoutRow, PayoutStatus},
+};
+use crate::programs::{
+    associated_token::get_associated_token_address, token::associated_account_len,
+};
+use crate::services::{
+    errors::ServiceError,
+    transaction_service::TransactionService,
+    transfer_service::{transfer_instructions, TokenMint, Transfer, TransferService},
+};
+use rusqlite::{params, Connection, Row};
+use serde::Deserialize;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::Message;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{Read, Write},
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+// getMultipleAccounts accepts at most 100 keys per request
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct PayoutRecord {
+    recipient: String,
+    amount: String,
+    #[serde(default)]
+    token: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PayoutEstimate {
+    pub transactions: usize,
+    /// Native SOL paid to recipients
+    pub lamports: u64,
+    /// Token amounts paid per mint
+    pub token_totals: BTreeMap<String, String>,
+    pub network_fees: u64,
+    pub new_token_accounts: usize,
+    pub token_account_rent: u64,
+}
+
+impl PayoutEstimate {
+    /// Lamports leaving the paying account, excluding tokens
+    pub fn total_lamports(&self) -> u64 {
+        self.lamports + self.network_fees + self.token_account_rent
+    }
+}
+
+pub struct PayoutService {
+    conn: Arc<Mutex<Connection>>,
+    client: Arc<RpcClient>,
+}
+
+impl PayoutService {
+    pub fn new(conn: Arc<Mutex<Connection>>, client: Arc<RpcClient>) -> Self {
+        Self { conn, client }
+    }
+
+    pub fn import_csv(&self, account_id: i32, path: &Path) -> Result<PayoutBatch, ServiceError> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        let file = std::fs::File::open(path).map_err(csv::Error::from)?;
+        self.import(account_id, &name, file)
+    }
+
+    /// Validate every CSV row and store the batch, keeping invalid rows so they can be reported
+    pub fn import<R: Read>(
+        &self,
+        account_id: i32,
+        name: &str,
+        reader: R,
+    ) -> Result<PayoutBatch, ServiceError> {
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(reader);
+        let records = csv_reader
+            .deserialize::<PayoutRecord>()
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mints: HashSet<Pubkey> = records
+            .iter()
+            .filter_map(|record| parse_token(&record.token).ok().flatten())
+            .collect();
+        let token_mints = self.token_mints(mints.into_iter())?;
+
+        let mut conn = self.conn.lock().unwrap();
+        let transaction = conn.transaction().map_err(DatabaseError::from)?;
+        transaction
+            .execute(
+                "INSERT INTO payout_batches (account_id, name) VALUES (?1, ?2)",
+                params![account_id, name],
+            )
+            .map_err(DatabaseError::from)?;
+        let batch_id = transaction.last_insert_rowid() as i32;
+
+        for (index, record) in records.iter().enumerate() {
+            let error = validate_record(record, &token_mints).err();
+            let status = match error {
+                Some(_) => PayoutStatus::Invalid,
+                None => PayoutStatus::Pending,
+            };
+            let token = match parse_token(&record.token) {
+                Ok(Some(mint)) => mint.to_string(),
+                Ok(None) => String::new(),
+                Err(_) => record.token.clone(),
+            };
+            transaction
+                .execute(
+                    "INSERT INTO payout_rows (batch_id, row_number, recipient, amount, token, status, error)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        batch_id,
+                        index as i32 + 1,
+                        &record.recipient,
+                        &record.amount,
+                        token,
+                        status.as_str(),
+                        error,
+                    ],
+                )
+                .map_err(DatabaseError::from)?;
+        }
+        transaction.commit().map_err(DatabaseError::from)?;
+
+        Ok(PayoutBatch {
+            id: Some(batch_id),
+            account_id,
+            name: name.to_string(),
+        })
+    }
+
+    pub fn get_latest_batch(&self, account_id: i32) -> Result<Option<PayoutBatch>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, account_id, name FROM payout_batches
+            WHERE account_id = ?1 ORDER BY id DESC LIMIT 1",
+        )?;
+        let mut rows = stmt.query(params![account_id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(PayoutBatch {
+                id: row.get(0)?,
+                account_id: row.get(1)?,
+                name: row.get(2)?,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn get_rows(&self, batch_id: i32) -> Result<Vec<PayoutRow>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, batch_id, row_number, recipient, amount, token, status, signature,
+            last_valid_block_height, error FROM payout_rows WHERE batch_id = ?1 ORDER BY row_number",
+        )?;
+        let row_iter = stmt.query_map(params![batch_id], payout_row_from_sql)?;
+
+        let mut rows = Vec::new();
+        for row in row_iter {
+            rows.push(row?);
+        }
+        Ok(rows)
+    }
+
+    pub fn update_row(&self, row: &PayoutRow) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE payout_rows SET status = ?1, signature = ?2, last_valid_block_height = ?3,
+            error = ?4 WHERE id = ?5",
+            params![
+                row.status.as_str(),
+                row.signature,
+                row.last_valid_block_height,
+                row.error,
+                row.id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Work out what paying the remaining rows will cost, including rent for new token accounts
+    pub fn estimate(
+        &self,
+        payer: &Pubkey,
+        rows: &[PayoutRow],
+    ) -> Result<PayoutEstimate, ServiceError> {
+        let payable: Vec<&PayoutRow> = rows.iter().filter(|row| row.status.is_payable()).collect();
+        let token_mints = self.row_token_mints(&payable)?;
+        let mut estimate = PayoutEstimate::default();
+
+        let mut token_units: BTreeMap<String, u64> = BTreeMap::new();
+        let mut token_accounts: HashMap<Pubkey, &TokenMint> = HashMap::new();
+        let mut groups = vec![];
+        for row in &payable {
+            let transfer_details = row_transfer(row)?;
+            match &transfer_details.mint {
+                None => {
+                    estimate.lamports += ui_amount_to_base_units(&row.amount, SOL_DECIMALS)?;
+                }
+                Some(mint) => {
+                    let token_mint = &token_mints[mint];
+                    let units = ui_amount_to_base_units(&row.amount, token_mint.decimals)?;
+                    *token_units.entry(row.token.clone()).or_default() += units;
+                    let address = get_associated_token_address(
+                        &transfer_details.recipient,
+                        mint,
+                        &token_mint.program_id,
+                    );
+                    token_accounts.insert(address, token_mint);
+                }
+            }
+            groups.push(transfer_instructions(
+                payer,
+                &transfer_details,
+                transfer_details
+                    .mint
+                    .as_ref()
+                    .map(|mint| &token_mints[mint]),
+            )?);
+        }
+
+        for (mint, units) in token_units {
+            let decimals = token_mints[&Pubkey::from_str(&mint)?].decimals;
+            estimate
+                .token_totals
+                .insert(mint, base_units_to_ui_amount(units, decimals));
+        }
+
+        let addresses: Vec<Pubkey> = token_accounts.keys().copied().collect();
+        for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let accounts = self.client.get_multiple_accounts(chunk)?;
+            for (address, account) in chunk.iter().zip(accounts) {
+                if account.is_none() {
+                    let token_mint = token_accounts[address];
+                    estimate.new_token_accounts += 1;
+                    estimate.token_account_rent +=
+                        self.client.get_minimum_balance_for_rent_exemption(
+                            associated_account_len(&token_mint.program_id),
+                        )?;
+                }
+            }
+        }
+
+        let packs = pack_instructions(payer, &groups);
+        let blockhash = self.client.get_latest_blockhash()?;
+        for pack in &packs {
+            let instructions: Vec<Instruction> = pack
+                .iter()
+                .flat_map(|index| groups[*index].clone())
+                .collect();
+            let message = Message::new_with_blockhash(&instructions, Some(payer), &blockhash);
+            estimate.network_fees += self.client.get_fee_for_message(&message)?;
+        }
+        estimate.transactions = packs.len();
+
+        Ok(estimate)
+    }
+
+    /// Pay every row that has not been paid yet, reporting each row as its status changes.
+    /// Rows are marked as sent with their signature before broadcasting, so an interrupted
+    /// run can be resumed without paying anyone twice.
+    pub fn execute<F: FnMut(&PayoutRow)>(
+        &self,
+        account: &Account,
+        batch_id: i32,
+        mut on_update: F,
+    ) -> Result<(), ServiceError> {
+        let keypair = account.account_keypair()?;
+        let payer = account.pubkey()?;
+        let transaction_service = TransactionService::new(self.client.clone());
+
+        let mut rows = self.get_rows(batch_id)?;
+        self.reconcile(&transaction_service, &mut rows, &mut on_update)?;
+
+        let mut payable: Vec<PayoutRow> = rows
+            .into_iter()
+            .filter(|row| row.status.is_payable())
+            .collect();
+        let token_mints = self.row_token_mints(&payable.iter().collect::<Vec<_>>())?;
+
+        let mut groups = vec![];
+        for row in &payable {
+            let transfer_details = row_transfer(row)?;
+            groups.push(transfer_instructions(
+                &payer,
+                &transfer_details,
+                transfer_details
+                    .mint
+                    .as_ref()
+                    .map(|mint| &token_mints[mint]),
+            )?);
+        }
+
+        for pack in pack_instructions(&payer, &groups) {
+            let instructions: Vec<Instruction> = pack
+                .iter()
+                .flat_map(|index| groups[*index].clone())
+                .collect();
+            let (transaction, last_valid_block_height) =
+                transaction_service.sign_instructions(&instructions, &keypair)?;
+            let signature = transaction.signatures[0];
+
+            for index in &pack {
+                let row = &mut payable[*index];
+                row.status = PayoutStatus::Sent;
+                row.signature = Some(signature.to_string());
+                row.last_valid_block_height = Some(last_valid_block_height);
+                row.error = None;
+                self.update_row(row)?;
+                on_update(row);
+            }
+
+            let result = transaction_service.send(&VersionedTransaction::from(transaction));
+            let outcome = match result {
+                Ok(_) => Some(Ok(())),
+                // The transaction may still land after a timeout, so ask the cluster
+                Err(e) => match transaction_service.signature_statuses(&[signature]) {
+                    Ok(statuses) => statuses.into_iter().next().flatten(),
+                    Err(_) => None,
+                }
+                .or_else(|| Some(Err(e.to_string())).filter(|_| is_rejected(&e))),
+            };
+
+            for index in &pack {
+                let row = &mut payable[*index];
+                match &outcome {
+                    Some(Ok(())) => row.status = PayoutStatus::Confirmed,
+                    Some(Err(error)) => {
+                        row.status = PayoutStatus::Failed;
+                        row.error = Some(error.clone());
+                    }
+                    // Unknown outcome, leave as sent and reconcile on the next run
+                    None => continue,
+                }
+                self.update_row(row)?;
+                on_update(row);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn export_results(&self, batch_id: i32, path: &Path) -> Result<(), ServiceError> {
+        let rows = self.get_rows(batch_id)?;
+        let file = std::fs::File::create(path).map_err(csv::Error::from)?;
+        write_results(&rows, file)
+    }
+
+    /// Settle rows left as sent by an earlier run
+    fn reconcile<F: FnMut(&PayoutRow)>(
+        &self,
+        transaction_service: &TransactionService,
+        rows: &mut [PayoutRow],
+        on_update: &mut F,
+    ) -> Result<(), ServiceError> {
+        let sent: Vec<usize> = (0..rows.len())
+            .filter(|index| rows[*index].status == PayoutStatus::Sent)
+            .collect();
+        if sent.is_empty() {
+            return Ok(());
+        }
+
+        let signatures = sent
+            .iter()
+            .map(|index| {
+                let signature = rows[*index].signature.clone().unwrap_or_default();
+                Signature::from_str(&signature).map_err(|e| ServiceError::Other(Box::new(e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let statuses = transaction_service.signature_statuses(&signatures)?;
+        let block_height = transaction_service.block_height()?;
+
+        for (index, status) in sent.into_iter().zip(statuses) {
+            let row = &mut rows[index];
+            match status {
+                Some(Ok(())) => row.status = PayoutStatus::Confirmed,
+                Some(Err(error)) => {
+                    row.status = PayoutStatus::Failed;
+                    row.error = Some(error);
+                }
+                // Once the blockhash has expired the transaction can never land
+                None if row.last_valid_block_height.unwrap_or(0) < block_height => {
+                    row.status = PayoutStatus::Pending;
+                    row.signature = None;
+                    row.last_valid_block_height = None;
+                }
+                None => continue,
+            }
+            self.update_row(row)?;
+            on_update(row);
+        }
+        Ok(())
+    }
+
+    fn row_token_mints(
+        &self,
+        rows: &[&PayoutRow],
+    ) -> Result<HashMap<Pubkey, TokenMint>, ServiceError> {
+        let mints = rows
+            .iter()
+            .filter(|row| !row.token.is_empty())
+            .map(|row| Pubkey::from_str(&row.token))
+            .collect::<Result<HashSet<_>, _>>()?;
+        self.token_mints(mints.into_iter())
+    }
+
+    fn token_mints(
+        &self,
+        mints: impl Iterator<Item = Pubkey>,
+    ) -> Result<HashMap<Pubkey, TokenMint>, ServiceError> {
+        let transfer_service = TransferService::new(self.client.clone());
+        let mut token_mints = HashMap::new();
+        for mint in mints {
+            match transfer_service.token_mint(&mint) {
+                Ok(token_mint) => {
+                    token_mints.insert(mint, token_mint);
+                }
+                Err(ServiceError::AccountNotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(token_mints)
+    }
+}
+
+/// Group transfers into as few transactions as fit in a packet, returning indices into `groups`
+pub fn pack_instructions(payer: &Pubkey, groups: &[Vec<Instruction>]) -> Vec<Vec<usize>> {
+    let mut packs: Vec<Vec<usize>> = vec![];
+    let mut current: Vec<usize> = vec![];
+    let mut current_instructions: Vec<Instruction> = vec![];
+
+    for (index, group) in groups.iter().enumerate() {
+        let mut candidate = current_instructions.clone();
+        candidate.extend(group.iter().cloned());
+
+        if !current.is_empty() && transaction_size(payer, &candidate) > PACKET_DATA_SIZE {
+            packs.push(std::mem::take(&mut current));
+            candidate = group.clone();
+        }
+        current.push(index);
+        current_instructions = candidate;
+    }
+
+    if !current.is_empty() {
+        packs.push(current);
+    }
+    packs
+}
+
+fn transaction_size(payer: &Pubkey, instructions: &[Instruction]) -> usize {
+    let message = Message::new(instructions, Some(payer));
+    let signatures = message.header.num_required_signatures as usize;
+    let message_size = bincode::serialized_size(&message).unwrap_or(u64::MAX) as usize;
+    // Compact length prefix for the signature list, then the signatures and message
+    1 + signatures * 64 + message_size
+}
+
+pub fn write_results<W: Write>(rows: &[PayoutRow], writer: W) -> Result<(), ServiceError> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record([
+        "row",
+        "recipient",
+        "amount",
+        "token",
+        "status",
+        "signature",
+        "error",
+    ])?;
+    for row in rows {
+        csv_writer.write_record([
+            row.row_number.to_string().as_str(),
+            &row.recipient,
+            &row.amount,
+            row.token_display(),
+            row.status.as_str(),
+            row.signature.as_deref().unwrap_or_default(),
+            row.error.as_deref().unwrap_or_default(),
+        ])?;
+    }
+    csv_writer.flush().map_err(csv::Error::from)?;
+    Ok(())
+}
+
+fn parse_token(token: &str) -> Result<Option<Pubkey>, ServiceError> {
+    if token.is_empty() || token.eq_ignore_ascii_case("SOL") {
+        return Ok(None);
+    }
+    Ok(Some(Pubkey::from_str(token)?))
+}
+
+fn validate_record(
+    record: &PayoutRecord,
+    token_mints: &HashMap<Pubkey, TokenMint>,
+) -> Result<(), String> {
+    Pubkey::from_str(&record.recipient).map_err(|_| "Invalid recipient address".to_string())?;
+    let decimals = match parse_token(&record.token).map_err(|_| "Invalid token mint")? {
+        None => SOL_DECIMALS,
+        Some(mint) => {
+            token_mints
+                .get(&mint)
+                .ok_or_else(|| "Token mint not found".to_string())?
+                .decimals
+        }
+    };
+
+    let amount = ui_amount_to_base_units(&record.amount, decimals).map_err(|e| e.to_string())?;
+    if amount == 0 {
+        return Err("Amount must be greater than zero".to_string());
+    }
+    Ok(())
+}
+
+fn row_transfer(row: &PayoutRow) -> Result<Transfer, ServiceError> {
+    Ok(Transfer {
+        recipient: Pubkey::from_str(&row.recipient)?,
+        amount: row.amount.clone(),
+        mint: parse_token(&row.token)?,
+        memo: None,
+        references: vec![],
+    })
+}
+
+// Errors returned before the transaction reached the cluster
+fn is_rejected(error: &ServiceError) -> bool {
+    matches!(error, ServiceError::ClientError(e) if e.get_transaction_error().is_some())
+}
+
+fn payout_row_from_sql(row: &Row) -> rusqlite::Result<PayoutRow> {
+    let status: String = row.get(6)?;
+    Ok(PayoutRow {
+        id: row.get(0)?,
+        batch_id: row.get(1)?,
+        row_number: row.get(2)?,
+        recipient: row.get(3)?,
+        amount: row.get(4)?,
+        token: row.get(5)?,
+        status: PayoutStatus::from_db(&status),
+        signature: row.get(7)?,
+        last_valid_block_height: row.get(8)?,
+        error: row.get(9)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database_connection;
+    use crate::services::account_service::AccountService;
+    use solana_system_interface::instruction::transfer;
+
+    fn setup_test_db() -> Arc<Mutex<Connection>> {
+        let conn = Arc::new(Mutex::new(database_connection().unwrap()));
+        let conn_binding = conn.clone();
+        let conn_clone = conn_binding.lock().unwrap();
+        conn_clone
+            .execute(
+                "CREATE TABLE accounts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                seed TEXT NOT NULL,
+                pubkey TEXT NOT NULL,
+                passphrase TEXT NOT NULL,
+                balance INTEGER
+            )",
+                [],
+            )
+            .unwrap();
+        conn_clone
+            .execute(
+                "CREATE TABLE payout_batches (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                account_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+                [],
+            )
+            .unwrap();
+        conn_clone
+            .execute(
+                "CREATE TABLE payout_rows (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                batch_id INTEGER NOT NULL,
+                row_number INTEGER NOT NULL,
+                recipient TEXT NOT NULL,
+                amount TEXT NOT NULL,
+                token TEXT NOT NULL,
+                status TEXT NOT NULL,
+                signature TEXT NULL,
+                last_valid_block_height INTEGER NULL,
+                error TEXT NULL
+            )",
+                [],
+            )
+            .unwrap();
+        conn
+    }
+
+    fn payout_service(conn: Arc<Mutex<Connection>>) -> PayoutService {
+        PayoutService::new(conn, Arc::new(RpcClient::new_mock("succeeds".to_string())))
+    }
+
+    fn sample_csv() -> String {
+        format!(
+            "recipient,amount,token\n{},1.5,\n{},0.25,SOL\nnot-a-key,1,\n{},0,\n{},0.0000000001,\n",
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique()
+        )
+    }
+
+    #[test]
+    fn test_import_validates_rows() {
+        let service = payout_service(setup_test_db());
+        let batch = service
+            .import(1, "payouts.csv", sample_csv().as_bytes())
+            .unwrap();
+        let rows = service.get_rows(batch.id.unwrap()).unwrap();
+
+        let statuses: Vec<PayoutStatus> = rows.iter().map(|row| row.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                PayoutStatus::Pending,
+                PayoutStatus::Pending,
+                PayoutStatus::Invalid,
+                PayoutStatus::Invalid,
+                PayoutStatus::Invalid,
+            ]
+        );
+        assert_eq!(rows[1].token, "");
+        assert_eq!(rows[2].error.as_deref(), Some("Invalid recipient address"));
+        assert_eq!(
+            rows[3].error.as_deref(),
+            Some("Amount must be greater than zero")
+        );
+        assert!(rows[4].error.is_some());
+
+        let latest = service.get_latest_batch(1).unwrap().unwrap();
+        assert_eq!(latest.id, batch.id);
+        assert_eq!(latest.name, "payouts.csv");
+        assert!(service.get_latest_batch(2).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_import_rejects_missing_columns() {
+        let service = payout_service(setup_test_db());
+        let result = service.import(1, "bad.csv", "address,value\nabc,1\n".as_bytes());
+        assert!(matches!(result, Err(ServiceError::CsvError(_))));
+    }
+
+    #[test]
+    fn test_update_row() {
+        let service = payout_service(setup_test_db());
+        let batch = service
+            .import(1, "payouts.csv", sample_csv().as_bytes())
+            .unwrap();
+        let mut row = service.get_rows(batch.id.unwrap()).unwrap().remove(0);
+
+        row.status = PayoutStatus::Sent;
+        row.signature = Some(Signature::new_unique().to_string());
+        row.last_valid_block_height = Some(1234);
+        service.update_row(&row).unwrap();
+
+        let stored = service.get_rows(batch.id.unwrap()).unwrap().remove(0);
+        assert_eq!(stored, row);
+    }
+
+    #[test]
+    fn test_pack_instructions() {
+        let payer = Pubkey::new_unique();
+        let groups: Vec<Vec<Instruction>> = (0..40)
+            .map(|_| vec![transfer(&payer, &Pubkey::new_unique(), 1)])
+            .collect();
+
+        let packs = pack_instructions(&payer, &groups);
+        assert!(packs.len() > 1);
+
+        let indices: Vec<usize> = packs.iter().flatten().copied().collect();
+        assert_eq!(indices, (0..40).collect::<Vec<_>>());
+        for pack in &packs {
+            let instructions: Vec<Instruction> = pack
+                .iter()
+                .flat_map(|index| groups[*index].clone())
+                .collect();
+            assert!(transaction_size(&payer, &instructions) <= PACKET_DATA_SIZE);
+        }
+    }
+
+    #[test]
+    fn test_estimate_sol_rows() {
+        let service = payout_service(setup_test_db());
+        let batch = service
+            .import(1, "payouts.csv", sample_csv().as_bytes())
+            .unwrap();
+        let rows = service.get_rows(batch.id.unwrap()).unwrap();
+
+        let estimate = service.estimate(&Pubkey::new_unique(), &rows).unwrap();
+        assert_eq!(estimate.transactions, 1);
+        assert_eq!(estimate.lamports, 1_750_000_000);
+        assert!(estimate.token_totals.is_empty());
+        assert_eq!(estimate.new_token_accounts, 0);
+        assert_eq!(
+            estimate.total_lamports(),
+            estimate.lamports + estimate.network_fees
+        );
+    }
+
+    #[test]
+    fn test_execute_pays_each_row_once() {
+        let conn = setup_test_db();
+        let account = AccountService::new(conn.clone()).create_account().unwrap();
+        let service = payout_service(conn);
+        let batch = service
+            .import(1, "payouts.csv", sample_csv().as_bytes())
+            .unwrap();
+        let batch_id = batch.id.unwrap();
+
+        let mut updates = vec![];
+        service
+            .execute(&account, batch_id, |row| updates.push(row.status))
+            .unwrap();
+        assert_eq!(
+            updates,
+            vec![
+                PayoutStatus::Sent,
+                PayoutStatus::Sent,
+                PayoutStatus::Confirmed,
+                PayoutStatus::Confirmed,
+            ]
+        );
+
+        let rows = service.get_rows(batch_id).unwrap();
+        assert_eq!(rows[0].status, PayoutStatus::Confirmed);
+        assert_eq!(rows[0].signature, rows[1].signature);
+        assert_eq!(rows[2].status, PayoutStatus::Invalid);
+
+        let mut resent = 0;
+        service
+            .execute(&account, batch_id, |_| resent += 1)
+            .unwrap();
+        assert_eq!(resent, 0);
+    }
+
+    #[test]
+    fn test_write_results() {
+        let row = PayoutRow {
+            id: Some(1),
+            batch_id: 1,
+            row_number: 1,
+            recipient: "recipient".to_string(),
+            amount: "2".to_string(),
+            token: String::new(),
+            status: PayoutStatus::Failed,
+            signature: None,
+            last_valid_block_height: None,
+            error: Some("insufficient funds".to_string()),
+        };
+
+        let mut output = vec![];
+        write_results(&[row], &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "row,recipient,amount,token,status,signature,error\n1,recipient,2,SOL,failed,,insufficient funds\n"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use crate::database::{errors::DatabaseError, token_balance::TokenBalance};
+use crate::services::{
+    errors::ServiceError,
+    token_service::{OwnedTokenAccount, TokenService},
+};
+use rusqlite::{params, Connection};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+pub struct PortfolioService {
+    conn: Arc<Mutex<Connection>>,
+    client: Arc<RpcClient>,
+}
+
+impl PortfolioService {
+    pub fn new(conn: Arc<Mutex<Connection>>, client: Arc<RpcClient>) -> Self {
+        Self { conn, client }
+    }
+
+    /// Balances stored by the last refresh of `owner`, shown until fresh ones arrive
+    pub fn cached(&self, owner: &Pubkey) -> Result<Vec<TokenBalance>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT owner, mint, program_id, amount, decimals FROM token_balances
+            WHERE owner = ?1 ORDER BY mint",
+        )?;
+        let balances = stmt
+            .query_map([owner.to_string()], |row| {
+                Ok(TokenBalance {
+                    owner: row.get(0)?,
+                    mint: row.get(1)?,
+                    program_id: row.get(2)?,
+                    amount: row.get::<_, String>(3)?.parse().unwrap_or(0),
+                    decimals: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(balances)
+    }
+
+    /// Load every token account of `owner`, replacing the cached balances
+    pub fn refresh(&self, owner: &Pubkey) -> Result<Vec<TokenBalance>, ServiceError> {
+        let balances = self.fetch(owner)?;
+        self.store(owner, &balances)?;
+        Ok(balances)
+    }
+
+    /// Load every token account of `owner` without touching the cached balances
+    pub fn fetch(&self, owner: &Pubkey) -> Result<Vec<TokenBalance>, ServiceError> {
+        let token_service = TokenService::new(self.client.clone());
+        let accounts = token_service.token_accounts(owner)?;
+        let mut mints: Vec<Pubkey> = accounts
+            .iter()
+            .map(|account| account.account.mint)
+            .collect();
+        mints.sort();
+        mints.dedup();
+        let decimals = token_service.mint_decimals(&mints)?;
+
+        Ok(group_balances(owner, &accounts, &decimals))
+    }
+
+    fn store(&self, owner: &Pubkey, balances: &[TokenBalance]) -> Result<(), DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let transaction = conn.transaction()?;
+        transaction.execute(
+            "DELETE FROM token_balances WHERE owner = ?1",
+            [owner.to_string()],
+        )?;
+        for balance in balances {
+            transaction.execute(
+                "INSERT INTO token_balances (owner, mint, program_id, amount, decimals)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    balance.owner,
+                    balance.mint,
+                    balance.program_id,
+                    balance.amount.to_string(),
+                    balance.decimals,
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+/// Sum the non-empty token accounts of `owner` per mint, skipping mints whose decimals are unknown
+fn group_balances(
+    owner: &Pubkey,
+    accounts: &[OwnedTokenAccount],
+    decimals: &HashMap<Pubkey, u8>,
+) -> Vec<TokenBalance> {
+    let mut grouped: BTreeMap<Pubkey, TokenBalance> = BTreeMap::new();
+    for account in accounts {
+        let mint = account.account.mint;
+        let Some(decimals) = decimals.get(&mint) else {
+            continue;
+        };
+        let balance = grouped.entry(mint).or_insert_with(|| TokenBalance {
+            owner: owner.to_string(),
+            mint: mint.to_string(),
+            program_id: account.program_id.to_string(),
+            amount: 0,
+            decimals: *decimals,
+        });
+        balance.amount = balance.amount.saturating_add(account.account.amount);
+    }
+    grouped
+        .into_values()
+        .filter(|balance| balance.amount > 0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database_connection;
+    use crate::programs::token::{TokenAccount, TOKEN_PROGRAM_ID};
+
+    fn setup_test_db() -> Arc<Mutex<Connection>> {
+        let conn = Arc::new(Mutex::new(database_connection().unwrap()));
+        conn.lock()
+            .unwrap()
+            .execute(
+                "CREATE TABLE token_balances (
+                owner TEXT NOT NULL,
+                mint TEXT NOT NULL,
+                program_id TEXT NOT NULL,
+                amount TEXT NOT NULL,
+                decimals INTEGER NOT NULL,
+                PRIMARY KEY (owner, mint)
+            )",
+                [],
+            )
+            .unwrap();
+        conn
+    }
+
+    fn token_account(owner: &Pubkey, mint: &Pubkey, amount: u64) -> OwnedTokenAccount {
+        OwnedTokenAccount {
+            address: Pubkey::new_unique(),
+            program_id: TOKEN_PROGRAM_ID,
+            account: TokenAccount {
+                mint: *mint,
+                owner: *owner,
+                amount,
+                delegate: None,
+                delegated_amount: 0,
+                close_authority: None,
+                frozen: false,
+            },
+        }
+    }
+
+    #[test]
+    fn test_group_balances() {
+        let owner = Pubkey::new_unique();
+        let held = Pubkey::new_unique();
+        let empty = Pubkey::new_unique();
+        let unknown = Pubkey::new_unique();
+        let accounts = vec![
+            token_account(&owner, &held, 1_000_000),
+            token_account(&owner, &held, 500_000),
+            token_account(&owner, &empty, 0),
+            token_account(&owner, &unknown, 7),
+        ];
+        let decimals = HashMap::from([(held, 6), (empty, 6)]);
+
+        let balances = group_balances(&owner, &accounts, &decimals);
+        assert_eq!(balances.len(), 1);
+        assert_eq!(balances[0].mint, held.to_string());
+        assert_eq!(balances[0].ui_amount(), "1.5");
+    }
+
+    #[test]
+    fn test_store_and_cached() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let service = PortfolioService::new(
+            setup_test_db(),
+            Arc::new(RpcClient::new_mock("succeeds".to_string())),
+        );
+        let balance = TokenBalance {
+            owner: owner.to_string(),
+            mint: mint.to_string(),
+            program_id: TOKEN_PROGRAM_ID.to_string(),
+            amount: u64::MAX,
+            decimals: 9,
+        };
+
+        service
+            .store(&owner, std::slice::from_ref(&balance))
+            .unwrap();
+        assert_eq!(service.cached(&owner).unwrap(), vec![balance]);
+
+        // A refresh replaces what was cached
+        service.store(&owner, &[]).unwrap();
+        assert!(service.cached(&owner).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,406 @@
+use crate::database::errors::DatabaseError;
+use crate::price_provider::{coingecko::CoinGecko, errors::PriceError};
+use crate::services::errors::ServiceError;
+use crate::token_value::TokenData;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+// At most one snapshot per mint this often, enough for an hourly 24h chart
+const SNAPSHOT_INTERVAL_SECS: i64 = 3600;
+// Most points a chart draws, so a year of snapshots stays cheap to render
+const CHART_POINTS: usize = 200;
+// Size of the box chart paths are drawn in, matching the viewbox in the UI
+const CHART_SIZE: f64 = 100.0;
+// How long a backfill that failed or came back empty is left before trying again
+const BACKFILL_RETRY_SECS: i64 = 6 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartRange {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl ChartRange {
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "24h" => Some(ChartRange::Day),
+            "7d" => Some(ChartRange::Week),
+            "30d" => Some(ChartRange::Month),
+            "1y" => Some(ChartRange::Year),
+            _ => None,
+        }
+    }
+
+    pub fn days(&self) -> u32 {
+        match self {
+            ChartRange::Day => 1,
+            ChartRange::Week => 7,
+            ChartRange::Month => 30,
+            ChartRange::Year => 365,
+        }
+    }
+
+    pub fn secs(&self) -> i64 {
+        self.days() as i64 * 86_400
+    }
+}
+
+/// A price line scaled to fit the chart box, with the prices the UI labels it with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chart {
+    /// SVG path commands, with the oldest point on the left and the highest price at the top
+    pub commands: String,
+    pub low: f64,
+    pub high: f64,
+    pub first: f64,
+    pub last: f64,
+}
+
+impl Chart {
+    /// Line through `points` between `since` and `now`, oldest first, or `None` with fewer
+    /// than two to draw
+    pub fn new(points: &[(i64, f64)], since: i64, now: i64) -> Option<Self> {
+        let points: Vec<(i64, f64)> = points
+            .iter()
+            .copied()
+            .filter(|(timestamp, _)| (since..=now).contains(timestamp))
+            .collect();
+        if points.len() < 2 || now <= since {
+            return None;
+        }
+        let step = points.len().div_ceil(CHART_POINTS);
+        let mut drawn: Vec<(i64, f64)> = points.iter().copied().step_by(step).collect();
+        if drawn.last() != points.last() {
+            drawn.extend(points.last());
+        }
+
+        let low = drawn
+            .iter()
+            .map(|(_, price)| *price)
+            .fold(f64::MAX, f64::min);
+        let high = drawn
+            .iter()
+            .map(|(_, price)| *price)
+            .fold(f64::MIN, f64::max);
+        let commands = drawn
+            .iter()
+            .enumerate()
+            .map(|(index, (timestamp, price))| {
+                let x = (timestamp - since) as f64 / (now - since) as f64 * CHART_SIZE;
+                // A flat line runs through the middle
+                let y = if high > low {
+                    (high - price) / (high - low) * CHART_SIZE
+                } else {
+                    CHART_SIZE / 2.0
+                };
+                let command = if index == 0 { "M" } else { "L" };
+                format!("{} {:.2} {:.2}", command, x, y)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Some(Chart {
+            commands,
+            low,
+            high,
+            first: drawn[0].1,
+            last: drawn[drawn.len() - 1].1,
+        })
+    }
+
+    /// Percentage change from the first point to the last
+    pub fn change(&self) -> Option<f64> {
+        (self.first > 0.0).then(|| (self.last - self.first) / self.first * 100.0)
+    }
+}
+
+pub struct PriceHistoryService {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl PriceHistoryService {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+
+    /// Snapshot `prices` at `now`, skipping mints snapshotted within the last interval
+    pub fn record(
+        &self,
+        prices: &HashMap<String, TokenData>,
+        now: i64,
+    ) -> Result<(), DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for (mint, data) in prices {
+            let last: Option<i64> = tx.query_row(
+                "SELECT MAX(timestamp) FROM price_history WHERE mint = ?1",
+                [mint],
+                |row| row.get(0),
+            )?;
+            if last.is_none_or(|last| now - last >= SNAPSHOT_INTERVAL_SECS) {
+                tx.execute(
+                    "INSERT OR IGNORE INTO price_history (mint, timestamp, price)
+                    VALUES (?1, ?2, ?3)",
+                    params![mint, now, data.price],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Stored prices of `mint` since `since`, oldest first
+    pub fn history(&self, mint: &str, since: i64) -> Result<Vec<(i64, f64)>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, price FROM price_history
+            WHERE mint = ?1 AND timestamp >= ?2 ORDER BY timestamp",
+        )?;
+        let points = stmt
+            .query_map(params![mint, since], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(points)
+    }
+
+    /// Chart of `mint` over `range` drawn from stored prices only, so it works offline
+    pub fn chart(
+        &self,
+        mint: &str,
+        range: ChartRange,
+        now: i64,
+    ) -> Result<Option<Chart>, DatabaseError> {
+        let since = now - range.secs();
+        Ok(Chart::new(&self.history(mint, since)?, since, now))
+    }
+
+    /// Fill in `range` from the history endpoint when stored prices do not reach back far
+    /// enough. Whether anything was fetched. Blocking, so only call it off the UI thread.
+    pub fn backfill(&self, mint: &str, range: ChartRange, now: i64) -> Result<bool, ServiceError> {
+        self.backfill_with(mint, range, now, || {
+            CoinGecko::from_env().history(mint, range.days())
+        })
+    }
+
+    fn backfill_with(
+        &self,
+        mint: &str,
+        range: ChartRange,
+        now: i64,
+        fetch: impl FnOnce() -> Result<Vec<(i64, f64)>, PriceError>,
+    ) -> Result<bool, ServiceError> {
+        if self.covers(mint, range, now)? || self.backing_off(mint, range, now)? {
+            return Ok(false);
+        }
+        // Mints the endpoint does not know are not asked about on every chart opened
+        let points = match fetch() {
+            Ok(points) if !points.is_empty() => points,
+            Ok(_) => {
+                self.record_attempt(mint, range, now)?;
+                return Ok(false);
+            }
+            Err(e) => {
+                self.record_attempt(mint, range, now)?;
+                return Err(e.into());
+            }
+        };
+        self.insert(mint, &points)?;
+        Ok(true)
+    }
+
+    // A backfill of `range` failed or came back empty too recently to try again
+    fn backing_off(&self, mint: &str, range: ChartRange, now: i64) -> Result<bool, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let attempted_at: Option<i64> = conn
+            .query_row(
+                "SELECT attempted_at FROM price_backfills WHERE mint = ?1 AND days = ?2",
+                params![mint, range.days()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(attempted_at.is_some_and(|attempted_at| now - attempted_at < BACKFILL_RETRY_SECS))
+    }
+
+    fn record_attempt(&self, mint: &str, range: ChartRange, now: i64) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO price_backfills (mint, days, attempted_at) VALUES (?1, ?2, ?3)
+            ON CONFLICT (mint, days) DO UPDATE SET attempted_at = excluded.attempted_at",
+            params![mint, range.days(), now],
+        )?;
+        Ok(())
+    }
+
+    // Stored prices start within the first tenth of the range
+    fn covers(&self, mint: &str, range: ChartRange, now: i64) -> Result<bool, DatabaseError> {
+        let since = now - range.secs();
+        let conn = self.conn.lock().unwrap();
+        let earliest: Option<i64> = conn.query_row(
+            "SELECT MIN(timestamp) FROM price_history WHERE mint = ?1 AND timestamp >= ?2",
+            params![mint, since],
+            |row| row.get(0),
+        )?;
+        Ok(earliest.is_some_and(|earliest| earliest - since <= range.secs() / 10))
+    }
+
+    fn insert(&self, mint: &str, points: &[(i64, f64)]) -> Result<(), DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for (timestamp, price) in points {
+            tx.execute(
+                "INSERT OR IGNORE INTO price_history (mint, timestamp, price) VALUES (?1, ?2, ?3)",
+                params![mint, timestamp, price],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database_connection;
+
+    fn setup_test_db() -> Arc<Mutex<Connection>> {
+        let conn = Arc::new(Mutex::new(database_connection().unwrap()));
+        conn.lock()
+            .unwrap()
+            .execute(
+                "CREATE TABLE price_history (
+                mint TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                price REAL NOT NULL,
+                PRIMARY KEY (mint, timestamp)
+            )",
+                [],
+            )
+            .unwrap();
+        conn.lock()
+            .unwrap()
+            .execute(
+                "CREATE TABLE price_backfills (
+                mint TEXT NOT NULL,
+                days INTEGER NOT NULL,
+                attempted_at INTEGER NOT NULL,
+                PRIMARY KEY (mint, days)
+            )",
+                [],
+            )
+            .unwrap();
+        conn
+    }
+
+    fn prices(price: f64) -> HashMap<String, TokenData> {
+        let data = TokenData {
+            id: "Sol".to_string(),
+            price,
+            price_change_24h: None,
+            updated_at: 0,
+        };
+        HashMap::from([("Sol".to_string(), data)])
+    }
+
+    #[test]
+    fn test_record_snapshots() {
+        let service = PriceHistoryService::new(setup_test_db());
+        let now = 1_000_000;
+        service.record(&prices(100.0), now).unwrap();
+        // Too soon for another snapshot
+        service.record(&prices(101.0), now + 60).unwrap();
+        service
+            .record(&prices(110.0), now + SNAPSHOT_INTERVAL_SECS)
+            .unwrap();
+
+        assert_eq!(
+            service.history("Sol", 0).unwrap(),
+            vec![(now, 100.0), (now + SNAPSHOT_INTERVAL_SECS, 110.0)]
+        );
+        assert_eq!(service.history("Sol", now + 1).unwrap().len(), 1);
+
+        let later = now + SNAPSHOT_INTERVAL_SECS;
+        assert!(!service.covers("Sol", ChartRange::Week, later).unwrap());
+        service
+            .insert("Sol", &[(later - ChartRange::Week.secs(), 90.0)])
+            .unwrap();
+        assert!(service.covers("Sol", ChartRange::Week, later).unwrap());
+
+        let chart = service
+            .chart("Sol", ChartRange::Week, later)
+            .unwrap()
+            .unwrap();
+        assert_eq!((chart.first, chart.last), (90.0, 110.0));
+        assert_eq!(
+            service.chart("Sol", ChartRange::Day, now - 1).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_backfill_backs_off() {
+        let service = PriceHistoryService::new(setup_test_db());
+        let now = 1_000_000;
+        let range = ChartRange::Week;
+        let unknown = || -> Result<Vec<(i64, f64)>, PriceError> {
+            Err(PriceError::Unavailable("not found".to_string()))
+        };
+
+        assert!(service
+            .backfill_with("Unknown", range, now, unknown)
+            .is_err());
+        // Opening the chart again soon after does not ask again
+        assert!(!service
+            .backfill_with("Unknown", range, now + 60, || panic!("asked again"))
+            .unwrap());
+        // Nor does an empty answer
+        assert!(!service
+            .backfill_with("Empty", range, now, || Ok(vec![]))
+            .unwrap());
+        assert!(!service
+            .backfill_with("Empty", range, now + 60, || panic!("asked again"))
+            .unwrap());
+        // Other ranges are tried on their own
+        assert!(service
+            .backfill_with("Unknown", ChartRange::Day, now + 60, unknown)
+            .is_err());
+
+        let later = now + BACKFILL_RETRY_SECS;
+        let since = later - range.secs();
+        assert!(service
+            .backfill_with("Unknown", range, later, || Ok(vec![
+                (since, 1.0),
+                (later, 2.0)
+            ]))
+            .unwrap());
+        assert_eq!(service.history("Unknown", 0).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_chart() {
+        let chart = Chart::new(&[(0, 10.0), (50, 20.0), (100, 15.0), (200, 99.0)], 0, 100).unwrap();
+        assert_eq!(chart.commands, "M 0.00 100.00 L 50.00 0.00 L 100.00 50.00");
+        assert_eq!((chart.low, chart.high), (10.0, 20.0));
+        assert_eq!(chart.change(), Some(50.0));
+
+        let flat = Chart::new(&[(0, 1.0), (10, 1.0)], 0, 10).unwrap();
+        assert_eq!(flat.commands, "M 0.00 50.00 L 100.00 50.00");
+        assert_eq!(Chart::new(&[(0, 1.0)], 0, 10), None);
+
+        // Long histories are thinned out but keep their latest point
+        let year: Vec<(i64, f64)> = (0..1000).map(|hour| (hour, hour as f64)).collect();
+        let chart = Chart::new(&year, 0, 999).unwrap();
+        assert!(chart.commands.matches('L').count() < CHART_POINTS + 1);
+        assert_eq!(chart.last, 999.0);
+    }
+
+    #[test]
+    fn test_chart_range() {
+        assert_eq!(ChartRange::from_label("7d"), Some(ChartRange::Week));
+        assert_eq!(ChartRange::from_label("2w"), None);
+        assert_eq!(ChartRange::Year.secs(), 365 * 86_400);
+    }
+}
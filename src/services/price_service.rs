@@ -0,0 +1,427 @@
+use crate::database::errors::DatabaseError;
+use crate::price_provider::{errors::PriceError, MAX_PRICE_AGE_SECS};
+use crate::services::{errors::ServiceError, price_history_service::PriceHistoryService};
+use crate::token_value::{TokenData, TokenValue};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+// Prices fetched within this long are served without asking the providers again
+pub const PRICE_TTL_SECS: i64 = 5 * 60;
+
+/// Prices of a set of mints, some possibly left over from an earlier fetch
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Prices {
+    pub prices: HashMap<String, TokenData>,
+    /// When the oldest price was fetched, set only when fresh prices could not be fetched
+    pub stale_since: Option<i64>,
+}
+
+impl Prices {
+    pub fn usd(&self) -> HashMap<String, f64> {
+        self.prices
+            .iter()
+            .map(|(mint, data)| (mint.clone(), data.price))
+            .collect()
+    }
+}
+
+pub struct PriceService {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl PriceService {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+
+    /// Prices of `ids`, from the cache while they are fresh and from the providers once
+    /// they expire. When no provider can be reached the last known prices are served
+    /// instead, marked stale, as are old quotes the providers fall back on. Blocking, so only
+    /// call it off the UI thread.
+    pub fn prices(&self, ids: &[String]) -> Result<Prices, ServiceError> {
+        self.prices_with(ids, Utc::now().timestamp(), |ids| {
+            Ok(TokenValue::new(ids)?.prices)
+        })
+    }
+
+    /// Stored price of each of `ids` with when it was fetched
+    pub fn cached(
+        &self,
+        ids: &[String],
+    ) -> Result<HashMap<String, (TokenData, i64)>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT price, price_change_24h, updated_at, fetched_at FROM prices WHERE mint = ?1",
+        )?;
+        let mut cached = HashMap::new();
+        for id in ids {
+            let row = stmt
+                .query_row([id], |row| {
+                    let data = TokenData {
+                        id: id.clone(),
+                        price: row.get(0)?,
+                        price_change_24h: row.get(1)?,
+                        updated_at: row.get(2)?,
+                    };
+                    Ok((data, row.get(3)?))
+                })
+                .optional()?;
+            if let Some(row) = row {
+                cached.insert(id.clone(), row);
+            }
+        }
+        Ok(cached)
+    }
+
+    fn prices_with(
+        &self,
+        ids: &[String],
+        now: i64,
+        fetch: impl FnOnce(&[String]) -> Result<HashMap<String, TokenData>, PriceError>,
+    ) -> Result<Prices, ServiceError> {
+        let mut cached = self.cached(ids)?;
+        let expired: Vec<String> = ids
+            .iter()
+            .filter(|id| {
+                cached
+                    .get(*id)
+                    .is_none_or(|(_, fetched_at)| now - fetched_at > PRICE_TTL_SECS)
+            })
+            .cloned()
+            .collect();
+        if expired.is_empty() {
+            return Ok(Prices {
+                prices: cached
+                    .into_iter()
+                    .map(|(id, (data, _))| (id, data))
+                    .collect(),
+                stale_since: None,
+            });
+        }
+
+        match fetch(&expired) {
+            Ok(fetched) => {
+                // Providers fall back on old quotes, which must not pass for fresh ones
+                let stale_since = fetched
+                    .values()
+                    .filter(|data| is_stale(data, now))
+                    .map(|data| data.updated_at)
+                    .min();
+                self.store(&fetched, now)?;
+                let fresh: HashMap<String, TokenData> = fetched
+                    .iter()
+                    .filter(|(_, data)| !is_stale(data, now))
+                    .map(|(id, data)| (id.clone(), data.clone()))
+                    .collect();
+                PriceHistoryService::new(self.conn.clone()).record(&fresh, now)?;
+                // Expired prices nobody quotes any more are dropped rather than served
+                cached.retain(|id, _| !expired.contains(id));
+                let mut prices: HashMap<String, TokenData> = cached
+                    .into_iter()
+                    .map(|(id, (data, _))| (id, data))
+                    .collect();
+                prices.extend(fetched);
+                Ok(Prices {
+                    prices,
+                    stale_since,
+                })
+            }
+            Err(e) if cached.is_empty() => Err(e.into()),
+            Err(_) => {
+                let stale_since = expired
+                    .iter()
+                    .filter_map(|id| cached.get(id).map(|(_, fetched_at)| *fetched_at))
+                    .min();
+                Ok(Prices {
+                    prices: cached
+                        .into_iter()
+                        .map(|(id, (data, _))| (id, data))
+                        .collect(),
+                    stale_since,
+                })
+            }
+        }
+    }
+
+    fn store(&self, prices: &HashMap<String, TokenData>, now: i64) -> Result<(), DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for (mint, data) in prices {
+            // Stale quotes count as fetched when they were current, so they expire straight away
+            let fetched_at = if is_stale(data, now) {
+                data.updated_at
+            } else {
+                now
+            };
+            tx.execute(
+                "INSERT INTO prices (mint, price, price_change_24h, updated_at, fetched_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT (mint) DO UPDATE SET price = excluded.price,
+                price_change_24h = excluded.price_change_24h, updated_at = excluded.updated_at,
+                fetched_at = excluded.fetched_at",
+                params![
+                    mint,
+                    data.price,
+                    data.price_change_24h,
+                    data.updated_at,
+                    fetched_at
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn is_stale(data: &TokenData, now: i64) -> bool {
+    now - data.updated_at > MAX_PRICE_AGE_SECS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database_connection;
+    use std::cell::RefCell;
+
+    fn setup_test_db() -> Arc<Mutex<Connection>> {
+        let conn = Arc::new(Mutex::new(database_connection().unwrap()));
+        conn.lock()
+            .unwrap()
+            .execute(
+                "CREATE TABLE prices (
+                mint TEXT PRIMARY KEY,
+                price REAL NOT NULL,
+                price_change_24h REAL,
+                updated_at INTEGER NOT NULL,
+                fetched_at INTEGER NOT NULL
+            )",
+                [],
+            )
+            .unwrap();
+        conn.lock()
+            .unwrap()
+            .execute(
+                "CREATE TABLE price_history (
+                mint TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                price REAL NOT NULL,
+                PRIMARY KEY (mint, timestamp)
+            )",
+                [],
+            )
+            .unwrap();
+        conn
+    }
+
+    fn quote(id: &str, price: f64, updated_at: i64) -> (String, TokenData) {
+        let data = TokenData {
+            id: id.to_string(),
+            price,
+            price_change_24h: Some(1.5),
+            updated_at,
+        };
+        (id.to_string(), data)
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_prices_served_from_cache_within_ttl() {
+        let service = PriceService::new(setup_test_db());
+        let now = 1_000_000;
+        let asked = RefCell::new(vec![]);
+        let fetch = |ids: &[String]| {
+            asked.borrow_mut().push(ids.to_vec());
+            Ok([quote("Sol", 150.0, now)].into_iter().collect())
+        };
+
+        let prices = service
+            .prices_with(&ids(&["Sol", "Unpriced"]), now, fetch)
+            .unwrap();
+        assert_eq!(prices.usd(), HashMap::from([("Sol".to_string(), 150.0)]));
+        assert_eq!(prices.stale_since, None);
+        assert_eq!(asked.borrow().len(), 1);
+
+        // Fresh prices come from the cache, so the providers are not asked again
+        let prices = service
+            .prices_with(&ids(&["Sol"]), now + PRICE_TTL_SECS, |_| {
+                panic!("fetched a fresh price")
+            })
+            .unwrap();
+        assert_eq!(prices.prices["Sol"].price_change_24h, Some(1.5));
+
+        // Once expired they are fetched again
+        let later = now + PRICE_TTL_SECS + 1;
+        let prices = service
+            .prices_with(&ids(&["Sol"]), later, |_| {
+                Ok([quote("Sol", 160.0, later)].into_iter().collect())
+            })
+            .unwrap();
+        assert_eq!(prices.prices["Sol"].price, 160.0);
+        assert_eq!(service.cached(&ids(&["Sol"])).unwrap()["Sol"].1, later);
+    }
+
+    #[test]
+    fn test_stale_prices_when_offline() {
+        let service = PriceService::new(setup_test_db());
+        let now = 1_000_000;
+        let offline = |_: &[String]| Err(PriceError::Unavailable("offline".to_string()));
+
+        // Nothing to fall back on
+        assert!(service.prices_with(&ids(&["Sol"]), now, offline).is_err());
+
+        service
+            .prices_with(&ids(&["Sol"]), now, |_| {
+                Ok([quote("Sol", 150.0, now)].into_iter().collect())
+            })
+            .unwrap();
+        let later = now + 3600;
+        let prices = service
+            .prices_with(&ids(&["Sol", "Bonk"]), later, offline)
+            .unwrap();
+        assert_eq!(prices.prices["Sol"].price, 150.0);
+        assert!(!prices.prices.contains_key("Bonk"));
+        assert_eq!(prices.stale_since, Some(now));
+    }
+
+    #[test]
+    fn test_expired_price_is_fetched() {
+        let service = PriceService::new(setup_test_db());
+        let now = 1_000_000;
+        service
+            .prices_with(&ids(&["Sol"]), now, |_| {
+                Ok([quote("Sol", 150.0, now)].into_iter().collect())
+            })
+            .unwrap();
+
+        let later = now + PRICE_TTL_SECS + 1;
+        let asked = RefCell::new(vec![]);
+        let prices = service
+            .prices_with(&ids(&["Sol"]), later, |ids| {
+                asked.borrow_mut().push(ids.to_vec());
+                Ok([quote("Sol", 160.0, later)].into_iter().collect())
+            })
+            .unwrap();
+        assert_eq!(*asked.borrow(), [ids(&["Sol"])]);
+        assert_eq!(prices.prices["Sol"].price, 160.0);
+        assert_eq!(prices.stale_since, None);
+    }
+
+    #[test]
+    fn test_failed_fetch_serves_stale_row() {
+        let service = PriceService::new(setup_test_db());
+        let now = 1_000_000;
+        service
+            .prices_with(&ids(&["Sol"]), now, |_| {
+                Ok([quote("Sol", 150.0, now - 30)].into_iter().collect())
+            })
+            .unwrap();
+
+        // Just past its TTL, the stored row stands in for the price that could not be fetched
+        let later = now + PRICE_TTL_SECS + 1;
+        let prices = service
+            .prices_with(&ids(&["Sol"]), later, |_| {
+                Err(PriceError::Unavailable("offline".to_string()))
+            })
+            .unwrap();
+        assert_eq!(prices.prices["Sol"].price, 150.0);
+        assert_eq!(prices.prices["Sol"].updated_at, now - 30);
+        assert_eq!(prices.stale_since, Some(now));
+        // The stale row is kept as it was
+        assert_eq!(service.cached(&ids(&["Sol"])).unwrap()["Sol"].1, now);
+    }
+
+    #[test]
+    fn test_stale_quote_from_provider() {
+        let service = PriceService::new(setup_test_db());
+        let now = 1_000_000;
+        let old = now - 3600;
+
+        // Every provider only had an hour-old quote for one of the mints
+        let prices = service
+            .prices_with(&ids(&["Sol", "Old"]), now, |_| {
+                Ok([quote("Sol", 150.0, now), quote("Old", 2.0, old)]
+                    .into_iter()
+                    .collect())
+            })
+            .unwrap();
+        assert_eq!(prices.prices["Old"].price, 2.0);
+        assert_eq!(prices.stale_since, Some(old));
+        // It is kept, but not as a fresh fetch
+        let cached = service.cached(&ids(&["Sol", "Old"])).unwrap();
+        assert_eq!(cached["Sol"].1, now);
+        assert_eq!(cached["Old"].1, old);
+        assert_eq!(
+            PriceHistoryService::new(service.conn.clone())
+                .history("Old", 0)
+                .unwrap(),
+            vec![]
+        );
+
+        // So the next call asks for it again
+        let asked = RefCell::new(vec![]);
+        service
+            .prices_with(&ids(&["Sol", "Old"]), now + 1, |ids| {
+                asked.borrow_mut().push(ids.to_vec());
+                Ok([quote("Old", 2.5, now + 1)].into_iter().collect())
+            })
+            .unwrap();
+        assert_eq!(*asked.borrow(), [ids(&["Old"])]);
+    }
+
+    #[test]
+    fn test_mixed_fresh_and_stale_prices() {
+        let service = PriceService::new(setup_test_db());
+        let now = 1_000_000;
+        service
+            .prices_with(&ids(&["Old"]), now, |_| {
+                Ok([quote("Old", 2.0, now)].into_iter().collect())
+            })
+            .unwrap();
+        let later = now + PRICE_TTL_SECS + 1;
+        service
+            .prices_with(&ids(&["New"]), later, |_| {
+                Ok([quote("New", 3.0, later)].into_iter().collect())
+            })
+            .unwrap();
+
+        // Only the expired mint is asked for
+        let asked = RefCell::new(vec![]);
+        let prices = service
+            .prices_with(&ids(&["Old", "New"]), later, |ids| {
+                asked.borrow_mut().push(ids.to_vec());
+                Ok([quote("Old", 2.5, later)].into_iter().collect())
+            })
+            .unwrap();
+        assert_eq!(*asked.borrow(), [ids(&["Old"])]);
+        assert_eq!(
+            prices.usd(),
+            HashMap::from([("Old".to_string(), 2.5), ("New".to_string(), 3.0)])
+        );
+        assert_eq!(prices.stale_since, None);
+
+        // When the fetch fails, only the expired price makes the result stale
+        let much_later = later + PRICE_TTL_SECS + 1;
+        service
+            .prices_with(&ids(&["New"]), much_later, |_| {
+                Ok([quote("New", 4.0, much_later)].into_iter().collect())
+            })
+            .unwrap();
+        let prices = service
+            .prices_with(&ids(&["Old", "New"]), much_later, |_| {
+                Err(PriceError::Unavailable("offline".to_string()))
+            })
+            .unwrap();
+        assert_eq!(
+            prices.usd(),
+            HashMap::from([("Old".to_string(), 2.5), ("New".to_string(), 4.0)])
+        );
+        assert_eq!(prices.stale_since, Some(later));
+    }
+}
@@ -0,0 +1,239 @@
+use crate::database::{
+    errors::DatabaseError,
+    token_registry::{TokenStatus, TokenVerdict},
+};
+use crate::services::errors::ServiceError;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+// Verified list bundled for the first run, before the wallet has been online
+const TOKEN_LIST_SNAPSHOT: &str = include_str!("token_list.json");
+const TOKEN_LIST_URL: &str = "https://lite-api.jup.ag/tokens/v1/tagged/verified";
+const TOKEN_LIST_MAX_AGE_SECS: i64 = 86_400;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+struct ListedToken {
+    address: String,
+    name: String,
+    symbol: String,
+    decimals: Option<u8>,
+}
+
+pub struct RegistryService {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl RegistryService {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+
+    /// Seed the registry from the bundled snapshot, then download the verified list once a day.
+    /// Blocking, since it fetches the list over HTTP.
+    pub fn refresh_if_stale(&self) -> Result<(), ServiceError> {
+        if self.last_updated()?.is_none() {
+            let snapshot: Vec<ListedToken> = serde_json::from_str(TOKEN_LIST_SNAPSHOT)
+                .map_err(|e| ServiceError::Other(Box::new(e)))?;
+            // Dated as old so the first online run still downloads the full list
+            self.replace_verified(&snapshot, 0)?;
+        }
+        let now = Utc::now().timestamp();
+        if self
+            .last_updated()?
+            .is_some_and(|updated_at| now - updated_at < TOKEN_LIST_MAX_AGE_SECS)
+        {
+            return Ok(());
+        }
+
+        let listed: Vec<ListedToken> = reqwest::blocking::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()?
+            .get(TOKEN_LIST_URL)
+            .send()?
+            .error_for_status()?
+            .json()?;
+        // An empty answer would unverify everything, so keep what is there instead
+        if !listed.is_empty() {
+            self.replace_verified(&listed, now)?;
+        }
+        Ok(())
+    }
+
+    /// Mark `mint` as trusted, or take that back
+    pub fn set_trusted(&self, mint: &str, trusted: bool) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO token_registry (mint, symbol, name, trusted, updated_at)
+            VALUES (?1, '', '', ?2, ?3)
+            ON CONFLICT (mint) DO UPDATE SET trusted = excluded.trusted",
+            params![mint, trusted, Utc::now().timestamp()],
+        )?;
+        conn.execute(
+            "DELETE FROM token_registry WHERE verified = 0 AND trusted = 0",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Status of each `(mint, symbol)`, flagging unverified mints that reuse a verified symbol
+    pub fn verdicts(
+        &self,
+        tokens: &[(String, String)],
+    ) -> Result<HashMap<String, TokenVerdict>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut by_mint =
+            conn.prepare("SELECT verified, trusted FROM token_registry WHERE mint = ?1")?;
+        let mut by_symbol = conn.prepare(
+            "SELECT symbol FROM token_registry
+            WHERE verified = 1 AND symbol <> '' AND LOWER(symbol) = LOWER(?1) LIMIT 1",
+        )?;
+
+        let mut verdicts = HashMap::new();
+        for (mint, symbol) in tokens {
+            let status = by_mint
+                .query_row([mint], |row| {
+                    Ok(TokenStatus::from_flags(row.get(0)?, row.get(1)?))
+                })
+                .optional()?
+                .unwrap_or(TokenStatus::Unverified);
+            let impersonates = match (status, symbol.trim()) {
+                (TokenStatus::Unverified, symbol) if !symbol.is_empty() => {
+                    by_symbol.query_row([symbol], |row| row.get(0)).optional()?
+                }
+                _ => None,
+            };
+            verdicts.insert(
+                mint.clone(),
+                TokenVerdict {
+                    status,
+                    impersonates,
+                },
+            );
+        }
+        Ok(verdicts)
+    }
+
+    fn last_updated(&self) -> Result<Option<i64>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let updated_at = conn.query_row(
+            "SELECT MAX(updated_at) FROM token_registry WHERE verified = 1",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(updated_at)
+    }
+
+    // Trusted entries survive a new list, whatever it says about them
+    fn replace_verified(&self, listed: &[ListedToken], now: i64) -> Result<(), DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let transaction = conn.transaction()?;
+        transaction.execute("UPDATE token_registry SET verified = 0", [])?;
+        for token in listed {
+            transaction.execute(
+                "INSERT INTO token_registry (mint, symbol, name, decimals, verified, updated_at)
+                VALUES (?1, ?2, ?3, ?4, 1, ?5)
+                ON CONFLICT (mint) DO UPDATE SET symbol = excluded.symbol, name = excluded.name,
+                decimals = excluded.decimals, verified = 1, updated_at = excluded.updated_at",
+                params![token.address, token.symbol, token.name, token.decimals, now],
+            )?;
+        }
+        transaction.execute(
+            "DELETE FROM token_registry WHERE verified = 0 AND trusted = 0",
+            [],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database_connection;
+
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const FAKE_USDC: &str = "FakeUsdc1111111111111111111111111111111111";
+
+    fn setup_test_db() -> Arc<Mutex<Connection>> {
+        let conn = Arc::new(Mutex::new(database_connection().unwrap()));
+        conn.lock()
+            .unwrap()
+            .execute(
+                "CREATE TABLE token_registry (
+                mint TEXT PRIMARY KEY,
+                symbol TEXT NOT NULL,
+                name TEXT NOT NULL,
+                decimals INTEGER NULL,
+                verified INTEGER NOT NULL DEFAULT 0,
+                trusted INTEGER NOT NULL DEFAULT 0,
+                updated_at INTEGER NOT NULL
+            )",
+                [],
+            )
+            .unwrap();
+        conn
+    }
+
+    fn seeded_service() -> RegistryService {
+        let service = RegistryService::new(setup_test_db());
+        let snapshot: Vec<ListedToken> = serde_json::from_str(TOKEN_LIST_SNAPSHOT).unwrap();
+        service.replace_verified(&snapshot, 0).unwrap();
+        service
+    }
+
+    #[test]
+    fn test_verdicts() {
+        let service = seeded_service();
+        let tokens = vec![
+            (USDC.to_string(), "USDC".to_string()),
+            (FAKE_USDC.to_string(), "usdc ".to_string()),
+            (
+                "Other11111111111111111111111111111111111111".to_string(),
+                "OTHER".to_string(),
+            ),
+        ];
+
+        let verdicts = service.verdicts(&tokens).unwrap();
+        assert_eq!(verdicts[USDC].status, TokenStatus::Verified);
+        assert_eq!(verdicts[USDC].impersonates, None);
+        assert_eq!(verdicts[FAKE_USDC].status, TokenStatus::Unverified);
+        assert_eq!(verdicts[FAKE_USDC].impersonates.as_deref(), Some("USDC"));
+        assert_eq!(verdicts[&tokens[2].0].impersonates, None);
+
+        // The user's word overrides the symbol check
+        service.set_trusted(FAKE_USDC, true).unwrap();
+        let verdicts = service.verdicts(&tokens).unwrap();
+        assert_eq!(verdicts[FAKE_USDC].status, TokenStatus::Trusted);
+        assert_eq!(verdicts[FAKE_USDC].impersonates, None);
+
+        service.set_trusted(FAKE_USDC, false).unwrap();
+        let verdicts = service.verdicts(&tokens).unwrap();
+        assert_eq!(verdicts[FAKE_USDC].status, TokenStatus::Unverified);
+    }
+
+    #[test]
+    fn test_replace_verified_keeps_trusted() {
+        let service = seeded_service();
+        service.set_trusted(USDC, true).unwrap();
+        let listed = vec![ListedToken {
+            address: "Jup".to_string(),
+            name: "Jupiter".to_string(),
+            symbol: "JUP".to_string(),
+            decimals: Some(6),
+        }];
+
+        service.replace_verified(&listed, 100).unwrap();
+        assert_eq!(service.last_updated().unwrap(), Some(100));
+        let verdicts = service
+            .verdicts(&[(USDC.to_string(), "USDC".to_string())])
+            .unwrap();
+        assert_eq!(verdicts[USDC].status, TokenStatus::Trusted);
+    }
+}
@@ -0,0 +1,555 @@
+use crate::amount::{base_units_to_ui_amount, SOL_DECIMALS};
+use crate::database::{errors::DatabaseError, stake_reward::StakeReward};
+use crate::services::{errors::ServiceError, stake_service::StakeAccount};
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone};
+use rusqlite::{params, Connection, Row};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+// Epochs fetched the first time a stake account is synced, a little over a year
+const MAX_LOOKBACK_EPOCHS: u64 = 200;
+
+// Epoch length assumed when block times are missing: 432,000 slots of 400ms
+const DEFAULT_EPOCH_SECS: f64 = 172_800.0;
+
+const SECS_PER_YEAR: f64 = 365.25 * 86_400.0;
+
+// Most recent epochs the APY is averaged over
+const APY_EPOCHS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatorApy {
+    pub vote_account: String,
+    pub apy: f64,
+    pub epochs: usize,
+}
+
+pub struct RewardService {
+    conn: Arc<Mutex<Connection>>,
+    client: Arc<RpcClient>,
+}
+
+impl RewardService {
+    pub fn new(conn: Arc<Mutex<Connection>>, client: Arc<RpcClient>) -> Self {
+        Self { conn, client }
+    }
+
+    /// Fetch rewards for every completed epoch not yet synced, returning how many were stored.
+    /// Progress is saved per epoch, so an interrupted sync resumes where it stopped.
+    pub fn sync(
+        &self,
+        account_id: i32,
+        stakes: &[StakeAccount],
+        current_epoch: u64,
+    ) -> Result<usize, ServiceError> {
+        // Rewards for an epoch are paid at the start of the next one
+        let Some(last_completed) = current_epoch.checked_sub(1) else {
+            return Ok(0);
+        };
+        let synced = self.synced_epochs(account_id)?;
+
+        let mut pending: BTreeMap<u64, Vec<(Pubkey, String)>> = BTreeMap::new();
+        for stake in stakes {
+            let Some(delegation) = stake.delegation else {
+                continue;
+            };
+            let first = delegation
+                .activation_epoch
+                .saturating_add(1)
+                .max(current_epoch.saturating_sub(MAX_LOOKBACK_EPOCHS))
+                .max(
+                    synced
+                        .get(&stake.address.to_string())
+                        .map_or(0, |epoch| epoch + 1),
+                );
+            let last = last_completed.min(delegation.deactivation_epoch);
+            for epoch in first..=last {
+                pending
+                    .entry(epoch)
+                    .or_default()
+                    .push((stake.address, delegation.voter_pubkey.to_string()));
+            }
+        }
+
+        let mut stored = 0;
+        for (epoch, stakes) in pending {
+            let addresses: Vec<Pubkey> = stakes.iter().map(|(address, _)| *address).collect();
+            let rewards = self.client.get_inflation_reward(&addresses, Some(epoch))?;
+
+            // All rewards of an epoch are paid in the same block
+            let block_time = match rewards.iter().flatten().next() {
+                Some(reward) => self.client.get_block_time(reward.effective_slot).ok(),
+                None => None,
+            };
+
+            let mut conn = self.conn.lock().unwrap();
+            let transaction = conn.transaction().map_err(DatabaseError::from)?;
+            for ((address, vote_account), reward) in stakes.iter().zip(rewards) {
+                let address = address.to_string();
+                if let Some(reward) = reward {
+                    stored += transaction
+                        .execute(
+                            "INSERT OR IGNORE INTO stake_rewards (account_id, stake_address, vote_account, epoch, amount, post_balance, commission, effective_slot, block_time)
+                            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                            params![
+                                account_id,
+                                address,
+                                vote_account,
+                                epoch,
+                                reward.amount,
+                                reward.post_balance,
+                                reward.commission,
+                                reward.effective_slot,
+                                block_time,
+                            ],
+                        )
+                        .map_err(DatabaseError::from)?;
+                }
+                transaction
+                    .execute(
+                        "INSERT INTO stake_reward_sync (account_id, stake_address, last_epoch) VALUES (?1, ?2, ?3)
+                        ON CONFLICT (account_id, stake_address) DO UPDATE SET last_epoch = excluded.last_epoch",
+                        params![account_id, address, epoch],
+                    )
+                    .map_err(DatabaseError::from)?;
+            }
+            transaction.commit().map_err(DatabaseError::from)?;
+        }
+        Ok(stored)
+    }
+
+    /// Price rewards paid on days without a stored price, one lookup per day.
+    /// Stops at the first failed lookup and keeps what was priced so far.
+    pub fn fill_prices<F>(&self, account_id: i32, mut price_on: F) -> Result<usize, ServiceError>
+    where
+        F: FnMut(NaiveDate) -> Result<Option<f64>, ServiceError>,
+    {
+        let unpriced: Vec<StakeReward> = self
+            .get_rewards(account_id)?
+            .into_iter()
+            .filter(|reward| reward.sol_price.is_none())
+            .collect();
+
+        let mut prices: HashMap<NaiveDate, Option<f64>> = HashMap::new();
+        let mut priced = 0;
+        for reward in unpriced {
+            let Some(date) = reward.block_time.and_then(paid_on) else {
+                continue;
+            };
+            let price = match prices.get(&date) {
+                Some(price) => *price,
+                None => {
+                    let price = price_on(date)?;
+                    prices.insert(date, price);
+                    price
+                }
+            };
+            if let Some(price) = price {
+                let conn = self.conn.lock().unwrap();
+                conn.execute(
+                    "UPDATE stake_rewards SET sol_price = ?1 WHERE id = ?2",
+                    params![price, reward.id],
+                )
+                .map_err(DatabaseError::from)?;
+                priced += 1;
+            }
+        }
+        Ok(priced)
+    }
+
+    /// Stored rewards, oldest epoch first
+    pub fn get_rewards(&self, account_id: i32) -> Result<Vec<StakeReward>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, account_id, stake_address, vote_account, epoch, amount, post_balance, commission, effective_slot, block_time, sol_price
+            FROM stake_rewards WHERE account_id = ?1 ORDER BY epoch, stake_address",
+        )?;
+        let rewards = stmt
+            .query_map([account_id], stake_reward_from_sql)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rewards)
+    }
+
+    /// Write the rewards paid during `year`, in local time, returning how many were written
+    pub fn export_year(
+        &self,
+        account_id: i32,
+        year: i32,
+        path: &Path,
+    ) -> Result<usize, ServiceError> {
+        let rewards = rewards_in_year(&self.get_rewards(account_id)?, year);
+        let file = std::fs::File::create(path).map_err(csv::Error::from)?;
+        write_rewards(&rewards, file)?;
+        Ok(rewards.len())
+    }
+
+    fn synced_epochs(&self, account_id: i32) -> Result<HashMap<String, u64>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT stake_address, last_epoch FROM stake_reward_sync WHERE account_id = ?1",
+        )?;
+        let synced = stmt
+            .query_map([account_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(synced)
+    }
+}
+
+// Rewards of one validator in one epoch, summed over its stake accounts
+#[derive(Default)]
+struct EpochRewards {
+    epoch: u64,
+    amount: u64,
+    principal: u64,
+    paid_at: Option<i64>,
+}
+
+/// Effective APY per validator from the most recent rewards, compounded once per epoch
+pub fn validator_apy(rewards: &[StakeReward]) -> Vec<ValidatorApy> {
+    let mut validators: BTreeMap<&str, BTreeMap<u64, EpochRewards>> = BTreeMap::new();
+    for reward in rewards {
+        let epoch = validators
+            .entry(&reward.vote_account)
+            .or_default()
+            .entry(reward.epoch)
+            .or_default();
+        epoch.epoch = reward.epoch;
+        epoch.amount += reward.amount;
+        epoch.principal += reward.principal();
+        epoch.paid_at = epoch.paid_at.or(reward.block_time);
+    }
+
+    validators
+        .into_iter()
+        .map(|(vote_account, epochs)| {
+            let recent: Vec<EpochRewards> = epochs.into_values().rev().take(APY_EPOCHS).collect();
+            let rates: Vec<f64> = recent
+                .iter()
+                .filter(|epoch| epoch.principal > 0)
+                .map(|epoch| epoch.amount as f64 / epoch.principal as f64)
+                .collect();
+            let rate = if rates.is_empty() {
+                0.0
+            } else {
+                rates.iter().sum::<f64>() / rates.len() as f64
+            };
+
+            // Newest first, so the span runs from the last timed epoch back to the first
+            let timed: Vec<(u64, i64)> = recent
+                .iter()
+                .filter_map(|epoch| epoch.paid_at.map(|time| (epoch.epoch, time)))
+                .collect();
+            let epoch_secs = match (timed.first(), timed.last()) {
+                (Some((last_epoch, last_time)), Some((first_epoch, first_time)))
+                    if last_epoch > first_epoch && last_time > first_time =>
+                {
+                    (last_time - first_time) as f64 / (last_epoch - first_epoch) as f64
+                }
+                _ => DEFAULT_EPOCH_SECS,
+            };
+
+            ValidatorApy {
+                vote_account: vote_account.to_string(),
+                apy: (1.0 + rate).powf(SECS_PER_YEAR / epoch_secs) - 1.0,
+                epochs: recent.len(),
+            }
+        })
+        .collect()
+}
+
+pub fn rewards_in_year(rewards: &[StakeReward], year: i32) -> Vec<StakeReward> {
+    rewards
+        .iter()
+        .filter(|reward| {
+            reward
+                .block_time
+                .and_then(|time| Local.timestamp_opt(time, 0).single())
+                .is_some_and(|time| time.year() == year)
+        })
+        .cloned()
+        .collect()
+}
+
+pub fn write_rewards<W: Write>(rewards: &[StakeReward], writer: W) -> Result<(), ServiceError> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record([
+        "epoch",
+        "paid_at",
+        "stake_account",
+        "validator",
+        "reward_sol",
+        "post_balance_sol",
+        "commission",
+        "sol_usd",
+        "reward_usd",
+    ])?;
+    for reward in rewards {
+        csv_writer.write_record([
+            reward.epoch.to_string().as_str(),
+            &reward
+                .block_time
+                .and_then(|time| Local.timestamp_opt(time, 0).single())
+                .map(|time| time.to_rfc3339())
+                .unwrap_or_default(),
+            &reward.stake_address,
+            &reward.vote_account,
+            &base_units_to_ui_amount(reward.amount, SOL_DECIMALS),
+            &base_units_to_ui_amount(reward.post_balance, SOL_DECIMALS),
+            &reward
+                .commission
+                .map(|commission| commission.to_string())
+                .unwrap_or_default(),
+            &reward
+                .sol_price
+                .map(|price| format!("{:.2}", price))
+                .unwrap_or_default(),
+            &reward
+                .fiat_value()
+                .map(|value| format!("{:.2}", value))
+                .unwrap_or_default(),
+        ])?;
+    }
+    csv_writer.flush().map_err(csv::Error::from)?;
+    Ok(())
+}
+
+// Day the reward was paid, in UTC like the daily prices
+fn paid_on(block_time: i64) -> Option<NaiveDate> {
+    DateTime::from_timestamp(block_time, 0).map(|time| time.date_naive())
+}
+
+fn stake_reward_from_sql(row: &Row) -> rusqlite::Result<StakeReward> {
+    Ok(StakeReward {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        stake_address: row.get(2)?,
+        vote_account: row.get(3)?,
+        epoch: row.get(4)?,
+        amount: row.get(5)?,
+        post_balance: row.get(6)?,
+        commission: row.get(7)?,
+        effective_slot: row.get(8)?,
+        block_time: row.get(9)?,
+        sol_price: row.get(10)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database_connection;
+    use crate::services::stake_service::ActivationState;
+    use serde_json::json;
+    use solana_rpc_client::rpc_client::Mocks;
+    use solana_rpc_client_api::request::RpcRequest;
+    use solana_stake_interface::{
+        stake_history::StakeHistoryEntry,
+        state::{Authorized, Delegation, Lockup},
+    };
+
+    const PAID_AT: i64 = 1_700_000_000;
+
+    fn setup_test_db() -> Arc<Mutex<Connection>> {
+        let conn = Arc::new(Mutex::new(database_connection().unwrap()));
+        let conn_binding = conn.clone();
+        let conn_clone = conn_binding.lock().unwrap();
+        conn_clone
+            .execute(
+                "CREATE TABLE stake_rewards (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                account_id INTEGER NOT NULL,
+                stake_address TEXT NOT NULL,
+                vote_account TEXT NOT NULL,
+                epoch INTEGER NOT NULL,
+                amount INTEGER NOT NULL,
+                post_balance INTEGER NOT NULL,
+                commission INTEGER NULL,
+                effective_slot INTEGER NOT NULL,
+                block_time INTEGER NULL,
+                sol_price REAL NULL,
+                UNIQUE (account_id, stake_address, epoch)
+            )",
+                [],
+            )
+            .unwrap();
+        conn_clone
+            .execute(
+                "CREATE TABLE stake_reward_sync (
+                account_id INTEGER NOT NULL,
+                stake_address TEXT NOT NULL,
+                last_epoch INTEGER NOT NULL,
+                PRIMARY KEY (account_id, stake_address)
+            )",
+                [],
+            )
+            .unwrap();
+        conn
+    }
+
+    fn reward_client() -> Arc<RpcClient> {
+        let mut mocks = Mocks::default();
+        let reward = json!({
+            "epoch": 0,
+            "effectiveSlot": 224,
+            "amount": 2_500_000,
+            "postBalance": 5_002_500_000u64,
+            "commission": 5,
+        });
+        mocks.insert(RpcRequest::GetInflationReward, json!([reward, reward]));
+        mocks.insert(RpcRequest::GetBlockTime, json!(PAID_AT));
+        Arc::new(RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            mocks,
+        ))
+    }
+
+    fn delegated_stake(activation_epoch: u64) -> StakeAccount {
+        StakeAccount {
+            address: Pubkey::new_unique(),
+            lamports: 5_002_500_000,
+            rent_exempt_reserve: 2_282_880,
+            authorized: Authorized::default(),
+            lockup: Lockup::default(),
+            delegation: Some(Delegation {
+                voter_pubkey: Pubkey::new_unique(),
+                stake: 5_000_000_000,
+                activation_epoch,
+                ..Delegation::default()
+            }),
+            state: ActivationState::Active,
+            activation: StakeHistoryEntry::default(),
+        }
+    }
+
+    fn reward(vote_account: &str, epoch: u64, amount: u64, block_time: i64) -> StakeReward {
+        StakeReward {
+            id: None,
+            account_id: 1,
+            stake_address: "stake".to_string(),
+            vote_account: vote_account.to_string(),
+            epoch,
+            amount,
+            post_balance: 1_000_000_000_000 + amount,
+            commission: Some(5),
+            effective_slot: 0,
+            block_time: Some(block_time),
+            sol_price: Some(100.0),
+        }
+    }
+
+    #[test]
+    fn test_sync_resumes_from_last_epoch() {
+        let service = RewardService::new(setup_test_db(), reward_client());
+        let stakes = [delegated_stake(5)];
+
+        // Mocked responses are only served once, later calls get the mock client defaults
+
+        // Epochs 6 and 7 are complete while 8 is running
+        assert_eq!(service.sync(1, &stakes, 8).unwrap(), 2);
+        assert_eq!(service.sync(1, &stakes, 8).unwrap(), 0);
+        assert_eq!(service.sync(1, &stakes, 9).unwrap(), 1);
+
+        let rewards = service.get_rewards(1).unwrap();
+        assert_eq!(
+            rewards
+                .iter()
+                .map(|reward| reward.epoch)
+                .collect::<Vec<_>>(),
+            vec![6, 7, 8]
+        );
+        assert_eq!(rewards[0].amount, 2_500_000);
+        assert_eq!(rewards[1].amount, 2_500);
+        assert_eq!(rewards[0].commission, Some(5));
+        assert_eq!(rewards[0].block_time, Some(PAID_AT));
+        assert_eq!(
+            rewards[0].vote_account,
+            stakes[0].delegation.unwrap().voter_pubkey.to_string()
+        );
+    }
+
+    #[test]
+    fn test_fill_prices_once_per_day() {
+        let service = RewardService::new(setup_test_db(), reward_client());
+        // Two stake accounts paid in the same block of epoch 7
+        service
+            .sync(1, &[delegated_stake(6), delegated_stake(6)], 8)
+            .unwrap();
+
+        let mut lookups = vec![];
+        let priced = service
+            .fill_prices(1, |date| {
+                lookups.push(date);
+                Ok(Some(150.0))
+            })
+            .unwrap();
+
+        assert_eq!(priced, 2);
+        assert_eq!(
+            lookups,
+            vec![NaiveDate::from_ymd_opt(2023, 11, 14).unwrap()]
+        );
+        let rewards = service.get_rewards(1).unwrap();
+        assert_eq!(rewards[0].sol_price, Some(150.0));
+        assert_eq!(rewards[0].fiat_value(), Some(0.375));
+
+        // Already priced rewards are not looked up again
+        let priced = service
+            .fill_prices(1, |_| panic!("unexpected lookup"))
+            .unwrap();
+        assert_eq!(priced, 0);
+    }
+
+    #[test]
+    fn test_validator_apy() {
+        let two_days = 2 * 86_400;
+        let rewards: Vec<StakeReward> = (0..5)
+            .flat_map(|index| {
+                let time = PAID_AT + index as i64 * two_days;
+                [
+                    reward("fast", 100 + index, 1_000_000_000, time),
+                    reward("slow", 100 + index, 500_000_000, time),
+                ]
+            })
+            .collect();
+
+        let apy = validator_apy(&rewards);
+        assert_eq!(apy.len(), 2);
+        assert_eq!(apy[0].vote_account, "fast");
+        assert_eq!(apy[0].epochs, 5);
+        let expected = 1.001f64.powf(SECS_PER_YEAR / two_days as f64) - 1.0;
+        assert!((apy[0].apy - expected).abs() < 1e-9);
+        assert!(apy[1].apy < apy[0].apy);
+    }
+
+    #[test]
+    fn test_write_rewards_for_year() {
+        let rewards = vec![
+            reward("validator", 500, 1_500_000_000, PAID_AT),
+            reward("validator", 400, 1_000_000_000, PAID_AT - 400 * 86_400),
+        ];
+        let year = Local.timestamp_opt(PAID_AT, 0).unwrap().year();
+        let in_year = rewards_in_year(&rewards, year);
+        assert_eq!(in_year.len(), 1);
+
+        let mut output = vec![];
+        write_rewards(&in_year, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "epoch,paid_at,stake_account,validator,reward_sol,post_balance_sol,commission,sol_usd,reward_usd"
+        );
+        let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(row[0], "500");
+        assert_eq!(row[4], "1.5");
+        assert_eq!(row[7], "100.00");
+        assert_eq!(row[8], "150.00");
+        assert!(lines.next().is_none());
+    }
+}
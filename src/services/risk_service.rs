@@ -0,0 +1,631 @@
+use crate::programs::token::{account_amount, is_token_program};
+use crate::services::errors::ServiceError;
+use serde::Deserialize;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::VersionedTransaction;
+use solana_system_interface::program::ID as SYSTEM_PROGRAM_ID;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, OnceLock},
+};
+
+// High-risk instruction patterns flagged before signing
+const RISK_RULES: &str = include_str!("risk_rules.json");
+
+#[derive(Debug, Clone, Deserialize)]
+struct RiskRule {
+    /// "system", "token" for either token program, or a program id
+    program: String,
+    /// Leading bytes of the instruction data that identify the instruction
+    discriminator: Vec<u8>,
+    condition: Condition,
+    /// Shown when the rule matches, with `{n}` replaced by the instruction's nth account
+    warning: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Condition {
+    Always,
+    /// The u64 amount at `offset` is the largest possible
+    UnlimitedAmount {
+        offset: usize,
+    },
+    /// The u64 amount at `offset` is all the `source` account holds, in tokens when it is a
+    /// token account and in lamports otherwise
+    FullBalance {
+        source: usize,
+        offset: usize,
+    },
+    /// The `account` is not one of this wallet's
+    NotOwned {
+        account: usize,
+    },
+}
+
+/// One instruction with its accounts, some of which may sit in unresolved lookup tables
+struct Call<'a> {
+    program_id: Pubkey,
+    accounts: Vec<Option<Pubkey>>,
+    data: &'a [u8],
+}
+
+pub struct RiskService {
+    client: Arc<RpcClient>,
+}
+
+impl RiskService {
+    pub fn new(client: Arc<RpcClient>) -> Self {
+        Self { client }
+    }
+
+    /// Warnings for instructions about to be signed by the wallet's `owned` accounts
+    pub fn check_instructions(
+        &self,
+        instructions: &[Instruction],
+        owned: &[Pubkey],
+    ) -> Result<Vec<String>, ServiceError> {
+        self.check(&instruction_calls(instructions), owned)
+    }
+
+    /// Warnings for a transaction built elsewhere, before the wallet signs it
+    pub fn check_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+        owned: &[Pubkey],
+    ) -> Result<Vec<String>, ServiceError> {
+        let keys = transaction.message.static_account_keys();
+        let calls: Vec<Call> = transaction
+            .message
+            .instructions()
+            .iter()
+            .filter_map(|instruction| {
+                Some(Call {
+                    program_id: *keys.get(instruction.program_id_index as usize)?,
+                    accounts: instruction
+                        .accounts
+                        .iter()
+                        .map(|index| keys.get(*index as usize).copied())
+                        .collect(),
+                    data: &instruction.data,
+                })
+            })
+            .collect();
+        self.check(&calls, owned)
+    }
+
+    fn check(&self, calls: &[Call], owned: &[Pubkey]) -> Result<Vec<String>, ServiceError> {
+        let rules = risk_rules();
+
+        // Only accounts that a full-balance rule looks at need their balance fetched
+        let mut sources: Vec<Pubkey> = vec![];
+        for call in calls {
+            for rule in rules.iter().filter(|rule| rule.applies_to(call)) {
+                if let Condition::FullBalance { source, .. } = rule.condition {
+                    if let Some(Some(source)) = call.accounts.get(source) {
+                        if !sources.contains(source) {
+                            sources.push(*source);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut balances = HashMap::new();
+        if !sources.is_empty() {
+            let accounts = self.client.get_multiple_accounts(&sources)?;
+            for (source, account) in sources.iter().zip(accounts) {
+                let balance = match account {
+                    Some(account) if is_token_program(&account.owner) => {
+                        account_amount(&account.data).unwrap_or(0)
+                    }
+                    Some(account) => account.lamports,
+                    None => 0,
+                };
+                balances.insert(*source, balance);
+            }
+        }
+
+        Ok(find_risks(rules, calls, owned, &balances))
+    }
+}
+
+fn instruction_calls(instructions: &[Instruction]) -> Vec<Call<'_>> {
+    instructions
+        .iter()
+        .map(|instruction| Call {
+            program_id: instruction.program_id,
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|meta| Some(meta.pubkey))
+                .collect(),
+            data: &instruction.data,
+        })
+        .collect()
+}
+
+fn risk_rules() -> &'static [RiskRule] {
+    static RULES: OnceLock<Vec<RiskRule>> = OnceLock::new();
+    RULES.get_or_init(|| parse_risk_rules(RISK_RULES))
+}
+
+fn parse_risk_rules(json: &str) -> Vec<RiskRule> {
+    serde_json::from_str(json).unwrap_or_default()
+}
+
+impl RiskRule {
+    fn applies_to(&self, call: &Call) -> bool {
+        let program = match self.program.as_str() {
+            "system" => call.program_id == SYSTEM_PROGRAM_ID,
+            "token" => is_token_program(&call.program_id),
+            id => Pubkey::from_str(id).is_ok_and(|id| id == call.program_id),
+        };
+        program && call.data.starts_with(&self.discriminator)
+    }
+
+    fn matches(&self, call: &Call, owned: &[Pubkey], balances: &HashMap<Pubkey, u64>) -> bool {
+        if !self.applies_to(call) {
+            return false;
+        }
+        match self.condition {
+            Condition::Always => true,
+            Condition::UnlimitedAmount { offset } => read_u64(call.data, offset) == Some(u64::MAX),
+            Condition::FullBalance { source, offset } => {
+                let balance = call
+                    .accounts
+                    .get(source)
+                    .copied()
+                    .flatten()
+                    .and_then(|source| balances.get(&source));
+                match (read_u64(call.data, offset), balance) {
+                    (Some(amount), Some(balance)) => amount >= *balance,
+                    _ => false,
+                }
+            }
+            // Accounts hidden in lookup tables cannot be shown to be the wallet's own
+            Condition::NotOwned { account } => match call.accounts.get(account) {
+                Some(Some(account)) => !owned.contains(account),
+                Some(None) => true,
+                None => false,
+            },
+        }
+    }
+
+    fn warning_for(&self, call: &Call) -> String {
+        call.accounts
+            .iter()
+            .enumerate()
+            .fold(self.warning.clone(), |warning, (index, account)| {
+                let account = match account {
+                    Some(account) => account.to_string(),
+                    None => "an address from a lookup table".to_string(),
+                };
+                warning.replace(&format!("{{{}}}", index), &account)
+            })
+    }
+}
+
+fn find_risks(
+    rules: &[RiskRule],
+    calls: &[Call],
+    owned: &[Pubkey],
+    balances: &HashMap<Pubkey, u64>,
+) -> Vec<String> {
+    let mut warnings = vec![];
+    for call in calls {
+        for rule in rules {
+            if rule.matches(call, owned, balances) {
+                warnings.push(rule.warning_for(call));
+            }
+        }
+    }
+    warnings
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::token::{
+        close_account, revoke, transfer_checked, ACCOUNT_LEN, TOKEN_2022_PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+    };
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde_json::json;
+    use solana_rpc_client::rpc_client::Mocks;
+    use solana_rpc_client_api::request::RpcRequest;
+    use solana_sdk::instruction::AccountMeta;
+    use solana_system_interface::instruction::{
+        allocate, allocate_with_seed, assign, assign_with_seed, transfer, transfer_with_seed,
+    };
+
+    fn approve(source: &Pubkey, delegate: &Pubkey, owner: &Pubkey, amount: u64) -> Instruction {
+        let mut data = vec![4];
+        data.extend_from_slice(&amount.to_le_bytes());
+        Instruction {
+            program_id: TOKEN_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(*source, false),
+                AccountMeta::new_readonly(*delegate, false),
+                AccountMeta::new_readonly(*owner, true),
+            ],
+            data,
+        }
+    }
+
+    fn approve_checked(
+        source: &Pubkey,
+        mint: &Pubkey,
+        delegate: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+    ) -> Instruction {
+        let mut data = vec![13];
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.push(6);
+        Instruction {
+            program_id: TOKEN_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(*source, false),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new_readonly(*delegate, false),
+                AccountMeta::new_readonly(*owner, true),
+            ],
+            data,
+        }
+    }
+
+    fn set_authority(account: &Pubkey, owner: &Pubkey, new_authority: &Pubkey) -> Instruction {
+        let mut data = vec![6, 2, 1];
+        data.extend_from_slice(new_authority.as_ref());
+        Instruction::new_with_bytes(
+            TOKEN_PROGRAM_ID,
+            &data,
+            vec![
+                AccountMeta::new(*account, false),
+                AccountMeta::new_readonly(*owner, true),
+            ],
+        )
+    }
+
+    // Warnings without the RPC round trip, with each account's balance given up front
+    fn risks(
+        instructions: &[Instruction],
+        owned: &[Pubkey],
+        balances: &[(Pubkey, u64)],
+    ) -> Vec<String> {
+        find_risks(
+            risk_rules(),
+            &instruction_calls(instructions),
+            owned,
+            &balances.iter().copied().collect(),
+        )
+    }
+
+    fn balance_client(lamports: u64) -> Arc<RpcClient> {
+        account_client(SYSTEM_PROGRAM_ID, lamports, vec![])
+    }
+
+    // A token account holding `amount` tokens
+    fn token_balance_client(amount: u64) -> Arc<RpcClient> {
+        let mut data = vec![0; ACCOUNT_LEN];
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        account_client(TOKEN_PROGRAM_ID, 2_039_280, data)
+    }
+
+    fn account_client(owner: Pubkey, lamports: u64, data: Vec<u8>) -> Arc<RpcClient> {
+        let mut mocks = Mocks::default();
+        mocks.insert(
+            RpcRequest::GetMultipleAccounts,
+            json!({
+                "context": {"slot": 1},
+                "value": [{
+                    "data": [STANDARD.encode(&data), "base64"],
+                    "executable": false,
+                    "lamports": lamports,
+                    "owner": owner.to_string(),
+                    "rentEpoch": 0,
+                    "space": data.len(),
+                }],
+            }),
+        );
+        Arc::new(RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            mocks,
+        ))
+    }
+
+    #[test]
+    fn test_risk_rules_parse() {
+        let rules = risk_rules();
+        assert_eq!(rules.len(), 10);
+        assert!(rules.iter().all(|rule| !rule.discriminator.is_empty()));
+    }
+
+    #[test]
+    fn test_token_risks() {
+        let wallet = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let stranger = Pubkey::new_unique();
+        let mut set_authority = vec![6, 2, 1];
+        set_authority.extend_from_slice(stranger.as_ref());
+        let instructions = vec![
+            approve(&token_account, &delegate, &wallet, u64::MAX),
+            approve(&token_account, &delegate, &wallet, 100),
+            close_account(&TOKEN_PROGRAM_ID, &token_account, &wallet, &wallet),
+            close_account(&TOKEN_PROGRAM_ID, &token_account, &stranger, &wallet),
+            Instruction::new_with_bytes(
+                TOKEN_PROGRAM_ID,
+                &set_authority,
+                vec![
+                    AccountMeta::new(token_account, false),
+                    AccountMeta::new_readonly(wallet, true),
+                ],
+            ),
+            transfer_checked(
+                &TOKEN_PROGRAM_ID,
+                &token_account,
+                &Pubkey::new_unique(),
+                &stranger,
+                &wallet,
+                10,
+                6,
+            ),
+        ];
+
+        let warnings = RiskService::new(token_balance_client(1_000))
+            .check_instructions(&instructions, &[wallet])
+            .unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                format!(
+                    "Lets {} spend every token in {}, now and in the future",
+                    delegate, token_account
+                ),
+                format!(
+                    "Closes {} and sends its SOL to {}, which this wallet does not control",
+                    token_account, stranger
+                ),
+                format!(
+                    "Changes an authority of token account or mint {}. The new authority can take control of it",
+                    token_account
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_system_risks() {
+        let wallet = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let instructions = vec![
+            transfer(&wallet, &recipient, 5_000),
+            assign(&wallet, &Pubkey::new_unique()),
+        ];
+
+        let warnings = RiskService::new(balance_client(5_000))
+            .check_instructions(&instructions, &[wallet])
+            .unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                format!(
+                    "Transfers the entire balance of {} to {}",
+                    wallet, recipient
+                ),
+                format!(
+                    "Hands {} over to another program, which then controls its SOL",
+                    wallet
+                ),
+            ]
+        );
+
+        // Leaving something behind is an ordinary transfer
+        let warnings = RiskService::new(balance_client(10_000))
+            .check_instructions(&instructions[..1], &[wallet])
+            .unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_token_transfer_rules() {
+        let wallet = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let mut data = vec![3];
+        data.extend_from_slice(&1_000u64.to_le_bytes());
+        let instructions = vec![
+            Instruction::new_with_bytes(
+                TOKEN_PROGRAM_ID,
+                &data,
+                vec![
+                    AccountMeta::new(token_account, false),
+                    AccountMeta::new(recipient, false),
+                    AccountMeta::new_readonly(wallet, true),
+                ],
+            ),
+            transfer_checked(
+                &TOKEN_2022_PROGRAM_ID,
+                &token_account,
+                &mint,
+                &recipient,
+                &wallet,
+                1_000,
+                6,
+            ),
+        ];
+        let emptied = format!(
+            "Transfers every token in {} to {}",
+            token_account, recipient
+        );
+
+        let warnings = RiskService::new(token_balance_client(1_000))
+            .check_instructions(&instructions, &[wallet])
+            .unwrap();
+        assert_eq!(warnings, vec![emptied.clone(), emptied]);
+
+        // The token account's lamports are rent, not what the transfer spends
+        let warnings = RiskService::new(token_balance_client(1_001))
+            .check_instructions(&instructions, &[wallet])
+            .unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_transfer_with_seed_rule() {
+        let wallet = Pubkey::new_unique();
+        let from = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let instruction = transfer_with_seed(
+            &from,
+            &wallet,
+            "savings".to_string(),
+            &SYSTEM_PROGRAM_ID,
+            &recipient,
+            5_000,
+        );
+
+        // The funds come from the seed account, not the base that signs for it
+        assert_eq!(
+            risks(
+                std::slice::from_ref(&instruction),
+                &[wallet],
+                &[(from, 5_000), (wallet, 1_000_000)]
+            ),
+            vec![format!(
+                "Transfers the entire balance of {} to {}",
+                from, recipient
+            )]
+        );
+        assert!(risks(
+            &[instruction],
+            &[wallet],
+            &[(from, 10_000), (wallet, 5_000)]
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_assign_rules() {
+        let wallet = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let instructions = vec![
+            assign(&wallet, &program),
+            assign_with_seed(&address, &wallet, "seed", &program),
+        ];
+        let handed_over = |account: &Pubkey| {
+            format!(
+                "Hands {} over to another program, which then controls its SOL",
+                account
+            )
+        };
+        assert_eq!(
+            risks(&instructions, &[wallet], &[]),
+            vec![handed_over(&wallet), handed_over(&address)]
+        );
+
+        // Allocating space leaves the owner as it is
+        let instructions = vec![
+            allocate(&wallet, 100),
+            allocate_with_seed(&address, &wallet, "seed", 100, &SYSTEM_PROGRAM_ID),
+        ];
+        assert!(risks(&instructions, &[wallet], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_approve_rules() {
+        let wallet = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let unlimited = format!(
+            "Lets {} spend every token in {}, now and in the future",
+            delegate, token_account
+        );
+
+        let instructions = vec![
+            approve(&token_account, &delegate, &wallet, u64::MAX),
+            approve_checked(&token_account, &mint, &delegate, &wallet, u64::MAX),
+        ];
+        assert_eq!(
+            risks(&instructions, &[wallet], &[]),
+            vec![unlimited.clone(), unlimited]
+        );
+
+        let instructions = vec![
+            approve(&token_account, &delegate, &wallet, 1_000),
+            approve_checked(&token_account, &mint, &delegate, &wallet, 1_000),
+        ];
+        assert!(risks(&instructions, &[wallet], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_set_authority_rule() {
+        let wallet = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        assert_eq!(
+            risks(
+                &[set_authority(&token_account, &wallet, &Pubkey::new_unique())],
+                &[wallet],
+                &[]
+            ),
+            vec![format!(
+                "Changes an authority of token account or mint {}. The new authority can take control of it",
+                token_account
+            )]
+        );
+        assert!(risks(
+            &[revoke(&TOKEN_PROGRAM_ID, &token_account, &wallet)],
+            &[wallet],
+            &[]
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_token_2022_rules() {
+        let wallet = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let stranger = Pubkey::new_unique();
+        let on_program = |program_id: Pubkey| -> Vec<Instruction> {
+            [
+                approve(&token_account, &stranger, &wallet, u64::MAX),
+                approve_checked(
+                    &token_account,
+                    &Pubkey::new_unique(),
+                    &stranger,
+                    &wallet,
+                    u64::MAX,
+                ),
+                set_authority(&token_account, &wallet, &stranger),
+                close_account(&TOKEN_PROGRAM_ID, &token_account, &stranger, &wallet),
+            ]
+            .into_iter()
+            .map(|instruction| Instruction {
+                program_id,
+                ..instruction
+            })
+            .collect()
+        };
+
+        assert_eq!(
+            risks(&on_program(TOKEN_2022_PROGRAM_ID), &[wallet], &[]).len(),
+            4
+        );
+        // The same bytes sent to some other program mean something else
+        assert!(risks(&on_program(Pubkey::new_unique()), &[wallet], &[]).is_empty());
+    }
+}
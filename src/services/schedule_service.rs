@@ -0,0 +1,633 @@
+use crate::amount::validate_ui_amount;
+use crate::database::{
+    errors::DatabaseError,
+    schedule::{Frequency, RunStatus, ScheduledRun, ScheduledTransfer},
+};
+use crate::services::{
+    account_service::AccountService,
+    errors::ServiceError,
+    transfer_service::{Transfer, TransferService},
+};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+const SCHEDULE_COLUMNS: &str = "s.id, s.account_id, s.recipient, s.amount, s.token, s.memo,
+    s.frequency, s.start_at, s.occurrence, s.next_run_at, s.active";
+
+// Number of recent runs shown in the wallet
+const RUN_HISTORY_LIMIT: i32 = 50;
+
+/// Runs due this long ago are still sent rather than reported as missed
+pub const MISSED_GRACE_SECS: i64 = 5 * 60;
+
+pub struct ScheduleService {
+    conn: Arc<Mutex<Connection>>,
+    client: Arc<RpcClient>,
+}
+
+impl ScheduleService {
+    pub fn new(conn: Arc<Mutex<Connection>>, client: Arc<RpcClient>) -> Self {
+        Self { conn, client }
+    }
+
+    pub fn create(
+        &self,
+        schedule: &ScheduledTransfer,
+        now: i64,
+    ) -> Result<ScheduledTransfer, ServiceError> {
+        schedule_transfer(schedule)?;
+        if schedule.start_at <= now {
+            return Err(ServiceError::InvalidSchedule(
+                "The first run must be in the future".to_string(),
+            ));
+        }
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO scheduled_transfers (account_id, recipient, amount, token, memo, frequency,
+            start_at, occurrence, next_run_at, active) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, ?7, 1)",
+            params![
+                schedule.account_id,
+                schedule.recipient,
+                schedule.amount,
+                schedule.token,
+                schedule.memo,
+                schedule.frequency.as_str(),
+                schedule.start_at,
+            ],
+        )
+        .map_err(DatabaseError::from)?;
+
+        Ok(ScheduledTransfer {
+            id: Some(conn.last_insert_rowid() as i32),
+            occurrence: 0,
+            next_run_at: Some(schedule.start_at),
+            active: true,
+            ..schedule.clone()
+        })
+    }
+
+    pub fn get_schedules(&self, account_id: i32) -> Result<Vec<ScheduledTransfer>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM scheduled_transfers s WHERE s.account_id = ?1 AND s.active = 1
+            ORDER BY s.next_run_at",
+            SCHEDULE_COLUMNS
+        ))?;
+        let schedules = stmt
+            .query_map(params![account_id], |row| schedule_from_sql(row, 0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(schedules)
+    }
+
+    pub fn cancel(&self, schedule_id: i32) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE scheduled_transfers SET active = 0, next_run_at = NULL WHERE id = ?1",
+            params![schedule_id],
+        )?;
+        Ok(())
+    }
+
+    /// Most recent runs of the account's schedules, with the schedule each run belongs to
+    pub fn get_runs(
+        &self,
+        account_id: i32,
+    ) -> Result<Vec<(ScheduledRun, ScheduledTransfer)>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT r.id, r.schedule_id, r.scheduled_for, r.ran_at, r.status, r.signature, r.error, {}
+            FROM scheduled_transfer_runs r JOIN scheduled_transfers s ON s.id = r.schedule_id
+            WHERE s.account_id = ?1 ORDER BY r.scheduled_for DESC, r.id DESC LIMIT ?2",
+            SCHEDULE_COLUMNS
+        ))?;
+        let runs = stmt
+            .query_map(params![account_id, RUN_HISTORY_LIMIT], |row| {
+                Ok((run_from_sql(row)?, schedule_from_sql(row, 7)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(runs)
+    }
+
+    /// Record a missed run for every occurrence that fell before `before` while the app
+    /// was closed. Missed runs are never sent automatically.
+    pub fn record_missed(&self, before: i64) -> Result<usize, DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let transaction = conn.transaction()?;
+        let mut schedules = overdue_schedules(&transaction, before)?;
+
+        let mut missed = 0;
+        for schedule in &mut schedules {
+            while let Some(scheduled_for) = schedule.next_run_at.filter(|run| *run < before) {
+                insert_run(
+                    &transaction,
+                    schedule.id.unwrap_or_default(),
+                    scheduled_for,
+                    None,
+                    RunStatus::Missed,
+                    None,
+                    None,
+                )?;
+                schedule.advance();
+                missed += 1;
+            }
+            save_progress(&transaction, schedule)?;
+        }
+        transaction.commit()?;
+        Ok(missed)
+    }
+
+    /// Claim the schedules due at `now`, moving each past its due runs before it is sent so a
+    /// slow or failed send is never picked up twice. Only the latest run still within the grace
+    /// period is sent. Runs left behind while the machine slept are recorded as missed, like
+    /// those that fell while the app was closed. Returns the runs to send and how many were
+    /// missed.
+    pub fn take_due(
+        &self,
+        now: i64,
+    ) -> Result<(Vec<(ScheduledTransfer, i64)>, usize), DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let transaction = conn.transaction()?;
+        let schedules = overdue_schedules(&transaction, now + 1)?;
+
+        let mut due = vec![];
+        let mut missed = 0;
+        for schedule in schedules {
+            let mut advanced = schedule.clone();
+            let mut runs = vec![];
+            while let Some(scheduled_for) = advanced.next_run_at.filter(|run| *run <= now) {
+                runs.push(scheduled_for);
+                advanced.advance();
+            }
+            save_progress(&transaction, &advanced)?;
+
+            if let Some(&latest) = runs
+                .last()
+                .filter(|latest| now - **latest <= MISSED_GRACE_SECS)
+            {
+                runs.pop();
+                due.push((schedule.clone(), latest));
+            }
+            for scheduled_for in runs {
+                insert_run(
+                    &transaction,
+                    schedule.id.unwrap_or_default(),
+                    scheduled_for,
+                    None,
+                    RunStatus::Missed,
+                    None,
+                    None,
+                )?;
+                missed += 1;
+            }
+        }
+        transaction.commit()?;
+        Ok((due, missed))
+    }
+
+    /// Send a due run and record the outcome
+    pub fn run(
+        &self,
+        schedule: &ScheduledTransfer,
+        scheduled_for: i64,
+        now: i64,
+    ) -> Result<ScheduledRun, ServiceError> {
+        let (status, signature, error) = run_outcome(self.send(schedule));
+        let conn = self.conn.lock().unwrap();
+        let id = insert_run(
+            &conn,
+            schedule.id.unwrap_or_default(),
+            scheduled_for,
+            Some(now),
+            status,
+            signature.as_deref(),
+            error.as_deref(),
+        )?;
+
+        Ok(ScheduledRun {
+            id: Some(id),
+            schedule_id: schedule.id.unwrap_or_default(),
+            scheduled_for,
+            ran_at: Some(now),
+            status,
+            signature,
+            error,
+        })
+    }
+
+    /// Send a run that was missed while the app was closed, once the user asks for it. The run
+    /// is claimed before it is sent, so asking twice never pays it twice.
+    pub fn send_missed(&self, run_id: i32, now: i64) -> Result<ScheduledRun, ServiceError> {
+        let (run, schedule) = {
+            let conn = self.conn.lock().unwrap();
+            let (run, schedule) = conn
+                .query_row(
+                    &format!(
+                    "SELECT r.id, r.schedule_id, r.scheduled_for, r.ran_at, r.status, r.signature,
+                    r.error, {} FROM scheduled_transfer_runs r
+                    JOIN scheduled_transfers s ON s.id = r.schedule_id WHERE r.id = ?1",
+                    SCHEDULE_COLUMNS
+                ),
+                    params![run_id],
+                    |row| Ok((run_from_sql(row)?, schedule_from_sql(row, 7)?)),
+                )
+                .optional()
+                .map_err(DatabaseError::from)?
+                .ok_or_else(|| {
+                    ServiceError::InvalidSchedule(format!("Run {} not found", run_id))
+                })?;
+            let claimed = conn
+                .execute(
+                    "UPDATE scheduled_transfer_runs SET status = ?1 WHERE id = ?2 AND status = ?3",
+                    params![
+                        RunStatus::Sending.as_str(),
+                        run_id,
+                        RunStatus::Missed.as_str()
+                    ],
+                )
+                .map_err(DatabaseError::from)?;
+            if claimed != 1 {
+                return Err(ServiceError::InvalidSchedule(format!(
+                    "Run {} is not a missed run",
+                    run_id
+                )));
+            }
+            (run, schedule)
+        };
+
+        let (status, signature, error) = run_outcome(self.send(&schedule));
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE scheduled_transfer_runs SET ran_at = ?1, status = ?2, signature = ?3, error = ?4
+            WHERE id = ?5",
+            params![now, status.as_str(), signature, error, run_id],
+        )
+        .map_err(DatabaseError::from)?;
+
+        Ok(ScheduledRun {
+            ran_at: Some(now),
+            status,
+            signature,
+            error,
+            ..run
+        })
+    }
+
+    fn send(&self, schedule: &ScheduledTransfer) -> Result<Signature, ServiceError> {
+        let account = AccountService::new(self.conn.clone())
+            .get_account_by_id(schedule.account_id)?
+            .ok_or_else(|| ServiceError::AccountNotFound(schedule.account_id.to_string()))?;
+        let transfer_details = schedule_transfer(schedule)?;
+        TransferService::new(self.client.clone()).send(&account, &transfer_details)
+    }
+}
+
+/// The transfer a schedule makes on each run
+pub fn schedule_transfer(schedule: &ScheduledTransfer) -> Result<Transfer, ServiceError> {
+    validate_ui_amount(&schedule.amount)?;
+    let mint = match schedule.token.as_str() {
+        "" => None,
+        token => Some(Pubkey::from_str(token)?),
+    };
+
+    Ok(Transfer {
+        recipient: Pubkey::from_str(&schedule.recipient)?,
+        amount: schedule.amount.clone(),
+        mint,
+        memo: Some(schedule.memo.clone()).filter(|memo| !memo.is_empty()),
+        references: vec![],
+    })
+}
+
+fn run_outcome(
+    result: Result<Signature, ServiceError>,
+) -> (RunStatus, Option<String>, Option<String>) {
+    match result {
+        Ok(signature) => (RunStatus::Sent, Some(signature.to_string()), None),
+        Err(e) => (RunStatus::Failed, None, Some(e.to_string())),
+    }
+}
+
+fn overdue_schedules(
+    conn: &Connection,
+    before: i64,
+) -> Result<Vec<ScheduledTransfer>, DatabaseError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM scheduled_transfers s
+        WHERE s.active = 1 AND s.next_run_at IS NOT NULL AND s.next_run_at < ?1",
+        SCHEDULE_COLUMNS
+    ))?;
+    let schedules = stmt
+        .query_map(params![before], |row| schedule_from_sql(row, 0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(schedules)
+}
+
+fn save_progress(conn: &Connection, schedule: &ScheduledTransfer) -> Result<(), DatabaseError> {
+    conn.execute(
+        "UPDATE scheduled_transfers SET occurrence = ?1, next_run_at = ?2, active = ?3 WHERE id = ?4",
+        params![
+            schedule.occurrence,
+            schedule.next_run_at,
+            schedule.active,
+            schedule.id
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_run(
+    conn: &Connection,
+    schedule_id: i32,
+    scheduled_for: i64,
+    ran_at: Option<i64>,
+    status: RunStatus,
+    signature: Option<&str>,
+    error: Option<&str>,
+) -> Result<i32, DatabaseError> {
+    conn.execute(
+        "INSERT INTO scheduled_transfer_runs (schedule_id, scheduled_for, ran_at, status, signature, error)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            schedule_id,
+            scheduled_for,
+            ran_at,
+            status.as_str(),
+            signature,
+            error
+        ],
+    )?;
+    Ok(conn.last_insert_rowid() as i32)
+}
+
+fn schedule_from_sql(row: &Row, offset: usize) -> rusqlite::Result<ScheduledTransfer> {
+    let frequency: String = row.get(offset + 6)?;
+    Ok(ScheduledTransfer {
+        id: row.get(offset)?,
+        account_id: row.get(offset + 1)?,
+        recipient: row.get(offset + 2)?,
+        amount: row.get(offset + 3)?,
+        token: row.get(offset + 4)?,
+        memo: row.get(offset + 5)?,
+        frequency: Frequency::from_db(&frequency).unwrap_or(Frequency::Once),
+        start_at: row.get(offset + 7)?,
+        occurrence: row.get(offset + 8)?,
+        next_run_at: row.get(offset + 9)?,
+        active: row.get(offset + 10)?,
+    })
+}
+
+fn run_from_sql(row: &Row) -> rusqlite::Result<ScheduledRun> {
+    let status: String = row.get(4)?;
+    Ok(ScheduledRun {
+        id: row.get(0)?,
+        schedule_id: row.get(1)?,
+        scheduled_for: row.get(2)?,
+        ran_at: row.get(3)?,
+        status: RunStatus::from_db(&status),
+        signature: row.get(5)?,
+        error: row.get(6)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database_connection;
+
+    const DAY: i64 = 24 * 60 * 60;
+    const START: i64 = 1_735_732_800;
+
+    fn setup_test_db() -> Arc<Mutex<Connection>> {
+        let conn = Arc::new(Mutex::new(database_connection().unwrap()));
+        let conn_binding = conn.clone();
+        let conn_clone = conn_binding.lock().unwrap();
+        conn_clone
+            .execute(
+                "CREATE TABLE accounts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                seed TEXT NOT NULL,
+                pubkey TEXT NOT NULL,
+                passphrase TEXT NOT NULL,
+                balance INTEGER
+            )",
+                [],
+            )
+            .unwrap();
+        conn_clone
+            .execute(
+                "CREATE TABLE scheduled_transfers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                account_id INTEGER NOT NULL,
+                recipient TEXT NOT NULL,
+                amount TEXT NOT NULL,
+                token TEXT NOT NULL,
+                memo TEXT NOT NULL,
+                frequency TEXT NOT NULL,
+                start_at INTEGER NOT NULL,
+                occurrence INTEGER NOT NULL DEFAULT 0,
+                next_run_at INTEGER NULL,
+                active INTEGER NOT NULL DEFAULT 1
+            )",
+                [],
+            )
+            .unwrap();
+        conn_clone
+            .execute(
+                "CREATE TABLE scheduled_transfer_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                schedule_id INTEGER NOT NULL,
+                scheduled_for INTEGER NOT NULL,
+                ran_at INTEGER NULL,
+                status TEXT NOT NULL,
+                signature TEXT NULL,
+                error TEXT NULL
+            )",
+                [],
+            )
+            .unwrap();
+        conn
+    }
+
+    fn schedule_service(conn: Arc<Mutex<Connection>>) -> ScheduleService {
+        ScheduleService::new(conn, Arc::new(RpcClient::new_mock("succeeds".to_string())))
+    }
+
+    fn weekly_schedule(account_id: i32) -> ScheduledTransfer {
+        ScheduledTransfer {
+            id: None,
+            account_id,
+            recipient: Pubkey::new_unique().to_string(),
+            amount: "0.5".to_string(),
+            token: String::new(),
+            memo: "rent".to_string(),
+            frequency: Frequency::Weekly,
+            start_at: START,
+            occurrence: 0,
+            next_run_at: None,
+            active: true,
+        }
+    }
+
+    fn insert_test_account(conn: Arc<Mutex<Connection>>) -> i32 {
+        let account_service = AccountService::new(conn);
+        let account = account_service.create_account().unwrap();
+        account_service.insert_account(&account).unwrap();
+        account_service.get_all_accounts().unwrap()[0].id.unwrap()
+    }
+
+    #[test]
+    fn test_create_schedule() {
+        let service = schedule_service(setup_test_db());
+        let schedule = service.create(&weekly_schedule(1), START - DAY).unwrap();
+        assert_eq!(schedule.next_run_at, Some(START));
+
+        let schedules = service.get_schedules(1).unwrap();
+        assert_eq!(schedules, vec![schedule.clone()]);
+
+        service.cancel(schedule.id.unwrap()).unwrap();
+        assert!(service.get_schedules(1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_create_schedule_rejects_invalid_input() {
+        let service = schedule_service(setup_test_db());
+        assert!(matches!(
+            service.create(&weekly_schedule(1), START + 1),
+            Err(ServiceError::InvalidSchedule(_))
+        ));
+
+        let mut schedule = weekly_schedule(1);
+        schedule.recipient = "not-a-key".to_string();
+        assert!(matches!(
+            service.create(&schedule, START - DAY),
+            Err(ServiceError::ParsePubkeyError(_))
+        ));
+    }
+
+    #[test]
+    fn test_record_missed_runs() {
+        let service = schedule_service(setup_test_db());
+        let schedule = service.create(&weekly_schedule(1), START - DAY).unwrap();
+
+        // The app was closed for the first three weekly runs
+        let missed = service.record_missed(START + 15 * DAY).unwrap();
+        assert_eq!(missed, 3);
+
+        let runs = service.get_runs(1).unwrap();
+        assert_eq!(runs.len(), 3);
+        assert!(runs.iter().all(|(run, _)| run.status == RunStatus::Missed));
+        assert_eq!(runs[0].0.scheduled_for, START + 14 * DAY);
+
+        let schedules = service.get_schedules(1).unwrap();
+        assert_eq!(schedules[0].id, schedule.id);
+        assert_eq!(schedules[0].occurrence, 3);
+        assert_eq!(schedules[0].next_run_at, Some(START + 21 * DAY));
+    }
+
+    #[test]
+    fn test_take_due_claims_each_run_once() {
+        let service = schedule_service(setup_test_db());
+        let mut once = weekly_schedule(1);
+        once.frequency = Frequency::Once;
+        service.create(&once, START - DAY).unwrap();
+
+        assert!(service.take_due(START - 1).unwrap().0.is_empty());
+        let (due, missed) = service.take_due(START).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].1, START);
+        assert_eq!(missed, 0);
+        assert!(service.take_due(START).unwrap().0.is_empty());
+        assert!(service.get_schedules(1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_take_due_after_sleep() {
+        let service = schedule_service(setup_test_db());
+        service.create(&weekly_schedule(1), START - DAY).unwrap();
+
+        // The machine slept through two weekly runs and woke just after the third
+        let (due, missed) = service.take_due(START + 14 * DAY + 60).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].1, START + 14 * DAY);
+        assert_eq!(missed, 2);
+        let runs = service.get_runs(1).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert!(runs.iter().all(|(run, _)| run.status == RunStatus::Missed));
+        assert_eq!(
+            service.get_schedules(1).unwrap()[0].next_run_at,
+            Some(START + 21 * DAY)
+        );
+
+        // Waking well after a run sends nothing
+        let (due, missed) = service
+            .take_due(START + 21 * DAY + MISSED_GRACE_SECS + 1)
+            .unwrap();
+        assert!(due.is_empty());
+        assert_eq!(missed, 1);
+        assert_eq!(service.get_runs(1).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_run_and_send_missed() {
+        let conn = setup_test_db();
+        let account_id = insert_test_account(conn.clone());
+        let service = schedule_service(conn);
+        let schedule = service
+            .create(&weekly_schedule(account_id), START - DAY)
+            .unwrap();
+
+        let run = service.run(&schedule, START, START + 5).unwrap();
+        assert_eq!(run.status, RunStatus::Sent);
+        assert!(run.signature.is_some());
+
+        service.record_missed(START + 8 * DAY).unwrap();
+        let (missed, _) = service.get_runs(account_id).unwrap().remove(0);
+        assert_eq!(missed.status, RunStatus::Missed);
+
+        let sent = service
+            .send_missed(missed.id.unwrap(), START + 9 * DAY)
+            .unwrap();
+        assert_eq!(sent.status, RunStatus::Sent);
+        assert_eq!(sent.ran_at, Some(START + 9 * DAY));
+        assert!(matches!(
+            service.send_missed(missed.id.unwrap(), START + 9 * DAY),
+            Err(ServiceError::InvalidSchedule(_))
+        ));
+    }
+
+    #[test]
+    fn test_send_missed_claims_run() {
+        let conn = setup_test_db();
+        let account_id = insert_test_account(conn.clone());
+        let service = schedule_service(conn.clone());
+        service
+            .create(&weekly_schedule(account_id), START - DAY)
+            .unwrap();
+        service.record_missed(START + DAY).unwrap();
+        let (missed, _) = service.get_runs(account_id).unwrap().remove(0);
+
+        // Another click got there first and is still waiting on its send
+        conn.lock()
+            .unwrap()
+            .execute(
+                "UPDATE scheduled_transfer_runs SET status = 'sending' WHERE id = ?1",
+                params![missed.id],
+            )
+            .unwrap();
+        assert!(matches!(
+            service.send_missed(missed.id.unwrap(), START + 2 * DAY),
+            Err(ServiceError::InvalidSchedule(_))
+        ));
+        let (run, _) = service.get_runs(account_id).unwrap().remove(0);
+        assert_eq!(run.status, RunStatus::Sending);
+        assert_eq!(run.signature, None);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::database::{
+    errors::DatabaseError, token_metadata::TokenMetadata, token_registry::TokenStatus,
+};
+use crate::services::nft_service::Nft;
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+};
+
+// Collections known to airdrop spam and link shorteners that hide where metadata lives
+const SPAM_LIST: &str = include_str!("spam_list.json");
+// Wording spam uses to lure holders to a drainer site
+const LURE_WORDS: [&str; 6] = [
+    "claim",
+    "airdrop",
+    "reward",
+    "redeem",
+    "voucher",
+    "free mint",
+];
+// Endings that turn a name into a web address
+const DOMAIN_ENDINGS: [&str; 10] = [
+    ".com", ".io", ".xyz", ".net", ".org", ".app", ".site", ".fun", ".gg", ".pro",
+];
+
+#[derive(Debug, Default, Deserialize)]
+struct SpamList {
+    collections: Vec<String>,
+    link_shorteners: Vec<String>,
+}
+
+/// Why a token or NFT looks like spam, and whether it is hidden once the user's choice applies
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpamVerdict {
+    /// What the heuristics noticed, if anything
+    pub reason: Option<String>,
+    pub hidden: bool,
+}
+
+pub struct SpamService {
+    conn: Arc<Mutex<Connection>>,
+    list: SpamList,
+}
+
+impl SpamService {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self {
+            conn,
+            list: serde_json::from_str(SPAM_LIST).unwrap_or_default(),
+        }
+    }
+
+    /// Why a token looks like spam. A missing price only counts once prices have been
+    /// fetched, and tokens that are verified or trusted never count.
+    pub fn token_reason(
+        &self,
+        status: TokenStatus,
+        metadata: Option<&TokenMetadata>,
+        price: Option<f64>,
+        priced: bool,
+    ) -> Option<String> {
+        if status != TokenStatus::Unverified {
+            return None;
+        }
+        if let Some(metadata) = metadata {
+            let reason = self.text_reason(&[&metadata.name, &metadata.symbol], "", &metadata.uri);
+            if reason.is_some() {
+                return reason;
+            }
+        }
+        (priced && price.unwrap_or(0.0) == 0.0)
+            .then(|| "Unverified and without a market price".to_string())
+    }
+
+    /// Why an NFT looks like spam
+    pub fn nft_reason(&self, nft: &Nft) -> Option<String> {
+        if nft
+            .collection_mint
+            .is_some_and(|collection| self.list.collections.contains(&collection.to_string()))
+        {
+            return Some("Part of a known spam collection".to_string());
+        }
+        let attributes = nft.attribute_labels();
+        let labels: Vec<&str> = std::iter::once(nft.name.as_str())
+            .chain(attributes.iter().map(String::as_str))
+            .collect();
+        self.text_reason(&labels, &nft.description, &nft.uri)
+    }
+
+    /// What the heuristics found for each address, overridden wherever the user hid or
+    /// unhid it
+    pub fn verdicts(
+        &self,
+        reasons: Vec<(String, Option<String>)>,
+    ) -> Result<HashMap<String, SpamVerdict>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare("SELECT address, hidden FROM spam_overrides")?;
+        let overrides: HashMap<String, bool> = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+
+        Ok(reasons
+            .into_iter()
+            .map(|(address, reason)| {
+                let hidden = overrides.get(&address).copied().unwrap_or(reason.is_some());
+                (address, SpamVerdict { reason, hidden })
+            })
+            .collect())
+    }
+
+    /// Hide `address` as spam or show it again, whatever the heuristics say
+    pub fn set_hidden(&self, address: &str, hidden: bool) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO spam_overrides (address, hidden, updated_at) VALUES (?1, ?2, ?3)
+            ON CONFLICT (address) DO UPDATE SET hidden = excluded.hidden,
+            updated_at = excluded.updated_at",
+            params![address, hidden, Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    // Spam names itself after the site it sends people to and tends to hide its metadata
+    fn text_reason(&self, labels: &[&str], description: &str, uri: &str) -> Option<String> {
+        if labels.iter().any(|label| has_link(label)) {
+            return Some("Its name points to a website".to_string());
+        }
+        if labels.iter().any(|label| has_lure(label)) {
+            return Some("Its name offers something to claim".to_string());
+        }
+        if has_link(description) && has_lure(description) {
+            return Some("Its description sends you to a website to claim something".to_string());
+        }
+        let host = uri_host(uri)?;
+        if self.list.link_shorteners.contains(&host) {
+            return Some(format!(
+                "Its metadata hides behind the link shortener {}",
+                host
+            ));
+        }
+        host.parse::<IpAddr>()
+            .is_ok()
+            .then(|| "Its metadata is served from a bare IP address".to_string())
+    }
+}
+
+fn has_link(text: &str) -> bool {
+    let text = text.to_lowercase();
+    text.contains("http")
+        || text.contains("www.")
+        || text.split_whitespace().any(|word| {
+            let word = word.trim_end_matches(|c: char| !c.is_alphanumeric());
+            DOMAIN_ENDINGS
+                .iter()
+                .any(|ending| word.len() > ending.len() && word.ends_with(ending))
+        })
+}
+
+fn has_lure(text: &str) -> bool {
+    let text = text.to_lowercase();
+    LURE_WORDS.iter().any(|word| text.contains(word))
+}
+
+// Lowercase host of a URI, without any credentials or port
+fn uri_host(uri: &str) -> Option<String> {
+    let (_, rest) = uri.trim().split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?.to_lowercase();
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database_connection;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn setup_test_db() -> Arc<Mutex<Connection>> {
+        let conn = Arc::new(Mutex::new(database_connection().unwrap()));
+        conn.lock()
+            .unwrap()
+            .execute(
+                "CREATE TABLE spam_overrides (
+                address TEXT PRIMARY KEY,
+                hidden INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            )",
+                [],
+            )
+            .unwrap();
+        conn
+    }
+
+    fn metadata(name: &str, symbol: &str, uri: &str) -> TokenMetadata {
+        TokenMetadata {
+            mint: Pubkey::new_unique().to_string(),
+            name: name.to_string(),
+            symbol: symbol.to_string(),
+            uri: uri.to_string(),
+            image: None,
+            image_path: None,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_token_reason() {
+        let service = SpamService::new(setup_test_db());
+        let legit = metadata("Bonk", "Bonk", "https://arweave.net/abc");
+        let unverified = TokenStatus::Unverified;
+
+        assert_eq!(
+            service.token_reason(unverified, Some(&legit), Some(0.0001), true),
+            None
+        );
+        // No price means nothing until prices have been fetched
+        assert_eq!(
+            service.token_reason(unverified, Some(&legit), None, false),
+            None
+        );
+        assert!(service
+            .token_reason(unverified, Some(&legit), None, true)
+            .is_some());
+        assert_eq!(
+            service.token_reason(TokenStatus::Trusted, Some(&legit), None, true),
+            None
+        );
+
+        let site = metadata("Visit solgift.xyz", "GIFT", "");
+        assert_eq!(
+            service
+                .token_reason(unverified, Some(&site), Some(1.0), true)
+                .as_deref(),
+            Some("Its name points to a website")
+        );
+        let lure = metadata("Claim your JUP", "JUP", "");
+        assert!(service
+            .token_reason(unverified, Some(&lure), Some(1.0), true)
+            .unwrap()
+            .contains("claim"));
+        let shortened = metadata("Token", "TKN", "https://bit.ly/3abc");
+        assert!(service
+            .token_reason(unverified, Some(&shortened), Some(1.0), true)
+            .unwrap()
+            .contains("bit.ly"));
+        let bare_ip = metadata("Token", "TKN", "http://203.0.113.7:8080/meta.json");
+        assert!(service
+            .token_reason(unverified, Some(&bare_ip), Some(1.0), true)
+            .unwrap()
+            .contains("IP address"));
+    }
+
+    #[test]
+    fn test_verdicts_with_overrides() {
+        let service = SpamService::new(setup_test_db());
+        let reasons = vec![
+            (
+                "Spam".to_string(),
+                Some("Its name points to a website".to_string()),
+            ),
+            ("Clean".to_string(), None),
+        ];
+
+        let verdicts = service.verdicts(reasons.clone()).unwrap();
+        assert!(verdicts["Spam"].hidden);
+        assert!(!verdicts["Clean"].hidden);
+
+        service.set_hidden("Spam", false).unwrap();
+        service.set_hidden("Clean", true).unwrap();
+        let verdicts = service.verdicts(reasons).unwrap();
+        assert!(!verdicts["Spam"].hidden);
+        assert!(verdicts["Spam"].reason.is_some());
+        assert!(verdicts["Clean"].hidden);
+    }
+
+    #[test]
+    fn test_uri_host() {
+        assert_eq!(
+            uri_host("https://user@Example.com:443/a?b").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(uri_host("ipfs://bafy").as_deref(), Some("bafy"));
+        assert_eq!(uri_host("not a uri"), None);
+    }
+}
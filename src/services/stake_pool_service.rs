@@ -0,0 +1,818 @@
+use crate::amount::{base_units_to_ui_amount, ui_amount_to_base_units, SOL_DECIMALS};
+use crate::database::account::Account;
+use crate::database::errors::DatabaseError;
+use crate::programs::{
+    associated_token::{create_associated_token_account_idempotent, get_associated_token_address},
+    decoder::describe_instructions,
+    stake_pool::{
+        deposit_sol, parse_stake_pool, parse_validator_list, validator_stake_address, withdraw_sol,
+        withdraw_stake, StakePool, ValidatorStakeInfo, STAKE_POOL_PROGRAM_ID,
+    },
+    token::{account_amount, mint_decimals},
+};
+use crate::services::{
+    errors::ServiceError, stake_service::StakeService, transaction_service::TransactionService,
+};
+use rusqlite::{params, Connection};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_stake_interface::{
+    instruction::deactivate_stake, program::ID as STAKE_PROGRAM_ID, state::StakeStateV2,
+};
+use solana_system_interface::instruction::create_account_with_seed;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+// Pools offered on every wallet; others are added by address
+const KNOWN_POOLS: [(&str, Pubkey); 2] = [
+    (
+        "JitoSOL",
+        pubkey!("Jito4APyf642JPZPx3hGc6WWJ8zPKtRbRs4P815Awbb"),
+    ),
+    (
+        "bSOL",
+        pubkey!("stk9ApL5HeVAwPLr3TLhDXdZS8ptVu7zp6ov8HFDuMi"),
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolEntry {
+    pub name: String,
+    pub address: Pubkey,
+    /// Added by the user rather than built in
+    pub custom: bool,
+}
+
+/// A stake pool as seen by one wallet, refreshed before every deposit or withdrawal
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolSummary {
+    pub entry: PoolEntry,
+    pub pool: StakePool,
+    pub epoch: u64,
+    pub decimals: u8,
+    /// Pool tokens in the wallet's associated token account
+    pub holding: u64,
+    /// Lamports the reserve can pay out without dropping below its rent reserve
+    pub reserve_available: u64,
+}
+
+impl PoolSummary {
+    /// SOL received per whole pool token, before fees
+    pub fn sol_per_token(&self) -> f64 {
+        sol_per_token(&self.pool, self.decimals)
+    }
+
+    pub fn holding_value(&self) -> u64 {
+        self.pool.lamports_for(self.holding)
+    }
+
+    /// Deposits and withdrawals fail until the pool has been updated for the current epoch
+    pub fn up_to_date(&self) -> bool {
+        self.pool.last_update_epoch >= self.epoch
+    }
+
+    /// Whether `lamports` can be withdrawn straight from the reserve
+    pub fn instant_withdrawal(&self, lamports: u64) -> bool {
+        self.pool.sol_withdraw_authority.is_none() && lamports <= self.reserve_available
+    }
+
+    fn tokens(&self, amount: u64) -> String {
+        format!(
+            "{} {}",
+            base_units_to_ui_amount(amount, self.decimals),
+            self.entry.name
+        )
+    }
+
+    fn rate(&self) -> String {
+        format!("1 {} = {:.6} SOL", self.entry.name, self.sol_per_token())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoolAction {
+    /// Amount in SOL
+    Deposit { amount: String },
+    /// Amount in pool tokens
+    Withdraw { amount: String },
+}
+
+// Instructions for a pool action along with what it means for the wallet
+struct PoolPlan {
+    instructions: Vec<Instruction>,
+    consequences: Vec<String>,
+}
+
+pub struct StakePoolService {
+    conn: Arc<Mutex<Connection>>,
+    client: Arc<RpcClient>,
+}
+
+impl StakePoolService {
+    pub fn new(conn: Arc<Mutex<Connection>>, client: Arc<RpcClient>) -> Self {
+        Self { conn, client }
+    }
+
+    pub fn pools(&self) -> Result<Vec<PoolEntry>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT name, address FROM stake_pools ORDER BY name")?;
+        let custom = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut pools: Vec<PoolEntry> = KNOWN_POOLS
+            .iter()
+            .map(|(name, address)| PoolEntry {
+                name: name.to_string(),
+                address: *address,
+                custom: false,
+            })
+            .collect();
+        for (name, address) in custom {
+            let Ok(address) = address.parse() else {
+                continue;
+            };
+            if pools.iter().all(|pool| pool.address != address) {
+                pools.push(PoolEntry {
+                    name,
+                    address,
+                    custom: true,
+                });
+            }
+        }
+        Ok(pools)
+    }
+
+    /// Remember a pool after checking that the address holds a stake pool
+    pub fn add_pool(&self, name: &str, address: &Pubkey) -> Result<(), ServiceError> {
+        let account = self.client.get_account(address)?;
+        if account.owner != STAKE_POOL_PROGRAM_ID || parse_stake_pool(&account.data).is_none() {
+            return Err(ServiceError::InvalidStake(format!(
+                "{} is not an SPL stake pool",
+                address
+            )));
+        }
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO stake_pools (address, name) VALUES (?1, ?2)",
+            params![address.to_string(), name],
+        )
+        .map_err(DatabaseError::from)?;
+        Ok(())
+    }
+
+    pub fn remove_pool(&self, address: &Pubkey) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM stake_pools WHERE address = ?1",
+            params![address.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Current state of every known pool that exists on this cluster
+    pub fn summaries(&self, owner: &Pubkey) -> Result<Vec<PoolSummary>, ServiceError> {
+        let epoch = self.client.get_epoch_info()?.epoch;
+        let pools = self.stake_pools()?;
+
+        // Mint, reserve and token account of each pool, fetched together
+        let related: Vec<Pubkey> = pools
+            .iter()
+            .flat_map(|(_, pool)| {
+                [
+                    pool.pool_mint,
+                    pool.reserve_stake,
+                    get_associated_token_address(owner, &pool.pool_mint, &pool.token_program_id),
+                ]
+            })
+            .collect();
+        let accounts = self.client.get_multiple_accounts(&related)?;
+
+        Ok(pools
+            .into_iter()
+            .zip(accounts.chunks(3))
+            .map(|((entry, pool), accounts)| {
+                let account = |index: usize| accounts.get(index).and_then(Option::as_ref);
+                let decimals = account(0)
+                    .and_then(|mint| mint_decimals(&mint.data))
+                    .unwrap_or(SOL_DECIMALS);
+                let reserve_available = account(1)
+                    .map(|reserve| {
+                        let rent_exempt_reserve =
+                            match bincode::deserialize::<StakeStateV2>(&reserve.data) {
+                                Ok(StakeStateV2::Initialized(meta)) => meta.rent_exempt_reserve,
+                                _ => reserve.lamports,
+                            };
+                        // The program keeps one lamport above the rent reserve
+                        reserve.lamports.saturating_sub(rent_exempt_reserve + 1)
+                    })
+                    .unwrap_or(0);
+                let holding = account(2)
+                    .and_then(|token_account| account_amount(&token_account.data))
+                    .unwrap_or(0);
+                PoolSummary {
+                    entry,
+                    pool,
+                    epoch,
+                    decimals,
+                    holding,
+                    reserve_available,
+                }
+            })
+            .collect())
+    }
+
+    /// SOL received per whole pool token of every pool, by pool mint. Unlike a summary this
+    /// does not depend on the wallet.
+    pub fn rates(&self) -> Result<HashMap<Pubkey, f64>, ServiceError> {
+        let pools = self.stake_pools()?;
+        let mints: Vec<Pubkey> = pools.iter().map(|(_, pool)| pool.pool_mint).collect();
+        let accounts = self.client.get_multiple_accounts(&mints)?;
+        Ok(pools
+            .into_iter()
+            .zip(accounts)
+            .map(|((_, pool), mint)| {
+                let decimals = mint
+                    .and_then(|mint| mint_decimals(&mint.data))
+                    .unwrap_or(SOL_DECIMALS);
+                (pool.pool_mint, sol_per_token(&pool, decimals))
+            })
+            .collect())
+    }
+
+    // Every pool on offer whose account could be loaded
+    fn stake_pools(&self) -> Result<Vec<(PoolEntry, StakePool)>, ServiceError> {
+        let entries = self.pools()?;
+        let addresses: Vec<Pubkey> = entries.iter().map(|entry| entry.address).collect();
+        Ok(entries
+            .into_iter()
+            .zip(self.client.get_multiple_accounts(&addresses)?)
+            .filter_map(|(entry, account)| {
+                let account = account.filter(|account| account.owner == STAKE_POOL_PROGRAM_ID)?;
+                Some((entry, parse_stake_pool(&account.data)?))
+            })
+            .collect())
+    }
+
+    /// What `action` will do, after checking the pool accepts it, followed by the instructions
+    /// that will be signed
+    pub fn preview(
+        &self,
+        account: &Account,
+        summary: &PoolSummary,
+        action: &PoolAction,
+    ) -> Result<Vec<String>, ServiceError> {
+        let plan = self.plan(account, summary, action)?;
+        Ok(plan
+            .consequences
+            .into_iter()
+            .chain(describe_instructions(&plan.instructions))
+            .collect())
+    }
+
+    pub fn apply(
+        &self,
+        account: &Account,
+        summary: &PoolSummary,
+        action: &PoolAction,
+    ) -> Result<Signature, ServiceError> {
+        let plan = self.plan(account, summary, action)?;
+        TransactionService::new(self.client.clone())
+            .send_instructions(&plan.instructions, &account.account_keypair()?)
+    }
+
+    fn plan(
+        &self,
+        account: &Account,
+        summary: &PoolSummary,
+        action: &PoolAction,
+    ) -> Result<PoolPlan, ServiceError> {
+        if !summary.up_to_date() {
+            return Err(ServiceError::InvalidStake(format!(
+                "{} has not been updated for epoch {} yet, try again shortly",
+                summary.entry.name, summary.epoch
+            )));
+        }
+        let owner = account.pubkey()?;
+        match action {
+            PoolAction::Deposit { amount } => plan_deposit(&owner, summary, amount),
+            PoolAction::Withdraw { amount } => {
+                let pool_tokens = ui_amount_to_base_units(amount, summary.decimals)?;
+                check_withdrawal(summary, pool_tokens)?;
+                let fee = summary.pool.sol_withdrawal_fee.apply(pool_tokens);
+                let lamports = summary.pool.lamports_for(pool_tokens - fee);
+                if summary.instant_withdrawal(lamports) {
+                    Ok(plan_withdraw_sol(&owner, summary, pool_tokens))
+                } else {
+                    self.plan_withdraw_stake(&owner, summary, pool_tokens)
+                }
+            }
+        }
+    }
+
+    // Without enough SOL in the reserve the pool pays out in stake, which is deactivated straight away
+    fn plan_withdraw_stake(
+        &self,
+        owner: &Pubkey,
+        summary: &PoolSummary,
+        pool_tokens: u64,
+    ) -> Result<PoolPlan, ServiceError> {
+        let pool = &summary.pool;
+        let fee = pool.stake_withdrawal_fee.apply(pool_tokens);
+        let lamports = pool.lamports_for(pool_tokens - fee);
+
+        let validators = parse_validator_list(&self.client.get_account(&pool.validator_list)?.data)
+            .ok_or_else(|| ServiceError::AccountNotFound(pool.validator_list.to_string()))?;
+        let source = withdrawal_source(pool, &validators, lamports);
+        if source.is_none() && validators.iter().any(|v| v.active_stake_lamports > 0) {
+            return Err(ServiceError::InvalidStake(format!(
+                "No stake account in {} is large enough for this withdrawal, try a smaller amount",
+                summary.entry.name
+            )));
+        }
+
+        let (receiver, seed) = StakeService::new(self.client.clone()).next_stake_address(owner)?;
+        let rent = self
+            .client
+            .get_minimum_balance_for_rent_exemption(StakeStateV2::size_of())?;
+        let source_stake = match &source {
+            Some(vote_account) => {
+                let seed_suffix = validators
+                    .iter()
+                    .find(|validator| validator.vote_account == *vote_account)
+                    .map(|validator| validator.validator_seed_suffix)
+                    .unwrap_or_default();
+                validator_stake_address(&summary.entry.address, vote_account, seed_suffix)
+            }
+            None => pool.reserve_stake,
+        };
+        let token_account =
+            get_associated_token_address(owner, &pool.pool_mint, &pool.token_program_id);
+
+        let mut instructions = vec![
+            create_account_with_seed(
+                owner,
+                &receiver,
+                owner,
+                &seed,
+                rent,
+                StakeStateV2::size_of() as u64,
+                &STAKE_PROGRAM_ID,
+            ),
+            withdraw_stake(
+                &summary.entry.address,
+                pool,
+                &source_stake,
+                &receiver,
+                owner,
+                &token_account,
+                pool_tokens,
+            ),
+        ];
+        let mut consequences = vec![
+            format!("Burn {} at {}", summary.tokens(pool_tokens), summary.rate()),
+            withdrawal_restriction(summary),
+        ];
+        match &source {
+            Some(vote_account) => {
+                instructions.push(deactivate_stake(&receiver, owner));
+                consequences.push(format!(
+                    "{} SOL is split from the pool's stake with {} into the new stake account {}, after the {:.2}% withdrawal fee",
+                    sol(lamports),
+                    vote_account,
+                    receiver,
+                    pool.stake_withdrawal_fee.percent()
+                ));
+                consequences.push(format!(
+                    "The stake account is deactivated now and can be withdrawn from the Staking list after epoch {} ends",
+                    summary.epoch
+                ));
+            }
+            None => {
+                consequences.push(format!(
+                    "{} SOL is split from the pool reserve into the new stake account {}, after the {:.2}% withdrawal fee",
+                    sol(lamports),
+                    receiver,
+                    pool.stake_withdrawal_fee.percent()
+                ));
+                consequences.push(
+                    "The stake account is inactive and can be withdrawn from the Staking list straight away"
+                        .to_string(),
+                );
+            }
+        }
+        consequences.push(format!(
+            "This wallet pays {} SOL rent for the new account, returned when it is withdrawn",
+            sol(rent)
+        ));
+        Ok(PoolPlan {
+            instructions,
+            consequences,
+        })
+    }
+}
+
+fn sol_per_token(pool: &StakePool, decimals: u8) -> f64 {
+    if pool.pool_token_supply == 0 {
+        return 1.0;
+    }
+    let lamports = pool.total_lamports as f64 / 10f64.powi(SOL_DECIMALS as i32);
+    let tokens = pool.pool_token_supply as f64 / 10f64.powi(decimals as i32);
+    lamports / tokens
+}
+
+fn plan_deposit(
+    owner: &Pubkey,
+    summary: &PoolSummary,
+    amount: &str,
+) -> Result<PoolPlan, ServiceError> {
+    let pool = &summary.pool;
+    if pool.sol_deposit_authority.is_some() {
+        return Err(ServiceError::InvalidStake(format!(
+            "{} only accepts SOL deposits through its own deposit authority",
+            summary.entry.name
+        )));
+    }
+    let lamports = ui_amount_to_base_units(amount, SOL_DECIMALS)?;
+    let pool_tokens = pool.pool_tokens_for(lamports);
+    let fee = pool.sol_deposit_fee.apply(pool_tokens);
+    // The wallet names itself as referrer, so the referral share of the fee comes back to it
+    let referral = fee * pool.sol_referral_fee as u64 / 100;
+    let received = pool_tokens - fee + referral;
+
+    let token_account =
+        get_associated_token_address(owner, &pool.pool_mint, &pool.token_program_id);
+    let instructions = vec![
+        create_associated_token_account_idempotent(
+            owner,
+            owner,
+            &pool.pool_mint,
+            &pool.token_program_id,
+        ),
+        deposit_sol(
+            &summary.entry.address,
+            pool,
+            owner,
+            &token_account,
+            lamports,
+        ),
+    ];
+    let consequences = vec![
+        format!(
+            "Deposit {} SOL into {} at {}",
+            sol(lamports),
+            summary.entry.name,
+            summary.rate()
+        ),
+        format!(
+            "Receive about {} after the {:.2}% deposit fee",
+            summary.tokens(received),
+            pool.sol_deposit_fee.percent()
+        ),
+        format!(
+            "The pool keeps {:.2}% of staking rewards each epoch",
+            pool.epoch_fee.percent()
+        ),
+        withdrawal_outlook(summary),
+    ];
+    Ok(PoolPlan {
+        instructions,
+        consequences,
+    })
+}
+
+fn plan_withdraw_sol(owner: &Pubkey, summary: &PoolSummary, pool_tokens: u64) -> PoolPlan {
+    let pool = &summary.pool;
+    let fee = pool.sol_withdrawal_fee.apply(pool_tokens);
+    let lamports = pool.lamports_for(pool_tokens - fee);
+    let token_account =
+        get_associated_token_address(owner, &pool.pool_mint, &pool.token_program_id);
+
+    PoolPlan {
+        instructions: vec![withdraw_sol(
+            &summary.entry.address,
+            pool,
+            owner,
+            &token_account,
+            pool_tokens,
+        )],
+        consequences: vec![
+            format!("Burn {} at {}", summary.tokens(pool_tokens), summary.rate()),
+            format!(
+                "Instant withdrawal: {} SOL arrives from the pool reserve after the {:.2}% withdrawal fee",
+                sol(lamports),
+                pool.sol_withdrawal_fee.percent()
+            ),
+        ],
+    }
+}
+
+fn check_withdrawal(summary: &PoolSummary, pool_tokens: u64) -> Result<(), ServiceError> {
+    if pool_tokens == 0 || pool_tokens > summary.holding {
+        return Err(ServiceError::InvalidStake(format!(
+            "This wallet holds {}",
+            summary.tokens(summary.holding)
+        )));
+    }
+    Ok(())
+}
+
+/// Vote account of the pool stake to split a delayed withdrawal from, `None` for the reserve
+fn withdrawal_source(
+    pool: &StakePool,
+    validators: &[ValidatorStakeInfo],
+    lamports: u64,
+) -> Option<Pubkey> {
+    let large_enough = |validator: &&ValidatorStakeInfo| {
+        validator.active && validator.active_stake_lamports > lamports
+    };
+    // The program insists on the preferred validator while it has enough stake
+    if let Some(preferred) = pool.preferred_withdraw_validator {
+        if let Some(validator) = validators
+            .iter()
+            .filter(large_enough)
+            .find(|validator| validator.vote_account == preferred)
+        {
+            return Some(validator.vote_account);
+        }
+    }
+    validators
+        .iter()
+        .filter(large_enough)
+        .max_by_key(|validator| validator.active_stake_lamports)
+        .map(|validator| validator.vote_account)
+}
+
+fn withdrawal_restriction(summary: &PoolSummary) -> String {
+    if summary.pool.sol_withdraw_authority.is_some() {
+        format!(
+            "Delayed withdrawal: {} does not allow instant SOL withdrawals",
+            summary.entry.name
+        )
+    } else {
+        format!(
+            "Delayed withdrawal: the pool reserve only holds {} SOL for instant withdrawals",
+            sol(summary.reserve_available)
+        )
+    }
+}
+
+fn withdrawal_outlook(summary: &PoolSummary) -> String {
+    if summary.pool.sol_withdraw_authority.is_some() {
+        format!(
+            "Withdrawals from {} arrive as a stake account that unlocks after the epoch ends",
+            summary.entry.name
+        )
+    } else {
+        format!(
+            "Withdrawals are instant while the reserve can cover them (currently {} SOL), otherwise they arrive as a stake account that unlocks after the epoch ends",
+            sol(summary.reserve_available)
+        )
+    }
+}
+
+fn sol(lamports: u64) -> String {
+    base_units_to_ui_amount(lamports, SOL_DECIMALS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database_connection;
+    use crate::programs::stake_pool::tests::{stake_pool, stake_pool_data, validator_list_data};
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde_json::json;
+    use solana_rpc_client::rpc_client::Mocks;
+    use solana_rpc_client_api::request::RpcRequest;
+
+    fn setup_test_db() -> Arc<Mutex<Connection>> {
+        let conn = Arc::new(Mutex::new(database_connection().unwrap()));
+        conn.lock()
+            .unwrap()
+            .execute(
+                "CREATE TABLE stake_pools (
+                address TEXT PRIMARY KEY,
+                name TEXT NOT NULL
+            )",
+                [],
+            )
+            .unwrap();
+        conn
+    }
+
+    fn ui_account(data: Vec<u8>, owner: &Pubkey) -> serde_json::Value {
+        json!({
+            "data": [STANDARD.encode(&data), "base64"],
+            "executable": false,
+            "lamports": 5_000_000,
+            "owner": owner.to_string(),
+            "rentEpoch": 0,
+            "space": data.len(),
+        })
+    }
+
+    fn mock_service(conn: Arc<Mutex<Connection>>, mocks: Mocks) -> StakePoolService {
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        StakePoolService::new(conn, Arc::new(client))
+    }
+
+    fn wallet_account(pubkey: &Pubkey) -> Account {
+        Account {
+            id: Some(1),
+            name: "Account 1".to_string(),
+            seed: String::new(),
+            pubkey: pubkey.to_string(),
+            passphrase: String::new(),
+            balance: None,
+        }
+    }
+
+    fn summary(holding: u64, reserve_available: u64) -> PoolSummary {
+        PoolSummary {
+            entry: PoolEntry {
+                name: "JitoSOL".to_string(),
+                address: KNOWN_POOLS[0].1,
+                custom: false,
+            },
+            pool: stake_pool(),
+            epoch: 600,
+            decimals: 9,
+            holding,
+            reserve_available,
+        }
+    }
+
+    #[test]
+    fn test_add_and_remove_pools() {
+        let conn = setup_test_db();
+        let address = Pubkey::new_unique();
+        let mut mocks = Mocks::default();
+        mocks.insert(
+            RpcRequest::GetAccountInfo,
+            json!({
+                "context": { "slot": 1 },
+                "value": ui_account(stake_pool_data(&stake_pool()), &STAKE_POOL_PROGRAM_ID),
+            }),
+        );
+        let service = mock_service(conn.clone(), mocks);
+        service.add_pool("Test pool", &address).unwrap();
+
+        let pools = service.pools().unwrap();
+        assert_eq!(pools.len(), KNOWN_POOLS.len() + 1);
+        assert_eq!(
+            pools.last(),
+            Some(&PoolEntry {
+                name: "Test pool".to_string(),
+                address,
+                custom: true,
+            })
+        );
+
+        // The mock now reports no account at the address
+        assert!(service.add_pool("Missing", &Pubkey::new_unique()).is_err());
+
+        service.remove_pool(&address).unwrap();
+        assert_eq!(service.pools().unwrap().len(), KNOWN_POOLS.len());
+    }
+
+    #[test]
+    fn test_summaries() {
+        let pool = stake_pool();
+        let mut mocks = Mocks::default();
+        mocks.insert(
+            RpcRequest::GetMultipleAccounts,
+            json!({
+                "context": { "slot": 1 },
+                "value": [ui_account(stake_pool_data(&pool), &STAKE_POOL_PROGRAM_ID), null],
+            }),
+        );
+        let service = mock_service(setup_test_db(), mocks);
+
+        let summaries = service.summaries(&Pubkey::new_unique()).unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].entry.name, "JitoSOL");
+        assert_eq!(summaries[0].pool, pool);
+        assert_eq!(summaries[0].holding, 0);
+        assert!((summaries[0].sol_per_token() - 1.1).abs() < 1e-9);
+        // The mock cluster is still in epoch 1
+        assert!(summaries[0].up_to_date());
+    }
+
+    #[test]
+    fn test_rates() {
+        let pool = stake_pool();
+        let mut mocks = Mocks::default();
+        mocks.insert(
+            RpcRequest::GetMultipleAccounts,
+            json!({
+                "context": { "slot": 1 },
+                "value": [null, ui_account(stake_pool_data(&pool), &STAKE_POOL_PROGRAM_ID)],
+            }),
+        );
+        let service = mock_service(setup_test_db(), mocks);
+
+        // Priced without asking about any wallet
+        let rates = service.rates().unwrap();
+        assert_eq!(rates.len(), 1);
+        assert!((rates[&pool.pool_mint] - 1.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_plan_deposit() {
+        let owner = Pubkey::new_unique();
+        let plan = plan_deposit(&owner, &summary(0, 0), "1.1").unwrap();
+
+        assert_eq!(plan.instructions.len(), 2);
+        assert_eq!(plan.instructions[1].program_id, STAKE_POOL_PROGRAM_ID);
+        assert!(plan.consequences[0].contains("1 JitoSOL = 1.100000 SOL"));
+        // 1 JitoSOL less the 0.1% fee, half of which comes back as the referral share
+        assert!(plan.consequences[1].contains("0.9995 JitoSOL"));
+
+        let mut restricted = summary(0, 0);
+        restricted.pool.sol_deposit_authority = Some(Pubkey::new_unique());
+        assert!(plan_deposit(&owner, &restricted, "1").is_err());
+    }
+
+    #[test]
+    fn test_plan_withdrawals() {
+        let owner = Pubkey::new_unique();
+        let account = wallet_account(&owner);
+        let vote_account = Pubkey::new_unique();
+        let mut mocks = Mocks::default();
+        mocks.insert(
+            RpcRequest::GetAccountInfo,
+            json!({
+                "context": { "slot": 1 },
+                "value": ui_account(
+                    validator_list_data(&[ValidatorStakeInfo {
+                        active_stake_lamports: 50_000_000_000,
+                        validator_seed_suffix: 0,
+                        active: true,
+                        vote_account,
+                    }]),
+                    &STAKE_POOL_PROGRAM_ID,
+                ),
+            }),
+        );
+        let service = mock_service(setup_test_db(), mocks);
+        let summary = summary(2_000_000_000, 1_000_000_000);
+
+        let withdraw = |amount: &str| PoolAction::Withdraw {
+            amount: amount.to_string(),
+        };
+        let instant = service.plan(&account, &summary, &withdraw("0.5")).unwrap();
+        assert_eq!(instant.instructions.len(), 1);
+        assert!(instant.consequences[1].starts_with("Instant withdrawal"));
+
+        // More than the reserve can pay out arrives as deactivating stake
+        let delayed = service.plan(&account, &summary, &withdraw("1.5")).unwrap();
+        assert_eq!(delayed.instructions.len(), 3);
+        assert_eq!(
+            delayed.instructions[1].accounts[3].pubkey,
+            validator_stake_address(&summary.entry.address, &vote_account, 0)
+        );
+        assert!(delayed.consequences[1].starts_with("Delayed withdrawal"));
+
+        assert!(service.plan(&account, &summary, &withdraw("3")).is_err());
+
+        let mut stale = summary.clone();
+        stale.epoch += 1;
+        assert!(service.plan(&account, &stale, &withdraw("0.5")).is_err());
+    }
+
+    #[test]
+    fn test_withdrawal_source() {
+        let validator = |stake: u64| ValidatorStakeInfo {
+            active_stake_lamports: stake,
+            validator_seed_suffix: 0,
+            active: true,
+            vote_account: Pubkey::new_unique(),
+        };
+        let validators = vec![validator(10), validator(30), validator(20)];
+        let mut pool = stake_pool();
+
+        assert_eq!(
+            withdrawal_source(&pool, &validators, 5),
+            Some(validators[1].vote_account)
+        );
+        pool.preferred_withdraw_validator = Some(validators[2].vote_account);
+        assert_eq!(
+            withdrawal_source(&pool, &validators, 5),
+            Some(validators[2].vote_account)
+        );
+        // Too large for the preferred validator, so the largest takes it
+        assert_eq!(
+            withdrawal_source(&pool, &validators, 25),
+            Some(validators[1].vote_account)
+        );
+        assert_eq!(withdrawal_source(&pool, &validators, 40), None);
+    }
+}
@@ -0,0 +1,1141 @@
+use crate::amount::{base_units_to_ui_amount, ui_amount_to_base_units, SOL_DECIMALS};
+use crate::database::account::Account;
+use crate::programs::decoder::describe_instructions;
+use crate::services::{errors::ServiceError, transaction_service::TransactionService};
+use crate::time::format_time;
+use chrono::Utc;
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::{
+    config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::account::Account as SolanaAccount;
+use solana_sdk::clock::DEFAULT_MS_PER_SLOT;
+use solana_sdk::epoch_info::EpochInfo;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_stake_interface::{
+    instruction::{self as stake_instruction, LockupArgs},
+    program::ID as STAKE_PROGRAM_ID,
+    stake_history::{StakeHistory, StakeHistoryEntry},
+    state::{Authorized, Delegation, Lockup, StakeAuthorize, StakeStateV2},
+};
+use solana_system_interface::instruction::transfer;
+use std::{collections::BTreeMap, sync::Arc};
+
+// Offsets of the authorities in a serialized stake account, after the state tag and rent reserve
+const STAKER_OFFSET: usize = 12;
+const WITHDRAWER_OFFSET: usize = 44;
+
+// Seeds tried per request when looking for an unused stake account address
+const SEED_BATCH: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActivationState {
+    Inactive,
+    Activating,
+    Active,
+    Deactivating,
+}
+
+impl ActivationState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivationState::Inactive => "Inactive",
+            ActivationState::Activating => "Activating",
+            ActivationState::Active => "Active",
+            ActivationState::Deactivating => "Deactivating",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StakeAccount {
+    pub address: Pubkey,
+    pub lamports: u64,
+    pub rent_exempt_reserve: u64,
+    pub authorized: Authorized,
+    pub lockup: Lockup,
+    pub delegation: Option<Delegation>,
+    pub state: ActivationState,
+    pub activation: StakeHistoryEntry,
+}
+
+impl StakeAccount {
+    /// Lamports that can be withdrawn now, ignoring any lockup
+    pub fn withdrawable(&self) -> u64 {
+        match self.state {
+            ActivationState::Inactive => self.lamports,
+            // Stake cooling down still counts as effective until it is done
+            _ => self.lamports.saturating_sub(
+                self.rent_exempt_reserve + self.activation.effective + self.activation.activating,
+            ),
+        }
+    }
+
+    pub fn lockup_in_force(&self, epoch: u64, unix_timestamp: i64) -> bool {
+        self.lockup.epoch > epoch || self.lockup.unix_timestamp > unix_timestamp
+    }
+}
+
+/// Where the cluster is in the current epoch, used to time warm-up and cool-down
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EpochTiming {
+    pub epoch: u64,
+    pub slot_index: u64,
+    pub slots_in_epoch: u64,
+}
+
+impl EpochTiming {
+    /// Estimated seconds until the next epoch boundary, when stake changes take effect
+    pub fn seconds_remaining(&self) -> u64 {
+        self.slots_in_epoch.saturating_sub(self.slot_index) * DEFAULT_MS_PER_SLOT / 1000
+    }
+}
+
+impl From<EpochInfo> for EpochTiming {
+    fn from(info: EpochInfo) -> Self {
+        Self {
+            epoch: info.epoch,
+            slot_index: info.slot_index,
+            slots_in_epoch: info.slots_in_epoch,
+        }
+    }
+}
+
+/// A change to an existing stake account, previewed before it is sent
+#[derive(Debug, Clone, PartialEq)]
+pub enum StakeChange {
+    Split {
+        amount: String,
+    },
+    Merge {
+        source: Pubkey,
+    },
+    Authorize {
+        role: StakeAuthorize,
+        new_authority: Pubkey,
+    },
+    SetLockup {
+        unix_timestamp: Option<i64>,
+        epoch: Option<u64>,
+        custodian: Option<Pubkey>,
+    },
+}
+
+// Instructions for a change along with what it means for the wallet
+struct ChangePlan {
+    instructions: Vec<Instruction>,
+    consequences: Vec<String>,
+}
+
+// How a stake account can take part in a merge, following the stake program rules
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MergeKind {
+    Inactive,
+    ActivationEpoch,
+    FullyActive,
+}
+
+pub struct StakeService {
+    client: Arc<RpcClient>,
+}
+
+impl StakeService {
+    pub fn new(client: Arc<RpcClient>) -> Self {
+        Self { client }
+    }
+
+    pub fn epoch_timing(&self) -> Result<EpochTiming, ServiceError> {
+        Ok(self.client.get_epoch_info()?.into())
+    }
+
+    /// Stake accounts where `owner` is the staker or the withdrawer
+    pub fn discover(&self, owner: &Pubkey, epoch: u64) -> Result<Vec<StakeAccount>, ServiceError> {
+        let history = self.stake_history()?;
+        let mut accounts = BTreeMap::new();
+
+        for offset in [STAKER_OFFSET, WITHDRAWER_OFFSET] {
+            let config = RpcProgramAccountsConfig {
+                filters: Some(authority_filters(owner, offset)),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            };
+            for (address, account) in self
+                .client
+                .get_program_accounts_with_config(&STAKE_PROGRAM_ID, config)?
+            {
+                if let Some(stake_account) =
+                    parse_stake_account(&address, &account, epoch, &history)
+                {
+                    accounts.insert(address, stake_account);
+                }
+            }
+        }
+        Ok(accounts.into_values().collect())
+    }
+
+    pub fn get_stake_account(
+        &self,
+        address: &Pubkey,
+        epoch: u64,
+    ) -> Result<StakeAccount, ServiceError> {
+        let account = self.client.get_account(address)?;
+        parse_stake_account(address, &account, epoch, &self.stake_history()?)
+            .ok_or_else(|| ServiceError::AccountNotFound(address.to_string()))
+    }
+
+    /// Fund a new stake account owned by `account`, optionally delegating it straight away
+    pub fn create(
+        &self,
+        account: &Account,
+        amount: &str,
+        vote_account: Option<&Pubkey>,
+    ) -> Result<(Pubkey, Signature), ServiceError> {
+        let owner = account.pubkey()?;
+        let lamports = ui_amount_to_base_units(amount, SOL_DECIMALS)?;
+        let minimum = self.minimum_stake_balance()?;
+        if lamports < minimum {
+            return Err(ServiceError::InvalidStake(format!(
+                "A stake account needs at least {} lamports",
+                minimum
+            )));
+        }
+
+        let (stake_address, seed) = self.next_stake_address(&owner)?;
+        let mut instructions = stake_instruction::create_account_with_seed(
+            &owner,
+            &stake_address,
+            &owner,
+            &seed,
+            &Authorized::auto(&owner),
+            &Lockup::default(),
+            lamports,
+        );
+        if let Some(vote_account) = vote_account {
+            instructions.push(stake_instruction::delegate_stake(
+                &stake_address,
+                &owner,
+                vote_account,
+            ));
+        }
+        let signature = TransactionService::new(self.client.clone())
+            .send_instructions(&instructions, &account.account_keypair()?)?;
+        Ok((stake_address, signature))
+    }
+
+    pub fn delegate(
+        &self,
+        account: &Account,
+        stake: &StakeAccount,
+        vote_account: &Pubkey,
+    ) -> Result<Signature, ServiceError> {
+        let staker = require_staker(account, stake)?;
+        let instruction = stake_instruction::delegate_stake(&stake.address, &staker, vote_account);
+        self.send(account, &[instruction])
+    }
+
+    pub fn deactivate(
+        &self,
+        account: &Account,
+        stake: &StakeAccount,
+    ) -> Result<Signature, ServiceError> {
+        let staker = require_staker(account, stake)?;
+        let instruction = stake_instruction::deactivate_stake(&stake.address, &staker);
+        self.send(account, &[instruction])
+    }
+
+    /// Withdraw to the signing account; `None` withdraws everything that is withdrawable
+    pub fn withdraw(
+        &self,
+        account: &Account,
+        stake: &StakeAccount,
+        amount: Option<&str>,
+    ) -> Result<Signature, ServiceError> {
+        let withdrawer = require_withdrawer(account, stake)?;
+        let epoch = self.client.get_epoch_info()?.epoch;
+        if stake.lockup_in_force(epoch, Utc::now().timestamp()) {
+            return Err(ServiceError::InvalidStake(format!(
+                "{} is locked up until epoch {} or {}",
+                stake.address, stake.lockup.epoch, stake.lockup.unix_timestamp
+            )));
+        }
+        let lamports = match amount {
+            Some(amount) => ui_amount_to_base_units(amount, SOL_DECIMALS)?,
+            None => stake.withdrawable(),
+        };
+        if lamports == 0 || lamports > stake.withdrawable() {
+            return Err(ServiceError::InvalidStake(
+                "Only inactive stake and excess balance can be withdrawn".to_string(),
+            ));
+        }
+
+        let instruction =
+            stake_instruction::withdraw(&stake.address, &withdrawer, &withdrawer, lamports, None);
+        self.send(account, &[instruction])
+    }
+
+    /// What `change` will do, after checking that `account` can make it, followed by the
+    /// instructions that will be signed
+    pub fn preview_change(
+        &self,
+        account: &Account,
+        stake: &StakeAccount,
+        change: &StakeChange,
+    ) -> Result<Vec<String>, ServiceError> {
+        let plan = self.plan_change(account, stake, change)?;
+        Ok(plan
+            .consequences
+            .into_iter()
+            .chain(describe_instructions(&plan.instructions))
+            .collect())
+    }
+
+    pub fn apply_change(
+        &self,
+        account: &Account,
+        stake: &StakeAccount,
+        change: &StakeChange,
+    ) -> Result<Signature, ServiceError> {
+        let plan = self.plan_change(account, stake, change)?;
+        self.send(account, &plan.instructions)
+    }
+
+    fn plan_change(
+        &self,
+        account: &Account,
+        stake: &StakeAccount,
+        change: &StakeChange,
+    ) -> Result<ChangePlan, ServiceError> {
+        let epoch = self.client.get_epoch_info()?.epoch;
+        let now = Utc::now().timestamp();
+        match change {
+            StakeChange::Split { amount } => self.plan_split(account, stake, amount),
+            StakeChange::Merge { source } => {
+                let source = self.get_stake_account(source, epoch)?;
+                plan_merge(account, stake, &source, epoch, now)
+            }
+            StakeChange::Authorize {
+                role,
+                new_authority,
+            } => plan_authorize(account, stake, *role, new_authority, epoch, now),
+            StakeChange::SetLockup {
+                unix_timestamp,
+                epoch: lockup_epoch,
+                custodian,
+            } => plan_set_lockup(
+                account,
+                stake,
+                &LockupArgs {
+                    unix_timestamp: *unix_timestamp,
+                    epoch: *lockup_epoch,
+                    custodian: *custodian,
+                },
+                epoch,
+                now,
+            ),
+        }
+    }
+
+    // The new account is funded with rent first, as the stake program requires of split destinations
+    fn plan_split(
+        &self,
+        account: &Account,
+        stake: &StakeAccount,
+        amount: &str,
+    ) -> Result<ChangePlan, ServiceError> {
+        let staker = require_staker(account, stake)?;
+        let lamports = ui_amount_to_base_units(amount, SOL_DECIMALS)?;
+        check_split(stake, lamports, self.client.get_stake_minimum_delegation()?)?;
+
+        let (split_address, seed) = self.next_stake_address(&staker)?;
+        let rent = stake.rent_exempt_reserve;
+        let mut instructions = vec![transfer(&staker, &split_address, rent)];
+        instructions.extend(stake_instruction::split_with_seed(
+            &stake.address,
+            &staker,
+            lamports,
+            &split_address,
+            &staker,
+            &seed,
+        ));
+
+        let mut consequences = vec![
+            format!(
+                "{} SOL moves from {} into the new stake account {}",
+                sol(lamports),
+                stake.address,
+                split_address
+            ),
+            format!(
+                "This wallet pays {} SOL rent for the new account",
+                sol(rent)
+            ),
+            "The new account keeps the same delegation, authorities and lockup".to_string(),
+        ];
+        if lamports == stake.lamports {
+            consequences.push(format!("{} is emptied and closed", stake.address));
+        } else {
+            consequences.push(format!(
+                "{} keeps {} SOL",
+                stake.address,
+                sol(stake.lamports - lamports)
+            ));
+        }
+        Ok(ChangePlan {
+            instructions,
+            consequences,
+        })
+    }
+
+    fn send(
+        &self,
+        account: &Account,
+        instructions: &[Instruction],
+    ) -> Result<Signature, ServiceError> {
+        TransactionService::new(self.client.clone())
+            .send_instructions(instructions, &account.account_keypair()?)
+    }
+
+    fn stake_history(&self) -> Result<StakeHistory, ServiceError> {
+        let account = self
+            .client
+            .get_account(&solana_sdk::sysvar::stake_history::ID)?;
+        bincode::deserialize(&account.data).map_err(|e| ServiceError::Other(e))
+    }
+
+    fn minimum_stake_balance(&self) -> Result<u64, ServiceError> {
+        let rent = self
+            .client
+            .get_minimum_balance_for_rent_exemption(StakeStateV2::size_of())?;
+        Ok(rent + self.client.get_stake_minimum_delegation()?)
+    }
+
+    // Stake accounts are derived from the owner with a numbered seed, so no extra keypair is stored
+    pub fn next_stake_address(&self, owner: &Pubkey) -> Result<(Pubkey, String), ServiceError> {
+        for batch in 0.. {
+            let candidates = (batch * SEED_BATCH..(batch + 1) * SEED_BATCH)
+                .map(|index| {
+                    let seed = format!("stake:{}", index);
+                    let address = Pubkey::create_with_seed(owner, &seed, &STAKE_PROGRAM_ID)
+                        .map_err(|e| ServiceError::Other(Box::new(e)))?;
+                    Ok((address, seed))
+                })
+                .collect::<Result<Vec<_>, ServiceError>>()?;
+            let addresses: Vec<Pubkey> = candidates.iter().map(|(address, _)| *address).collect();
+            let accounts = self.client.get_multiple_accounts(&addresses)?;
+
+            if let Some(index) = accounts.iter().position(Option::is_none) {
+                return Ok(candidates[index].clone());
+            }
+        }
+        unreachable!("the seed search only ends by returning")
+    }
+}
+
+fn authority_filters(owner: &Pubkey, offset: usize) -> Vec<RpcFilterType> {
+    vec![
+        RpcFilterType::DataSize(StakeStateV2::size_of() as u64),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, owner.as_ref())),
+    ]
+}
+
+fn require_staker(account: &Account, stake: &StakeAccount) -> Result<Pubkey, ServiceError> {
+    let pubkey = account.pubkey()?;
+    if stake.authorized.staker != pubkey {
+        return Err(ServiceError::NotASigner(pubkey.to_string()));
+    }
+    Ok(pubkey)
+}
+
+fn require_withdrawer(account: &Account, stake: &StakeAccount) -> Result<Pubkey, ServiceError> {
+    let pubkey = account.pubkey()?;
+    if stake.authorized.withdrawer != pubkey {
+        return Err(ServiceError::NotASigner(pubkey.to_string()));
+    }
+    Ok(pubkey)
+}
+
+/// Check that splitting `lamports` off `stake` leaves both parts valid
+pub fn check_split(
+    stake: &StakeAccount,
+    lamports: u64,
+    minimum_delegation: u64,
+) -> Result<(), ServiceError> {
+    if lamports == 0 || lamports > stake.lamports {
+        return Err(ServiceError::InvalidStake(format!(
+            "Split between 0 and {} SOL",
+            sol(stake.lamports)
+        )));
+    }
+    // Stake that is earning must stay above the minimum delegation on both sides
+    let minimum_stake = if stake.activation.effective > 0 {
+        minimum_delegation
+    } else {
+        0
+    };
+    if lamports < minimum_stake {
+        return Err(ServiceError::InvalidStake(format!(
+            "The new account needs at least {} SOL staked",
+            sol(minimum_stake)
+        )));
+    }
+    let remaining = stake.lamports - lamports;
+    if remaining > 0 && remaining < stake.rent_exempt_reserve + minimum_stake {
+        return Err(ServiceError::InvalidStake(format!(
+            "{} must keep at least {} SOL, or split everything",
+            stake.address,
+            sol(stake.rent_exempt_reserve + minimum_stake)
+        )));
+    }
+    Ok(())
+}
+
+/// Check that `source` can be merged into `destination`
+pub fn check_merge(
+    destination: &StakeAccount,
+    source: &StakeAccount,
+    epoch: u64,
+    unix_timestamp: i64,
+) -> Result<(), ServiceError> {
+    if destination.address == source.address {
+        return Err(ServiceError::InvalidStake(
+            "A stake account cannot be merged into itself".to_string(),
+        ));
+    }
+    if destination.authorized != source.authorized {
+        return Err(ServiceError::InvalidStake(
+            "Both accounts need the same staker and withdrawer".to_string(),
+        ));
+    }
+    let lockups_match = destination.lockup == source.lockup
+        || (!destination.lockup_in_force(epoch, unix_timestamp)
+            && !source.lockup_in_force(epoch, unix_timestamp));
+    if !lockups_match {
+        return Err(ServiceError::InvalidStake(
+            "Both accounts need the same lockup".to_string(),
+        ));
+    }
+
+    let same_validator = destination
+        .delegation
+        .map(|delegation| delegation.voter_pubkey)
+        == source.delegation.map(|delegation| delegation.voter_pubkey);
+    match (merge_kind(destination), merge_kind(source)) {
+        (Some(MergeKind::Inactive), Some(MergeKind::Inactive))
+        | (Some(MergeKind::Inactive), Some(MergeKind::ActivationEpoch))
+        | (Some(MergeKind::ActivationEpoch), Some(MergeKind::Inactive)) => Ok(()),
+        (Some(MergeKind::ActivationEpoch), Some(MergeKind::ActivationEpoch))
+        | (Some(MergeKind::FullyActive), Some(MergeKind::FullyActive)) => {
+            if same_validator {
+                Ok(())
+            } else {
+                Err(ServiceError::InvalidStake(
+                    "Both accounts need to be delegated to the same validator".to_string(),
+                ))
+            }
+        }
+        _ => Err(ServiceError::InvalidStake(
+            "Accounts that are warming up or cooling down can only be merged after the epoch boundary"
+                .to_string(),
+        )),
+    }
+}
+
+fn merge_kind(stake: &StakeAccount) -> Option<MergeKind> {
+    let status = &stake.activation;
+    match (status.effective, status.activating, status.deactivating) {
+        (0, 0, 0) => Some(MergeKind::Inactive),
+        (0, _, _) => Some(MergeKind::ActivationEpoch),
+        (_, 0, 0) => Some(MergeKind::FullyActive),
+        _ => None,
+    }
+}
+
+fn plan_merge(
+    account: &Account,
+    destination: &StakeAccount,
+    source: &StakeAccount,
+    epoch: u64,
+    unix_timestamp: i64,
+) -> Result<ChangePlan, ServiceError> {
+    let staker = require_staker(account, destination)?;
+    check_merge(destination, source, epoch, unix_timestamp)?;
+
+    let outcome = match (merge_kind(destination), &destination.delegation) {
+        (Some(MergeKind::Inactive), _) | (_, None) => {
+            format!("{} stays undelegated", destination.address)
+        }
+        (_, Some(delegation)) => format!(
+            "{} stays delegated to {}",
+            destination.address, delegation.voter_pubkey
+        ),
+    };
+    Ok(ChangePlan {
+        instructions: stake_instruction::merge(&destination.address, &source.address, &staker),
+        consequences: vec![
+            format!(
+                "All {} SOL in {} moves into {}",
+                sol(source.lamports),
+                source.address,
+                destination.address
+            ),
+            format!("{} is closed", source.address),
+            outcome,
+        ],
+    })
+}
+
+fn plan_authorize(
+    account: &Account,
+    stake: &StakeAccount,
+    role: StakeAuthorize,
+    new_authority: &Pubkey,
+    epoch: u64,
+    unix_timestamp: i64,
+) -> Result<ChangePlan, ServiceError> {
+    let owner = account.pubkey()?;
+    let authorized = &stake.authorized;
+    // The withdrawer may also reassign the staker
+    let (current, allowed) = match role {
+        StakeAuthorize::Staker => (
+            authorized.staker,
+            owner == authorized.staker || owner == authorized.withdrawer,
+        ),
+        StakeAuthorize::Withdrawer => (authorized.withdrawer, owner == authorized.withdrawer),
+    };
+    if !allowed {
+        return Err(ServiceError::NotASigner(owner.to_string()));
+    }
+    if current == *new_authority {
+        return Err(ServiceError::InvalidStake(format!(
+            "{} already holds that authority",
+            new_authority
+        )));
+    }
+
+    // While locked up, a new withdrawer also needs the custodian's signature
+    let locked = stake.lockup_in_force(epoch, unix_timestamp);
+    let custodian = match role {
+        StakeAuthorize::Withdrawer if locked => {
+            if stake.lockup.custodian != owner {
+                return Err(ServiceError::InvalidStake(format!(
+                    "{} is locked up, changing its withdrawer needs the custodian {}",
+                    stake.address, stake.lockup.custodian
+                )));
+            }
+            Some(&owner)
+        }
+        _ => None,
+    };
+    let instruction =
+        stake_instruction::authorize(&stake.address, &owner, new_authority, role, custodian);
+
+    let consequences = match role {
+        StakeAuthorize::Staker => {
+            let mut consequences = vec![format!(
+                "{} becomes the stake authority of {}",
+                new_authority, stake.address
+            )];
+            if owner == authorized.withdrawer {
+                consequences.push(
+                    "This wallet stays the withdrawer and can take the stake authority back"
+                        .to_string(),
+                );
+            } else if *new_authority != owner {
+                consequences.push(
+                    "This wallet will no longer be able to delegate, deactivate, split or merge it"
+                        .to_string(),
+                );
+            }
+            consequences
+        }
+        StakeAuthorize::Withdrawer => {
+            let mut consequences = vec![format!(
+                "{} becomes the withdraw authority of {}",
+                new_authority, stake.address
+            )];
+            if *new_authority != owner {
+                consequences.push(
+                    "This wallet will no longer be able to withdraw from it, change its authorities or set its lockup"
+                        .to_string(),
+                );
+                consequences.push("Only the new withdrawer can undo this".to_string());
+            }
+            consequences
+        }
+    };
+    Ok(ChangePlan {
+        instructions: vec![instruction],
+        consequences,
+    })
+}
+
+fn plan_set_lockup(
+    account: &Account,
+    stake: &StakeAccount,
+    lockup: &LockupArgs,
+    epoch: u64,
+    unix_timestamp: i64,
+) -> Result<ChangePlan, ServiceError> {
+    let owner = account.pubkey()?;
+    if lockup.unix_timestamp.is_none() && lockup.epoch.is_none() && lockup.custodian.is_none() {
+        return Err(ServiceError::InvalidStake(
+            "Set a date, an epoch or a custodian".to_string(),
+        ));
+    }
+    // The withdrawer sets a lockup, but only the custodian can change one in force
+    if stake.lockup_in_force(epoch, unix_timestamp) {
+        if stake.lockup.custodian != owner {
+            return Err(ServiceError::InvalidStake(format!(
+                "{} is locked up, only the custodian {} can change the lockup",
+                stake.address, stake.lockup.custodian
+            )));
+        }
+    } else if stake.authorized.withdrawer != owner {
+        return Err(ServiceError::NotASigner(owner.to_string()));
+    }
+
+    let new_lockup = Lockup {
+        unix_timestamp: lockup.unix_timestamp.unwrap_or(stake.lockup.unix_timestamp),
+        epoch: lockup.epoch.unwrap_or(stake.lockup.epoch),
+        custodian: lockup.custodian.unwrap_or(stake.lockup.custodian),
+    };
+    let until = Some(format_time(new_lockup.unix_timestamp))
+        .filter(|until| !until.is_empty())
+        .unwrap_or_else(|| new_lockup.unix_timestamp.to_string());
+    let mut consequences = vec![
+        format!(
+            "Withdrawing and changing the withdrawer are blocked until {} and epoch {} have both passed, unless the custodian signs",
+            until, new_lockup.epoch
+        ),
+        format!(
+            "While the lockup is in force only the custodian {} can change it",
+            new_lockup.custodian
+        ),
+        "Delegating, deactivating, splitting and merging stay possible".to_string(),
+    ];
+    if new_lockup.custodian != owner {
+        consequences.push("This wallet will not be able to shorten or lift the lockup".to_string());
+    }
+    Ok(ChangePlan {
+        instructions: vec![stake_instruction::set_lockup(
+            &stake.address,
+            lockup,
+            &owner,
+        )],
+        consequences,
+    })
+}
+
+fn sol(lamports: u64) -> String {
+    base_units_to_ui_amount(lamports, SOL_DECIMALS)
+}
+
+pub fn parse_stake_account(
+    address: &Pubkey,
+    account: &SolanaAccount,
+    epoch: u64,
+    history: &StakeHistory,
+) -> Option<StakeAccount> {
+    if account.owner != STAKE_PROGRAM_ID {
+        return None;
+    }
+
+    let (meta, delegation) = match bincode::deserialize::<StakeStateV2>(&account.data).ok()? {
+        StakeStateV2::Initialized(meta) => (meta, None),
+        StakeStateV2::Stake(meta, stake, _) => (meta, Some(stake.delegation)),
+        StakeStateV2::Uninitialized | StakeStateV2::RewardsPool => return None,
+    };
+    let (state, activation) = match &delegation {
+        Some(delegation) => activation(delegation, epoch, history),
+        None => (ActivationState::Inactive, StakeHistoryEntry::default()),
+    };
+
+    Some(StakeAccount {
+        address: *address,
+        lamports: account.lamports,
+        rent_exempt_reserve: meta.rent_exempt_reserve,
+        authorized: meta.authorized,
+        lockup: meta.lockup,
+        delegation,
+        state,
+        activation,
+    })
+}
+
+fn activation(
+    delegation: &Delegation,
+    epoch: u64,
+    history: &StakeHistory,
+) -> (ActivationState, StakeHistoryEntry) {
+    // The reduced warm-up and cool-down rate is active on every current cluster
+    let status = delegation.stake_activating_and_deactivating(epoch, history, Some(0));
+    let state = if status.deactivating > 0 {
+        ActivationState::Deactivating
+    } else if status.activating > 0 {
+        ActivationState::Activating
+    } else if status.effective > 0 {
+        ActivationState::Active
+    } else {
+        ActivationState::Inactive
+    };
+    (state, status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_stake_interface::{stake_flags::StakeFlags, state::Meta, state::Stake};
+
+    const RENT: u64 = 2_282_880;
+
+    fn stake_account_data(authorized: Authorized, delegation: Option<Delegation>) -> SolanaAccount {
+        let meta = Meta {
+            rent_exempt_reserve: RENT,
+            authorized,
+            lockup: Lockup::default(),
+        };
+        let state = match delegation {
+            Some(delegation) => StakeStateV2::Stake(
+                meta,
+                Stake {
+                    delegation,
+                    credits_observed: 0,
+                },
+                StakeFlags::empty(),
+            ),
+            None => StakeStateV2::Initialized(meta),
+        };
+        let mut data = bincode::serialize(&state).unwrap();
+        data.resize(StakeStateV2::size_of(), 0);
+        SolanaAccount {
+            lamports: RENT + 5_000_000_000,
+            data,
+            owner: STAKE_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn delegation(activation_epoch: u64, deactivation_epoch: u64) -> Delegation {
+        Delegation {
+            voter_pubkey: Pubkey::new_unique(),
+            stake: 5_000_000_000,
+            activation_epoch,
+            deactivation_epoch,
+            ..Delegation::default()
+        }
+    }
+
+    fn wallet_account(pubkey: &Pubkey) -> Account {
+        Account {
+            id: Some(1),
+            name: "Account 1".to_string(),
+            seed: String::new(),
+            pubkey: pubkey.to_string(),
+            passphrase: String::new(),
+            balance: None,
+        }
+    }
+
+    fn stake_at(
+        authorized: Authorized,
+        delegation: Option<Delegation>,
+        epoch: u64,
+    ) -> StakeAccount {
+        parse_stake_account(
+            &Pubkey::new_unique(),
+            &stake_account_data(authorized, delegation),
+            epoch,
+            &StakeHistory::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_authority_offsets() {
+        let authorized = Authorized {
+            staker: Pubkey::new_unique(),
+            withdrawer: Pubkey::new_unique(),
+        };
+        let account = stake_account_data(authorized, None);
+
+        assert_eq!(
+            &account.data[STAKER_OFFSET..STAKER_OFFSET + 32],
+            authorized.staker.as_ref()
+        );
+        assert_eq!(
+            &account.data[WITHDRAWER_OFFSET..WITHDRAWER_OFFSET + 32],
+            authorized.withdrawer.as_ref()
+        );
+        assert_eq!(
+            authority_filters(&authorized.staker, STAKER_OFFSET)[0],
+            RpcFilterType::DataSize(200)
+        );
+    }
+
+    #[test]
+    fn test_parse_stake_account_activation() {
+        let owner = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let history = StakeHistory::default();
+        let state_at = |account: &SolanaAccount, epoch| {
+            parse_stake_account(&address, account, epoch, &history)
+                .unwrap()
+                .state
+        };
+
+        let initialized = stake_account_data(Authorized::auto(&owner), None);
+        assert_eq!(state_at(&initialized, 10), ActivationState::Inactive);
+
+        let delegated =
+            stake_account_data(Authorized::auto(&owner), Some(delegation(10, u64::MAX)));
+        assert_eq!(state_at(&delegated, 10), ActivationState::Activating);
+        assert_eq!(state_at(&delegated, 11), ActivationState::Active);
+
+        let deactivated = stake_account_data(Authorized::auto(&owner), Some(delegation(10, 20)));
+        assert_eq!(state_at(&deactivated, 20), ActivationState::Deactivating);
+        assert_eq!(state_at(&deactivated, 21), ActivationState::Inactive);
+
+        let mut not_stake = initialized.clone();
+        not_stake.owner = Pubkey::new_unique();
+        assert!(parse_stake_account(&address, &not_stake, 10, &history).is_none());
+    }
+
+    #[test]
+    fn test_withdrawable() {
+        let owner = Pubkey::new_unique();
+        let history = StakeHistory::default();
+        let mut account =
+            stake_account_data(Authorized::auto(&owner), Some(delegation(10, u64::MAX)));
+        account.lamports += 1_000;
+
+        let active = parse_stake_account(&Pubkey::new_unique(), &account, 11, &history).unwrap();
+        assert_eq!(active.withdrawable(), 1_000);
+
+        let inactive = parse_stake_account(&Pubkey::new_unique(), &account, 9, &history).unwrap();
+        assert_eq!(inactive.withdrawable(), account.lamports);
+
+        let mut account = stake_account_data(Authorized::auto(&owner), Some(delegation(10, 20)));
+        account.lamports += 1_000;
+        let deactivating =
+            parse_stake_account(&Pubkey::new_unique(), &account, 20, &history).unwrap();
+        assert_eq!(deactivating.state, ActivationState::Deactivating);
+        assert_eq!(deactivating.withdrawable(), 1_000);
+    }
+
+    #[test]
+    fn test_lockup_in_force() {
+        let owner = Pubkey::new_unique();
+        let mut stake = parse_stake_account(
+            &Pubkey::new_unique(),
+            &stake_account_data(Authorized::auto(&owner), None),
+            10,
+            &StakeHistory::default(),
+        )
+        .unwrap();
+        assert!(!stake.lockup_in_force(10, 1_700_000_000));
+
+        stake.lockup.epoch = 12;
+        assert!(stake.lockup_in_force(10, 1_700_000_000));
+        assert!(!stake.lockup_in_force(12, 1_700_000_000));
+    }
+
+    #[test]
+    fn test_epoch_timing() {
+        let timing = EpochTiming {
+            epoch: 500,
+            slot_index: 431_000,
+            slots_in_epoch: 432_000,
+        };
+        assert_eq!(timing.seconds_remaining(), 400);
+    }
+
+    #[test]
+    fn test_requires_authority() {
+        let account = wallet_account(&Pubkey::new_unique());
+        let other = Pubkey::new_unique();
+        let stake = stake_at(Authorized::auto(&other), None, 10);
+
+        assert!(matches!(
+            require_staker(&account, &stake),
+            Err(ServiceError::NotASigner(_))
+        ));
+        assert!(matches!(
+            require_withdrawer(&account, &stake),
+            Err(ServiceError::NotASigner(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_split() {
+        let owner = Pubkey::new_unique();
+        let minimum = 1_000_000_000;
+        let active = stake_at(Authorized::auto(&owner), Some(delegation(10, u64::MAX)), 11);
+        let total = active.lamports;
+
+        assert!(check_split(&active, 2_000_000_000, minimum).is_ok());
+        assert!(check_split(&active, total, minimum).is_ok());
+        assert!(check_split(&active, 0, minimum).is_err());
+        assert!(check_split(&active, total + 1, minimum).is_err());
+        // The new part must keep the minimum delegation
+        assert!(check_split(&active, minimum - 1, minimum).is_err());
+        // So must the part left behind
+        assert!(check_split(&active, total - RENT - minimum + 1, minimum).is_err());
+
+        let initialized = stake_at(Authorized::auto(&owner), None, 11);
+        assert!(check_split(&initialized, 1, minimum).is_ok());
+        assert!(check_split(&initialized, initialized.lamports - RENT + 1, minimum).is_err());
+    }
+
+    #[test]
+    fn test_check_merge() {
+        let owner = Pubkey::new_unique();
+        let authorized = Authorized::auto(&owner);
+        let now = 1_700_000_000;
+        let active = delegation(10, u64::MAX);
+        let mut other_validator = delegation(10, u64::MAX);
+        other_validator.voter_pubkey = Pubkey::new_unique();
+
+        let inactive = stake_at(authorized, None, 20);
+        let activating = stake_at(authorized, Some(delegation(20, u64::MAX)), 20);
+        let fully_active = stake_at(authorized, Some(active), 20);
+        let deactivating = stake_at(authorized, Some(delegation(10, 20)), 20);
+
+        assert!(check_merge(&inactive, &stake_at(authorized, None, 20), 20, now).is_ok());
+        assert!(check_merge(&activating, &inactive, 20, now).is_ok());
+        assert!(check_merge(
+            &fully_active,
+            &stake_at(authorized, Some(active), 20),
+            20,
+            now
+        )
+        .is_ok());
+        assert!(check_merge(&fully_active, &inactive, 20, now).is_err());
+        assert!(check_merge(&fully_active, &deactivating, 20, now).is_err());
+        assert!(check_merge(&fully_active, &fully_active, 20, now).is_err());
+        assert!(check_merge(
+            &fully_active,
+            &stake_at(authorized, Some(other_validator), 20),
+            20,
+            now
+        )
+        .is_err());
+        assert!(check_merge(
+            &inactive,
+            &stake_at(Authorized::auto(&Pubkey::new_unique()), None, 20),
+            20,
+            now
+        )
+        .is_err());
+
+        let mut locked = stake_at(authorized, None, 20);
+        locked.lockup.epoch = 30;
+        assert!(check_merge(&inactive, &locked, 20, now).is_err());
+        assert!(check_merge(&inactive, &locked, 30, now).is_ok());
+    }
+
+    #[test]
+    fn test_plan_authorize() {
+        let owner = Pubkey::new_unique();
+        let new_authority = Pubkey::new_unique();
+        let now = 1_700_000_000;
+        let stake = stake_at(Authorized::auto(&owner), None, 10);
+
+        let plan = plan_authorize(
+            &wallet_account(&owner),
+            &stake,
+            StakeAuthorize::Withdrawer,
+            &new_authority,
+            10,
+            now,
+        )
+        .unwrap();
+        assert_eq!(plan.instructions.len(), 1);
+        assert!(plan.consequences[0].contains(&new_authority.to_string()));
+        assert_eq!(plan.consequences.len(), 3);
+
+        // A staker that is not the withdrawer cannot hand over the withdraw authority
+        let staker = Pubkey::new_unique();
+        let split_roles = stake_at(
+            Authorized {
+                staker,
+                withdrawer: owner,
+            },
+            None,
+            10,
+        );
+        let result = plan_authorize(
+            &wallet_account(&staker),
+            &split_roles,
+            StakeAuthorize::Withdrawer,
+            &new_authority,
+            10,
+            now,
+        );
+        assert!(matches!(result, Err(ServiceError::NotASigner(_))));
+
+        // A lockup in force blocks a new withdrawer unless this wallet is the custodian
+        let mut locked = stake.clone();
+        locked.lockup.epoch = 20;
+        locked.lockup.custodian = Pubkey::new_unique();
+        let result = plan_authorize(
+            &wallet_account(&owner),
+            &locked,
+            StakeAuthorize::Withdrawer,
+            &new_authority,
+            10,
+            now,
+        );
+        assert!(matches!(result, Err(ServiceError::InvalidStake(_))));
+        assert!(plan_authorize(
+            &wallet_account(&owner),
+            &locked,
+            StakeAuthorize::Staker,
+            &new_authority,
+            10,
+            now
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_plan_set_lockup() {
+        let owner = Pubkey::new_unique();
+        let custodian = Pubkey::new_unique();
+        let now = 1_700_000_000;
+        let stake = stake_at(Authorized::auto(&owner), None, 10);
+        let lockup = LockupArgs {
+            unix_timestamp: None,
+            epoch: Some(50),
+            custodian: Some(custodian),
+        };
+
+        let plan = plan_set_lockup(&wallet_account(&owner), &stake, &lockup, 10, now).unwrap();
+        assert!(plan.consequences[0].contains("epoch 50"));
+        assert!(plan.consequences[1].contains(&custodian.to_string()));
+        assert_eq!(plan.consequences.len(), 4);
+
+        assert!(plan_set_lockup(
+            &wallet_account(&owner),
+            &stake,
+            &LockupArgs::default(),
+            10,
+            now
+        )
+        .is_err());
+
+        // Once in force, only the custodian can change it
+        let mut locked = stake.clone();
+        locked.lockup.epoch = 50;
+        locked.lockup.custodian = custodian;
+        assert!(plan_set_lockup(&wallet_account(&owner), &locked, &lockup, 10, now).is_err());
+        assert!(plan_set_lockup(&wallet_account(&custodian), &locked, &lockup, 10, now).is_ok());
+    }
+}
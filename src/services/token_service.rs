@@ -0,0 +1,373 @@
+use crate::amount::{base_units_to_ui_amount, SOL_DECIMALS};
+use crate::database::account::Account;
+use crate::programs::token::{
+    burn, close_account, is_token_program, mint_decimals, parse_account, revoke, TokenAccount,
+    TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID,
+};
+use crate::services::{errors::ServiceError, transaction_service::TransactionService};
+use serde_json::json;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::{
+    request::RpcRequest,
+    response::{Response, RpcKeyedAccount},
+};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+// Most accounts getMultipleAccounts returns in one request
+const ACCOUNTS_PER_REQUEST: usize = 100;
+// Revokes are tiny, so this many fit comfortably in one transaction
+const REVOKES_PER_TRANSACTION: usize = 20;
+// Each closed account takes a burn and a close instruction
+const CLOSES_PER_TRANSACTION: usize = 10;
+
+/// A token account found under one of the wallet's accounts
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedTokenAccount {
+    pub address: Pubkey,
+    pub program_id: Pubkey,
+    pub account: TokenAccount,
+}
+
+impl OwnedTokenAccount {
+    /// Whether anyone besides the owner has a say over this account, or it is frozen
+    pub fn has_approval(&self) -> bool {
+        self.account.delegate.is_some()
+            || self.account.close_authority.is_some()
+            || self.account.frozen
+    }
+}
+
+pub struct TokenService {
+    client: Arc<RpcClient>,
+}
+
+impl TokenService {
+    pub fn new(client: Arc<RpcClient>) -> Self {
+        Self { client }
+    }
+
+    /// Every Token and Token-2022 account owned by `owner`
+    pub fn token_accounts(&self, owner: &Pubkey) -> Result<Vec<OwnedTokenAccount>, ServiceError> {
+        let mut accounts = self.program_token_accounts(owner, &TOKEN_PROGRAM_ID)?;
+        accounts.extend(self.program_token_accounts(owner, &TOKEN_2022_PROGRAM_ID)?);
+        Ok(accounts)
+    }
+
+    /// Token accounts of `owner` with a delegate, a close authority or a frozen balance
+    pub fn approvals(&self, owner: &Pubkey) -> Result<Vec<OwnedTokenAccount>, ServiceError> {
+        Ok(self
+            .token_accounts(owner)?
+            .into_iter()
+            .filter(OwnedTokenAccount::has_approval)
+            .collect())
+    }
+
+    /// Decimals of each of `mints` that exists and belongs to a token program
+    pub fn mint_decimals(&self, mints: &[Pubkey]) -> Result<HashMap<Pubkey, u8>, ServiceError> {
+        let mut decimals = HashMap::new();
+        for batch in mints.chunks(ACCOUNTS_PER_REQUEST) {
+            let accounts = self.client.get_multiple_accounts(batch)?;
+            for (mint, account) in batch.iter().zip(accounts) {
+                if let Some(value) = account
+                    .filter(|account| is_token_program(&account.owner))
+                    .and_then(|account| mint_decimals(&account.data))
+                {
+                    decimals.insert(*mint, value);
+                }
+            }
+        }
+        Ok(decimals)
+    }
+
+    /// Revoke the delegates of `token_accounts`, batching as many as fit in each transaction
+    pub fn revoke(
+        &self,
+        account: &Account,
+        token_accounts: &[OwnedTokenAccount],
+    ) -> Result<Vec<Signature>, ServiceError> {
+        let keypair = account.account_keypair()?;
+        let instructions = revoke_instructions(&account.pubkey()?, token_accounts);
+        let transaction_service = TransactionService::new(self.client.clone());
+        instructions
+            .chunks(REVOKES_PER_TRANSACTION)
+            .map(|batch| transaction_service.send_instructions(batch, &keypair))
+            .collect()
+    }
+
+    /// What burning and closing `token_accounts` does, with the SOL each one refunds
+    pub fn preview_burn_and_close(
+        &self,
+        owner: &Pubkey,
+        token_accounts: &[OwnedTokenAccount],
+    ) -> Result<Vec<String>, ServiceError> {
+        let mut lines = vec![];
+        let mut total = 0;
+        for batch in token_accounts.chunks(ACCOUNTS_PER_REQUEST) {
+            let addresses: Vec<Pubkey> = batch
+                .iter()
+                .map(|token_account| token_account.address)
+                .collect();
+            let accounts = self.client.get_multiple_accounts(&addresses)?;
+            for (token_account, account) in batch.iter().zip(accounts) {
+                if let Some(reason) = cannot_close(owner, token_account) {
+                    lines.push(format!("{} stays open, {}", token_account.address, reason));
+                    continue;
+                }
+                let lamports = account.map_or(0, |account| account.lamports);
+                total += lamports;
+                lines.push(format!(
+                    "Burns {} base units of {} and closes {}, refunding {} SOL",
+                    token_account.account.amount,
+                    token_account.account.mint,
+                    token_account.address,
+                    base_units_to_ui_amount(lamports, SOL_DECIMALS)
+                ));
+            }
+        }
+        lines.push(format!(
+            "This wallet gets {} SOL back in total",
+            base_units_to_ui_amount(total, SOL_DECIMALS)
+        ));
+        Ok(lines)
+    }
+
+    /// Burn what `token_accounts` hold and close them for their rent, batching as many as fit
+    /// in each transaction
+    pub fn burn_and_close(
+        &self,
+        account: &Account,
+        token_accounts: &[OwnedTokenAccount],
+    ) -> Result<Vec<Signature>, ServiceError> {
+        let keypair = account.account_keypair()?;
+        let groups = burn_and_close_instructions(&account.pubkey()?, token_accounts);
+        let transaction_service = TransactionService::new(self.client.clone());
+        groups
+            .chunks(CLOSES_PER_TRANSACTION)
+            .map(|batch| transaction_service.send_instructions(&batch.concat(), &keypair))
+            .collect()
+    }
+
+    // Raw account data, which the client's own helper only offers parsed as JSON
+    fn program_token_accounts(
+        &self,
+        owner: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<Vec<OwnedTokenAccount>, ServiceError> {
+        let response: Response<Vec<RpcKeyedAccount>> = self.client.send(
+            RpcRequest::GetTokenAccountsByOwner,
+            json!([
+                owner.to_string(),
+                { "programId": program_id.to_string() },
+                { "encoding": "base64", "commitment": self.client.commitment().commitment },
+            ]),
+        )?;
+
+        Ok(response
+            .value
+            .into_iter()
+            .filter_map(|keyed| {
+                let data = keyed.account.data.decode()?;
+                Some(OwnedTokenAccount {
+                    address: Pubkey::from_str(&keyed.pubkey).ok()?,
+                    program_id: *program_id,
+                    account: parse_account(&data)?,
+                })
+            })
+            .collect())
+    }
+}
+
+/// Revoke instructions for the delegated accounts among `token_accounts` that `owner` controls
+pub fn revoke_instructions(
+    owner: &Pubkey,
+    token_accounts: &[OwnedTokenAccount],
+) -> Vec<Instruction> {
+    token_accounts
+        .iter()
+        .filter(|token_account| {
+            token_account.account.owner == *owner && token_account.account.delegate.is_some()
+        })
+        .map(|token_account| revoke(&token_account.program_id, &token_account.address, owner))
+        .collect()
+}
+
+/// Burn and close instructions for each of `token_accounts` that `owner` can close
+pub fn burn_and_close_instructions(
+    owner: &Pubkey,
+    token_accounts: &[OwnedTokenAccount],
+) -> Vec<Vec<Instruction>> {
+    token_accounts
+        .iter()
+        .filter(|token_account| cannot_close(owner, token_account).is_none())
+        .map(|token_account| {
+            let mut instructions = vec![];
+            if token_account.account.amount > 0 {
+                instructions.push(burn(
+                    &token_account.program_id,
+                    &token_account.address,
+                    &token_account.account.mint,
+                    owner,
+                    token_account.account.amount,
+                ));
+            }
+            instructions.push(close_account(
+                &token_account.program_id,
+                &token_account.address,
+                owner,
+                owner,
+            ));
+            instructions
+        })
+        .collect()
+}
+
+// Why `owner` cannot burn and close `token_account`, if anything stops it
+fn cannot_close(owner: &Pubkey, token_account: &OwnedTokenAccount) -> Option<String> {
+    let account = &token_account.account;
+    if account.owner != *owner {
+        return Some(format!("as {} owns it", account.owner));
+    }
+    if account.frozen {
+        return Some("as it is frozen".to_string());
+    }
+    match account.close_authority {
+        Some(authority) if authority != *owner => {
+            Some(format!("as {} holds its close authority", authority))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use solana_rpc_client::rpc_client::Mocks;
+
+    fn token_account_data(
+        mint: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+        delegate: Option<&Pubkey>,
+    ) -> Vec<u8> {
+        let mut data = vec![0u8; 165];
+        data[..32].copy_from_slice(mint.as_ref());
+        data[32..64].copy_from_slice(owner.as_ref());
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        if let Some(delegate) = delegate {
+            data[72] = 1;
+            data[76..108].copy_from_slice(delegate.as_ref());
+        }
+        data[108] = 1;
+        data
+    }
+
+    fn keyed_account(address: &Pubkey, data: &[u8]) -> serde_json::Value {
+        json!({
+            "pubkey": address.to_string(),
+            "account": {
+                "data": [STANDARD.encode(data), "base64"],
+                "executable": false,
+                "lamports": 2_039_280,
+                "owner": TOKEN_PROGRAM_ID.to_string(),
+                "rentEpoch": 0,
+                "space": data.len(),
+            },
+        })
+    }
+
+    #[test]
+    fn test_approvals() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let delegated = Pubkey::new_unique();
+        let plain = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let mut mocks = Mocks::default();
+        mocks.insert(
+            RpcRequest::GetTokenAccountsByOwner,
+            json!({
+                "context": {"slot": 1},
+                "value": [
+                    keyed_account(&delegated, &token_account_data(&mint, &owner, 10, Some(&delegate))),
+                    keyed_account(&plain, &token_account_data(&mint, &owner, 10, None)),
+                ],
+            }),
+        );
+        let service = TokenService::new(Arc::new(RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            mocks,
+        )));
+
+        let accounts = service
+            .program_token_accounts(&owner, &TOKEN_PROGRAM_ID)
+            .unwrap();
+        assert_eq!(accounts.len(), 2);
+        let approvals: Vec<_> = accounts
+            .into_iter()
+            .filter(OwnedTokenAccount::has_approval)
+            .collect();
+        assert_eq!(approvals.len(), 1);
+        assert_eq!(approvals[0].address, delegated);
+        assert_eq!(approvals[0].account.delegate, Some(delegate));
+    }
+
+    #[test]
+    fn test_revoke_instructions() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let token_account = |owner: &Pubkey, delegate: Option<&Pubkey>| OwnedTokenAccount {
+            address: Pubkey::new_unique(),
+            program_id: TOKEN_2022_PROGRAM_ID,
+            account: parse_account(&token_account_data(&mint, owner, 1, delegate)).unwrap(),
+        };
+        let accounts = vec![
+            token_account(&owner, Some(&delegate)),
+            token_account(&owner, None),
+            token_account(&Pubkey::new_unique(), Some(&delegate)),
+        ];
+
+        let instructions = revoke_instructions(&owner, &accounts);
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].program_id, TOKEN_2022_PROGRAM_ID);
+        assert_eq!(instructions[0].accounts[0].pubkey, accounts[0].address);
+        assert_eq!(instructions[0].accounts[1].pubkey, owner);
+    }
+
+    #[test]
+    fn test_burn_and_close_instructions() {
+        let owner = Pubkey::new_unique();
+        let token_account = |amount: u64, frozen: bool| OwnedTokenAccount {
+            address: Pubkey::new_unique(),
+            program_id: TOKEN_PROGRAM_ID,
+            account: TokenAccount {
+                mint: Pubkey::new_unique(),
+                owner,
+                amount,
+                delegate: None,
+                delegated_amount: 0,
+                close_authority: None,
+                frozen,
+            },
+        };
+        let accounts = vec![
+            token_account(1_000, false),
+            token_account(0, false),
+            token_account(5, true),
+        ];
+
+        let groups = burn_and_close_instructions(&owner, &accounts);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].len(), 2);
+        assert_eq!(groups[0][0].data[0], 8);
+        assert_eq!(&groups[0][0].data[1..], &1_000u64.to_le_bytes());
+        // An empty account only needs closing
+        assert_eq!(groups[1].len(), 1);
+        assert_eq!(groups[1][0].accounts[0].pubkey, accounts[1].address);
+        assert!(cannot_close(&owner, &accounts[2]).is_some());
+    }
+}
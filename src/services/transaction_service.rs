@@ -0,0 +1,489 @@
+use crate::services::errors::ServiceError;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::config::RpcSimulateTransactionConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
+use solana_system_interface::program::ID as SYSTEM_PROGRAM_ID;
+use std::{path::Path, sync::Arc};
+
+// Most signatures the RPC looks up in one getSignatureStatuses request
+const MAX_SIGNATURE_STATUSES: usize = 256;
+// Bincode tag of the system program's AdvanceNonceAccount instruction
+const ADVANCE_NONCE_ACCOUNT: [u8; 4] = [4, 0, 0, 0];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationSummary {
+    pub error: Option<String>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+}
+
+impl SimulationSummary {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureState {
+    Signed,
+    Missing,
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequiredSigner {
+    pub pubkey: Pubkey,
+    pub state: SignatureState,
+}
+
+pub struct TransactionService {
+    client: Arc<RpcClient>,
+}
+
+impl TransactionService {
+    pub fn new(client: Arc<RpcClient>) -> Self {
+        Self { client }
+    }
+
+    pub fn simulate(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> Result<SimulationSummary, ServiceError> {
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            ..RpcSimulateTransactionConfig::default()
+        };
+        let result = self
+            .client
+            .simulate_transaction_with_config(transaction, config)?
+            .value;
+
+        Ok(SimulationSummary {
+            error: result.err.map(|err| err.to_string()),
+            logs: result.logs.unwrap_or_default(),
+            units_consumed: result.units_consumed,
+        })
+    }
+
+    pub fn send(&self, transaction: &VersionedTransaction) -> Result<Signature, ServiceError> {
+        let signature = self.client.send_and_confirm_transaction(transaction)?;
+        Ok(signature)
+    }
+
+    /// Build, sign and send a transaction paid for by `signer`
+    pub fn send_instructions(
+        &self,
+        instructions: &[Instruction],
+        signer: &Keypair,
+    ) -> Result<Signature, ServiceError> {
+        let (transaction, _) = self.sign_instructions(instructions, signer)?;
+        let signature = self.client.send_and_confirm_transaction(&transaction)?;
+        Ok(signature)
+    }
+
+    /// Sign a transaction without sending it, returning the block height after which it expires
+    pub fn sign_instructions(
+        &self,
+        instructions: &[Instruction],
+        signer: &Keypair,
+    ) -> Result<(Transaction, u64), ServiceError> {
+        let (blockhash, last_valid_block_height) = self
+            .client
+            .get_latest_blockhash_with_commitment(self.client.commitment())?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signer.pubkey()),
+            &[signer],
+            blockhash,
+        );
+        Ok((transaction, last_valid_block_height))
+    }
+
+    pub fn block_height(&self) -> Result<u64, ServiceError> {
+        Ok(self.client.get_block_height()?)
+    }
+
+    /// Look up whether previously sent transactions landed, `None` when the cluster has no record
+    pub fn signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> Result<Vec<Option<Result<(), String>>>, ServiceError> {
+        let mut statuses = Vec::with_capacity(signatures.len());
+        for chunk in signatures.chunks(MAX_SIGNATURE_STATUSES) {
+            let chunk = self
+                .client
+                .get_signature_statuses_with_history(chunk)?
+                .value;
+            statuses.extend(
+                chunk.into_iter().map(|status| {
+                    status.map(|status| status.status.map_err(|err| err.to_string()))
+                }),
+            );
+        }
+        Ok(statuses)
+    }
+}
+
+/// Add the signature of `keypair` to a transaction that may already carry other signatures
+pub fn sign_partial(
+    transaction: &mut VersionedTransaction,
+    keypair: &Keypair,
+) -> Result<(), ServiceError> {
+    let pubkey = keypair.pubkey();
+    let required_signatures = transaction.message.header().num_required_signatures as usize;
+    let index = transaction
+        .message
+        .static_account_keys()
+        .iter()
+        .take(required_signatures)
+        .position(|key| *key == pubkey)
+        .ok_or_else(|| ServiceError::NotASigner(pubkey.to_string()))?;
+
+    if transaction.signatures.len() < required_signatures {
+        transaction
+            .signatures
+            .resize(required_signatures, Signature::default());
+    }
+    transaction.signatures[index] = keypair.sign_message(&transaction.message.serialize());
+    Ok(())
+}
+
+/// Encode a transaction with the signatures collected so far, to hand to the next signer
+pub fn encode_transaction(transaction: &VersionedTransaction) -> Result<String, ServiceError> {
+    let bytes = bincode::serialize(transaction)
+        .map_err(|e| ServiceError::InvalidTransaction(e.to_string()))?;
+    Ok(STANDARD.encode(bytes))
+}
+
+/// Decode a transaction exported by `encode_transaction` or another wallet, ignoring line breaks
+pub fn decode_transaction(encoded: &str) -> Result<VersionedTransaction, ServiceError> {
+    let encoded: String = encoded.split_whitespace().collect();
+    let bytes = STANDARD
+        .decode(encoded)
+        .map_err(|e| ServiceError::InvalidTransaction(e.to_string()))?;
+    let mut transaction: VersionedTransaction = bincode::deserialize(&bytes)
+        .map_err(|e| ServiceError::InvalidTransaction(e.to_string()))?;
+    // Trailing bytes would be lost when the transaction is exported again
+    if bincode::serialized_size(&transaction).ok() != Some(bytes.len() as u64) {
+        return Err(ServiceError::InvalidTransaction(
+            "unexpected data after the transaction".to_string(),
+        ));
+    }
+
+    let required_signatures = transaction.message.header().num_required_signatures as usize;
+    if transaction.signatures.len() < required_signatures {
+        transaction
+            .signatures
+            .resize(required_signatures, Signature::default());
+    }
+    transaction
+        .sanitize()
+        .map_err(|e| ServiceError::InvalidTransaction(e.to_string()))?;
+    Ok(transaction)
+}
+
+/// Read a transaction that another wallet exported to a file
+pub fn read_transaction_file(path: &Path) -> Result<VersionedTransaction, ServiceError> {
+    let encoded = std::fs::read_to_string(path).map_err(|e| ServiceError::Other(Box::new(e)))?;
+    decode_transaction(&encoded)
+}
+
+pub fn write_transaction_file(
+    path: &Path,
+    transaction: &VersionedTransaction,
+) -> Result<(), ServiceError> {
+    let encoded = encode_transaction(transaction)?;
+    std::fs::write(path, encoded + "\n").map_err(|e| ServiceError::Other(Box::new(e)))
+}
+
+/// Every key that must sign, in message order, so the fee payer comes first
+pub fn required_signers(transaction: &VersionedTransaction) -> Vec<RequiredSigner> {
+    let message = transaction.message.serialize();
+    let required_signatures = transaction.message.header().num_required_signatures as usize;
+    transaction
+        .message
+        .static_account_keys()
+        .iter()
+        .take(required_signatures)
+        .enumerate()
+        .map(|(index, pubkey)| {
+            let state = match transaction.signatures.get(index) {
+                None => SignatureState::Missing,
+                Some(signature) if *signature == Signature::default() => SignatureState::Missing,
+                Some(signature) if signature.verify(pubkey.as_ref(), &message) => {
+                    SignatureState::Signed
+                }
+                Some(_) => SignatureState::Invalid,
+            };
+            RequiredSigner {
+                pubkey: *pubkey,
+                state,
+            }
+        })
+        .collect()
+}
+
+/// Check a transaction a merchant asks `wallet` to sign. Every other required signer,
+/// the fee payer included, must be the merchant and have signed already, and the
+/// wallet's own signature must still be missing.
+pub fn check_merchant_transaction(
+    transaction: &VersionedTransaction,
+    wallet: &Pubkey,
+) -> Result<(), ServiceError> {
+    let signers = required_signers(transaction);
+    if !signers.iter().any(|signer| signer.pubkey == *wallet) {
+        return Err(ServiceError::NotASigner(wallet.to_string()));
+    }
+    for (index, signer) in signers.iter().enumerate() {
+        let role = if index == 0 { "fee payer" } else { "signer" };
+        if signer.pubkey == *wallet {
+            if signer.state != SignatureState::Missing {
+                return Err(ServiceError::InvalidTransaction(format!(
+                    "it already carries a signature for {}",
+                    wallet
+                )));
+            }
+        } else if signer.state != SignatureState::Signed {
+            return Err(ServiceError::InvalidTransaction(format!(
+                "{} {} is neither this account nor the merchant",
+                role, signer.pubkey
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the transaction starts by advancing a durable nonce, so its blockhash does not expire
+pub fn uses_durable_nonce(transaction: &VersionedTransaction) -> bool {
+    let keys = transaction.message.static_account_keys();
+    transaction
+        .message
+        .instructions()
+        .first()
+        .is_some_and(|instruction| {
+            keys.get(instruction.program_id_index as usize) == Some(&SYSTEM_PROGRAM_ID)
+                && instruction.data.starts_with(&ADVANCE_NONCE_ACCOUNT)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{Message, VersionedMessage};
+    use solana_system_interface::instruction::transfer;
+
+    fn unsigned_transaction(signers: &[&Keypair]) -> VersionedTransaction {
+        let instructions: Vec<Instruction> = signers
+            .iter()
+            .map(|signer| transfer(&signer.pubkey(), &signer.pubkey(), 1))
+            .collect();
+        let message = Message::new_with_blockhash(
+            &instructions,
+            Some(&signers[0].pubkey()),
+            &Hash::new_unique(),
+        );
+        VersionedTransaction {
+            signatures: vec![],
+            message: VersionedMessage::Legacy(message),
+        }
+    }
+
+    #[test]
+    fn test_sign_partial() {
+        let payer = Keypair::new();
+        let cosigner = Keypair::new();
+        let mut transaction = unsigned_transaction(&[&payer, &cosigner]);
+
+        sign_partial(&mut transaction, &cosigner).unwrap();
+        assert_eq!(transaction.signatures.len(), 2);
+        assert_eq!(transaction.signatures[0], Signature::default());
+        assert!(transaction.verify_with_results()[1]);
+
+        sign_partial(&mut transaction, &payer).unwrap();
+        assert!(transaction.verify_with_results().iter().all(|valid| *valid));
+    }
+
+    #[test]
+    fn test_sign_partial_rejects_unknown_signer() {
+        let payer = Keypair::new();
+        let mut transaction = unsigned_transaction(&[&payer]);
+
+        let result = sign_partial(&mut transaction, &Keypair::new());
+        assert!(matches!(result, Err(ServiceError::NotASigner(_))));
+    }
+
+    #[test]
+    fn test_encode_transaction_round_trip() {
+        let payer = Keypair::new();
+        let cosigner = Keypair::new();
+        let mut transaction = unsigned_transaction(&[&payer, &cosigner]);
+        sign_partial(&mut transaction, &cosigner).unwrap();
+
+        let encoded = encode_transaction(&transaction).unwrap();
+        let decoded = decode_transaction(&encoded).unwrap();
+        assert_eq!(decoded, transaction);
+        assert_eq!(encode_transaction(&decoded).unwrap(), encoded);
+
+        // Line breaks from copying between apps are ignored
+        let wrapped: String = encoded
+            .as_bytes()
+            .chunks(40)
+            .map(|chunk| format!("{}\n", String::from_utf8_lossy(chunk)))
+            .collect();
+        assert_eq!(decode_transaction(&wrapped).unwrap(), transaction);
+
+        let mut bytes = STANDARD.decode(&encoded).unwrap();
+        bytes.push(0);
+        assert!(decode_transaction(&STANDARD.encode(bytes)).is_err());
+        assert!(decode_transaction("not a transaction").is_err());
+    }
+
+    #[test]
+    fn test_transaction_file() {
+        let payer = Keypair::new();
+        let cosigner = Keypair::new();
+        let mut transaction = unsigned_transaction(&[&payer, &cosigner]);
+        sign_partial(&mut transaction, &payer).unwrap();
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        write_transaction_file(file.path(), &transaction).unwrap();
+        assert_eq!(read_transaction_file(file.path()).unwrap(), transaction);
+    }
+
+    #[test]
+    fn test_required_signers() {
+        let payer = Keypair::new();
+        let cosigner = Keypair::new();
+        let mut transaction = unsigned_transaction(&[&payer, &cosigner]);
+        sign_partial(&mut transaction, &cosigner).unwrap();
+
+        let signers = required_signers(&transaction);
+        assert_eq!(
+            signers,
+            vec![
+                RequiredSigner {
+                    pubkey: payer.pubkey(),
+                    state: SignatureState::Missing,
+                },
+                RequiredSigner {
+                    pubkey: cosigner.pubkey(),
+                    state: SignatureState::Signed,
+                },
+            ]
+        );
+
+        transaction.signatures[0] = cosigner.sign_message(b"something else");
+        assert_eq!(
+            required_signers(&transaction)[0].state,
+            SignatureState::Invalid
+        );
+    }
+
+    #[test]
+    fn test_check_merchant_transaction() {
+        let merchant = Keypair::new();
+        let wallet = Keypair::new();
+        let mut transaction = unsigned_transaction(&[&merchant, &wallet]);
+        // Until the merchant signs, its key could be anyone's
+        assert!(check_merchant_transaction(&transaction, &wallet.pubkey()).is_err());
+
+        sign_partial(&mut transaction, &merchant).unwrap();
+        check_merchant_transaction(&transaction, &wallet.pubkey()).unwrap();
+        assert!(matches!(
+            check_merchant_transaction(&transaction, &Keypair::new().pubkey()),
+            Err(ServiceError::NotASigner(_))
+        ));
+
+        // The fee payer must not be some third party left to sign later
+        let stranger = Keypair::new();
+        let transaction = unsigned_transaction(&[&stranger, &wallet]);
+        assert!(matches!(
+            check_merchant_transaction(&transaction, &wallet.pubkey()),
+            Err(ServiceError::InvalidTransaction(reason)) if reason.starts_with("fee payer")
+        ));
+
+        // Nor may the wallet's slot be filled in already
+        let mut transaction = unsigned_transaction(&[&wallet]);
+        transaction.signatures = vec![merchant.sign_message(b"something else")];
+        assert!(check_merchant_transaction(&transaction, &wallet.pubkey()).is_err());
+    }
+
+    #[test]
+    fn test_uses_durable_nonce() {
+        let payer = Keypair::new();
+        assert!(!uses_durable_nonce(&unsigned_transaction(&[&payer])));
+
+        let nonce_account = Pubkey::new_unique();
+        let message = Message::new_with_nonce(
+            vec![transfer(&payer.pubkey(), &payer.pubkey(), 1)],
+            Some(&payer.pubkey()),
+            &nonce_account,
+            &payer.pubkey(),
+        );
+        let transaction = VersionedTransaction {
+            signatures: vec![],
+            message: VersionedMessage::Legacy(message),
+        };
+        assert!(uses_durable_nonce(&transaction));
+    }
+
+    #[test]
+    fn test_simulate() {
+        let payer = Keypair::new();
+        let transaction = unsigned_transaction(&[&payer]);
+        let service =
+            TransactionService::new(Arc::new(RpcClient::new_mock("succeeds".to_string())));
+
+        let summary = service.simulate(&transaction).unwrap();
+        assert!(summary.succeeded());
+        assert!(summary.logs.is_empty());
+    }
+
+    #[test]
+    fn test_send_instructions() {
+        let payer = Keypair::new();
+        let service =
+            TransactionService::new(Arc::new(RpcClient::new_mock("succeeds".to_string())));
+
+        let result =
+            service.send_instructions(&[transfer(&payer.pubkey(), &payer.pubkey(), 1)], &payer);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_sign_instructions() {
+        let payer = Keypair::new();
+        let service =
+            TransactionService::new(Arc::new(RpcClient::new_mock("succeeds".to_string())));
+
+        let (transaction, last_valid_block_height) = service
+            .sign_instructions(&[transfer(&payer.pubkey(), &payer.pubkey(), 1)], &payer)
+            .unwrap();
+        assert!(transaction.is_signed());
+        assert_eq!(last_valid_block_height, 1234);
+    }
+
+    #[test]
+    fn test_signature_statuses() {
+        let service =
+            TransactionService::new(Arc::new(RpcClient::new_mock("succeeds".to_string())));
+        let statuses = service.signature_statuses(&[Signature::default()]).unwrap();
+        assert_eq!(statuses, vec![Some(Ok(()))]);
+
+        let service =
+            TransactionService::new(Arc::new(RpcClient::new_mock("sig_not_found".to_string())));
+        let statuses = service.signature_statuses(&[Signature::default()]).unwrap();
+        assert_eq!(statuses, vec![None]);
+
+        // Large batches are looked up a chunk at a time
+        let signatures = vec![Signature::default(); MAX_SIGNATURE_STATUSES * 2 + 1];
+        let statuses = service.signature_statuses(&signatures).unwrap();
+        assert_eq!(statuses.len(), signatures.len());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::amount::{ui_amount_to_base_units, SOL_DECIMALS};
+use crate::database::account::Account;
+use crate::programs::{
+    associated_token::{create_associated_token_account_idempotent, get_associated_token_address},
+    memo::build_memo,
+    token::{is_token_program, mint_decimals, transfer_checked},
+};
+use crate::services::{errors::ServiceError, transaction_service::TransactionService};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_system_interface::instruction::transfer;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transfer {
+    pub recipient: Pubkey,
+    /// Decimal amount in SOL or in whole tokens of `mint`
+    pub amount: String,
+    /// SPL token mint, `None` for native SOL
+    pub mint: Option<Pubkey>,
+    pub memo: Option<String>,
+    pub references: Vec<Pubkey>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenMint {
+    pub program_id: Pubkey,
+    pub decimals: u8,
+}
+
+pub struct TransferService {
+    client: Arc<RpcClient>,
+}
+
+impl TransferService {
+    pub fn new(client: Arc<RpcClient>) -> Self {
+        Self { client }
+    }
+
+    pub fn token_mint(&self, mint: &Pubkey) -> Result<TokenMint, ServiceError> {
+        let account = self.client.get_account(mint)?;
+        let decimals = mint_decimals(&account.data)
+            .filter(|_| is_token_program(&account.owner))
+            .ok_or_else(|| ServiceError::AccountNotFound(format!("mint {}", mint)))?;
+
+        Ok(TokenMint {
+            program_id: account.owner,
+            decimals,
+        })
+    }
+
+    pub fn instructions(
+        &self,
+        sender: &Pubkey,
+        transfer_details: &Transfer,
+    ) -> Result<Vec<Instruction>, ServiceError> {
+        let token_mint = match &transfer_details.mint {
+            Some(mint) => Some(self.token_mint(mint)?),
+            None => None,
+        };
+        transfer_instructions(sender, transfer_details, token_mint.as_ref())
+    }
+
+    pub fn send(
+        &self,
+        account: &Account,
+        transfer_details: &Transfer,
+    ) -> Result<Signature, ServiceError> {
+        let keypair = account.account_keypair()?;
+        let instructions = self.instructions(&account.pubkey()?, transfer_details)?;
+        TransactionService::new(self.client.clone()).send_instructions(&instructions, &keypair)
+    }
+}
+
+/// Build the instructions for a transfer whose mint details are already known
+pub fn transfer_instructions(
+    sender: &Pubkey,
+    transfer_details: &Transfer,
+    token_mint: Option<&TokenMint>,
+) -> Result<Vec<Instruction>, ServiceError> {
+    let mut instructions = vec![];
+
+    let mut transfer_instruction = match (&transfer_details.mint, token_mint) {
+        (None, _) => {
+            let lamports = ui_amount_to_base_units(&transfer_details.amount, SOL_DECIMALS)?;
+            transfer(sender, &transfer_details.recipient, lamports)
+        }
+        (Some(mint), Some(token_mint)) => {
+            let amount = ui_amount_to_base_units(&transfer_details.amount, token_mint.decimals)?;
+            let program_id = &token_mint.program_id;
+            let source = get_associated_token_address(sender, mint, program_id);
+            let destination =
+                get_associated_token_address(&transfer_details.recipient, mint, program_id);
+
+            instructions.push(create_associated_token_account_idempotent(
+                sender,
+                &transfer_details.recipient,
+                mint,
+                program_id,
+            ));
+            transfer_checked(
+                program_id,
+                &source,
+                mint,
+                &destination,
+                sender,
+                amount,
+                token_mint.decimals,
+            )
+        }
+        (Some(mint), None) => {
+            return Err(ServiceError::AccountNotFound(format!("mint {}", mint)));
+        }
+    };
+
+    // Solana Pay references are attached as read-only keys so the payment can be located
+    transfer_instruction.accounts.extend(
+        transfer_details
+            .references
+            .iter()
+            .map(|reference| AccountMeta::new_readonly(*reference, false)),
+    );
+
+    // The memo must come immediately before the transfer it describes
+    if let Some(memo) = transfer_details
+        .memo
+        .as_deref()
+        .filter(|memo| !memo.is_empty())
+    {
+        instructions.push(build_memo(memo, &[]));
+    }
+    instructions.push(transfer_instruction);
+    Ok(instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::{memo::MEMO_PROGRAM_ID, token::TOKEN_PROGRAM_ID};
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde_json::json;
+    use solana_rpc_client::rpc_client::Mocks;
+    use solana_rpc_client_api::request::RpcRequest;
+    use solana_system_interface::program::ID as SYSTEM_PROGRAM_ID;
+
+    fn sol_transfer() -> Transfer {
+        Transfer {
+            recipient: Pubkey::new_unique(),
+            amount: "1.5".to_string(),
+            mint: None,
+            memo: None,
+            references: vec![],
+        }
+    }
+
+    fn mock_mint_account(decimals: u8) -> serde_json::Value {
+        let mut data = vec![0u8; 82];
+        data[44] = decimals;
+        data[45] = 1;
+        json!({
+            "context": { "slot": 1 },
+            "value": {
+                "data": [STANDARD.encode(data), "base64"],
+                "executable": false,
+                "lamports": 1_461_600,
+                "owner": TOKEN_PROGRAM_ID.to_string(),
+                "rentEpoch": 0,
+                "space": 82,
+            }
+        })
+    }
+
+    #[test]
+    fn test_sol_transfer_instructions() {
+        let service = TransferService::new(Arc::new(RpcClient::new_mock("succeeds".to_string())));
+        let sender = Pubkey::new_unique();
+        let reference = Pubkey::new_unique();
+        let transfer_details = Transfer {
+            memo: Some("order 7".to_string()),
+            references: vec![reference],
+            ..sol_transfer()
+        };
+
+        let instructions = service.instructions(&sender, &transfer_details).unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].program_id, MEMO_PROGRAM_ID);
+        assert_eq!(instructions[1].program_id, SYSTEM_PROGRAM_ID);
+
+        let reference_meta = instructions[1].accounts.last().unwrap();
+        assert_eq!(reference_meta.pubkey, reference);
+        assert!(!reference_meta.is_signer && !reference_meta.is_writable);
+    }
+
+    #[test]
+    fn test_sol_transfer_rejects_invalid_amount() {
+        let service = TransferService::new(Arc::new(RpcClient::new_mock("succeeds".to_string())));
+        let transfer_details = Transfer {
+            amount: "1.0000000001".to_string(),
+            ..sol_transfer()
+        };
+
+        let result = service.instructions(&Pubkey::new_unique(), &transfer_details);
+        assert!(matches!(result, Err(ServiceError::AmountError(_))));
+    }
+
+    #[test]
+    fn test_token_transfer_instructions() {
+        let mut mocks = Mocks::default();
+        mocks.insert(RpcRequest::GetAccountInfo, mock_mint_account(6));
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        let service = TransferService::new(Arc::new(client));
+        let mint = Pubkey::new_unique();
+        let transfer_details = Transfer {
+            mint: Some(mint),
+            ..sol_transfer()
+        };
+
+        let instructions = service
+            .instructions(&Pubkey::new_unique(), &transfer_details)
+            .unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[1].program_id, TOKEN_PROGRAM_ID);
+        assert_eq!(&instructions[1].data[1..9], &1_500_000u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_token_mint_not_found() {
+        let service = TransferService::new(Arc::new(RpcClient::new_mock("succeeds".to_string())));
+        let result = service.token_mint(&Pubkey::new_unique());
+        assert!(result.is_err());
+    }
+}
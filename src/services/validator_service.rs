@@ -0,0 +1,302 @@
+use crate::database::errors::DatabaseError;
+use crate::services::errors::ServiceError;
+use rusqlite::{params, Connection};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::response::RpcVoteAccountInfo;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Validator {
+    pub vote_account: String,
+    pub identity: String,
+    pub commission: u8,
+    pub activated_stake: u64,
+    pub last_vote: u64,
+    pub delinquent: bool,
+    /// Vote credits earned so far in the current epoch
+    pub epoch_credits: Option<u64>,
+    /// Vote credits earned in the previous epoch
+    pub previous_epoch_credits: Option<u64>,
+    /// Share of its leader slots this epoch that produced no block
+    pub skip_rate: Option<f64>,
+}
+
+impl Validator {
+    /// Case-insensitive match on the vote account or identity
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        query.is_empty()
+            || self.vote_account.to_lowercase().contains(&query)
+            || self.identity.to_lowercase().contains(&query)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValidatorSort {
+    Stake,
+    Commission,
+    SkipRate,
+    Credits,
+    LastVote,
+}
+
+impl ValidatorSort {
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            "Commission" => ValidatorSort::Commission,
+            "Skip rate" => ValidatorSort::SkipRate,
+            "Credits" => ValidatorSort::Credits,
+            "Last vote" => ValidatorSort::LastVote,
+            _ => ValidatorSort::Stake,
+        }
+    }
+}
+
+pub struct ValidatorService {
+    conn: Arc<Mutex<Connection>>,
+    client: Arc<RpcClient>,
+}
+
+impl ValidatorService {
+    pub fn new(conn: Arc<Mutex<Connection>>, client: Arc<RpcClient>) -> Self {
+        Self { conn, client }
+    }
+
+    /// Current and delinquent vote accounts with this epoch's block production
+    pub fn list(&self) -> Result<Vec<Validator>, ServiceError> {
+        let vote_accounts = self.client.get_vote_accounts()?;
+        let production = self.client.get_block_production()?.value.by_identity;
+
+        let current = vote_accounts
+            .current
+            .iter()
+            .map(|info| validator_from_vote_account(info, false, &production));
+        let delinquent = vote_accounts
+            .delinquent
+            .iter()
+            .map(|info| validator_from_vote_account(info, true, &production));
+        Ok(current.chain(delinquent).collect())
+    }
+
+    pub fn get_favourites(&self) -> Result<HashSet<String>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT vote_account FROM validator_favourites")?;
+        let favourites = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<HashSet<String>, _>>()?;
+        Ok(favourites)
+    }
+
+    pub fn set_favourite(&self, vote_account: &str, favourite: bool) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        if favourite {
+            conn.execute(
+                "INSERT OR IGNORE INTO validator_favourites (vote_account) VALUES (?1)",
+                params![vote_account],
+            )?;
+        } else {
+            conn.execute(
+                "DELETE FROM validator_favourites WHERE vote_account = ?1",
+                params![vote_account],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Sort best first; validators without a value for the chosen column go last
+pub fn sort_validators(validators: &mut [Validator], sort: ValidatorSort) {
+    validators.sort_by(|a, b| match sort {
+        ValidatorSort::Stake => b.activated_stake.cmp(&a.activated_stake),
+        ValidatorSort::Commission => a.commission.cmp(&b.commission),
+        ValidatorSort::SkipRate => match (a.skip_rate, b.skip_rate) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        },
+        ValidatorSort::Credits => b.epoch_credits.cmp(&a.epoch_credits),
+        ValidatorSort::LastVote => b.last_vote.cmp(&a.last_vote),
+    });
+}
+
+fn validator_from_vote_account(
+    info: &RpcVoteAccountInfo,
+    delinquent: bool,
+    production: &HashMap<String, (usize, usize)>,
+) -> Validator {
+    // Entries are (epoch, credits, previous credits), oldest first
+    let credits_in = |index: usize| {
+        info.epoch_credits
+            .iter()
+            .rev()
+            .nth(index)
+            .map(|(_, credits, previous)| credits.saturating_sub(*previous))
+    };
+    let skip_rate = production
+        .get(&info.node_pubkey)
+        .filter(|(leader_slots, _)| *leader_slots > 0)
+        .map(|(leader_slots, produced)| 1.0 - *produced as f64 / *leader_slots as f64);
+
+    Validator {
+        vote_account: info.vote_pubkey.clone(),
+        identity: info.node_pubkey.clone(),
+        commission: info.commission,
+        activated_stake: info.activated_stake,
+        last_vote: info.last_vote,
+        delinquent,
+        epoch_credits: credits_in(0),
+        previous_epoch_credits: credits_in(1),
+        skip_rate,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database_connection;
+    use serde_json::json;
+    use solana_rpc_client::rpc_client::Mocks;
+    use solana_rpc_client_api::request::RpcRequest;
+
+    fn setup_test_db() -> Arc<Mutex<Connection>> {
+        let conn = Arc::new(Mutex::new(database_connection().unwrap()));
+        conn.lock()
+            .unwrap()
+            .execute(
+                "CREATE TABLE validator_favourites (
+                vote_account TEXT PRIMARY KEY
+            )",
+                [],
+            )
+            .unwrap();
+        conn
+    }
+
+    fn validator(
+        vote_account: &str,
+        stake: u64,
+        commission: u8,
+        skip_rate: Option<f64>,
+    ) -> Validator {
+        Validator {
+            vote_account: vote_account.to_string(),
+            identity: format!("{}-identity", vote_account),
+            commission,
+            activated_stake: stake,
+            last_vote: stake,
+            delinquent: false,
+            epoch_credits: Some(stake),
+            previous_epoch_credits: None,
+            skip_rate,
+        }
+    }
+
+    #[test]
+    fn test_list_validators() {
+        let mut mocks = Mocks::default();
+        mocks.insert(
+            RpcRequest::GetVoteAccounts,
+            json!({
+                "current": [{
+                    "votePubkey": "Vote111",
+                    "nodePubkey": "Node111",
+                    "activatedStake": 42_000_000_000_000u64,
+                    "commission": 7,
+                    "epochVoteAccount": true,
+                    "epochCredits": [[600, 1_000, 400], [601, 1_300, 1_000]],
+                    "lastVote": 1_234,
+                    "rootSlot": 1_200,
+                }],
+                "delinquent": [{
+                    "votePubkey": "Vote222",
+                    "nodePubkey": "Node222",
+                    "activatedStake": 1,
+                    "commission": 100,
+                    "epochVoteAccount": false,
+                    "epochCredits": [],
+                    "lastVote": 10,
+                    "rootSlot": 0,
+                }],
+            }),
+        );
+        mocks.insert(
+            RpcRequest::GetBlockProduction,
+            json!({
+                "context": { "slot": 1 },
+                "value": {
+                    "byIdentity": { "Node111": [40, 38] },
+                    "range": { "firstSlot": 0, "lastSlot": 100 },
+                },
+            }),
+        );
+        let client = Arc::new(RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            mocks,
+        ));
+
+        let validators = ValidatorService::new(setup_test_db(), client)
+            .list()
+            .unwrap();
+        assert_eq!(validators.len(), 2);
+        assert_eq!(validators[0].commission, 7);
+        assert!(!validators[0].delinquent);
+        assert_eq!(validators[0].epoch_credits, Some(300));
+        assert_eq!(validators[0].previous_epoch_credits, Some(600));
+        assert!((validators[0].skip_rate.unwrap() - 0.05).abs() < 1e-9);
+        assert!(validators[1].delinquent);
+        assert_eq!(validators[1].epoch_credits, None);
+        assert_eq!(validators[1].skip_rate, None);
+    }
+
+    #[test]
+    fn test_favourites() {
+        let service = ValidatorService::new(
+            setup_test_db(),
+            Arc::new(RpcClient::new_mock("succeeds".to_string())),
+        );
+        service.set_favourite("Vote111", true).unwrap();
+        service.set_favourite("Vote111", true).unwrap();
+        service.set_favourite("Vote222", true).unwrap();
+        service.set_favourite("Vote222", false).unwrap();
+
+        let favourites = service.get_favourites().unwrap();
+        assert_eq!(favourites, HashSet::from(["Vote111".to_string()]));
+    }
+
+    #[test]
+    fn test_sort_and_search() {
+        let mut validators = vec![
+            validator("alpha", 10, 5, Some(0.2)),
+            validator("beta", 30, 0, None),
+            validator("gamma", 20, 10, Some(0.01)),
+        ];
+        let order = |validators: &[Validator]| {
+            validators
+                .iter()
+                .map(|validator| validator.vote_account.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        sort_validators(&mut validators, ValidatorSort::Stake);
+        assert_eq!(order(&validators), "beta,gamma,alpha");
+        sort_validators(&mut validators, ValidatorSort::Commission);
+        assert_eq!(order(&validators), "beta,alpha,gamma");
+        sort_validators(&mut validators, ValidatorSort::SkipRate);
+        assert_eq!(order(&validators), "gamma,alpha,beta");
+
+        assert!(validators[0].matches("GAM"));
+        assert!(validators[0].matches("gamma-ident"));
+        assert!(validators[0].matches(" "));
+        assert!(!validators[0].matches("beta"));
+        assert_eq!(
+            ValidatorSort::from_label("Skip rate"),
+            ValidatorSort::SkipRate
+        );
+    }
+}
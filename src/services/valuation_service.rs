@@ -0,0 +1,408 @@
+use crate::amount::SOL_DECIMALS;
+use crate::database::token_balance::TokenBalance;
+use crate::programs::token::NATIVE_MINT;
+use crate::services::{
+    errors::ServiceError,
+    portfolio_service::PortfolioService,
+    price_service::{PriceService, Prices},
+    stake_pool_service::StakePoolService,
+    stake_service::{StakeAccount, StakeService},
+};
+use crate::token_value::TokenData;
+use rusqlite::Connection;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// USD value now and 24 hours ago at today's holdings
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Value {
+    pub usd: f64,
+    pub usd_24h_ago: f64,
+}
+
+impl Value {
+    /// Percentage change over the last 24 hours, `None` when there was nothing to compare
+    pub fn change_24h(&self) -> Option<f64> {
+        (self.usd_24h_ago > 0.0).then(|| (self.usd - self.usd_24h_ago) / self.usd_24h_ago * 100.0)
+    }
+
+    fn add(&mut self, other: Value) {
+        self.usd += other.usd;
+        self.usd_24h_ago += other.usd_24h_ago;
+    }
+}
+
+/// Everything an account holds, in whole units of each mint. SOL, staked SOL included, is
+/// counted under the native mint.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Holdings {
+    pub owner: Pubkey,
+    pub amounts: HashMap<String, f64>,
+}
+
+impl Holdings {
+    fn add(&mut self, mint: &str, amount: f64) {
+        *self.amounts.entry(mint.to_string()).or_default() += amount;
+    }
+
+    /// Value at `prices`, leaving out mints without a price
+    pub fn value(&self, prices: &HashMap<String, TokenData>) -> Value {
+        let mut value = Value::default();
+        for (mint, amount) in &self.amounts {
+            if let Some(price) = prices.get(mint) {
+                value.add(Value {
+                    usd: amount * price.price,
+                    usd_24h_ago: amount * price.price_24h_ago(),
+                });
+            }
+        }
+        value
+    }
+}
+
+/// Value of each account and of all of them together
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Valuation {
+    pub accounts: HashMap<Pubkey, Value>,
+    pub total: Value,
+    /// When the oldest price used was fetched, if fresh prices could not be fetched
+    pub stale_since: Option<i64>,
+    /// What was valued, kept to value again as prices move
+    pub holdings: Vec<Holdings>,
+}
+
+impl Valuation {
+    pub fn new(holdings: &[Holdings], prices: &Prices) -> Self {
+        let mut valuation = Valuation {
+            stale_since: prices.stale_since,
+            holdings: holdings.to_vec(),
+            ..Default::default()
+        };
+        for account in holdings {
+            let value = account.value(&prices.prices);
+            valuation.total.add(value);
+            valuation
+                .accounts
+                .entry(account.owner)
+                .or_default()
+                .add(value);
+        }
+        valuation
+    }
+}
+
+pub struct ValuationService {
+    conn: Arc<Mutex<Connection>>,
+    client: Arc<RpcClient>,
+}
+
+impl ValuationService {
+    pub fn new(conn: Arc<Mutex<Connection>>, client: Arc<RpcClient>) -> Self {
+        Self { conn, client }
+    }
+
+    /// Value every owner's SOL, staked SOL and tokens, with all mints priced together
+    pub fn value(&self, owners: &[Pubkey]) -> Result<Valuation, ServiceError> {
+        let holdings = self.holdings(owners)?;
+        let prices = self.prices(&holdings)?;
+        Ok(Valuation::new(&holdings, &prices))
+    }
+
+    /// Value the holdings of `valuation` again at current prices, without looking them up anew
+    pub fn reprice(&self, valuation: &Valuation) -> Result<Valuation, ServiceError> {
+        let prices = self.prices(&valuation.holdings)?;
+        Ok(Valuation::new(&valuation.holdings, &prices))
+    }
+
+    /// Prices of every mint in `holdings` and of SOL
+    fn prices(&self, holdings: &[Holdings]) -> Result<Prices, ServiceError> {
+        let mut mints: Vec<String> = holdings
+            .iter()
+            .flat_map(|account| account.amounts.keys().cloned())
+            .chain([NATIVE_MINT.to_string()])
+            .collect();
+        mints.sort();
+        mints.dedup();
+
+        let mut prices = PriceService::new(self.conn.clone()).prices(&mints)?;
+        if mints.iter().any(|mint| !prices.prices.contains_key(mint)) {
+            let rates = StakePoolService::new(self.conn.clone(), self.client.clone()).rates()?;
+            price_pool_tokens(&mut prices, &rates);
+        }
+        Ok(prices)
+    }
+
+    // Read from the cluster as is, leaving the balances cached for the token list alone
+    fn holdings(&self, owners: &[Pubkey]) -> Result<Vec<Holdings>, ServiceError> {
+        let sol_accounts = self.client.get_multiple_accounts(owners)?;
+        let epoch = self.client.get_epoch_info()?.epoch;
+        let portfolio = PortfolioService::new(self.conn.clone(), self.client.clone());
+        let stake_service = StakeService::new(self.client.clone());
+
+        let mut discovered = vec![];
+        for owner in owners {
+            discovered.push((*owner, stake_service.discover(owner, epoch)?));
+        }
+        let staked = staked_by_owner(owners, discovered);
+
+        owners
+            .iter()
+            .zip(sol_accounts)
+            .map(|(owner, sol_account)| {
+                let lamports = sol_account.map(|sol| sol.lamports).unwrap_or(0)
+                    + staked.get(owner).copied().unwrap_or(0);
+                Ok(account_holdings(owner, lamports, &portfolio.fetch(owner)?))
+            })
+            .collect()
+    }
+}
+
+// Lamports staked by each of `owners`, given the stake accounts found for each. A stake
+// account two owners share as staker and withdrawer is found for both but counted once, under
+// its withdrawer.
+fn staked_by_owner(
+    owners: &[Pubkey],
+    discovered: Vec<(Pubkey, Vec<StakeAccount>)>,
+) -> HashMap<Pubkey, u64> {
+    let mut stakes: HashMap<Pubkey, (Pubkey, u64)> = HashMap::new();
+    for (owner, accounts) in discovered {
+        for stake in accounts {
+            let withdrawer = stake.authorized.withdrawer;
+            let holder = if owners.contains(&withdrawer) {
+                withdrawer
+            } else {
+                owner
+            };
+            stakes.insert(stake.address, (holder, stake.lamports));
+        }
+    }
+
+    let mut staked = HashMap::new();
+    for (holder, lamports) in stakes.into_values() {
+        *staked.entry(holder).or_default() += lamports;
+    }
+    staked
+}
+
+fn account_holdings(owner: &Pubkey, lamports: u64, balances: &[TokenBalance]) -> Holdings {
+    let mut account = Holdings {
+        owner: *owner,
+        ..Default::default()
+    };
+    account.add(
+        &NATIVE_MINT.to_string(),
+        whole_units(lamports, SOL_DECIMALS),
+    );
+    for balance in balances {
+        account.add(&balance.mint, whole_units(balance.amount, balance.decimals));
+    }
+    account
+}
+
+// Pool tokens the market does not price are worth the SOL they can be withdrawn for
+fn price_pool_tokens(prices: &mut Prices, rates: &HashMap<Pubkey, f64>) {
+    let Some(sol) = prices.prices.get(&NATIVE_MINT.to_string()).cloned() else {
+        return;
+    };
+    for (pool_mint, rate) in rates {
+        prices
+            .prices
+            .entry(pool_mint.to_string())
+            .or_insert_with(|| TokenData {
+                id: pool_mint.to_string(),
+                price: sol.price * rate,
+                price_change_24h: sol.price_change_24h,
+                updated_at: sol.updated_at,
+            });
+    }
+}
+
+fn whole_units(amount: u64, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::stake_service::ActivationState;
+    use solana_stake_interface::{
+        stake_history::StakeHistoryEntry,
+        state::{Authorized, Lockup},
+    };
+
+    fn price(mint: &str, price: f64, change: Option<f64>) -> (String, TokenData) {
+        (
+            mint.to_string(),
+            TokenData {
+                id: mint.to_string(),
+                price,
+                price_change_24h: change,
+                updated_at: 0,
+            },
+        )
+    }
+
+    #[test]
+    fn test_valuation() {
+        let sol = NATIVE_MINT.to_string();
+        let prices = Prices {
+            prices: [
+                price(&sol, 150.0, Some(50.0)),
+                price("Usdc", 1.0, None),
+                price("Down", 10.0, Some(-50.0)),
+            ]
+            .into_iter()
+            .collect(),
+            stale_since: Some(100),
+        };
+
+        let first = Holdings {
+            owner: Pubkey::new_unique(),
+            amounts: [(sol.clone(), 2.0), ("Usdc".to_string(), 100.0)]
+                .into_iter()
+                .collect(),
+        };
+        let second = Holdings {
+            owner: Pubkey::new_unique(),
+            amounts: [("Down".to_string(), 5.0), ("Unpriced".to_string(), 1e9)]
+                .into_iter()
+                .collect(),
+        };
+
+        let valuation = Valuation::new(&[first.clone(), second.clone()], &prices);
+        // 2 SOL went from 100 to 150 and USDC is assumed unchanged
+        assert_eq!(
+            valuation.accounts[&first.owner],
+            Value {
+                usd: 400.0,
+                usd_24h_ago: 300.0
+            }
+        );
+        assert_eq!(
+            valuation.accounts[&second.owner],
+            Value {
+                usd: 50.0,
+                usd_24h_ago: 100.0
+            }
+        );
+        assert_eq!(valuation.total.usd, 450.0);
+        assert_eq!(valuation.total.change_24h(), Some(12.5));
+        assert_eq!(valuation.stale_since, Some(100));
+        // Kept so the same holdings can be valued again at later prices
+        assert_eq!(valuation.holdings, [first, second]);
+        assert_eq!(Value::default().change_24h(), None);
+    }
+
+    fn balance(owner: &Pubkey, mint: &str, amount: u64, decimals: u8) -> TokenBalance {
+        TokenBalance {
+            owner: owner.to_string(),
+            mint: mint.to_string(),
+            program_id: String::new(),
+            amount,
+            decimals,
+        }
+    }
+
+    #[test]
+    fn test_account_holdings() {
+        let sol = NATIVE_MINT.to_string();
+        let owner = Pubkey::new_unique();
+        // 1.5 SOL in the wallet and 2 SOL staked
+        let holdings = account_holdings(
+            &owner,
+            3_500_000_000,
+            &[balance(&owner, "Usdc", 12_500_000, 6)],
+        );
+        assert_eq!(holdings.owner, owner);
+        assert_eq!(holdings.amounts[&sol], 3.5);
+        assert_eq!(holdings.amounts["Usdc"], 12.5);
+
+        let prices = Prices {
+            prices: [price(&sol, 100.0, None), price("Usdc", 1.0, None)]
+                .into_iter()
+                .collect(),
+            stale_since: None,
+        };
+        let other = Pubkey::new_unique();
+        let accounts = [
+            holdings,
+            account_holdings(&other, 0, &[balance(&other, "Usdc", 7_500_000, 6)]),
+        ];
+        let valuation = Valuation::new(&accounts, &prices);
+        assert_eq!(valuation.accounts[&owner].usd, 362.5);
+        assert_eq!(valuation.accounts[&other].usd, 7.5);
+        assert_eq!(valuation.total.usd, 370.0);
+    }
+
+    fn stake(staker: &Pubkey, withdrawer: &Pubkey, lamports: u64) -> StakeAccount {
+        StakeAccount {
+            address: Pubkey::new_unique(),
+            lamports,
+            rent_exempt_reserve: 0,
+            authorized: Authorized {
+                staker: *staker,
+                withdrawer: *withdrawer,
+            },
+            lockup: Lockup::default(),
+            delegation: None,
+            state: ActivationState::Inactive,
+            activation: StakeHistoryEntry::default(),
+        }
+    }
+
+    #[test]
+    fn test_staked_by_owner() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let outsider = Pubkey::new_unique();
+        let own = stake(&first, &first, 1_000);
+        // Staked by the first account but withdrawable by the second, so found for both
+        let shared = stake(&first, &second, 2_000);
+        // Only staked by the second account
+        let delegated = stake(&second, &outsider, 4_000);
+
+        let staked = staked_by_owner(
+            &[first, second],
+            vec![
+                (first, vec![own, shared.clone()]),
+                (second, vec![shared, delegated]),
+            ],
+        );
+        assert_eq!(staked[&first], 1_000);
+        assert_eq!(staked[&second], 6_000);
+        assert_eq!(staked.values().sum::<u64>(), 7_000);
+    }
+
+    #[test]
+    fn test_price_pool_tokens() {
+        let sol = NATIVE_MINT.to_string();
+        let unpriced = Pubkey::new_unique();
+        let listed = Pubkey::new_unique();
+        let mut prices = Prices {
+            prices: [
+                price(&sol, 100.0, Some(25.0)),
+                price(&listed.to_string(), 120.0, None),
+            ]
+            .into_iter()
+            .collect(),
+            stale_since: None,
+        };
+        let rates = [(unpriced, 1.1), (listed, 1.2)].into_iter().collect();
+
+        price_pool_tokens(&mut prices, &rates);
+        let pool_price = &prices.prices[&unpriced.to_string()];
+        assert!((pool_price.price - 110.0).abs() < 1e-9);
+        assert_eq!(pool_price.price_change_24h, Some(25.0));
+        // Market prices win over the pool's own rate
+        assert_eq!(prices.prices[&listed.to_string()].price, 120.0);
+
+        // Without a SOL price there is nothing to go by
+        let mut prices = Prices::default();
+        price_pool_tokens(&mut prices, &rates);
+        assert!(prices.prices.is_empty());
+    }
+}
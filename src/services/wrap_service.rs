@@ -0,0 +1,160 @@
+use crate::amount::{ui_amount_to_base_units, AmountError, SOL_DECIMALS};
+use crate::database::account::Account;
+use crate::programs::{
+    associated_token::{create_associated_token_account_idempotent, get_associated_token_address},
+    token::{account_amount, close_account, sync_native, NATIVE_MINT, TOKEN_PROGRAM_ID},
+};
+use crate::services::{errors::ServiceError, transaction_service::TransactionService};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_system_interface::instruction::transfer;
+use std::sync::Arc;
+
+pub struct WrapService {
+    client: Arc<RpcClient>,
+}
+
+impl WrapService {
+    pub fn new(client: Arc<RpcClient>) -> Self {
+        Self { client }
+    }
+
+    /// wSOL held in the owner's associated token account, `None` when it does not exist
+    pub fn wrapped_balance(&self, owner: &Pubkey) -> Result<Option<u64>, ServiceError> {
+        let address = wrapped_sol_address(owner);
+        let account = self
+            .client
+            .get_multiple_accounts(&[address])?
+            .into_iter()
+            .next()
+            .flatten();
+        Ok(account.and_then(|account| account_amount(&account.data)))
+    }
+
+    pub fn wrap(&self, account: &Account, amount: &str) -> Result<Signature, ServiceError> {
+        let lamports = ui_amount_to_base_units(amount, SOL_DECIMALS)?;
+        if lamports == 0 {
+            return Err(AmountError::Invalid(amount.to_string()).into());
+        }
+        let instructions = wrap_instructions(&account.pubkey()?, lamports);
+        self.send(account, &instructions)
+    }
+
+    /// Unwrap all wSOL by closing the account, which also returns its rent
+    pub fn unwrap(&self, account: &Account) -> Result<Signature, ServiceError> {
+        let owner = account.pubkey()?;
+        if self.wrapped_balance(&owner)?.is_none() {
+            return Err(ServiceError::AccountNotFound(format!(
+                "wSOL account {}",
+                wrapped_sol_address(&owner)
+            )));
+        }
+        self.send(account, &unwrap_instructions(&owner))
+    }
+
+    fn send(
+        &self,
+        account: &Account,
+        instructions: &[Instruction],
+    ) -> Result<Signature, ServiceError> {
+        TransactionService::new(self.client.clone())
+            .send_instructions(instructions, &account.account_keypair()?)
+    }
+}
+
+pub fn wrapped_sol_address(owner: &Pubkey) -> Pubkey {
+    get_associated_token_address(owner, &NATIVE_MINT, &TOKEN_PROGRAM_ID)
+}
+
+// Creating the account is a no-op when it exists, and syncing credits the lamports as wSOL
+pub fn wrap_instructions(owner: &Pubkey, lamports: u64) -> Vec<Instruction> {
+    let wrapped_account = wrapped_sol_address(owner);
+    vec![
+        create_associated_token_account_idempotent(owner, owner, &NATIVE_MINT, &TOKEN_PROGRAM_ID),
+        transfer(owner, &wrapped_account, lamports),
+        sync_native(&TOKEN_PROGRAM_ID, &wrapped_account),
+    ]
+}
+
+pub fn unwrap_instructions(owner: &Pubkey) -> Vec<Instruction> {
+    vec![close_account(
+        &TOKEN_PROGRAM_ID,
+        &wrapped_sol_address(owner),
+        owner,
+        owner,
+    )]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::associated_token::ASSOCIATED_TOKEN_PROGRAM_ID;
+    use crate::programs::token::ACCOUNT_LEN;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde_json::json;
+    use solana_rpc_client::rpc_client::Mocks;
+    use solana_rpc_client_api::request::RpcRequest;
+
+    #[test]
+    fn test_wrap_instructions() {
+        let owner = Pubkey::new_unique();
+        let instructions = wrap_instructions(&owner, 250_000_000);
+
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[0].program_id, ASSOCIATED_TOKEN_PROGRAM_ID);
+        assert_eq!(
+            instructions[1].accounts[1].pubkey,
+            wrapped_sol_address(&owner)
+        );
+        assert_eq!(instructions[2].program_id, TOKEN_PROGRAM_ID);
+        assert_eq!(
+            instructions[2].accounts[0].pubkey,
+            wrapped_sol_address(&owner)
+        );
+    }
+
+    #[test]
+    fn test_unwrap_instructions() {
+        let owner = Pubkey::new_unique();
+        let instructions = unwrap_instructions(&owner);
+
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(
+            instructions[0].accounts[0].pubkey,
+            wrapped_sol_address(&owner)
+        );
+        assert_eq!(instructions[0].accounts[1].pubkey, owner);
+    }
+
+    #[test]
+    fn test_wrapped_balance() {
+        let mut data = vec![0u8; ACCOUNT_LEN];
+        data[64..72].copy_from_slice(&1_500_000u64.to_le_bytes());
+        let mut mocks = Mocks::default();
+        mocks.insert(
+            RpcRequest::GetMultipleAccounts,
+            json!({
+                "context": { "slot": 1 },
+                "value": [{
+                    "data": [STANDARD.encode(&data), "base64"],
+                    "executable": false,
+                    "lamports": 3_539_280,
+                    "owner": TOKEN_PROGRAM_ID.to_string(),
+                    "rentEpoch": 0,
+                    "space": ACCOUNT_LEN,
+                }],
+            }),
+        );
+        let service = WrapService::new(Arc::new(RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            mocks,
+        )));
+
+        let owner = Pubkey::new_unique();
+        assert_eq!(service.wrapped_balance(&owner).unwrap(), Some(1_500_000));
+        // The default mock response has no account
+        assert_eq!(service.wrapped_balance(&owner).unwrap(), None);
+    }
+}
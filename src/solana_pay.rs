@@ -0,0 +1,3 @@
+pub mod errors;
+pub mod transaction_request;
+pub mod uri;
@@ -0,0 +1,29 @@
+use crate::amount::AmountError;
+use reqwest::Error as ReqwestError;
+use solana_sdk::pubkey::ParsePubkeyError;
+use thiserror::Error;
+use url::ParseError as UrlParseError;
+
+#[derive(Error, Debug)]
+pub enum SolanaPayError {
+    #[error("Not a Solana Pay URI: {0}")]
+    InvalidScheme(String),
+
+    #[error("Invalid URI: {0}")]
+    UrlParseError(#[from] UrlParseError),
+
+    #[error("Invalid public key: {0}")]
+    ParsePubkeyError(#[from] ParsePubkeyError),
+
+    #[error("Invalid amount: {0}")]
+    AmountError(#[from] AmountError),
+
+    #[error("Transaction request link must use https: {0}")]
+    InsecureLink(String),
+
+    #[error("Request error: {0}")]
+    RequestError(#[from] ReqwestError),
+
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+}
@@ -0,0 +1,193 @@
+use crate::solana_pay::errors::SolanaPayError;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::VersionedTransaction;
+use url::Url;
+
+// Merchant details returned by the GET request
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TransactionRequestMetadata {
+    pub label: String,
+    pub icon: String,
+}
+
+#[derive(Serialize)]
+struct TransactionRequestBody {
+    account: String,
+}
+
+// Transaction returned by the POST request
+#[derive(Deserialize, Debug, Clone)]
+pub struct TransactionRequestResponse {
+    pub transaction: String,
+    pub message: Option<String>,
+}
+
+impl TransactionRequestResponse {
+    /// Decode the base64 wire transaction sent by the merchant
+    pub fn decode_transaction(&self) -> Result<VersionedTransaction, SolanaPayError> {
+        let bytes = STANDARD
+            .decode(&self.transaction)
+            .map_err(|err| SolanaPayError::InvalidTransaction(err.to_string()))?;
+        bincode::deserialize(&bytes)
+            .map_err(|err| SolanaPayError::InvalidTransaction(err.to_string()))
+    }
+}
+
+pub struct TransactionRequestClient {
+    client: reqwest::Client,
+}
+
+impl TransactionRequestClient {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn fetch_metadata(
+        &self,
+        link: &Url,
+    ) -> Result<TransactionRequestMetadata, SolanaPayError> {
+        let metadata = self
+            .client
+            .get(link.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(metadata)
+    }
+
+    pub async fn fetch_transaction(
+        &self,
+        link: &Url,
+        account: &Pubkey,
+    ) -> Result<TransactionRequestResponse, SolanaPayError> {
+        let body = TransactionRequestBody {
+            account: account.to_string(),
+        };
+        let response = self
+            .client
+            .post(link.clone())
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{Message, VersionedMessage};
+    use solana_system_interface::instruction::transfer;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Minimal HTTP server answering GET and POST with canned JSON bodies
+    async fn spawn_stub_server(get_body: String, post_body: String) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    break;
+                };
+                let mut buffer = vec![0u8; 4096];
+                let read = stream.read(&mut buffer).await.unwrap();
+                let request = String::from_utf8_lossy(&buffer[..read]).to_string();
+
+                let body = if request.starts_with("POST") {
+                    assert!(request.contains("\"account\""));
+                    &post_body
+                } else {
+                    &get_body
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        Url::parse(&format!("http://{}/pay?order=1", address)).unwrap()
+    }
+
+    fn encoded_transaction(payer: &Pubkey) -> String {
+        let message = Message::new_with_blockhash(
+            &[transfer(payer, &Pubkey::new_unique(), 10)],
+            Some(payer),
+            &Hash::new_unique(),
+        );
+        let transaction = VersionedTransaction {
+            signatures: vec![Default::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        STANDARD.encode(bincode::serialize(&transaction).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_metadata() {
+        let link = spawn_stub_server(
+            r#"{"label":"Coffee Shop","icon":"https://example.com/icon.svg"}"#.to_string(),
+            String::new(),
+        )
+        .await;
+
+        let metadata = TransactionRequestClient::new()
+            .fetch_metadata(&link)
+            .await
+            .unwrap();
+        assert_eq!(metadata.label, "Coffee Shop");
+        assert_eq!(metadata.icon, "https://example.com/icon.svg");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_and_decode_transaction() {
+        let payer = Pubkey::new_unique();
+        let post_body = format!(
+            r#"{{"transaction":"{}","message":"Thanks for your order"}}"#,
+            encoded_transaction(&payer)
+        );
+        let link = spawn_stub_server(String::new(), post_body).await;
+
+        let response = TransactionRequestClient::new()
+            .fetch_transaction(&link, &payer)
+            .await
+            .unwrap();
+        assert_eq!(response.message.as_deref(), Some("Thanks for your order"));
+
+        let transaction = response.decode_transaction().unwrap();
+        assert_eq!(transaction.message.static_account_keys()[0], payer);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_metadata_invalid_body() {
+        let link = spawn_stub_server("not json".to_string(), String::new()).await;
+
+        let result = TransactionRequestClient::new().fetch_metadata(&link).await;
+        assert!(matches!(result, Err(SolanaPayError::RequestError(_))));
+    }
+
+    #[test]
+    fn test_decode_invalid_transaction() {
+        let response = TransactionRequestResponse {
+            transaction: "%%%".to_string(),
+            message: None,
+        };
+        assert!(matches!(
+            response.decode_transaction(),
+            Err(SolanaPayError::InvalidTransaction(_))
+        ));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::amount::validate_ui_amount;
+use crate::solana_pay::errors::SolanaPayError;
+use percent_encoding::percent_decode_str;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use url::{form_urlencoded, Url};
+
+pub const SOLANA_PAY_SCHEME: &str = "solana";
+
+/// A non-interactive payment request, `solana:<recipient>?amount=...`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TransferRequest {
+    pub recipient: Pubkey,
+    pub amount: Option<String>,
+    pub spl_token: Option<Pubkey>,
+    pub references: Vec<Pubkey>,
+    pub label: Option<String>,
+    pub message: Option<String>,
+    pub memo: Option<String>,
+}
+
+/// An interactive request, `solana:<https link>`, answered by a merchant server
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionRequest {
+    pub link: Url,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SolanaPayUri {
+    Transfer(TransferRequest),
+    Transaction(TransactionRequest),
+}
+
+impl SolanaPayUri {
+    pub fn parse(uri: &str) -> Result<Self, SolanaPayError> {
+        let uri = uri.trim();
+        let (scheme, rest) = uri
+            .split_once(':')
+            .ok_or_else(|| SolanaPayError::InvalidScheme(uri.to_string()))?;
+
+        if !scheme.eq_ignore_ascii_case(SOLANA_PAY_SCHEME) {
+            return Err(SolanaPayError::InvalidScheme(uri.to_string()));
+        }
+
+        // A transaction request link is URL-encoded, so it never contains a raw `?`
+        let path = rest.split('?').next().unwrap_or_default();
+        let decoded = percent_decode_str(path).decode_utf8_lossy();
+        if decoded.contains("://") {
+            return Ok(SolanaPayUri::Transaction(parse_transaction_request(
+                &decoded,
+            )?));
+        }
+
+        Ok(SolanaPayUri::Transfer(parse_transfer_request(rest)?))
+    }
+}
+
+fn parse_transaction_request(link: &str) -> Result<TransactionRequest, SolanaPayError> {
+    let link = Url::parse(link)?;
+    if link.scheme() != "https" {
+        return Err(SolanaPayError::InsecureLink(link.to_string()));
+    }
+    Ok(TransactionRequest { link })
+}
+
+fn parse_transfer_request(rest: &str) -> Result<TransferRequest, SolanaPayError> {
+    let (recipient, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let mut request = TransferRequest {
+        recipient: Pubkey::from_str(recipient)?,
+        ..TransferRequest::default()
+    };
+
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        let value = value.into_owned();
+        match key.as_ref() {
+            "amount" => {
+                validate_ui_amount(&value)?;
+                request.amount = Some(value);
+            }
+            "spl-token" => request.spl_token = Some(Pubkey::from_str(&value)?),
+            "reference" => request.references.push(Pubkey::from_str(&value)?),
+            "label" => request.label = Some(value),
+            "message" => request.message = Some(value),
+            "memo" => request.memo = Some(value),
+            _ => {}
+        }
+    }
+
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPIENT: &str = "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    #[test]
+    fn test_parse_minimal_transfer() {
+        let uri = format!("solana:{}", RECIPIENT);
+        let parsed = SolanaPayUri::parse(&uri).unwrap();
+
+        assert_eq!(
+            parsed,
+            SolanaPayUri::Transfer(TransferRequest {
+                recipient: Pubkey::from_str(RECIPIENT).unwrap(),
+                ..TransferRequest::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_full_transfer() {
+        let reference = Pubkey::new_unique();
+        let uri = format!(
+            "solana:{}?amount=0.01&spl-token={}&reference={}&label=Michael&message=Thanks%20for%20all%20the%20fish&memo=OrderId12345",
+            RECIPIENT, USDC, reference
+        );
+
+        let SolanaPayUri::Transfer(request) = SolanaPayUri::parse(&uri).unwrap() else {
+            panic!("expected a transfer request");
+        };
+        assert_eq!(request.amount.as_deref(), Some("0.01"));
+        assert_eq!(request.spl_token, Some(Pubkey::from_str(USDC).unwrap()));
+        assert_eq!(request.references, vec![reference]);
+        assert_eq!(request.label.as_deref(), Some("Michael"));
+        assert_eq!(request.message.as_deref(), Some("Thanks for all the fish"));
+        assert_eq!(request.memo.as_deref(), Some("OrderId12345"));
+    }
+
+    #[test]
+    fn test_parse_multiple_references() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let uri = format!(
+            "solana:{}?reference={}&reference={}",
+            RECIPIENT, first, second
+        );
+
+        let SolanaPayUri::Transfer(request) = SolanaPayUri::parse(&uri).unwrap() else {
+            panic!("expected a transfer request");
+        };
+        assert_eq!(request.references, vec![first, second]);
+    }
+
+    #[test]
+    fn test_parse_transfer_with_link_in_memo() {
+        let uri = format!(
+            "solana:{}?memo=https%3A%2F%2Fexample.com%2Finvoice",
+            RECIPIENT
+        );
+
+        let SolanaPayUri::Transfer(request) = SolanaPayUri::parse(&uri).unwrap() else {
+            panic!("expected a transfer request");
+        };
+        assert_eq!(request.memo.as_deref(), Some("https://example.com/invoice"));
+    }
+
+    #[test]
+    fn test_parse_transaction_request() {
+        let uri = "solana:https%3A%2F%2Fexample.com%2Fsolana-pay%3Forder%3D12345";
+        let parsed = SolanaPayUri::parse(uri).unwrap();
+
+        assert_eq!(
+            parsed,
+            SolanaPayUri::Transaction(TransactionRequest {
+                link: Url::parse("https://example.com/solana-pay?order=12345").unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_input() {
+        assert!(matches!(
+            SolanaPayUri::parse("bitcoin:abc"),
+            Err(SolanaPayError::InvalidScheme(_))
+        ));
+        assert!(matches!(
+            SolanaPayUri::parse("solana:not-a-key"),
+            Err(SolanaPayError::ParsePubkeyError(_))
+        ));
+        assert!(matches!(
+            SolanaPayUri::parse(&format!("solana:{}?amount=1e3", RECIPIENT)),
+            Err(SolanaPayError::AmountError(_))
+        ));
+        assert!(matches!(
+            SolanaPayUri::parse("solana:http%3A%2F%2Fexample.com"),
+            Err(SolanaPayError::InsecureLink(_))
+        ));
+    }
+}
@@ -0,0 +1,12 @@
+use chrono::{Local, TimeZone};
+
+pub const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Format a unix timestamp in local time for display
+pub fn format_time(timestamp: i64) -> String {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.format(DATE_TIME_FORMAT).to_string())
+        .unwrap_or_default()
+}
@@ -1,57 +1,43 @@
-use reqwest;
-use serde::{Deserialize, Serialize};
+use crate::price_provider::{errors::PriceError, PriceChain};
 use std::collections::HashMap;
-use std::error::Error;
 
-// Struct for the token price response
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// A USD price as reported by one of the price providers
+#[derive(Debug, Clone, PartialEq)]
 pub struct TokenData {
     pub id: String,
-    pub price: String,
+    pub price: f64,
+    /// Percentage change over the last 24 hours, when the provider knows it
+    pub price_change_24h: Option<f64>,
+    /// Unix timestamp the provider last updated the price at
+    pub updated_at: i64,
 }
 
 impl TokenData {
     /// Get the price formatted to two decimal places
     pub fn formatted_price(&self) -> String {
-        if let Ok(price) = self.price.parse::<f64>() {
-            format!("{:.2}", price)
-        } else {
-            self.price.clone() // Fallback to the original string if parsing fails
-        }
+        format!("{:.2}", self.price)
     }
-}
 
-// Struct for the full response
-#[derive(Serialize, Deserialize, Debug)]
-pub struct TokenResponse {
-    pub data: HashMap<String, TokenData>,
-    #[serde(rename = "timeTaken")]
-    pub time_taken: Option<f64>,
+    /// What the price was 24 hours ago, taken to be unchanged when the change is unknown
+    pub fn price_24h_ago(&self) -> f64 {
+        match self.price_change_24h {
+            Some(change) if change > -100.0 => self.price / (1.0 + change / 100.0),
+            _ => self.price,
+        }
+    }
 }
 
 // The main struct
 pub struct TokenValue {
-    pub ids: Vec<String>,
     pub prices: HashMap<String, TokenData>,
 }
 
 impl TokenValue {
-    /// Create a new TokenValue and fetch prices for the given keys
-    pub async fn new(ids: &[&str]) -> Result<Self, Box<dyn Error>> {
-        // Construct the URL with all keys
-        let url = format!("https://api.jup.ag/price/v2?ids={}", ids.join(","));
-
-        // Make the HTTP request
-        let response: TokenResponse = reqwest::get(&url).await?.json().await?;
-
-        Ok(TokenValue {
-            ids: ids.iter().map(|&key| key.to_string()).collect(),
-            prices: response.data,
-        })
-    }
-
-    /// Get price information for a specific key
-    pub fn get_price(&self, id: &str) -> Option<&TokenData> {
-        self.prices.get(id)
+    /// Fetch prices for all of `ids` at once from the configured providers, falling back
+    /// to the next one for ids a provider cannot price. Ids nobody prices are left out.
+    /// Blocking, so only call it off the UI thread.
+    pub fn new(ids: &[String]) -> Result<Self, PriceError> {
+        let prices = PriceChain::from_env().prices(ids)?;
+        Ok(TokenValue { prices })
     }
 }